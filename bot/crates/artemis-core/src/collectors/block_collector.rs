@@ -6,6 +6,7 @@ use ethers::{
     providers::PubsubClient,
     types::{U256, U64},
 };
+use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio_stream::StreamExt;
 
@@ -16,7 +17,7 @@ pub struct BlockCollector<M> {
 }
 
 /// A new block event, containing the block number and hash.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewBlock {
     pub number: U64,
     pub gas_used: U256,
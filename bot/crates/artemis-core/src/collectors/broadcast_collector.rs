@@ -0,0 +1,46 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use tokio::sync::broadcast;
+use tokio_stream::{
+    wrappers::{errors::BroadcastStreamRecvError, BroadcastStream},
+    StreamExt,
+};
+use tracing::warn;
+
+use crate::types::{Collector, CollectorStream};
+
+/// A collector that emits every value sent on a broadcast channel, e.g. the
+/// [reports](crate::executors::flashbots_executor::SubmissionReport) sent back by executors.
+pub struct BroadcastCollector<T> {
+    sender: broadcast::Sender<T>,
+}
+
+impl<T> BroadcastCollector<T> {
+    pub fn new(sender: broadcast::Sender<T>) -> Self {
+        Self { sender }
+    }
+}
+
+/// Implementation of the [Collector](Collector) trait for the
+/// [BroadcastCollector](BroadcastCollector). Only values sent after subscribing are emitted,
+/// values missed because the stream lagged behind are skipped.
+#[async_trait]
+impl<T> Collector<T> for BroadcastCollector<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, T>> {
+        let stream = BroadcastStream::new(self.sender.subscribe());
+        let stream = stream.filter_map(|value| match value {
+            Ok(value) => Some(value),
+            Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                warn!(
+                    "Broadcast collector lagged behind, skipped {} values",
+                    skipped
+                );
+                None
+            }
+        });
+        Ok(Box::pin(stream))
+    }
+}
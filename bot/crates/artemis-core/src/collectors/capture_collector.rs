@@ -0,0 +1,93 @@
+use std::{marker::PhantomData, path::PathBuf, time::Duration};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{future, stream, StreamExt};
+use serde::de::DeserializeOwned;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    collectors::replay_collector::ReplaySpeed,
+    types::{Collector, CollectorStream},
+    utilities::capture::{read_capture, CaptureRecord},
+};
+
+/// A collector that feeds the events of a capture file (written by
+/// [RecordingCollector](crate::collectors::recording_collector::RecordingCollector)s) back into an
+/// engine, in the order they originally arrived. Recorded actions are skipped.
+///
+/// Once the last event has been emitted the stream stays open without emitting anything, so that
+/// the engine doesn't restart it.
+pub struct CaptureCollector<E> {
+    path: PathBuf,
+    speed: ReplaySpeed,
+    finished: CancellationToken,
+    _event: PhantomData<fn() -> E>,
+}
+
+impl<E> CaptureCollector<E> {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            speed: ReplaySpeed::Instant,
+            finished: CancellationToken::new(),
+            _event: PhantomData,
+        }
+    }
+
+    /// Sets how fast events are emitted, [scaled](ReplaySpeed::Scaled) speeds use the time that
+    /// passed between events when they were captured.
+    pub fn with_speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Token that is cancelled once every event has been emitted.
+    pub fn finished(&self) -> CancellationToken {
+        self.finished.clone()
+    }
+}
+
+#[async_trait]
+impl<E> Collector<E> for CaptureCollector<E>
+where
+    E: DeserializeOwned + Send + Sync + 'static,
+{
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, E>> {
+        let path = self.path.clone();
+        let records = tokio::task::spawn_blocking(move || read_capture::<E>(path)).await??;
+
+        let mut previous_at: Option<Duration> = None;
+        let events: Vec<(Duration, E)> = records
+            .into_iter()
+            .filter_map(|record| match record {
+                CaptureRecord::Event { at, event } => {
+                    let at = Duration::from_millis(at);
+                    let delay = self
+                        .speed
+                        .delay(previous_at.map(|previous_at| at.saturating_sub(previous_at)));
+                    previous_at = Some(at);
+                    Some((delay, event))
+                }
+                CaptureRecord::Action { .. } => None,
+            })
+            .collect();
+
+        let finished = self.finished.clone();
+        let stream = stream::iter(events)
+            .then(|(delay, event)| async move {
+                tokio::time::sleep(delay).await;
+                event
+            })
+            .chain(
+                stream::once(async move {
+                    finished.cancel();
+                    None::<E>
+                })
+                .filter_map(future::ready),
+            )
+            .chain(stream::pending());
+
+        Ok(Box::pin(stream))
+    }
+}
@@ -0,0 +1,130 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::types::{Address, Bytes, H256};
+use futures::{stream, StreamExt};
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+use crate::types::{Collector, CollectorStream};
+
+/// A hint about a pending tx, as streamed by the MEV-Share event stream. Fields the sender chose
+/// not to share are empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MevShareEvent {
+    /// Hash of the tx (or bundle) the hint is about.
+    pub hash: H256,
+    /// Logs the tx emits.
+    #[serde(default)]
+    pub logs: Vec<MevShareLog>,
+    /// Partial view of the txs, `None` for bundles' txs with nothing shared.
+    #[serde(default)]
+    pub txs: Option<Vec<MevShareTx>>,
+}
+
+/// A log emitted by a hinted tx, `data` is empty if it wasn't shared.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MevShareLog {
+    pub address: Address,
+    #[serde(default)]
+    pub topics: Vec<H256>,
+    #[serde(default)]
+    pub data: Bytes,
+}
+
+/// Partial view of a hinted tx.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MevShareTx {
+    pub to: Option<Address>,
+    pub function_selector: Option<Bytes>,
+    pub call_data: Option<Bytes>,
+}
+
+/// A collector that listens to a MEV-Share server-sent event stream (e.g.
+/// `https://mev-share.flashbots.net`), and generates a stream of [events](MevShareEvent) which
+/// contain the hints about each pending tx.
+pub struct MevShareCollector {
+    url: Url,
+    client: reqwest::Client,
+}
+
+impl MevShareCollector {
+    pub fn new(url: Url) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::new(),
+        }
+    }
+}
+
+/// Implementation of the [Collector](Collector) trait for the
+/// [MevShareCollector](MevShareCollector). The stream ends when the server closes the connection,
+/// events that can't be decoded are skipped.
+#[async_trait]
+impl Collector<MevShareEvent> for MevShareCollector {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, MevShareEvent>> {
+        let response = self
+            .client
+            .get(self.url.clone())
+            .header("Accept", "text/event-stream")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| anyhow!("Failed to connect to MEV-Share event stream: {}", e))?;
+
+        let chunks = Box::pin(response.bytes_stream());
+        let stream = stream::unfold(
+            (chunks, Vec::<u8>::new()),
+            |(mut chunks, mut buffer)| async move {
+                loop {
+                    // events are separated by a blank line
+                    if let Some(end) = buffer.windows(2).position(|window| window == b"\n\n") {
+                        let frame: Vec<u8> = buffer.drain(..end + 2).collect();
+                        match parse_frame(&frame) {
+                            Some(event) => return Some((event, (chunks, buffer))),
+                            None => continue,
+                        }
+                    }
+
+                    match chunks.next().await {
+                        Some(Ok(chunk)) => {
+                            buffer.extend(chunk.iter().filter(|&&byte| byte != b'\r'))
+                        }
+                        Some(Err(e)) => {
+                            warn!("MEV-Share event stream failed: {}", e);
+                            return None;
+                        }
+                        None => return None,
+                    }
+                }
+            },
+        );
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Decode the `data` lines of a single server-sent event, `None` for comments (keep alives) and
+/// events that aren't valid hints.
+fn parse_frame(frame: &[u8]) -> Option<MevShareEvent> {
+    let frame = String::from_utf8_lossy(frame);
+    let data = frame
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    if data.is_empty() {
+        return None;
+    }
+
+    match serde_json::from_str(&data) {
+        Ok(event) => Some(event),
+        Err(e) => {
+            warn!("Skipping invalid MEV-Share event {}: {}", data, e);
+            None
+        }
+    }
+}
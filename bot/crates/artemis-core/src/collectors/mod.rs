@@ -7,3 +7,24 @@ pub mod block_collector;
 
 /// This collector listens to a stream of new pending transactions.
 pub mod mempool_collector;
+
+/// This collector resubscribes to another collector's stream whenever it ends.
+pub mod reconnecting_collector;
+
+/// This collector merges the pending transactions of several nodes.
+pub mod multi_mempool_collector;
+
+/// This collector listens to the MEV-Share event stream.
+pub mod mev_share_collector;
+
+/// This collector replays historical blocks and their transactions.
+pub mod replay_collector;
+
+/// This collector records the events of another collector to a capture file.
+pub mod recording_collector;
+
+/// This collector replays the events of a capture file.
+pub mod capture_collector;
+
+/// This collector listens to a broadcast channel, e.g. reports sent back by executors.
+pub mod broadcast_collector;
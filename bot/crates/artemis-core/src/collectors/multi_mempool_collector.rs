@@ -0,0 +1,282 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    prelude::Middleware,
+    providers::PubsubClient,
+    types::{Transaction, H256},
+};
+use futures::{future, StreamExt};
+use tracing::{debug, error};
+
+use crate::{
+    collectors::reconnecting_collector::{reconnecting_stream, StreamEvent, StreamGap},
+    types::{Collector, CollectorStream},
+};
+
+/// How a [MempoolSource](MempoolSource) streams pending txs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PendingTxSubscription {
+    /// Erigon's `newPendingTransactionsWithBody`, which streams full txs.
+    WithBody,
+    /// Standard `newPendingTransactions`, each hash is resolved with `eth_getTransactionByHash`.
+    Hashes,
+}
+
+/// A node that pending txs are streamed from.
+pub struct MempoolSource<M> {
+    /// Name that txs first seen by this node are tagged with.
+    pub name: String,
+    pub provider: Arc<M>,
+    pub subscription: PendingTxSubscription,
+}
+
+/// A pending tx, tagged with the source that saw it first.
+#[derive(Debug, Clone)]
+pub struct SeenTransaction {
+    pub tx: Transaction,
+    /// Name of the first source the tx was seen on.
+    pub source: String,
+    /// When the tx (or its hash) was first seen.
+    pub first_seen: SystemTime,
+}
+
+/// An event from a [MultiMempoolCollector](MultiMempoolCollector).
+#[derive(Debug, Clone)]
+pub enum MempoolEvent {
+    /// A pending tx, emitted once whichever sources see it.
+    Transaction(SeenTransaction),
+    /// A source's stream ended and has been resubscribed, txs that only this source saw in
+    /// between were missed.
+    SourceGap { source: String, gap: StreamGap },
+}
+
+/// Hashes of the txs already emitted, the oldest are forgotten once `capacity` is reached.
+struct SeenHashes {
+    hashes: HashSet<H256>,
+    order: VecDeque<H256>,
+    capacity: usize,
+}
+
+impl SeenHashes {
+    fn contains(&self, hash: &H256) -> bool {
+        self.hashes.contains(hash)
+    }
+
+    /// Returns `false` if `hash` was already seen.
+    fn insert(&mut self, hash: H256) -> bool {
+        if !self.hashes.insert(hash) {
+            return false;
+        }
+
+        self.order.push_back(hash);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.hashes.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// A collector that merges the pending tx streams of several nodes, so that txs a single node
+/// never saw are not missed, and generates a stream of [events](MempoolEvent) deduplicated by
+/// tx hash. Each source is resubscribed on its own whenever its stream ends, with exponential
+/// backoff, so that a dead source doesn't stay down while the others are alive.
+pub struct MultiMempoolCollector<M> {
+    sources: Vec<MempoolSource<M>>,
+    /// Kept across resubscriptions so that txs are never emitted twice.
+    seen: Arc<Mutex<SeenHashes>>,
+    /// How many txs each source saw first.
+    first_seen_counts: Arc<Mutex<HashMap<String, u64>>>,
+    /// Max concurrent `eth_getTransactionByHash` requests per hash subscription.
+    max_concurrent_lookups: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl<M> MultiMempoolCollector<M> {
+    pub fn new(sources: Vec<MempoolSource<M>>) -> Self {
+        Self {
+            sources,
+            seen: Arc::new(Mutex::new(SeenHashes {
+                hashes: HashSet::new(),
+                order: VecDeque::new(),
+                capacity: 100_000,
+            })),
+            first_seen_counts: Arc::new(Mutex::new(HashMap::new())),
+            max_concurrent_lookups: 64,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets how many tx hashes are remembered for deduplication.
+    pub fn with_dedup_capacity(self, capacity: usize) -> Self {
+        self.seen.lock().unwrap().capacity = capacity;
+        self
+    }
+
+    /// Sets how many `eth_getTransactionByHash` requests each hash subscription can have in
+    /// flight.
+    pub fn with_max_concurrent_lookups(mut self, max_concurrent_lookups: usize) -> Self {
+        self.max_concurrent_lookups = max_concurrent_lookups.max(1);
+        self
+    }
+
+    /// Sets how long to wait before resubscribing a source, the delay doubles after each failed
+    /// attempt up to `max`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Returns a handle to the number of txs each source saw first, that stays valid after the
+    /// collector is moved into the engine.
+    pub fn first_seen_counts(&self) -> Arc<Mutex<HashMap<String, u64>>> {
+        self.first_seen_counts.clone()
+    }
+}
+
+impl<M> MultiMempoolCollector<M>
+where
+    M: Middleware + 'static,
+    M::Provider: PubsubClient,
+    M::Error: 'static,
+{
+    /// Subscribe to a single source's pending txs.
+    async fn subscribe<'a>(
+        &'a self,
+        source: &'a MempoolSource<M>,
+    ) -> Result<CollectorStream<'a, SeenTransaction>> {
+        let name = source.name.clone();
+
+        match source.subscription {
+            PendingTxSubscription::WithBody => {
+                let stream = source
+                    .provider
+                    .subscribe::<_, Transaction>(["newPendingTransactionsWithBody"])
+                    .await
+                    .map_err(|e| anyhow!("{}", e))?;
+                let stream = stream.map(move |tx| SeenTransaction {
+                    tx,
+                    source: name.clone(),
+                    first_seen: SystemTime::now(),
+                });
+                Ok(Box::pin(stream))
+            }
+            PendingTxSubscription::Hashes => {
+                let stream = source
+                    .provider
+                    .subscribe_pending_txs()
+                    .await
+                    .map_err(|e| anyhow!("{}", e))?;
+                let seen = self.seen.clone();
+                let provider = source.provider.clone();
+                let stream = stream
+                    // don't look up txs another source already delivered
+                    .filter(move |hash| future::ready(!seen.lock().unwrap().contains(hash)))
+                    .map(move |hash| {
+                        let provider = provider.clone();
+                        let name = name.clone();
+                        let first_seen = SystemTime::now();
+                        async move {
+                            match provider.get_transaction(hash).await {
+                                // mined before we could fetch it, no longer pending
+                                Ok(Some(tx)) if tx.block_number.is_some() => None,
+                                Ok(Some(tx)) => Some(SeenTransaction {
+                                    tx,
+                                    source: name,
+                                    first_seen,
+                                }),
+                                // dropped before we could fetch it
+                                Ok(None) => None,
+                                Err(e) => {
+                                    debug!("{} failed to get tx {:?}: {}", name, hash, e);
+                                    None
+                                }
+                            }
+                        }
+                    })
+                    .buffer_unordered(self.max_concurrent_lookups)
+                    .filter_map(future::ready);
+                Ok(Box::pin(stream))
+            }
+        }
+    }
+}
+
+/// Implementation of the [Collector](Collector) trait for the
+/// [MultiMempoolCollector](MultiMempoolCollector). Sources that fail to subscribe are retried in
+/// the background, as long as at least one source subscribed.
+#[async_trait]
+impl<M> Collector<MempoolEvent> for MultiMempoolCollector<M>
+where
+    M: Middleware + 'static,
+    M::Provider: PubsubClient,
+    M::Error: 'static,
+{
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, MempoolEvent>> {
+        let mut streams = vec![];
+        let mut subscribed = false;
+        for source in &self.sources {
+            let stream = match self.subscribe(source).await {
+                Ok(stream) => {
+                    subscribed = true;
+                    Some(stream)
+                }
+                Err(e) => {
+                    error!(
+                        "Failed to subscribe to mempool source {}: {}",
+                        source.name, e
+                    );
+                    None
+                }
+            };
+            let name = source.name.clone();
+            let stream = reconnecting_stream(
+                format!("mempool source {}", source.name),
+                stream,
+                move || self.subscribe(source),
+                self.initial_backoff,
+                self.max_backoff,
+            )
+            .map(move |event| match event {
+                StreamEvent::Event(seen_tx) => MempoolEvent::Transaction(seen_tx),
+                StreamEvent::Gap(gap) => MempoolEvent::SourceGap {
+                    source: name.clone(),
+                    gap,
+                },
+            });
+            streams.push(Box::pin(stream) as CollectorStream<'_, MempoolEvent>);
+        }
+        if !subscribed {
+            return Err(anyhow!("Failed to subscribe to any mempool source"));
+        }
+
+        let seen = self.seen.clone();
+        let first_seen_counts = self.first_seen_counts.clone();
+        let stream = futures::stream::select_all(streams).filter(move |event| {
+            let seen_tx = match event {
+                MempoolEvent::Transaction(seen_tx) => seen_tx,
+                MempoolEvent::SourceGap { .. } => return future::ready(true),
+            };
+            let first = seen.lock().unwrap().insert(seen_tx.tx.hash);
+            if first {
+                *first_seen_counts
+                    .lock()
+                    .unwrap()
+                    .entry(seen_tx.source.clone())
+                    .or_default() += 1;
+            }
+            future::ready(first)
+        });
+        Ok(Box::pin(stream))
+    }
+}
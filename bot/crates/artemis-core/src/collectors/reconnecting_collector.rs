@@ -0,0 +1,152 @@
+use std::{
+    future::Future,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio_stream::StreamExt;
+use tracing::{error, info, warn};
+
+use crate::types::{Collector, CollectorStream};
+
+/// An event from a [ReconnectingCollector](ReconnectingCollector).
+#[derive(Debug, Clone)]
+pub enum StreamEvent<E> {
+    /// An event from the wrapped collector.
+    Event(E),
+    /// The wrapped collector's stream ended and has been resubscribed, events emitted in between
+    /// were missed.
+    Gap(StreamGap),
+}
+
+/// A period during which a collector was not subscribed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct StreamGap {
+    /// How long the collector was unsubscribed for.
+    pub downtime: Duration,
+}
+
+/// A collector that resubscribes to the wrapped collector's stream whenever it ends (e.g. when the
+/// websocket drops), with exponential backoff, and emits a [gap](StreamEvent::Gap) once it is
+/// subscribed again.
+pub struct ReconnectingCollector<E> {
+    collector: Box<dyn Collector<E>>,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl<E> ReconnectingCollector<E> {
+    pub fn new(collector: Box<dyn Collector<E>>) -> Self {
+        Self {
+            collector,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+
+    /// Sets how long to wait before resubscribing, the delay doubles after each failed attempt up
+    /// to `max`.
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+}
+
+#[async_trait]
+impl<E> Collector<StreamEvent<E>> for ReconnectingCollector<E>
+where
+    E: Send + Sync + 'static,
+{
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, StreamEvent<E>>> {
+        let stream = self.collector.get_event_stream().await?;
+        Ok(reconnecting_stream(
+            "collector".to_string(),
+            Some(stream),
+            move || self.collector.get_event_stream(),
+            self.initial_backoff,
+            self.max_backoff,
+        ))
+    }
+}
+
+/// Where a reconnecting stream is at.
+struct ReconnectState<'a, E, F> {
+    name: String,
+    stream: Option<CollectorStream<'a, E>>,
+    subscribe: F,
+    disconnected_at: Instant,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    backoff: Duration,
+}
+
+/// Streams the events of `stream`, calling `subscribe` to resubscribe whenever it ends (or right
+/// away if `stream` is `None`) with exponential backoff, and emits a [gap](StreamEvent::Gap) once
+/// subscribed again. `name` identifies the stream in logs.
+pub(crate) fn reconnecting_stream<'a, E, F, Fut>(
+    name: String,
+    stream: Option<CollectorStream<'a, E>>,
+    subscribe: F,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+) -> CollectorStream<'a, StreamEvent<E>>
+where
+    E: Send + 'a,
+    F: Fn() -> Fut + Send + 'a,
+    Fut: Future<Output = Result<CollectorStream<'a, E>>> + Send + 'a,
+{
+    let state = ReconnectState {
+        name,
+        stream,
+        subscribe,
+        disconnected_at: Instant::now(),
+        initial_backoff,
+        max_backoff,
+        backoff: initial_backoff,
+    };
+
+    let stream = futures::stream::unfold(state, |mut state| async move {
+        loop {
+            let stream = match state.stream.as_mut() {
+                Some(stream) => stream,
+                None => {
+                    tokio::time::sleep(state.backoff).await;
+                    match (state.subscribe)().await {
+                        Ok(stream) => {
+                            let downtime = state.disconnected_at.elapsed();
+                            info!("{} resubscribed after {:?}", state.name, downtime);
+                            state.stream = Some(stream);
+                            state.backoff = state.initial_backoff;
+                            return Some((StreamEvent::Gap(StreamGap { downtime }), state));
+                        }
+                        Err(e) => {
+                            state.backoff = (state.backoff * 2).min(state.max_backoff);
+                            error!(
+                                "error resubscribing {}: {}, retrying in {:?}",
+                                state.name, e, state.backoff
+                            );
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            match stream.next().await {
+                Some(event) => return Some((StreamEvent::Event(event), state)),
+                None => {
+                    warn!(
+                        "{} stream ended, resubscribing in {:?}",
+                        state.name, state.backoff
+                    );
+                    state.stream = None;
+                    state.disconnected_at = Instant::now();
+                }
+            }
+        }
+    });
+
+    Box::pin(stream)
+}
@@ -0,0 +1,37 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+use tokio_stream::StreamExt;
+
+use crate::{
+    types::{Collector, CollectorStream},
+    utilities::capture::CaptureWriter,
+};
+
+/// A collector that passes the wrapped collector's events through untouched, appending each one
+/// to a capture file as it arrives.
+pub struct RecordingCollector<E> {
+    collector: Box<dyn Collector<E>>,
+    writer: CaptureWriter,
+}
+
+impl<E> RecordingCollector<E> {
+    pub fn new(collector: Box<dyn Collector<E>>, writer: CaptureWriter) -> Self {
+        Self { collector, writer }
+    }
+}
+
+#[async_trait]
+impl<E> Collector<E> for RecordingCollector<E>
+where
+    E: Serialize + Send + Sync + 'static,
+{
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, E>> {
+        let stream = self.collector.get_event_stream().await?;
+        let stream = stream.map(move |event| {
+            self.writer.write_event(&event);
+            event
+        });
+        Ok(Box::pin(stream))
+    }
+}
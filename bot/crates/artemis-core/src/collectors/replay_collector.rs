@@ -0,0 +1,281 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    types::{Block, Transaction, U256, U64},
+};
+use futures::{future, stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use tracing::warn;
+
+use crate::{
+    collectors::block_collector::NewBlock,
+    types::{Collector, CollectorStream, Events},
+};
+
+/// A historical block and the txs it included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayBlock {
+    pub block: NewBlock,
+    /// Included txs, with their inclusion fields cleared so that they look pending.
+    pub txs: Vec<Transaction>,
+}
+
+impl ReplayBlock {
+    /// `None` for pending blocks.
+    pub fn from_block(block: Block<Transaction>) -> Option<Self> {
+        let txs = block
+            .transactions
+            .into_iter()
+            .map(|mut tx| {
+                tx.block_hash = None;
+                tx.block_number = None;
+                tx.transaction_index = None;
+                tx
+            })
+            .collect();
+
+        Some(Self {
+            block: NewBlock {
+                number: block.number?,
+                gas_used: block.gas_used,
+                gas_limit: block.gas_limit,
+                base_fee_per_gas: block.base_fee_per_gas.unwrap_or_default(),
+                timestamp: block.timestamp,
+            },
+            txs,
+        })
+    }
+
+    /// The block's txs as pending txs, followed by the block itself.
+    fn into_events(self) -> Vec<Events> {
+        let mut events: Vec<Events> = self.txs.into_iter().map(Events::Transaction).collect();
+        events.push(Events::NewBlock(self.block));
+        events
+    }
+}
+
+/// A collector that fetches the blocks `from_block..=to_block` (with their txs) from an RPC, the
+/// stream ends after `to_block`. Blocks that can't be fetched are skipped.
+pub struct RpcBlockSource<M> {
+    provider: Arc<M>,
+    from_block: U64,
+    to_block: U64,
+}
+
+impl<M: Middleware> RpcBlockSource<M> {
+    pub fn new(provider: Arc<M>, from_block: U64, to_block: U64) -> Self {
+        Self {
+            provider,
+            from_block,
+            to_block,
+        }
+    }
+
+    /// Fetch every block and write them to `path` (one json encoded [ReplayBlock](ReplayBlock)
+    /// per line) so that they can be replayed offline using a [FileBlockSource](FileBlockSource),
+    /// returns the number of blocks written.
+    pub async fn record(&self, path: impl Into<PathBuf>) -> Result<usize>
+    where
+        M: 'static,
+        M::Error: 'static,
+    {
+        let mut writer = BufWriter::new(File::create(path.into())?);
+        let mut blocks = self.get_event_stream().await?;
+
+        let mut recorded = 0;
+        while let Some(block) = blocks.next().await {
+            serde_json::to_writer(&mut writer, &block)?;
+            writer.write_all(b"\n")?;
+            recorded += 1;
+        }
+        writer.flush()?;
+
+        Ok(recorded)
+    }
+}
+
+#[async_trait]
+impl<M> Collector<ReplayBlock> for RpcBlockSource<M>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+{
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, ReplayBlock>> {
+        let stream = stream::unfold(self.from_block, move |mut number| async move {
+            while number <= self.to_block {
+                match self.provider.get_block_with_txs(number).await {
+                    Ok(Some(block)) => {
+                        if let Some(block) = ReplayBlock::from_block(block) {
+                            return Some((block, number + 1));
+                        }
+                    }
+                    Ok(None) => warn!("Skipping block {}: not found", number),
+                    Err(e) => warn!("Skipping block {}: {}", number, e),
+                }
+                number += 1;
+            }
+            None
+        });
+
+        Ok(Box::pin(stream))
+    }
+}
+
+/// A collector that reads blocks recorded by [RpcBlockSource::record](RpcBlockSource::record).
+pub struct FileBlockSource {
+    path: PathBuf,
+}
+
+impl FileBlockSource {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl Collector<ReplayBlock> for FileBlockSource {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, ReplayBlock>> {
+        let contents = tokio::fs::read_to_string(&self.path)
+            .await
+            .map_err(|e| anyhow!("Failed to read {}: {}", self.path.display(), e))?;
+
+        let blocks = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str(line).map_err(|e| {
+                    anyhow!(
+                        "Failed to parse line {} of {}: {}",
+                        index + 1,
+                        self.path.display(),
+                        e
+                    )
+                })
+            })
+            .collect::<Result<Vec<ReplayBlock>>>()?;
+
+        Ok(Box::pin(stream::iter(blocks)))
+    }
+}
+
+/// How fast a [ReplayCollector](ReplayCollector) emits blocks (or a
+/// [CaptureCollector](crate::collectors::capture_collector::CaptureCollector) emits events).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Emit blocks as fast as the engine takes them.
+    Instant,
+    /// Wait the time that passed between consecutive blocks divided by the factor (`1.0` replays
+    /// in real time, `10.0` ten times faster).
+    Scaled(f64),
+    /// Wait a fixed amount of time between consecutive blocks.
+    Interval(Duration),
+}
+
+impl ReplaySpeed {
+    /// How long to wait before emitting an item that originally came `elapsed` after the previous
+    /// one (`None` for the first item).
+    pub(crate) fn delay(&self, elapsed: Option<Duration>) -> Duration {
+        let elapsed = match elapsed {
+            Some(elapsed) => elapsed,
+            None => return Duration::ZERO,
+        };
+
+        match self {
+            Self::Instant => Duration::ZERO,
+            Self::Scaled(factor) if *factor > 0.0 => elapsed.div_f64(*factor),
+            Self::Scaled(_) => Duration::ZERO,
+            Self::Interval(interval) => *interval,
+        }
+    }
+}
+
+/// A collector that replays historical blocks from another collector (e.g. an
+/// [RpcBlockSource](RpcBlockSource) or a [FileBlockSource](FileBlockSource)). Each block's txs are
+/// emitted as pending txs, followed by the block itself, so that a strategy sees them like it
+/// would have live.
+///
+/// Once the last block has been emitted the stream stays open without emitting anything, so that
+/// the engine doesn't restart it, unless the collector [repeats](ReplayCollector::with_repeat).
+pub struct ReplayCollector {
+    source: Box<dyn Collector<ReplayBlock>>,
+    speed: ReplaySpeed,
+    repeat: bool,
+    finished: CancellationToken,
+}
+
+impl ReplayCollector {
+    pub fn new(source: Box<dyn Collector<ReplayBlock>>) -> Self {
+        Self {
+            source,
+            speed: ReplaySpeed::Instant,
+            repeat: false,
+            finished: CancellationToken::new(),
+        }
+    }
+
+    /// Sets how fast blocks are emitted.
+    pub fn with_speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// End the stream after the last block, the engine then restarts the collector and the blocks
+    /// are replayed again (e.g. for load testing).
+    pub fn with_repeat(mut self, repeat: bool) -> Self {
+        self.repeat = repeat;
+        self
+    }
+
+    /// Token that is cancelled once every block has been emitted.
+    pub fn finished(&self) -> CancellationToken {
+        self.finished.clone()
+    }
+}
+
+#[async_trait]
+impl Collector<Events> for ReplayCollector {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Events>> {
+        let blocks = self.source.get_event_stream().await?;
+        let speed = self.speed;
+        let finished = self.finished.clone();
+
+        let mut previous_timestamp = None;
+        let stream = blocks
+            .then(move |replay_block| {
+                let timestamp = replay_block.block.timestamp;
+                let elapsed = previous_timestamp.map(|previous_timestamp: U256| {
+                    Duration::from_secs(timestamp.saturating_sub(previous_timestamp).as_u64())
+                });
+                let delay = speed.delay(elapsed);
+                previous_timestamp = Some(timestamp);
+                async move {
+                    tokio::time::sleep(delay).await;
+                    replay_block
+                }
+            })
+            .flat_map(|replay_block| stream::iter(replay_block.into_events()))
+            .chain(
+                stream::once(async move {
+                    finished.cancel();
+                    None::<Events>
+                })
+                .filter_map(future::ready),
+            );
+
+        match self.repeat {
+            true => Ok(Box::pin(stream)),
+            false => Ok(Box::pin(stream.chain(stream::pending()))),
+        }
+    }
+}
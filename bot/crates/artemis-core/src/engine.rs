@@ -1,10 +1,134 @@
-use tokio::sync::broadcast::{self, Sender};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::mpsc::{self, error::TrySendError};
 use tokio::task::JoinSet;
 use tokio_stream::StreamExt;
-use tracing::{error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{error, info, warn};
 
 use crate::types::{Collector, Executor, Strategy};
 
+/// Default capacity of the event and action channels.
+const DEFAULT_CHANNEL_CAPACITY: usize = 512;
+
+/// Default delay before restarting a failed collector, doubled after each consecutive failure.
+const DEFAULT_RESTART_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Default upper bound of the collector restart delay.
+const DEFAULT_MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60);
+
+/// What happens when a strategy (or executor) falls behind and its channel is full.
+pub enum BackpressurePolicy<T> {
+    /// Senders never wait, a receiver that falls behind skips the oldest items it has not seen.
+    DropOldest,
+    /// Senders wait until every receiver has room, nothing is dropped.
+    Block,
+    /// Items matching the predicate are never dropped and are received before any other item,
+    /// every other item is handled like [DropOldest](BackpressurePolicy::DropOldest).
+    Prioritise(fn(&T) -> bool),
+}
+
+impl<T> BackpressurePolicy<T> {
+    /// Whether the sender should wait for receivers to make room for `item`.
+    fn blocks(&self, item: &T) -> bool {
+        match self {
+            Self::DropOldest => false,
+            Self::Block => true,
+            Self::Prioritise(is_priority) => is_priority(item),
+        }
+    }
+}
+
+impl<T> Clone for BackpressurePolicy<T> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::DropOldest => Self::DropOldest,
+            Self::Block => Self::Block,
+            Self::Prioritise(is_priority) => Self::Prioritise(*is_priority),
+        }
+    }
+}
+
+impl<T> std::fmt::Debug for BackpressurePolicy<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DropOldest => write!(f, "DropOldest"),
+            Self::Block => write!(f, "Block"),
+            Self::Prioritise(_) => write!(f, "Prioritise"),
+        }
+    }
+}
+
+/// Counters of a single strategy's (or executor's) input channel.
+#[derive(Debug, Default)]
+pub struct ChannelMetrics {
+    received: AtomicU64,
+    lagged: AtomicU64,
+    blocked: AtomicU64,
+}
+
+impl ChannelMetrics {
+    /// Snapshot of the counters.
+    pub fn stats(&self) -> ChannelStats {
+        ChannelStats {
+            received: self.received.load(Ordering::Relaxed),
+            lagged: self.lagged.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Snapshot of a [ChannelMetrics](ChannelMetrics).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ChannelStats {
+    /// Items received.
+    pub received: u64,
+    /// Items skipped because the receiver fell behind.
+    pub lagged: u64,
+    /// Sends that had to wait for the receiver to make room.
+    pub blocked: u64,
+}
+
+/// Backpressure counters of every strategy and executor, in the order they were added.
+#[derive(Debug, Default)]
+pub struct EngineMetrics {
+    strategies: Mutex<Vec<Arc<ChannelMetrics>>>,
+    executors: Mutex<Vec<Arc<ChannelMetrics>>>,
+}
+
+impl EngineMetrics {
+    /// Counters of each strategy's event channel.
+    pub fn strategies(&self) -> Vec<ChannelStats> {
+        let strategies = self.strategies.lock().unwrap();
+        strategies.iter().map(|metrics| metrics.stats()).collect()
+    }
+
+    /// Counters of each executor's action channel.
+    pub fn executors(&self) -> Vec<ChannelStats> {
+        let executors = self.executors.lock().unwrap();
+        executors.iter().map(|metrics| metrics.stats()).collect()
+    }
+
+    fn add_strategy(&self) -> Arc<ChannelMetrics> {
+        let metrics = Arc::new(ChannelMetrics::default());
+        self.strategies.lock().unwrap().push(metrics.clone());
+        metrics
+    }
+
+    fn add_executor(&self) -> Arc<ChannelMetrics> {
+        let metrics = Arc::new(ChannelMetrics::default());
+        self.executors.lock().unwrap().push(metrics.clone());
+        metrics
+    }
+}
+
 /// The main engine of Artemis. This struct is responsible for orchestrating the
 /// data flow between collectors, strategies, and executors.
 pub struct Engine<E, A> {
@@ -12,10 +136,34 @@ pub struct Engine<E, A> {
     collectors: Vec<Box<dyn Collector<E>>>,
 
     /// The set of strategies that the engine will use to process events.
-    strategies: Vec<Box<dyn Strategy<E, A>>>,
+    strategies: Vec<(Box<dyn Strategy<E, A>>, Arc<ChannelMetrics>)>,
 
     /// The set of executors that the engine will use to execute actions.
-    executors: Vec<Box<dyn Executor<A>>>,
+    executors: Vec<(Box<dyn Executor<A>>, Arc<ChannelMetrics>)>,
+
+    /// Capacity of each strategy's event channel.
+    event_channel_capacity: usize,
+
+    /// Capacity of each executor's action channel.
+    action_channel_capacity: usize,
+
+    /// What happens to events when a strategy falls behind.
+    event_policy: BackpressurePolicy<E>,
+
+    /// What happens to actions when an executor falls behind.
+    action_policy: BackpressurePolicy<A>,
+
+    /// Shared with the caller, see [metrics](Engine::metrics).
+    metrics: Arc<EngineMetrics>,
+
+    /// Cancelled to shut the engine down, see [shutdown_token](Engine::shutdown_token).
+    shutdown: CancellationToken,
+
+    /// Delay before the first restart of a failed collector.
+    restart_backoff: Duration,
+
+    /// Upper bound of the collector restart delay.
+    max_restart_backoff: Duration,
 }
 
 impl<E, A> Engine<E, A> {
@@ -24,8 +172,81 @@ impl<E, A> Engine<E, A> {
             collectors: vec![],
             strategies: vec![],
             executors: vec![],
+            event_channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            action_channel_capacity: DEFAULT_CHANNEL_CAPACITY,
+            event_policy: BackpressurePolicy::DropOldest,
+            action_policy: BackpressurePolicy::DropOldest,
+            metrics: Arc::new(EngineMetrics::default()),
+            shutdown: CancellationToken::new(),
+            restart_backoff: DEFAULT_RESTART_BACKOFF,
+            max_restart_backoff: DEFAULT_MAX_RESTART_BACKOFF,
         }
     }
+
+    /// Sets the capacity of each strategy's event channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_event_channel_capacity(mut self, capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "event channel capacity must be greater than 0"
+        );
+        self.event_channel_capacity = capacity;
+        self
+    }
+
+    /// Sets the capacity of each executor's action channel.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `capacity` is 0.
+    pub fn with_action_channel_capacity(mut self, capacity: usize) -> Self {
+        assert!(
+            capacity > 0,
+            "action channel capacity must be greater than 0"
+        );
+        self.action_channel_capacity = capacity;
+        self
+    }
+
+    /// Sets what happens to events when a strategy falls behind (defaults to dropping the
+    /// oldest events).
+    pub fn with_event_backpressure(mut self, policy: BackpressurePolicy<E>) -> Self {
+        self.event_policy = policy;
+        self
+    }
+
+    /// Sets what happens to actions when an executor falls behind (defaults to dropping the
+    /// oldest actions).
+    pub fn with_action_backpressure(mut self, policy: BackpressurePolicy<A>) -> Self {
+        self.action_policy = policy;
+        self
+    }
+
+    /// Sets how long to wait before restarting a collector whose stream failed or ended, the
+    /// delay doubles after each consecutive failure up to `max`.
+    pub fn with_restart_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.restart_backoff = initial;
+        self.max_restart_backoff = max.max(initial);
+        self
+    }
+
+    /// Returns the engine's backpressure counters, the handle stays valid once the engine runs.
+    pub fn metrics(&self) -> Arc<EngineMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Returns a token that shuts the engine down once cancelled.
+    ///
+    /// Collectors stop first, strategies then process the events left in their channel before
+    /// being [flushed](Strategy::flush), and executors execute the actions left in their channel
+    /// before being [flushed](Executor::flush). The engine's [JoinSet](JoinSet) completes once
+    /// every component has stopped.
+    pub fn shutdown_token(&self) -> CancellationToken {
+        self.shutdown.clone()
+    }
 }
 
 impl<E, A> Default for Engine<E, A> {
@@ -46,79 +267,246 @@ where
 
     /// Adds a strategy to be used by the engine.
     pub fn add_strategy(&mut self, strategy: Box<dyn Strategy<E, A>>) {
-        self.strategies.push(strategy);
+        self.strategies
+            .push((strategy, self.metrics.add_strategy()));
     }
 
     /// Adds an executor to be used by the engine.
     pub fn add_executor(&mut self, executor: Box<dyn Executor<A>>) {
-        self.executors.push(executor);
+        self.executors.push((executor, self.metrics.add_executor()));
     }
 
     /// The core run loop of the engine. This function will spawn a thread for
     /// each collector, strategy, and executor. It will then orchestrate the
     /// data flow between them.
+    ///
+    /// Collectors whose event stream fails to start or ends are restarted with
+    /// exponential backoff until the engine shuts down.
     pub async fn run(self) -> Result<JoinSet<()>, Box<dyn std::error::Error>> {
-        let (event_sender, _): (Sender<E>, _) = broadcast::channel(512);
-        let (action_sender, _): (Sender<A>, _) = broadcast::channel(512);
+        let (event_sender, event_receivers) = channel(
+            self.event_policy,
+            self.event_channel_capacity,
+            self.strategies.iter().map(|(_, metrics)| metrics.clone()),
+        );
+        let (action_sender, action_receivers) = channel(
+            self.action_policy,
+            self.action_channel_capacity,
+            self.executors.iter().map(|(_, metrics)| metrics.clone()),
+        );
 
         let mut set = JoinSet::new();
 
         // Spawn executors in separate threads.
-        for executor in self.executors {
-            let mut receiver = action_sender.subscribe();
+        for ((executor, _), mut receiver) in self.executors.into_iter().zip(action_receivers) {
             set.spawn(async move {
                 info!("starting executor... ");
-                loop {
-                    match receiver.recv().await {
-                        Ok(action) => match executor.execute(action).await {
-                            Ok(_) => {}
-                            Err(e) => error!("error executing action: {}", e),
-                        },
-                        Err(e) => error!("error receiving action: {}", e),
+                while let Some(action) = receiver.recv().await {
+                    match executor.execute(action).await {
+                        Ok(_) => {}
+                        Err(e) => error!("error executing action: {}", e),
                     }
                 }
+
+                // every strategy has stopped
+                if let Err(e) = executor.flush().await {
+                    error!("error flushing executor: {}", e);
+                }
+                info!("executor stopped");
             });
         }
 
         // Spawn strategies in separate threads.
-        for mut strategy in self.strategies {
-            let mut event_receiver = event_sender.subscribe();
+        for ((mut strategy, _), mut event_receiver) in
+            self.strategies.into_iter().zip(event_receivers)
+        {
             let action_sender = action_sender.clone();
             strategy.sync_state().await?;
 
             set.spawn(async move {
                 info!("starting strategy... ");
-                loop {
-                    match event_receiver.recv().await {
-                        Ok(event) => {
-                            if let Some(action) = strategy.process_event(event).await {
-                                match action_sender.send(action) {
-                                    Ok(_) => {}
-                                    Err(e) => error!("error sending action: {}", e),
-                                }
-                            }
-                        }
-                        Err(e) => error!("error receiving event: {}", e),
+                while let Some(event) = event_receiver.recv().await {
+                    for action in strategy.process_event(event).await {
+                        action_sender.send(action).await;
                     }
                 }
+
+                // every collector has stopped
+                for action in strategy.flush().await {
+                    action_sender.send(action).await;
+                }
+                info!("strategy stopped");
             });
         }
 
         // Spawn collectors in separate threads.
         for collector in self.collectors {
             let event_sender = event_sender.clone();
+            let shutdown = self.shutdown.clone();
+            let (initial_backoff, max_backoff) = (self.restart_backoff, self.max_restart_backoff);
             set.spawn(async move {
                 info!("starting collector... ");
-                let mut event_stream = collector.get_event_stream().await.unwrap();
-                while let Some(event) = event_stream.next().await {
-                    match event_sender.send(event) {
-                        Ok(_) => {}
-                        Err(e) => error!("error sending event: {}", e),
-                    }
-                }
+                supervise_collector(
+                    collector,
+                    event_sender,
+                    shutdown,
+                    initial_backoff,
+                    max_backoff,
+                )
+                .await;
+                info!("collector stopped");
             });
         }
 
         Ok(set)
     }
 }
+
+/// Forward a collector's events until the engine shuts down, restarting its event stream with
+/// exponential backoff whenever it fails to start or ends.
+async fn supervise_collector<E: Clone>(
+    collector: Box<dyn Collector<E>>,
+    event_sender: FanOutSender<E>,
+    shutdown: CancellationToken,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+) {
+    let mut backoff = initial_backoff;
+    loop {
+        let event_stream = tokio::select! {
+            _ = shutdown.cancelled() => return,
+            event_stream = collector.get_event_stream() => event_stream,
+        };
+
+        match event_stream {
+            Ok(mut event_stream) => loop {
+                let event = tokio::select! {
+                    _ = shutdown.cancelled() => return,
+                    event = event_stream.next() => event,
+                };
+                match event {
+                    Some(event) => {
+                        backoff = initial_backoff;
+                        event_sender.send(event).await;
+                    }
+                    None => {
+                        warn!("collector stream ended, restarting in {:?}", backoff);
+                        break;
+                    }
+                }
+            },
+            Err(e) => error!("error starting collector: {}, retrying in {:?}", e, backoff),
+        }
+
+        tokio::select! {
+            _ = shutdown.cancelled() => return,
+            _ = tokio::time::sleep(backoff) => {}
+        }
+        backoff = (backoff * 2).min(max_backoff);
+    }
+}
+
+/// Creates a channel that fans each item out to one receiver per `metrics`.
+///
+/// Items the policy lets drop go through a broadcast channel, the others through a bounded mpsc
+/// channel per receiver so that the sender can wait on the slowest receiver.
+fn channel<T: Clone>(
+    policy: BackpressurePolicy<T>,
+    capacity: usize,
+    metrics: impl Iterator<Item = Arc<ChannelMetrics>>,
+) -> (FanOutSender<T>, Vec<FanOutReceiver<T>>) {
+    let (broadcast, _) = broadcast::channel(capacity);
+    let mut blocking = vec![];
+    let mut receivers = vec![];
+
+    for metrics in metrics {
+        let (sender, receiver) = mpsc::channel(capacity);
+        blocking.push((sender, metrics.clone()));
+        receivers.push(FanOutReceiver {
+            broadcast: broadcast.subscribe(),
+            blocking: receiver,
+            metrics,
+        });
+    }
+
+    let sender = FanOutSender {
+        policy,
+        broadcast,
+        blocking: Arc::new(blocking),
+    };
+    (sender, receivers)
+}
+
+/// Sending half of [channel](channel).
+struct FanOutSender<T> {
+    policy: BackpressurePolicy<T>,
+    broadcast: broadcast::Sender<T>,
+    blocking: Arc<Vec<(mpsc::Sender<T>, Arc<ChannelMetrics>)>>,
+}
+
+impl<T> Clone for FanOutSender<T> {
+    fn clone(&self) -> Self {
+        Self {
+            policy: self.policy.clone(),
+            broadcast: self.broadcast.clone(),
+            blocking: self.blocking.clone(),
+        }
+    }
+}
+
+impl<T: Clone> FanOutSender<T> {
+    /// Send `item` to every receiver, waiting for room if the policy says so.
+    async fn send(&self, item: T) {
+        if !self.policy.blocks(&item) {
+            if let Err(e) = self.broadcast.send(item) {
+                error!("error sending to channel: {}", e);
+            }
+            return;
+        }
+
+        for (sender, metrics) in self.blocking.iter() {
+            match sender.try_send(item.clone()) {
+                Ok(_) => {}
+                Err(TrySendError::Full(item)) => {
+                    metrics.blocked.fetch_add(1, Ordering::Relaxed);
+                    if let Err(e) = sender.send(item).await {
+                        error!("error sending to channel: {}", e);
+                    }
+                }
+                Err(TrySendError::Closed(_)) => error!("error sending to channel: receiver closed"),
+            }
+        }
+    }
+}
+
+/// Receiving half of [channel](channel).
+struct FanOutReceiver<T> {
+    broadcast: broadcast::Receiver<T>,
+    blocking: mpsc::Receiver<T>,
+    metrics: Arc<ChannelMetrics>,
+}
+
+impl<T: Clone> FanOutReceiver<T> {
+    /// Receive the next item, items that can't be dropped first. Returns `None` once every
+    /// sender is gone.
+    async fn recv(&mut self) -> Option<T> {
+        loop {
+            let received = tokio::select! {
+                biased;
+                Some(item) = self.blocking.recv() => Ok(item),
+                item = self.broadcast.recv() => item,
+            };
+
+            match received {
+                Ok(item) => {
+                    self.metrics.received.fetch_add(1, Ordering::Relaxed);
+                    return Some(item);
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    self.metrics.lagged.fetch_add(skipped, Ordering::Relaxed);
+                    warn!("receiver fell behind, skipped {} items", skipped);
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Result};
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{Address, I256, U256},
+};
+use ethers_flashbots::{BundleRequest, FlashbotsMiddleware};
+use reqwest::Url;
+use serde::Serialize;
+use tracing::info;
+
+use crate::executors::flashbots_executor::BundleSubmission;
+
+/// What a strategy expects to happen when its bundle lands, used to validate simulations.
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleExpectation {
+    /// The account that signs the strategy's txs in the bundle.
+    pub searcher: Address,
+    /// Amount paid to coinbase by the searcher's txs.
+    pub coinbase_payment: U256,
+    /// Searcher's profit after paying for gas (can be negative when overpaying on purpose).
+    pub profit: I256,
+}
+
+/// Result of simulating a bundle, measured for the searcher's txs only.
+#[derive(Debug, Clone)]
+pub struct BundleSimulation {
+    /// Amount paid to coinbase by the searcher's txs.
+    pub coinbase_payment: U256,
+    /// Searcher's profit after paying for gas, `None` if the simulator can't measure it.
+    pub profit: Option<I256>,
+}
+
+impl BundleExpectation {
+    /// Errors with the reason if `simulation` deviates from the expectation by more than
+    /// `tolerance_bps` basis points.
+    pub fn check(&self, simulation: &BundleSimulation, tolerance_bps: u64) -> Result<()> {
+        let expected = I256::from_raw(self.coinbase_payment);
+        let simulated = I256::from_raw(simulation.coinbase_payment);
+        ensure!(
+            is_within_tolerance(expected, simulated, tolerance_bps),
+            "simulated coinbase payment {} differs from expected {}",
+            simulation.coinbase_payment,
+            self.coinbase_payment
+        );
+
+        if let Some(profit) = simulation.profit {
+            ensure!(
+                is_within_tolerance(self.profit, profit, tolerance_bps),
+                "simulated profit {} differs from expected {}",
+                profit,
+                self.profit
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns true if `actual` is within `tolerance_bps` basis points of `expected`.
+fn is_within_tolerance(expected: I256, actual: I256, tolerance_bps: u64) -> bool {
+    let deviation = (actual - expected).unsigned_abs();
+    deviation * U256::from(10_000) <= expected.unsigned_abs() * U256::from(tolerance_bps)
+}
+
+/// Simulate the bundle, errors with the reason it shouldn't be sent. Bundles without an
+/// expectation are never simulated.
+pub async fn check_submission(
+    simulator: &dyn BundleSimulator,
+    submission: &BundleSubmission,
+    tolerance_bps: u64,
+) -> Result<()> {
+    let expectation = match &submission.expectation {
+        Some(expectation) => expectation,
+        None => return Ok(()),
+    };
+
+    let simulation = simulator
+        .simulate(&submission.request, expectation.searcher)
+        .await
+        .map_err(|e| anyhow!("simulation failed: {:?}", e))?;
+
+    expectation.check(&simulation, tolerance_bps)?;
+    info!("Simulation Result: {:?}", simulation);
+    Ok(())
+}
+
+/// BundleSimulator trait, which simulates a bundle before it is submitted.
+#[async_trait]
+pub trait BundleSimulator: Send + Sync {
+    /// Simulate a bundle and measure the outcome of the txs sent by `searcher`.
+    async fn simulate(&self, bundle: &BundleRequest, searcher: Address)
+        -> Result<BundleSimulation>;
+}
+
+/// A bundle simulator that uses a relay's `eth_callBundle` endpoint.
+pub struct RelayBundleSimulator<M, S> {
+    /// The Flashbots middleware used for simulations.
+    fb_client: FlashbotsMiddleware<Arc<M>, S>,
+}
+
+impl<M: Middleware, S: Signer> RelayBundleSimulator<M, S> {
+    pub fn new(client: Arc<M>, relay_signer: S, relay_url: impl Into<Url>) -> Self {
+        let fb_client = FlashbotsMiddleware::new(client, relay_url, relay_signer);
+        Self { fb_client }
+    }
+}
+
+#[async_trait]
+impl<M, S> BundleSimulator for RelayBundleSimulator<M, S>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    /// Simulate a bundle using `eth_callBundle`, profit is not measured.
+    async fn simulate(
+        &self,
+        bundle: &BundleRequest,
+        searcher: Address,
+    ) -> Result<BundleSimulation> {
+        let simulated_bundle = self
+            .fb_client
+            .simulate_bundle(bundle)
+            .await
+            .map_err(|e| anyhow!("eth_callBundle failed: {:?}", e))?;
+
+        let mut coinbase_payment = U256::zero();
+        for tx in simulated_bundle
+            .transactions
+            .iter()
+            .filter(|tx| tx.from == searcher)
+        {
+            ensure!(
+                tx.error.is_none() && tx.revert.is_none(),
+                "tx {:?} failed in simulation: {:?} {:?}",
+                tx.hash,
+                tx.error,
+                tx.revert
+            );
+            coinbase_payment += tx.coinbase_diff;
+        }
+
+        Ok(BundleSimulation {
+            coinbase_payment,
+            profit: None,
+        })
+    }
+}
@@ -1,29 +1,127 @@
 use std::sync::Arc;
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use ethers::{providers::Middleware, signers::Signer};
+use ethers::{providers::Middleware, signers::Signer, types::H256};
 use ethers_flashbots::{BundleRequest, FlashbotsMiddleware};
 use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
 use tracing::{error, info};
+use uuid::Uuid;
 
+use crate::executors::bundle_simulator::{check_submission, BundleExpectation, BundleSimulator};
 use crate::types::Executor;
 
 /// A Flashbots executor that sends transactions to the Flashbots relay.
 pub struct FlashbotsExecutor<M, S> {
     /// The Flashbots middleware.
     fb_client: FlashbotsMiddleware<Arc<M>, S>,
+    /// Optional simulator used to validate bundles before sending them.
+    simulator: Option<Box<dyn BundleSimulator>>,
+    /// How far (in basis points) a simulation may deviate from the expected outcome.
+    tolerance_bps: u64,
+    /// Optional channel that what happened to each bundle is reported on.
+    reports: Option<broadcast::Sender<SubmissionReport>>,
+}
+
+/// A bundle request along with what the strategy expects from it.
+#[derive(Debug, Clone, Serialize)]
+pub struct BundleSubmission {
+    /// The bundle to send.
+    pub request: BundleRequest,
+    /// Expected outcome, bundles without one are never simulated.
+    pub expectation: Option<BundleExpectation>,
+    /// Bundles sent under the same uuid replace each other, and can be cancelled by uuid.
+    pub replacement_uuid: Option<Uuid>,
+}
+
+impl From<BundleRequest> for BundleSubmission {
+    fn from(request: BundleRequest) -> Self {
+        Self {
+            request,
+            expectation: None,
+            replacement_uuid: None,
+        }
+    }
+}
+
+/// What an executor did with a bundle, reported back so that the strategy knows which bundles
+/// never reached a builder.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmissionReport {
+    /// Uuid the bundle was sent under (if any).
+    pub replacement_uuid: Option<Uuid>,
+    /// Hashes of the bundle's transactions, in bundle order.
+    pub transaction_hashes: Vec<H256>,
+    pub status: SubmissionStatus,
+}
+
+/// Whether a bundle was sent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SubmissionStatus {
+    /// At least one builder accepted the bundle.
+    Sent,
+    /// The bundle's simulation deviated from its expectation, it was not sent.
+    Dropped(String),
+    /// No builder accepted the bundle.
+    Failed(String),
+}
+
+impl SubmissionReport {
+    pub fn new(submission: &BundleSubmission, status: SubmissionStatus) -> Self {
+        Self {
+            replacement_uuid: submission.replacement_uuid,
+            transaction_hashes: submission.request.transaction_hashes(),
+            status,
+        }
+    }
+}
+
+/// Send a report if a channel is set, reports are lost when nothing is subscribed.
+pub(crate) fn report(
+    reports: &Option<broadcast::Sender<SubmissionReport>>,
+    submission: &BundleSubmission,
+    status: SubmissionStatus,
+) {
+    if let Some(reports) = reports {
+        let _ = reports.send(SubmissionReport::new(submission, status));
+    }
 }
 
 /// A bundle of transactions to send to the Flashbots relay.
-/// Sending vec of bundle request because multiple actions per event not supported
-/// See issue: https://github.com/paradigmxyz/artemis/issues/34
-pub type FlashbotsBundle = Vec<BundleRequest>;
+pub type FlashbotsBundle = BundleSubmission;
+
+/// Replacement uuid of a bundle to cancel.
+pub type FlashbotsCancellation = Uuid;
 
 impl<M: Middleware, S: Signer> FlashbotsExecutor<M, S> {
     pub fn new(client: Arc<M>, relay_signer: S, relay_url: impl Into<Url>) -> Self {
         let fb_client = FlashbotsMiddleware::new(client, relay_url, relay_signer);
-        Self { fb_client }
+        Self {
+            fb_client,
+            simulator: None,
+            tolerance_bps: 0,
+            reports: None,
+        }
+    }
+
+    /// Simulate bundles before sending them, bundles whose simulation deviates from their
+    /// expectation by more than `tolerance_bps` basis points are dropped.
+    pub fn with_simulator(
+        mut self,
+        simulator: Box<dyn BundleSimulator>,
+        tolerance_bps: u64,
+    ) -> Self {
+        self.simulator = Some(simulator);
+        self.tolerance_bps = tolerance_bps;
+        self
+    }
+
+    /// Report what happened to each bundle on `reports`.
+    pub fn with_reports(mut self, reports: broadcast::Sender<SubmissionReport>) -> Self {
+        self.reports = Some(reports);
+        self
     }
 }
 
@@ -35,25 +133,119 @@ where
     S: Signer + 'static,
 {
     /// Send a bundle to transactions to the Flashbots relay.
-    async fn execute(&self, action: FlashbotsBundle) -> Result<()> {
-        for bundle in action {
-            //// Simulate bundle.
-            //let simulated_bundle = self.fb_client.simulate_bundle(&bundle).await;
-
-            //match simulated_bundle {
-            //    Ok(res) => info!("Simulation Result: {:?}", res),
-            //    Err(simulate_error) => error!("Error simulating bundle: {:?}", simulate_error),
-            //}
-
-            // Send bundle.
-            let pending_bundle = self.fb_client.send_bundle(&bundle).await;
-
-            match pending_bundle {
-                Ok(res) => info!("Simulation Result: {:?}", res.await),
-                Err(send_error) => error!("Error sending bundle: {:?}", send_error),
+    async fn execute(&self, submission: FlashbotsBundle) -> Result<()> {
+        // Simulate bundle.
+        if let Some(simulator) = &self.simulator {
+            if let Err(e) =
+                check_submission(simulator.as_ref(), &submission, self.tolerance_bps).await
+            {
+                error!("Dropping bundle, {}", e);
+                report(
+                    &self.reports,
+                    &submission,
+                    SubmissionStatus::Dropped(e.to_string()),
+                );
+                return Ok(());
+            }
+        }
+
+        // Send bundle, replacing any bundle previously sent under the same uuid.
+        if let Some(uuid) = submission.replacement_uuid {
+            let status =
+                match send_replaceable_bundle(&self.fb_client, &submission.request, uuid).await {
+                    Ok(_) => {
+                        info!("Sent bundle with replacement uuid {}", uuid);
+                        SubmissionStatus::Sent
+                    }
+                    Err(send_error) => {
+                        error!("Error sending bundle: {:?}", send_error);
+                        SubmissionStatus::Failed(send_error.to_string())
+                    }
+                };
+            report(&self.reports, &submission, status);
+            return Ok(());
+        }
+
+        let pending_bundle = self.fb_client.send_bundle(&submission.request).await;
+
+        match pending_bundle {
+            Ok(res) => {
+                report(&self.reports, &submission, SubmissionStatus::Sent);
+                info!("Simulation Result: {:?}", res.await)
+            }
+            Err(send_error) => {
+                error!("Error sending bundle: {:?}", send_error);
+                report(
+                    &self.reports,
+                    &submission,
+                    SubmissionStatus::Failed(format!("{:?}", send_error)),
+                );
             }
         }
 
         Ok(())
     }
 }
+
+#[async_trait]
+impl<M, S> Executor<FlashbotsCancellation> for FlashbotsExecutor<M, S>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    /// Cancel a bundle by its replacement uuid.
+    async fn execute(&self, uuid: FlashbotsCancellation) -> Result<()> {
+        match cancel_bundle(&self.fb_client, uuid).await {
+            Ok(_) => info!("Cancelled bundle with replacement uuid {}", uuid),
+            Err(e) => error!("Error cancelling bundle: {:?}", e),
+        }
+
+        Ok(())
+    }
+}
+
+/// Send a bundle using `eth_sendBundle` with a `replacementUuid`, the relay replaces any bundle
+/// previously sent under the same uuid by the same signer.
+pub(crate) async fn send_replaceable_bundle<M, S>(
+    fb_client: &FlashbotsMiddleware<Arc<M>, S>,
+    bundle: &BundleRequest,
+    replacement_uuid: Uuid,
+) -> Result<()>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    let mut params = serde_json::to_value(bundle)?;
+    params["replacementUuid"] = serde_json::Value::String(replacement_uuid.to_string());
+
+    fb_client
+        .relay()
+        .request::<_, serde_json::Value>("eth_sendBundle", [params])
+        .await
+        .map_err(|e| anyhow!("eth_sendBundle failed: {:?}", e))?;
+
+    Ok(())
+}
+
+/// Cancel a bundle using `eth_cancelBundle`.
+pub(crate) async fn cancel_bundle<M, S>(
+    fb_client: &FlashbotsMiddleware<Arc<M>, S>,
+    replacement_uuid: Uuid,
+) -> Result<()>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    let params = serde_json::json!({ "replacementUuid": replacement_uuid.to_string() });
+
+    fb_client
+        .relay()
+        .request::<_, serde_json::Value>("eth_cancelBundle", [params])
+        .await
+        .map_err(|e| anyhow!("eth_cancelBundle failed: {:?}", e))?;
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    signers::Signer,
+    types::{Bytes, H256, U64},
+};
+use ethers_flashbots::FlashbotsMiddleware;
+use reqwest::Url;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tracing::{error, info};
+
+use crate::types::Executor;
+
+/// A bundle that backruns a tx only known through its MEV-Share hint.
+#[derive(Debug, Clone, Serialize)]
+pub struct MevShareBundle {
+    /// Hash of the hinted tx, placed first in the bundle by the matchmaker.
+    pub victim_hash: H256,
+    /// Raw signed txs that follow the hinted tx, in bundle order.
+    pub txs: Vec<Bytes>,
+    /// First block the bundle is valid for.
+    pub block: U64,
+    /// Last block the bundle is valid for.
+    pub max_block: U64,
+}
+
+impl MevShareBundle {
+    /// `mev_sendBundle` params, none of our txs are allowed to revert.
+    pub fn params(&self) -> Value {
+        let mut body = vec![json!({ "hash": self.victim_hash })];
+        body.extend(
+            self.txs
+                .iter()
+                .map(|tx| json!({ "tx": tx, "canRevert": false })),
+        );
+
+        json!({
+            "version": "v0.1",
+            "inclusion": { "block": self.block, "maxBlock": self.max_block },
+            "body": body,
+        })
+    }
+}
+
+/// An executor that sends backrun bundles to the MEV-Share matchmaker using `mev_sendBundle`.
+pub struct MevShareExecutor<M, S> {
+    /// Signs requests like the Flashbots relay expects.
+    fb_client: FlashbotsMiddleware<Arc<M>, S>,
+}
+
+impl<M: Middleware, S: Signer> MevShareExecutor<M, S> {
+    pub fn new(client: Arc<M>, relay_signer: S, relay_url: impl Into<Url>) -> Self {
+        Self {
+            fb_client: FlashbotsMiddleware::new(client, relay_url, relay_signer),
+        }
+    }
+}
+
+#[async_trait]
+impl<M, S> Executor<MevShareBundle> for MevShareExecutor<M, S>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    /// Send a backrun bundle to the matchmaker.
+    async fn execute(&self, bundle: MevShareBundle) -> Result<()> {
+        let response = self
+            .fb_client
+            .relay()
+            .request::<_, Value>("mev_sendBundle", [bundle.params()])
+            .await
+            .map_err(|e| anyhow!("mev_sendBundle failed: {:?}", e));
+
+        match response {
+            Ok(response) => info!(
+                "Sent backrun of {:?}: {}",
+                bundle.victim_hash, response["bundleHash"]
+            ),
+            Err(e) => error!("Error sending backrun of {:?}: {:?}", bundle.victim_hash, e),
+        }
+
+        Ok(())
+    }
+}
@@ -2,5 +2,23 @@
 //! executing them in different domains. For example, an executor might take a
 //! `SubmitTx` action and submit it to the mempool.
 
+/// This module contains simulators used to validate bundles before they are submitted.
+pub mod bundle_simulator;
+
 /// This executor submits transactions to the flashbots relay.
 pub mod flashbots_executor;
+
+/// This executor submits bundles to multiple builders concurrently.
+pub mod multi_builder_executor;
+
+/// This executor submits backrun bundles to the MEV-Share matchmaker.
+pub mod mev_share_executor;
+
+/// This executor simulates bundles once their target block is mined instead of sending them.
+pub mod paper_trading_executor;
+
+/// This executor records every action to a capture file.
+pub mod recorder_executor;
+
+/// This executor posts notifications to Discord or generic webhooks.
+pub mod webhook_executor;
@@ -0,0 +1,296 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{providers::Middleware, signers::Signer};
+use ethers_flashbots::FlashbotsMiddleware;
+use reqwest::Url;
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::executors::bundle_simulator::{check_submission, BundleSimulator};
+use crate::executors::flashbots_executor::{
+    cancel_bundle, report, send_replaceable_bundle, BundleSubmission, FlashbotsBundle,
+    FlashbotsCancellation, SubmissionReport, SubmissionStatus,
+};
+use crate::types::Executor;
+
+/// Configuration for a single builder endpoint.
+#[derive(Debug, Clone)]
+pub struct BuilderConfig<S> {
+    /// Name used in logs and stats.
+    pub name: String,
+    /// The builder's bundle endpoint.
+    pub url: Url,
+    /// Signer used for the builder's auth header.
+    pub signer: S,
+    /// How long to wait for the builder to accept a bundle.
+    pub timeout: Duration,
+    /// Disabled builders never receive bundles.
+    pub enabled: bool,
+}
+
+/// Submission statistics for a single builder.
+#[derive(Debug, Clone, Default)]
+pub struct BuilderStats {
+    /// Number of bundles sent to the builder.
+    pub submitted: u64,
+    /// Number of bundles the builder accepted.
+    pub accepted: u64,
+    /// Number of bundles the builder rejected.
+    pub rejected: u64,
+    /// Number of submissions that hit the builder's timeout.
+    pub timed_out: u64,
+    /// Sum of the latencies of accepted submissions.
+    pub total_latency: Duration,
+}
+
+impl BuilderStats {
+    /// Average latency of accepted submissions.
+    pub fn average_latency(&self) -> Duration {
+        match self.accepted {
+            0 => Duration::ZERO,
+            accepted => self.total_latency / accepted as u32,
+        }
+    }
+}
+
+/// Outcome of sending one bundle to one builder.
+#[derive(Debug, Clone)]
+pub enum SubmissionOutcome {
+    Accepted(Duration),
+    Rejected(String),
+    TimedOut,
+}
+
+/// Called with each bundle, builder name and outcome after a submission.
+pub type SubmissionHook = Box<dyn Fn(&BundleSubmission, &str, &SubmissionOutcome) + Send + Sync>;
+
+/// A builder endpoint along with its client.
+struct Builder<M, S> {
+    name: String,
+    timeout: Duration,
+    fb_client: FlashbotsMiddleware<Arc<M>, S>,
+}
+
+/// An executor that fans each bundle out to multiple builders concurrently.
+pub struct MultiBuilderExecutor<M, S> {
+    /// Enabled builders.
+    builders: Vec<Builder<M, S>>,
+    /// Per builder submission stats.
+    stats: Arc<Mutex<HashMap<String, BuilderStats>>>,
+    /// Optional simulator used to validate bundles before sending them.
+    simulator: Option<Box<dyn BundleSimulator>>,
+    /// How far (in basis points) a simulation may deviate from the expected outcome.
+    tolerance_bps: u64,
+    /// Optional hook notified of every submission outcome.
+    submission_hook: Option<SubmissionHook>,
+    /// Optional channel that what happened to each bundle is reported on.
+    reports: Option<broadcast::Sender<SubmissionReport>>,
+}
+
+impl<M: Middleware, S: Signer> MultiBuilderExecutor<M, S> {
+    pub fn new(client: Arc<M>, builders: Vec<BuilderConfig<S>>) -> Self {
+        let builders = builders
+            .into_iter()
+            .filter(|builder| builder.enabled)
+            .map(|builder| Builder {
+                fb_client: FlashbotsMiddleware::new(client.clone(), builder.url, builder.signer),
+                name: builder.name,
+                timeout: builder.timeout,
+            })
+            .collect::<Vec<_>>();
+
+        let stats = builders
+            .iter()
+            .map(|builder| (builder.name.clone(), BuilderStats::default()))
+            .collect();
+
+        Self {
+            builders,
+            stats: Arc::new(Mutex::new(stats)),
+            simulator: None,
+            tolerance_bps: 0,
+            submission_hook: None,
+            reports: None,
+        }
+    }
+
+    /// Simulate bundles before sending them, bundles whose simulation deviates from their
+    /// expectation by more than `tolerance_bps` basis points are dropped.
+    pub fn with_simulator(
+        mut self,
+        simulator: Box<dyn BundleSimulator>,
+        tolerance_bps: u64,
+    ) -> Self {
+        self.simulator = Some(simulator);
+        self.tolerance_bps = tolerance_bps;
+        self
+    }
+
+    /// Notify `hook` of every submission outcome, e.g. to persist them.
+    pub fn with_submission_hook(mut self, hook: SubmissionHook) -> Self {
+        self.submission_hook = Some(hook);
+        self
+    }
+
+    /// Report what happened to each bundle on `reports`, a bundle counts as sent once any builder
+    /// accepts it.
+    pub fn with_reports(mut self, reports: broadcast::Sender<SubmissionReport>) -> Self {
+        self.reports = Some(reports);
+        self
+    }
+
+    /// Returns a handle to the per builder stats that stays valid after the executor is moved
+    /// into the engine.
+    pub fn stats(&self) -> Arc<Mutex<HashMap<String, BuilderStats>>> {
+        self.stats.clone()
+    }
+}
+
+impl<M, S> MultiBuilderExecutor<M, S>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    /// Send a bundle to a single builder, waiting at most the builder's timeout.
+    async fn submit(
+        &self,
+        builder: &Builder<M, S>,
+        submission: &BundleSubmission,
+    ) -> SubmissionOutcome {
+        let start = Instant::now();
+        let send = async {
+            match submission.replacement_uuid {
+                Some(uuid) => {
+                    send_replaceable_bundle(&builder.fb_client, &submission.request, uuid).await
+                }
+                None => builder
+                    .fb_client
+                    .send_bundle(&submission.request)
+                    .await
+                    .map(|_| ())
+                    .map_err(|e| anyhow!("{:?}", e)),
+            }
+        };
+
+        match tokio::time::timeout(builder.timeout, send).await {
+            Ok(Ok(_)) => SubmissionOutcome::Accepted(start.elapsed()),
+            Ok(Err(e)) => SubmissionOutcome::Rejected(format!("{:?}", e)),
+            Err(_) => SubmissionOutcome::TimedOut,
+        }
+    }
+
+    /// Record an outcome against the builder's stats.
+    fn record(&self, builder: &str, outcome: &SubmissionOutcome) {
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats.entry(builder.to_string()).or_default();
+        stats.submitted += 1;
+        match outcome {
+            SubmissionOutcome::Accepted(latency) => {
+                stats.accepted += 1;
+                stats.total_latency += *latency;
+            }
+            SubmissionOutcome::Rejected(_) => stats.rejected += 1,
+            SubmissionOutcome::TimedOut => stats.timed_out += 1,
+        }
+    }
+}
+
+#[async_trait]
+impl<M, S> Executor<FlashbotsBundle> for MultiBuilderExecutor<M, S>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    /// Send a bundle to all enabled builders concurrently.
+    async fn execute(&self, submission: FlashbotsBundle) -> Result<()> {
+        // Simulate bundle once for all builders.
+        if let Some(simulator) = &self.simulator {
+            if let Err(e) =
+                check_submission(simulator.as_ref(), &submission, self.tolerance_bps).await
+            {
+                error!("Dropping bundle, {}", e);
+                report(
+                    &self.reports,
+                    &submission,
+                    SubmissionStatus::Dropped(e.to_string()),
+                );
+                return Ok(());
+            }
+        }
+
+        let outcomes = futures::future::join_all(
+            self.builders
+                .iter()
+                .map(|builder| self.submit(builder, &submission)),
+        )
+        .await;
+
+        let mut failures = vec![];
+        for (builder, outcome) in self.builders.iter().zip(outcomes) {
+            match &outcome {
+                SubmissionOutcome::Accepted(latency) => {
+                    info!("{} accepted bundle in {:?}", builder.name, latency)
+                }
+                SubmissionOutcome::Rejected(e) => {
+                    error!("{} rejected bundle: {}", builder.name, e);
+                    failures.push(format!("{} rejected the bundle: {}", builder.name, e));
+                }
+                SubmissionOutcome::TimedOut => {
+                    warn!("{} timed out after {:?}", builder.name, builder.timeout);
+                    failures.push(format!("{} timed out", builder.name));
+                }
+            }
+            self.record(&builder.name, &outcome);
+            if let Some(hook) = &self.submission_hook {
+                hook(&submission, &builder.name, &outcome);
+            }
+        }
+
+        let status = if self.builders.is_empty() {
+            SubmissionStatus::Failed("no builder is enabled".to_string())
+        } else if failures.len() < self.builders.len() {
+            SubmissionStatus::Sent
+        } else {
+            SubmissionStatus::Failed(failures.join(", "))
+        };
+        report(&self.reports, &submission, status);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<M, S> Executor<FlashbotsCancellation> for MultiBuilderExecutor<M, S>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+    S: Signer + 'static,
+{
+    /// Cancel a bundle on all enabled builders concurrently.
+    async fn execute(&self, uuid: FlashbotsCancellation) -> Result<()> {
+        let results = futures::future::join_all(self.builders.iter().map(|builder| {
+            tokio::time::timeout(builder.timeout, cancel_bundle(&builder.fb_client, uuid))
+        }))
+        .await;
+
+        for (builder, result) in self.builders.iter().zip(results) {
+            match result {
+                Ok(Ok(_)) => info!("{} cancelled bundle {}", builder.name, uuid),
+                Ok(Err(e)) => {
+                    error!("{} failed to cancel bundle {}: {:?}", builder.name, uuid, e)
+                }
+                Err(_) => warn!("{} timed out cancelling bundle {}", builder.name, uuid),
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, Weak,
+    },
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    types::{Address, Block, Bytes, Transaction, H256, I256, U256, U64},
+    utils::rlp::{Decodable, Rlp},
+};
+use ethers_flashbots::BundleRequest;
+use tokio::sync::oneshot;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::executors::bundle_simulator::BundleSimulator;
+use crate::executors::flashbots_executor::{
+    BundleSubmission, FlashbotsBundle, FlashbotsCancellation,
+};
+use crate::executors::mev_share_executor::MevShareBundle;
+use crate::types::Executor;
+
+/// Running totals of the bundles the paper trading executor has simulated.
+#[derive(Debug, Clone, Default)]
+pub struct PaperTradingStats {
+    /// Bundles simulated once their target block arrived.
+    pub simulated: u64,
+    /// Bundles whose simulation failed (reverted or errored).
+    pub failed: u64,
+    /// Bundles never simulated because they were replaced or cancelled before their target block.
+    pub replaced: u64,
+    /// MEV-Share backruns never simulated because their hinted tx wasn't mined by their target
+    /// block.
+    pub unmatched: u64,
+    /// Sum of the simulated profit of successful bundles.
+    pub profit: I256,
+    /// Sum of the simulated coinbase payments of successful bundles.
+    pub coinbase_payment: U256,
+}
+
+/// State shared with the tasks waiting on target blocks.
+struct PaperTrader<M> {
+    client: Arc<M>,
+    simulator: Box<dyn BundleSimulator>,
+    stats: Arc<Mutex<PaperTradingStats>>,
+    /// Id of the latest submission sent under each replacement uuid.
+    latest: Mutex<HashMap<Uuid, u64>>,
+    /// Bundles waiting on their target block, keyed by block number.
+    waiting: Mutex<BTreeMap<u64, Vec<oneshot::Sender<Block<H256>>>>>,
+    /// Set once the block poller has been spawned.
+    polling: AtomicBool,
+    poll_interval: Duration,
+}
+
+/// An executor that never sends bundles. Each bundle is simulated once its target block has been
+/// mined, at the top of that block, and its hypothetical pnl is logged. MEV-Share backruns are
+/// simulated behind their hinted tx, which can only be fetched once it has been mined.
+///
+/// Clones share their state, so that one clone can handle bundles and another cancellations.
+pub struct PaperTradingExecutor<M> {
+    trader: Arc<PaperTrader<M>>,
+    /// Used to tell replaced submissions apart.
+    next_id: Arc<AtomicU64>,
+}
+
+impl<M> Clone for PaperTradingExecutor<M> {
+    fn clone(&self) -> Self {
+        Self {
+            trader: self.trader.clone(),
+            next_id: self.next_id.clone(),
+        }
+    }
+}
+
+impl<M: Middleware + 'static> PaperTradingExecutor<M> {
+    pub fn new(client: Arc<M>, simulator: Box<dyn BundleSimulator>) -> Self {
+        Self {
+            trader: Arc::new(PaperTrader {
+                client,
+                simulator,
+                stats: Arc::new(Mutex::new(PaperTradingStats::default())),
+                latest: Mutex::new(HashMap::new()),
+                waiting: Mutex::new(BTreeMap::new()),
+                polling: AtomicBool::new(false),
+                poll_interval: Duration::from_secs(1),
+            }),
+            next_id: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns a handle to the paper trading stats that stays valid after the executor is moved
+    /// into the engine.
+    pub fn stats(&self) -> Arc<Mutex<PaperTradingStats>> {
+        self.trader.stats.clone()
+    }
+
+    /// Spawn the block poller, unless it is already running.
+    fn start_polling(&self) {
+        if !self.trader.polling.swap(true, Ordering::Relaxed) {
+            tokio::spawn(poll_blocks(Arc::downgrade(&self.trader)));
+        }
+    }
+}
+
+impl<M: Middleware + 'static> PaperTrader<M> {
+    /// Wait for the bundle's target block then simulate the bundle on top of it, unless the bundle
+    /// was replaced or cancelled in the meantime.
+    async fn trade(&self, id: u64, submission: BundleSubmission) {
+        let (target_block, searcher) = match (
+            submission.request.block(),
+            submission.expectation.as_ref().map(|e| e.searcher),
+        ) {
+            (Some(target_block), Some(searcher)) => (target_block, searcher),
+            _ => {
+                warn!("Paper trading skipped bundle without target block or expectation");
+                return;
+            }
+        };
+
+        let block = match self.wait_for_block(target_block.as_u64()).await {
+            Some(block) => block,
+            None => return,
+        };
+
+        if let Some(uuid) = submission.replacement_uuid {
+            let mut latest = self.latest.lock().unwrap();
+            if latest.get(&uuid) != Some(&id) {
+                self.stats.lock().unwrap().replaced += 1;
+                return;
+            }
+            latest.remove(&uuid);
+        }
+
+        self.simulate(target_block, submission.request, searcher, block)
+            .await;
+    }
+
+    /// Wait for the backrun's target block then simulate it behind its hinted tx.
+    async fn trade_backrun(&self, bundle: MevShareBundle) {
+        let searcher = match bundle.txs.first().map(recover_sender) {
+            Some(Ok(searcher)) => searcher,
+            Some(Err(e)) => {
+                warn!("Paper trading skipped backrun: {:?}", e);
+                return;
+            }
+            None => {
+                warn!("Paper trading skipped backrun without txs");
+                return;
+            }
+        };
+
+        let block = match self.wait_for_block(bundle.block.as_u64()).await {
+            Some(block) => block,
+            None => return,
+        };
+
+        let victim = match self.client.get_transaction(bundle.victim_hash).await {
+            Ok(Some(victim)) if victim.block_number.is_some() => victim,
+            Ok(_) => {
+                self.stats.lock().unwrap().unmatched += 1;
+                info!(
+                    "[PAPER] block {}: hinted tx {:?} wasn't mined, backrun not simulated",
+                    bundle.block, bundle.victim_hash
+                );
+                return;
+            }
+            Err(e) => {
+                error!(
+                    "Paper trading failed to get hinted tx {:?}: {:?}",
+                    bundle.victim_hash, e
+                );
+                return;
+            }
+        };
+
+        let mut request = BundleRequest::new()
+            .push_transaction(victim)
+            .set_block(bundle.block)
+            .set_simulation_block(bundle.block - 1);
+        for tx in bundle.txs {
+            request = request.push_transaction(tx);
+        }
+
+        self.simulate(bundle.block, request, searcher, block).await;
+    }
+
+    /// Simulate `request` at the top of the mined `block` and add its pnl to the stats.
+    async fn simulate(
+        &self,
+        target_block: U64,
+        request: BundleRequest,
+        searcher: Address,
+        block: Block<H256>,
+    ) {
+        // hypothetical position is at the top of the real block
+        let request = request.set_simulation_timestamp(block.timestamp.as_u64());
+        let tx_hashes = request.transaction_hashes();
+        let landed = tx_hashes
+            .iter()
+            .filter(|hash| block.transactions.contains(hash))
+            .count();
+
+        let simulation = self.simulator.simulate(&request, searcher).await;
+
+        let mut stats = self.stats.lock().unwrap();
+        stats.simulated += 1;
+        match simulation {
+            Ok(simulation) => {
+                let profit = simulation.profit.unwrap_or_default();
+                stats.profit += profit;
+                stats.coinbase_payment += simulation.coinbase_payment;
+                info!(
+                    "[PAPER] block {}: profit {} coinbase payment {} ({}/{} bundle txs landed in the real block), total profit {}",
+                    target_block,
+                    profit,
+                    simulation.coinbase_payment,
+                    landed,
+                    tx_hashes.len(),
+                    stats.profit
+                );
+            }
+            Err(e) => {
+                stats.failed += 1;
+                info!(
+                    "[PAPER] block {}: bundle would have failed: {:?}",
+                    target_block, e
+                );
+            }
+        }
+    }
+
+    /// Wait until the block poller has seen `number` mined.
+    async fn wait_for_block(&self, number: u64) -> Option<Block<H256>> {
+        let (sender, receiver) = oneshot::channel();
+        self.waiting
+            .lock()
+            .unwrap()
+            .entry(number)
+            .or_default()
+            .push(sender);
+        receiver.await.ok()
+    }
+
+    /// Hand each mined block to the bundles waiting on it, blocks are fetched once however many
+    /// bundles target them.
+    async fn resolve_mined_blocks(&self) {
+        if self.waiting.lock().unwrap().is_empty() {
+            return;
+        }
+
+        let latest_block = match self.client.get_block_number().await {
+            Ok(number) => number.as_u64(),
+            Err(e) => {
+                error!("Paper trading failed to get the block number: {:?}", e);
+                return;
+            }
+        };
+
+        let mined: Vec<u64> = self
+            .waiting
+            .lock()
+            .unwrap()
+            .range(..=latest_block)
+            .map(|(number, _)| *number)
+            .collect();
+        for number in mined {
+            match self.client.get_block(number).await {
+                Ok(Some(block)) => {
+                    let waiters = self.waiting.lock().unwrap().remove(&number);
+                    for waiter in waiters.unwrap_or_default() {
+                        let _ = waiter.send(block.clone());
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => error!("Paper trading failed to get block {}: {:?}", number, e),
+            }
+        }
+    }
+}
+
+/// Recover the sender of a raw signed tx.
+fn recover_sender(raw: &Bytes) -> Result<Address> {
+    let tx = Transaction::decode(&Rlp::new(raw)).map_err(|e| anyhow!("bad raw tx: {:?}", e))?;
+    tx.recover_from()
+        .map_err(|e| anyhow!("failed to recover tx sender: {:?}", e))
+}
+
+/// Poll for mined blocks on behalf of every waiting bundle, stops once the executor is dropped.
+async fn poll_blocks<M: Middleware + 'static>(trader: Weak<PaperTrader<M>>) {
+    while let Some(trader) = trader.upgrade() {
+        trader.resolve_mined_blocks().await;
+        let poll_interval = trader.poll_interval;
+        drop(trader);
+        tokio::time::sleep(poll_interval).await;
+    }
+}
+
+#[async_trait]
+impl<M> Executor<FlashbotsBundle> for PaperTradingExecutor<M>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+{
+    /// Queue a bundle to be simulated once its target block is mined.
+    async fn execute(&self, submission: FlashbotsBundle) -> Result<()> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Some(uuid) = submission.replacement_uuid {
+            self.trader.latest.lock().unwrap().insert(uuid, id);
+        }
+
+        self.start_polling();
+
+        let trader = self.trader.clone();
+        tokio::spawn(async move { trader.trade(id, submission).await });
+
+        Ok(())
+    }
+
+    /// Log the final totals, bundles still waiting on their target block are not simulated.
+    async fn flush(&self) -> Result<()> {
+        let stats = self.trader.stats.lock().unwrap().clone();
+        let pending = self.trader.latest.lock().unwrap().len();
+        info!(
+            "[PAPER] simulated {} (failed {}, replaced {}, unmatched {}, {} pending), total profit {}, total coinbase payment {}",
+            stats.simulated, stats.failed, stats.replaced, stats.unmatched, pending, stats.profit, stats.coinbase_payment
+        );
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<M> Executor<FlashbotsCancellation> for PaperTradingExecutor<M>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+{
+    /// Cancelled bundles are never simulated.
+    async fn execute(&self, uuid: FlashbotsCancellation) -> Result<()> {
+        self.trader.latest.lock().unwrap().remove(&uuid);
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<M> Executor<MevShareBundle> for PaperTradingExecutor<M>
+where
+    M: Middleware + 'static,
+    M::Error: 'static,
+{
+    /// Queue a MEV-Share backrun to be simulated once its target block is mined.
+    async fn execute(&self, bundle: MevShareBundle) -> Result<()> {
+        self.start_polling();
+
+        let trader = self.trader.clone();
+        tokio::spawn(async move { trader.trade_backrun(bundle).await });
+
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::{types::Executor, utilities::capture::CaptureWriter};
+
+/// A passive executor that appends every action it receives to a capture file, run it next to the
+/// executors that actually act on them.
+pub struct RecorderExecutor {
+    writer: CaptureWriter,
+}
+
+impl RecorderExecutor {
+    pub fn new(writer: CaptureWriter) -> Self {
+        Self { writer }
+    }
+}
+
+#[async_trait]
+impl<A> Executor<A> for RecorderExecutor
+where
+    A: Serialize + Send + Sync + 'static,
+{
+    /// Record the action.
+    async fn execute(&self, action: A) -> Result<()> {
+        self.writer.write_action(&action);
+        Ok(())
+    }
+
+    /// Flush every record written so far, including the events recorded by collectors sharing the
+    /// writer.
+    async fn flush(&self) -> Result<()> {
+        self.writer.flush().await
+    }
+}
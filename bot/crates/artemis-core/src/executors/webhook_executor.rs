@@ -0,0 +1,365 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use reqwest::{StatusCode, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{sleep, sleep_until, timeout_at, Instant},
+};
+use tracing::{error, warn};
+
+use crate::types::Executor;
+
+/// Longest message Discord accepts in a webhook's `content`.
+const DISCORD_MAX_CONTENT: usize = 2000;
+
+/// How many times a post is retried after being rate limited before its notifications are dropped.
+const MAX_RETRIES: usize = 3;
+
+/// Longest `retry_after` that is honoured, posts rate limited for longer are dropped.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// How urgent a notification is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationLevel {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A message posted to a webhook.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    pub level: NotificationLevel,
+    /// Short summary, e.g. `Sandwich landed`.
+    pub title: String,
+    pub message: String,
+}
+
+impl Notification {
+    pub fn info(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(NotificationLevel::Info, title, message)
+    }
+
+    pub fn warning(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(NotificationLevel::Warning, title, message)
+    }
+
+    pub fn error(title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(NotificationLevel::Error, title, message)
+    }
+
+    fn new(level: NotificationLevel, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            title: title.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Markdown line used in Discord messages.
+    fn to_discord_line(&self) -> String {
+        let level = match self.level {
+            NotificationLevel::Info => "info",
+            NotificationLevel::Warning => "warning",
+            NotificationLevel::Error => "error",
+        };
+        format!("**[{}] {}** {}", level, self.title, self.message)
+    }
+}
+
+/// Payload posted to the webhook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookFormat {
+    /// `{"content": "..."}`, one line per notification, split into several posts when longer than
+    /// Discord's limit.
+    Discord,
+    /// `{"notifications": [{"level": ..., "title": ..., "message": ...}]}`.
+    Generic,
+}
+
+/// Configuration for a single webhook.
+#[derive(Debug, Clone)]
+pub struct WebhookConfig {
+    pub url: Url,
+    pub format: WebhookFormat,
+    /// How long notifications are collected for before being posted together.
+    pub batch_interval: Duration,
+    /// Most notifications posted together.
+    pub max_batch_size: usize,
+    /// Least time between two posts.
+    pub min_interval: Duration,
+    /// Most notifications waiting to be posted, new notifications are dropped once it's reached.
+    pub queue_size: usize,
+}
+
+impl WebhookConfig {
+    /// Defaults stay well under Discord's limit of 30 messages per minute per webhook.
+    pub fn new(url: Url, format: WebhookFormat) -> Self {
+        Self {
+            url,
+            format,
+            batch_interval: Duration::from_secs(2),
+            max_batch_size: 10,
+            min_interval: Duration::from_secs(2),
+            queue_size: 256,
+        }
+    }
+}
+
+/// Running totals of the notifications handled by the webhook executor.
+#[derive(Debug, Clone, Default)]
+pub struct WebhookStats {
+    /// Notifications the webhook accepted.
+    pub sent: u64,
+    /// Requests made to the webhook (including rate limited ones).
+    pub posts: u64,
+    /// Responses telling us to slow down (HTTP 429).
+    pub rate_limited: u64,
+    /// Notifications dropped because the queue was full.
+    pub dropped: u64,
+    /// Notifications dropped because their post failed.
+    pub failed: u64,
+}
+
+/// Sent to the worker.
+enum Message {
+    Notify(Notification),
+    /// Post everything queued so far, then answer.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Posts batches of notifications, owned by the spawned task.
+struct WebhookWorker {
+    client: reqwest::Client,
+    config: WebhookConfig,
+    stats: Arc<Mutex<WebhookStats>>,
+}
+
+/// An executor that posts notifications to a Discord or generic webhook.
+///
+/// Notifications are queued and posted in batches by a background task, so that `execute` never
+/// waits on the webhook. Posts are spaced by at least `min_interval`, and rate limited posts are
+/// retried after the `retry_after` the webhook asks for.
+pub struct WebhookExecutor {
+    sender: mpsc::Sender<Message>,
+    stats: Arc<Mutex<WebhookStats>>,
+}
+
+impl WebhookExecutor {
+    /// Spawn the task posting to the webhook (must be called from within a tokio runtime).
+    pub fn new(config: WebhookConfig) -> Self {
+        let (sender, receiver) = mpsc::channel(config.queue_size.max(1));
+        let stats = Arc::new(Mutex::new(WebhookStats::default()));
+
+        let worker = WebhookWorker {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .expect("valid webhook client"),
+            config,
+            stats: stats.clone(),
+        };
+        tokio::spawn(worker.run(receiver));
+
+        Self { sender, stats }
+    }
+
+    /// Returns a handle to the webhook stats that stays valid after the executor is moved into the
+    /// engine.
+    pub fn stats(&self) -> Arc<Mutex<WebhookStats>> {
+        self.stats.clone()
+    }
+}
+
+#[async_trait]
+impl Executor<Notification> for WebhookExecutor {
+    /// Queue the notification, it is dropped if the queue is full.
+    async fn execute(&self, notification: Notification) -> Result<()> {
+        match self.sender.try_send(Message::Notify(notification)) {
+            Ok(()) => Ok(()),
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.stats.lock().unwrap().dropped += 1;
+                warn!("Webhook queue is full, dropped notification");
+                Ok(())
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => Err(anyhow!("Webhook task has stopped")),
+        }
+    }
+
+    /// Post every queued notification without waiting for the batch interval.
+    async fn flush(&self) -> Result<()> {
+        let (done, flushed) = oneshot::channel();
+        self.sender
+            .send(Message::Flush(done))
+            .await
+            .map_err(|_| anyhow!("Webhook task has stopped"))?;
+        flushed
+            .await
+            .map_err(|_| anyhow!("Webhook task has stopped"))
+    }
+}
+
+impl WebhookWorker {
+    /// Collect notifications into batches and post them until every sender is dropped.
+    async fn run(self, mut receiver: mpsc::Receiver<Message>) {
+        let mut last_post: Option<Instant> = None;
+
+        loop {
+            let first = match receiver.recv().await {
+                Some(Message::Notify(notification)) => notification,
+                Some(Message::Flush(done)) => {
+                    let _ = done.send(());
+                    continue;
+                }
+                None => return,
+            };
+
+            // keep collecting until the batch is full, the interval is over, or we are flushed
+            let mut batch = vec![first];
+            let mut flushed = None;
+            let mut closed = false;
+            let deadline = Instant::now() + self.config.batch_interval;
+            while batch.len() < self.config.max_batch_size {
+                match timeout_at(deadline, receiver.recv()).await {
+                    Ok(Some(Message::Notify(notification))) => batch.push(notification),
+                    Ok(Some(Message::Flush(done))) => {
+                        flushed = Some(done);
+                        break;
+                    }
+                    Ok(None) => {
+                        closed = true;
+                        break;
+                    }
+                    Err(_) => break,
+                }
+            }
+
+            if let Some(last_post) = last_post {
+                sleep_until(last_post + self.config.min_interval).await;
+            }
+            self.post_batch(&batch).await;
+            last_post = Some(Instant::now());
+
+            if let Some(done) = flushed {
+                let _ = done.send(());
+            }
+            if closed {
+                return;
+            }
+        }
+    }
+
+    /// Post a batch, updating the stats with how many notifications got through.
+    async fn post_batch(&self, batch: &[Notification]) {
+        for (count, payload) in self.payloads(batch) {
+            match self.post(&payload).await {
+                Ok(()) => self.stats.lock().unwrap().sent += count as u64,
+                Err(e) => {
+                    error!("Failed to post {} notification(s) to webhook: {}", count, e);
+                    self.stats.lock().unwrap().failed += count as u64;
+                }
+            }
+        }
+    }
+
+    /// Payloads to post for `batch`, along with how many notifications each one holds.
+    fn payloads(&self, batch: &[Notification]) -> Vec<(usize, Value)> {
+        match self.config.format {
+            WebhookFormat::Generic => vec![(batch.len(), json!({ "notifications": batch }))],
+            WebhookFormat::Discord => {
+                let mut payloads = vec![];
+                let mut content = String::new();
+                let mut count = 0;
+                for notification in batch {
+                    let line: String = notification
+                        .to_discord_line()
+                        .chars()
+                        .take(DISCORD_MAX_CONTENT)
+                        .collect();
+                    if count > 0
+                        && content.chars().count() + 1 + line.chars().count() > DISCORD_MAX_CONTENT
+                    {
+                        payloads.push((count, json!({ "content": content })));
+                        content = String::new();
+                        count = 0;
+                    }
+                    if count > 0 {
+                        content.push('\n');
+                    }
+                    content.push_str(&line);
+                    count += 1;
+                }
+                if count > 0 {
+                    payloads.push((count, json!({ "content": content })));
+                }
+                payloads
+            }
+        }
+    }
+
+    /// Post a payload, waiting out rate limits.
+    async fn post(&self, payload: &Value) -> Result<()> {
+        for _ in 0..=MAX_RETRIES {
+            self.stats.lock().unwrap().posts += 1;
+            let response = self
+                .client
+                .post(self.config.url.clone())
+                .header("Content-Type", "application/json")
+                .body(payload.to_string())
+                .send()
+                .await?;
+
+            let status = response.status();
+            if status == StatusCode::TOO_MANY_REQUESTS {
+                self.stats.lock().unwrap().rate_limited += 1;
+                let retry_after = retry_after(response).await;
+                if retry_after > MAX_RETRY_AFTER {
+                    return Err(anyhow!("rate limited for {:?}", retry_after));
+                }
+                warn!("Webhook rate limited, retrying in {:?}", retry_after);
+                sleep(retry_after).await;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(anyhow!("webhook responded with {}", status));
+            }
+            return Ok(());
+        }
+
+        Err(anyhow!("still rate limited after {} retries", MAX_RETRIES))
+    }
+}
+
+/// How long a rate limited response asks us to wait, read from the `Retry-After` header or from
+/// Discord's `retry_after` field (both in seconds), one second if neither is set.
+async fn retry_after(response: reqwest::Response) -> Duration {
+    let header = response
+        .headers()
+        .get("Retry-After")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<f64>().ok());
+    let seconds = match header {
+        Some(seconds) => Some(seconds),
+        None => response
+            .bytes()
+            .await
+            .ok()
+            .and_then(|body| serde_json::from_slice::<Value>(&body).ok())
+            .and_then(|body| body["retry_after"].as_f64()),
+    };
+
+    match seconds {
+        Some(seconds) if seconds.is_finite() && seconds >= 0.0 => {
+            Duration::from_secs_f64(seconds.min(MAX_RETRY_AFTER.as_secs_f64() + 1.0))
+        }
+        _ => Duration::from_secs(1),
+    }
+}
@@ -25,8 +25,14 @@ pub trait Strategy<E, A>: Send + Sync {
     /// onchain data.
     async fn sync_state(&mut self) -> Result<()>;
 
-    /// Process an event, and return an action if needed.
-    async fn process_event(&mut self, event: E) -> Option<A>;
+    /// Process an event, and return the actions it produced (if any).
+    async fn process_event(&mut self, event: E) -> Vec<A>;
+
+    /// Called once the engine shuts down and every remaining event has been
+    /// processed, returns any final actions to execute.
+    async fn flush(&mut self) -> Vec<A> {
+        vec![]
+    }
 }
 
 /// Executor trait, responsible for executing actions returned by strategies.
@@ -34,6 +40,12 @@ pub trait Strategy<E, A>: Send + Sync {
 pub trait Executor<A>: Send + Sync {
     /// Execute an action.
     async fn execute(&self, action: A) -> Result<()>;
+
+    /// Called once the engine shuts down and every remaining action has been
+    /// executed.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
 }
 
 /// CollectorMap is a wrapper around a [Collector](Collector) that maps outgoing
@@ -90,9 +102,14 @@ where
             None => Ok(()),
         }
     }
+
+    async fn flush(&self) -> Result<()> {
+        self.executor.flush().await
+    }
 }
 
 /// Convenience enum containing all the events that can be emitted by collectors.
+#[derive(Debug, Clone)]
 pub enum Events {
     NewBlock(NewBlock),
     Transaction(Transaction),
@@ -0,0 +1,203 @@
+use std::{
+    fs::OpenOptions,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    fs::File,
+    io::{AsyncWriteExt, BufWriter},
+    sync::{mpsc, oneshot},
+};
+use tracing::{error, warn};
+
+/// How often buffered records are flushed to the capture file.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A single line of a capture file: an event or action, along with when it was seen.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CaptureRecord<E, A> {
+    /// An event, as it arrived from a collector.
+    Event {
+        /// Unix time in milliseconds.
+        at: u64,
+        event: E,
+    },
+    /// An action, as it reached the executors.
+    Action {
+        /// Unix time in milliseconds.
+        at: u64,
+        action: A,
+    },
+}
+
+impl<E, A> CaptureRecord<E, A> {
+    /// When the record was captured.
+    pub fn at(&self) -> Duration {
+        match self {
+            Self::Event { at, .. } | Self::Action { at, .. } => Duration::from_millis(*at),
+        }
+    }
+}
+
+/// A write waiting to be applied to the capture file.
+enum Write {
+    /// An encoded record, newline included.
+    Record(Vec<u8>),
+    /// Answered once every earlier record is flushed to the file.
+    Flush(oneshot::Sender<()>),
+}
+
+/// Appends [records](CaptureRecord) to a capture file (one json encoded record per line), shared
+/// by every [RecordingCollector](crate::collectors::recording_collector::RecordingCollector) and
+/// [RecorderExecutor](crate::executors::recorder_executor::RecorderExecutor) of an engine.
+///
+/// Records are encoded by the caller and written through a buffer by a dedicated task, so that
+/// recording never blocks a collector stream on disk I/O. The buffer is flushed every
+/// [FLUSH_INTERVAL], on [flush](CaptureWriter::flush), and once every handle is dropped, so a
+/// crash loses at most the last interval and leaves a truncated last line.
+#[derive(Clone)]
+pub struct CaptureWriter {
+    sender: mpsc::UnboundedSender<Write>,
+}
+
+impl CaptureWriter {
+    /// Open `path` for appending, creating it if needed, and spawn the writer task (must be called
+    /// from within a tokio runtime).
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow!("Failed to open capture file {}: {}", path.display(), e))?;
+
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(write_records(
+            BufWriter::new(File::from_std(file)),
+            receiver,
+        ));
+
+        Ok(Self { sender })
+    }
+
+    /// Append an event, seen now.
+    pub fn write_event<E: Serialize>(&self, event: &E) {
+        self.write(&CaptureRecord::<&E, ()>::Event { at: now(), event });
+    }
+
+    /// Append an action, seen now.
+    pub fn write_action<A: Serialize>(&self, action: &A) {
+        self.write(&CaptureRecord::<(), &A>::Action { at: now(), action });
+    }
+
+    /// Wait until every record written so far is flushed to the file.
+    pub async fn flush(&self) -> Result<()> {
+        let (done, flushed) = oneshot::channel();
+        self.sender
+            .send(Write::Flush(done))
+            .map_err(|_| anyhow!("Capture writer has stopped"))?;
+        flushed
+            .await
+            .map_err(|_| anyhow!("Capture writer has stopped"))
+    }
+
+    /// Recording is best effort, failures are logged instead of interrupting the engine.
+    fn write<E: Serialize, A: Serialize>(&self, record: &CaptureRecord<E, A>) {
+        let mut line = match serde_json::to_vec(record) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to encode capture record: {}", e);
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        if self.sender.send(Write::Record(line)).is_err() {
+            error!("Failed to write capture record: capture writer has stopped");
+        }
+    }
+}
+
+/// Apply writes until every [CaptureWriter] handle is dropped, flushing the buffer periodically
+/// and once more before returning.
+async fn write_records(mut file: BufWriter<File>, mut receiver: mpsc::UnboundedReceiver<Write>) {
+    let mut flush_interval = tokio::time::interval(FLUSH_INTERVAL);
+    loop {
+        tokio::select! {
+            write = receiver.recv() => match write {
+                Some(Write::Record(line)) => {
+                    if let Err(e) = file.write_all(&line).await {
+                        error!("Failed to write capture record: {}", e);
+                    }
+                }
+                Some(Write::Flush(done)) => {
+                    flush_records(&mut file).await;
+                    let _ = done.send(());
+                }
+                None => {
+                    flush_records(&mut file).await;
+                    return;
+                }
+            },
+            _ = flush_interval.tick() => flush_records(&mut file).await,
+        }
+    }
+}
+
+async fn flush_records(file: &mut BufWriter<File>) {
+    if let Err(e) = file.flush().await {
+        error!("Failed to flush capture file: {}", e);
+    }
+}
+
+/// Read every record of a capture file, actions are left undecoded. A truncated last line (e.g.
+/// from a crash mid write) is skipped, any other malformed line is an error.
+pub fn read_capture<E: DeserializeOwned>(
+    path: impl Into<PathBuf>,
+) -> Result<Vec<CaptureRecord<E, Value>>> {
+    let path = path.into();
+    let contents = std::fs::read_to_string(&path)
+        .map_err(|e| anyhow!("Failed to read capture file {}: {}", path.display(), e))?;
+
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    let mut records = Vec::with_capacity(lines.len());
+    for (index, line) in lines.iter().enumerate() {
+        match serde_json::from_str(line) {
+            Ok(record) => records.push(record),
+            Err(e) if index + 1 == lines.len() && !contents.ends_with('\n') => {
+                warn!(
+                    "Skipping truncated last record of {}: {}",
+                    path.display(),
+                    e
+                )
+            }
+            Err(e) => {
+                return Err(anyhow!(
+                    "Failed to parse record {} of {}: {}",
+                    index + 1,
+                    path.display(),
+                    e
+                ))
+            }
+        }
+    }
+
+    Ok(records)
+}
+
+/// Unix time in milliseconds.
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
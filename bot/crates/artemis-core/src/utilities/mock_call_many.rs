@@ -0,0 +1,240 @@
+use std::{
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::{anyhow, Result};
+use ethers::{
+    providers::{Http, Provider},
+    types::Bytes,
+};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::sync::{oneshot, Mutex};
+
+/// Shared with the server.
+struct NodeState {
+    /// Anvil node that requests are executed on.
+    upstream: Provider<Http>,
+    /// Held while a bundle is executed, so that concurrent requests don't see each other's state.
+    lock: Mutex<()>,
+    /// How many `eth_callMany` requests were handled.
+    call_many_requests: AtomicUsize,
+}
+
+/// A local JSON-RPC node that adds `eth_callMany` on top of an anvil node (which doesn't support
+/// it), so that simulation backends relying on it can be tested offline. Every other method is
+/// forwarded to anvil untouched.
+///
+/// Bundles are executed for real between an `evm_snapshot` and an `evm_revert`: state overrides
+/// are applied with anvil's cheat methods, then each call is `eth_call`ed for its result and sent
+/// from an impersonated sender and mined so that the next call sees its effects (reverted calls
+/// aren't sent). The state context and block overrides are ignored, calls run on top of anvil's
+/// latest block, so anvil's mempool should be empty. The server shuts down when dropped.
+pub struct MockCallMany {
+    /// Address the server is listening on.
+    address: SocketAddr,
+    /// Shared with the server.
+    state: Arc<NodeState>,
+    /// Stops the server.
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockCallMany {
+    /// Spawn the node on a random local port in front of the anvil node at `upstream` (must be
+    /// called from within a tokio runtime).
+    pub fn spawn(upstream: Url) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let state = Arc::new(NodeState {
+            upstream: Provider::new(Http::new(upstream)),
+            lock: Mutex::new(()),
+            call_many_requests: AtomicUsize::new(0),
+        });
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle_request(request, state.clone())
+                }))
+            }
+        });
+
+        let (shutdown, shutdown_receiver) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                shutdown_receiver.await.ok();
+            });
+        tokio::spawn(server);
+
+        Ok(Self {
+            address,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Url to use as the node's rpc url.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}", self.address)).expect("valid node url")
+    }
+
+    /// How many `eth_callMany` requests were handled.
+    pub fn call_many_requests(&self) -> usize {
+        self.state.call_many_requests.load(Ordering::SeqCst)
+    }
+}
+
+impl Drop for MockCallMany {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Emulate `eth_callMany`, forward anything else to anvil.
+async fn handle_request(
+    request: Request<Body>,
+    state: Arc<NodeState>,
+) -> Result<Response<Body>, Infallible> {
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return Ok(error_response(Value::Null, -32700, &e)),
+    };
+
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => return Ok(error_response(Value::Null, -32700, &e)),
+    };
+    let id = payload["id"].clone();
+    let params = payload["params"].clone();
+
+    let result = match payload["method"].as_str().unwrap_or_default() {
+        "eth_callMany" => {
+            state.call_many_requests.fetch_add(1, Ordering::SeqCst);
+            call_many(&state, params).await
+        }
+        method => state
+            .upstream
+            .request::<_, Value>(method, params)
+            .await
+            .map_err(|e| anyhow!("{}", e)),
+    };
+
+    Ok(match result {
+        Ok(result) => json_response(json!({ "jsonrpc": "2.0", "id": id, "result": result })),
+        Err(e) => error_response(id, -32000, &e),
+    })
+}
+
+/// Execute `eth_callMany`'s bundles inside a snapshot that is reverted afterwards.
+async fn call_many(state: &NodeState, params: Value) -> Result<Value> {
+    let _guard = state.lock.lock().await;
+    let upstream = &state.upstream;
+
+    let snapshot: Value = upstream.request("evm_snapshot", ()).await?;
+    let results = execute_bundles(upstream, &params).await;
+    upstream
+        .request::<_, Value>("evm_revert", [snapshot])
+        .await?;
+
+    results
+}
+
+/// Apply the state overrides (third param) then run every bundle (first param), returns each
+/// call's `{ value }` or `{ error }`.
+async fn execute_bundles(upstream: &Provider<Http>, params: &Value) -> Result<Value> {
+    let cheat = |method: &'static str, params: Value| async move {
+        upstream
+            .request::<_, Value>(method, params)
+            .await
+            .map_err(|e| anyhow!("{} failed: {}", method, e))
+    };
+
+    if let Some(overrides) = params[2].as_object() {
+        for (address, account) in overrides {
+            if let Some(balance) = account.get("balance") {
+                cheat("anvil_setBalance", json!([address, balance])).await?;
+            }
+            if let Some(nonce) = account.get("nonce") {
+                cheat("anvil_setNonce", json!([address, nonce])).await?;
+            }
+            if let Some(code) = account.get("code") {
+                cheat("anvil_setCode", json!([address, code])).await?;
+            }
+            // anvil can't wipe an account's storage, so `state` only sets the given slots
+            for key in ["state", "stateDiff"] {
+                for (slot, value) in account
+                    .get(key)
+                    .and_then(Value::as_object)
+                    .into_iter()
+                    .flatten()
+                {
+                    cheat("anvil_setStorageAt", json!([address, slot, value])).await?;
+                }
+            }
+        }
+    }
+
+    let bundles = params[0].as_array().ok_or(anyhow!("missing bundles"))?;
+
+    let mut results = vec![];
+    for bundle in bundles {
+        let txs = bundle["transactions"]
+            .as_array()
+            .ok_or(anyhow!("missing transactions"))?;
+
+        let mut bundle_results = vec![];
+        for tx in txs {
+            let from = tx["from"].clone();
+            cheat("anvil_impersonateAccount", json!([from])).await?;
+
+            match upstream
+                .request::<_, Bytes>("eth_call", json!([tx, "latest"]))
+                .await
+            {
+                Ok(value) => {
+                    // commit the call so that the next ones see its effects
+                    cheat("eth_sendTransaction", json!([tx])).await?;
+                    cheat("evm_mine", json!([])).await?;
+                    bundle_results.push(json!({ "value": value }));
+                }
+                Err(e) => bundle_results.push(json!({ "error": e.to_string() })),
+            }
+
+            cheat("anvil_stopImpersonatingAccount", json!([from])).await?;
+        }
+        results.push(bundle_results);
+    }
+
+    Ok(json!(results))
+}
+
+fn error_response(id: Value, code: i64, message: &dyn std::fmt::Display) -> Response<Body> {
+    json_response(json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message.to_string() },
+    }))
+}
+
+fn json_response(body: Value) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::OK)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
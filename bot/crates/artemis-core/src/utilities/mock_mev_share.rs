@@ -0,0 +1,120 @@
+use std::{
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use hyper::{
+    body::Bytes,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server,
+};
+use reqwest::Url;
+use tokio::sync::{mpsc, oneshot};
+use tokio_stream::wrappers::UnboundedReceiverStream;
+
+use crate::collectors::mev_share_collector::MevShareEvent;
+
+/// A local HTTP server that streams MEV-Share events (server-sent events) to every connected
+/// client, so that the MEV-Share collector and backrun strategies can be tested offline.
+///
+/// Events are only sent to clients connected at the time they are sent. The server shuts down
+/// when dropped.
+pub struct MockMevShare {
+    /// Address the server is listening on.
+    address: SocketAddr,
+    /// Open event streams, one per client.
+    clients: Arc<Mutex<Vec<mpsc::UnboundedSender<Result<Bytes, Infallible>>>>>,
+    /// Stops the server.
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockMevShare {
+    /// Spawn the server on a random local port (must be called from within a tokio runtime).
+    pub fn spawn() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let clients = Arc::new(Mutex::new(vec![]));
+        let service_clients = clients.clone();
+        let make_service = make_service_fn(move |_| {
+            let clients = service_clients.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle_request(request, clients.clone())
+                }))
+            }
+        });
+
+        let (shutdown, shutdown_receiver) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                shutdown_receiver.await.ok();
+            });
+        tokio::spawn(server);
+
+        Ok(Self {
+            address,
+            clients,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Url to use as the event stream url.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}", self.address)).expect("valid event stream url")
+    }
+
+    /// Number of clients currently connected.
+    pub fn clients(&self) -> usize {
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|client| !client.is_closed());
+        clients.len()
+    }
+
+    /// Send an event to every connected client.
+    pub fn send(&self, event: &MevShareEvent) {
+        let frame = format!("data: {}\n\n", serde_json::to_string(event).unwrap());
+        self.send_raw(&frame);
+    }
+
+    /// Send raw bytes (e.g. keep alive comments or malformed events) to every connected client.
+    pub fn send_raw(&self, frame: &str) {
+        self.clients
+            .lock()
+            .unwrap()
+            .retain(|client| client.send(Ok(Bytes::from(frame.to_string()))).is_ok());
+    }
+
+    /// Close every open event stream, clients can reconnect.
+    pub fn disconnect_clients(&self) {
+        self.clients.lock().unwrap().clear();
+    }
+}
+
+impl Drop for MockMevShare {
+    fn drop(&mut self) {
+        self.disconnect_clients();
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Open an event stream that stays open until the client disconnects or the server drops it.
+async fn handle_request(
+    _request: Request<Body>,
+    clients: Arc<Mutex<Vec<mpsc::UnboundedSender<Result<Bytes, Infallible>>>>>,
+) -> Result<Response<Body>, Infallible> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    clients.lock().unwrap().push(sender);
+
+    Ok(Response::builder()
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-cache")
+        .body(Body::wrap_stream(UnboundedReceiverStream::new(receiver)))
+        .expect("valid response"))
+}
@@ -0,0 +1,406 @@
+use std::{
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    str::FromStr,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{anyhow, Result};
+use ethers::{
+    types::{Address, Bytes, Signature, Transaction, H256, U64},
+    utils::{
+        keccak256,
+        rlp::{Decodable, Rlp},
+    },
+};
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// A bundle received by the [MockRelay](MockRelay).
+#[derive(Debug, Clone)]
+pub struct RecordedBundle {
+    /// Address recovered from the request's `X-Flashbots-Signature` header.
+    pub signer: Address,
+    /// Raw signed transactions, in bundle order.
+    pub txs: Vec<Bytes>,
+    /// Block the bundle targets.
+    pub block_number: U64,
+    /// Uuid the bundle was sent under (if any).
+    pub replacement_uuid: Option<Uuid>,
+    /// Untouched request params.
+    pub params: Value,
+}
+
+impl RecordedBundle {
+    /// Decode the bundle's transactions and recover their senders.
+    pub fn transactions(&self) -> Result<Vec<Transaction>> {
+        self.txs.iter().map(decode_transaction).collect()
+    }
+
+    /// Bundle hash, keccak256 of the concatenated tx hashes (same as the Flashbots relay).
+    pub fn bundle_hash(&self) -> H256 {
+        let tx_hashes: Vec<u8> = self
+            .txs
+            .iter()
+            .flat_map(|tx| keccak256(tx).to_vec())
+            .collect();
+        H256::from(keccak256(tx_hashes))
+    }
+}
+
+/// Everything the relay has received.
+#[derive(Debug, Default)]
+struct RelayState {
+    /// Bundles from `eth_sendBundle`.
+    bundles: Vec<RecordedBundle>,
+    /// Bundles from `eth_callBundle`.
+    simulations: Vec<RecordedBundle>,
+    /// Uuids from `eth_cancelBundle`.
+    cancellations: Vec<Uuid>,
+    /// Bundles from `mev_sendBundle`.
+    mev_share_bundles: Vec<RecordedBundle>,
+    /// Requests refused because of a missing or invalid signature.
+    rejected: usize,
+    /// Returned from `eth_callBundle` instead of the default (empty) simulation.
+    call_bundle_response: Option<Value>,
+}
+
+/// A local HTTP server that speaks the Flashbots relay's bundle methods (`eth_sendBundle`,
+/// `eth_callBundle`, `eth_cancelBundle` and MEV-Share's `mev_sendBundle`), so that executors can
+/// be tested offline.
+///
+/// Requests must be signed like the real relay expects (`X-Flashbots-Signature` header), every
+/// accepted request is recorded. The server shuts down when dropped.
+pub struct MockRelay {
+    /// Address the server is listening on.
+    address: SocketAddr,
+    /// Shared with the server.
+    state: Arc<Mutex<RelayState>>,
+    /// Stops the server.
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockRelay {
+    /// Spawn the relay on a random local port (must be called from within a tokio runtime).
+    pub fn spawn() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let state = Arc::new(Mutex::new(RelayState::default()));
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle_request(request, state.clone())
+                }))
+            }
+        });
+
+        let (shutdown, shutdown_receiver) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                shutdown_receiver.await.ok();
+            });
+        tokio::spawn(server);
+
+        Ok(Self {
+            address,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Url to use as the relay url.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}", self.address)).expect("valid relay url")
+    }
+
+    /// Every bundle sent with `eth_sendBundle`, in the order they were received.
+    pub fn bundles(&self) -> Vec<RecordedBundle> {
+        self.state.lock().unwrap().bundles.clone()
+    }
+
+    /// Bundles that the relay would still try to land: only the latest bundle per replacement
+    /// uuid, and none whose uuid was cancelled.
+    pub fn live_bundles(&self) -> Vec<RecordedBundle> {
+        let state = self.state.lock().unwrap();
+        state
+            .bundles
+            .iter()
+            .enumerate()
+            .filter(|(i, bundle)| match bundle.replacement_uuid {
+                Some(uuid) => {
+                    !state.cancellations.contains(&uuid)
+                        && !state.bundles[i + 1..]
+                            .iter()
+                            .any(|later| later.replacement_uuid == Some(uuid))
+                }
+                None => true,
+            })
+            .map(|(_, bundle)| bundle.clone())
+            .collect()
+    }
+
+    /// Every bundle simulated with `eth_callBundle`.
+    pub fn simulations(&self) -> Vec<RecordedBundle> {
+        self.state.lock().unwrap().simulations.clone()
+    }
+
+    /// Every uuid cancelled with `eth_cancelBundle`.
+    pub fn cancellations(&self) -> Vec<Uuid> {
+        self.state.lock().unwrap().cancellations.clone()
+    }
+
+    /// Every bundle sent with `mev_sendBundle`, `txs` only holds our own txs (not the hinted tx
+    /// hashes).
+    pub fn mev_share_bundles(&self) -> Vec<RecordedBundle> {
+        self.state.lock().unwrap().mev_share_bundles.clone()
+    }
+
+    /// How many requests were refused because their signature was missing or invalid.
+    pub fn rejected(&self) -> usize {
+        self.state.lock().unwrap().rejected
+    }
+
+    /// Respond to `eth_callBundle` with `response` (the `result` field) from now on.
+    pub fn set_call_bundle_response(&self, response: Value) {
+        self.state.lock().unwrap().call_bundle_response = Some(response);
+    }
+}
+
+impl Drop for MockRelay {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Verify the request's signature, then dispatch it to the matching bundle method.
+async fn handle_request(
+    request: Request<Body>,
+    state: Arc<Mutex<RelayState>>,
+) -> Result<Response<Body>, Infallible> {
+    let signature = request
+        .headers()
+        .get("X-Flashbots-Signature")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => {
+            return Ok(error_response(
+                StatusCode::BAD_REQUEST,
+                Value::Null,
+                -32700,
+                &e,
+            ))
+        }
+    };
+
+    let payload: Value = match serde_json::from_slice(&body) {
+        Ok(payload) => payload,
+        Err(e) => {
+            return Ok(error_response(
+                StatusCode::BAD_REQUEST,
+                Value::Null,
+                -32700,
+                &e,
+            ))
+        }
+    };
+    let id = payload["id"].clone();
+
+    let signer = match signature.and_then(|signature| verify_signature(&signature, &body)) {
+        Some(signer) => signer,
+        None => {
+            state.lock().unwrap().rejected += 1;
+            return Ok(error_response(
+                StatusCode::FORBIDDEN,
+                id,
+                -32600,
+                &"missing or invalid X-Flashbots-Signature",
+            ));
+        }
+    };
+
+    let params = payload["params"][0].clone();
+    let result = match payload["method"].as_str().unwrap_or_default() {
+        "eth_sendBundle" => record_bundle(signer, params).map(|bundle| {
+            let bundle_hash = bundle.bundle_hash();
+            state.lock().unwrap().bundles.push(bundle);
+            json!({ "bundleHash": bundle_hash })
+        }),
+        "eth_callBundle" => record_bundle(signer, params).and_then(|bundle| {
+            let mut state = state.lock().unwrap();
+            let response = match &state.call_bundle_response {
+                Some(response) => response.clone(),
+                None => simulate_bundle(&bundle)?,
+            };
+            state.simulations.push(bundle);
+            Ok(response)
+        }),
+        "eth_cancelBundle" => params["replacementUuid"]
+            .as_str()
+            .and_then(|uuid| Uuid::parse_str(uuid).ok())
+            .ok_or(anyhow!("missing replacementUuid"))
+            .map(|uuid| {
+                state.lock().unwrap().cancellations.push(uuid);
+                Value::Null
+            }),
+        "mev_sendBundle" => record_mev_share_bundle(signer, params).map(|bundle| {
+            let bundle_hash = bundle.bundle_hash();
+            state.lock().unwrap().mev_share_bundles.push(bundle);
+            json!({ "bundleHash": bundle_hash })
+        }),
+        method => {
+            return Ok(error_response(
+                StatusCode::OK,
+                id,
+                -32601,
+                &format!("method {} not supported", method),
+            ))
+        }
+    };
+
+    Ok(match result {
+        Ok(result) => json_response(
+            StatusCode::OK,
+            json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        ),
+        Err(e) => error_response(StatusCode::OK, id, -32602, &e),
+    })
+}
+
+/// Recover the signer of `body` from a `<address>:<signature>` header, the signature is over the
+/// hex encoded keccak256 hash of the body.
+fn verify_signature(header: &str, body: &[u8]) -> Option<Address> {
+    let (address, signature) = header.split_once(':')?;
+    let address = Address::from_str(address).ok()?;
+    let signature = Signature::from_str(signature).ok()?;
+
+    let message = format!("0x{:x}", H256::from(keccak256(body)));
+    signature.verify(message, address).ok()?;
+
+    Some(address)
+}
+
+/// Parse `eth_sendBundle`/`eth_callBundle` params.
+fn record_bundle(signer: Address, params: Value) -> Result<RecordedBundle> {
+    let txs =
+        serde_json::from_value(params["txs"].clone()).map_err(|e| anyhow!("invalid txs: {}", e))?;
+    let block_number = serde_json::from_value(params["blockNumber"].clone())
+        .map_err(|e| anyhow!("invalid blockNumber: {}", e))?;
+    let replacement_uuid = match params["replacementUuid"].as_str() {
+        Some(uuid) => Some(Uuid::parse_str(uuid)?),
+        None => None,
+    };
+
+    Ok(RecordedBundle {
+        signer,
+        txs,
+        block_number,
+        replacement_uuid,
+        params,
+    })
+}
+
+/// Parse `mev_sendBundle` params.
+fn record_mev_share_bundle(signer: Address, params: Value) -> Result<RecordedBundle> {
+    let txs = params["body"]
+        .as_array()
+        .ok_or(anyhow!("missing body"))?
+        .iter()
+        .filter(|item| item.get("hash").is_none())
+        .map(|item| serde_json::from_value(item["tx"].clone()))
+        .collect::<Result<_, _>>()
+        .map_err(|e| anyhow!("invalid body: {}", e))?;
+    let block_number = serde_json::from_value(params["inclusion"]["block"].clone())
+        .map_err(|e| anyhow!("invalid inclusion block: {}", e))?;
+
+    Ok(RecordedBundle {
+        signer,
+        txs,
+        block_number,
+        replacement_uuid: None,
+        params,
+    })
+}
+
+/// Default `eth_callBundle` result: every tx succeeds without paying the coinbase.
+fn simulate_bundle(bundle: &RecordedBundle) -> Result<Value> {
+    let state_block_number: U64 = serde_json::from_value(bundle.params["stateBlockNumber"].clone())
+        .map_err(|e| anyhow!("invalid stateBlockNumber: {}", e))?;
+
+    let results = bundle
+        .transactions()?
+        .into_iter()
+        .map(|tx| {
+            json!({
+                "txHash": tx.hash,
+                "fromAddress": tx.from,
+                "toAddress": tx.to,
+                "gasUsed": 21000,
+                "gasPrice": "0",
+                "gasFees": "0",
+                "coinbaseDiff": "0",
+                "ethSentToCoinbase": "0",
+                "value": "0x",
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(json!({
+        "bundleHash": bundle.bundle_hash(),
+        "bundleGasPrice": "0",
+        "coinbaseDiff": "0",
+        "ethSentToCoinbase": "0",
+        "gasFees": "0",
+        "stateBlockNumber": state_block_number.as_u64(),
+        "totalGasUsed": 21000 * results.len(),
+        "results": results,
+    }))
+}
+
+/// Decode a raw signed transaction and recover its sender.
+fn decode_transaction(raw: &Bytes) -> Result<Transaction> {
+    let mut tx = Transaction::decode(&Rlp::new(raw))
+        .map_err(|e| anyhow!("Failed to decode bundle tx: {:?}", e))?;
+    tx.recover_from_mut()
+        .map_err(|e| anyhow!("Failed to recover bundle tx sender: {:?}", e))?;
+    Ok(tx)
+}
+
+fn error_response(
+    status: StatusCode,
+    id: Value,
+    code: i64,
+    message: &dyn std::fmt::Display,
+) -> Response<Body> {
+    json_response(
+        status,
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": code, "message": message.to_string() },
+        }),
+    )
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
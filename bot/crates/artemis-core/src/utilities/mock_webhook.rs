@@ -0,0 +1,145 @@
+use std::{
+    convert::Infallible,
+    net::{SocketAddr, TcpListener},
+    sync::{Arc, Mutex},
+};
+
+use anyhow::Result;
+use hyper::{
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::sync::oneshot;
+
+/// Everything the webhook has received.
+#[derive(Debug, Default)]
+struct WebhookState {
+    /// Bodies of the accepted requests.
+    requests: Vec<Value>,
+    /// Requests answered with a 429.
+    rate_limited: usize,
+    /// How many of the next requests are answered with a 429.
+    rate_limit_next: usize,
+    /// `retry_after` (in seconds) sent along with each 429.
+    retry_after: f64,
+}
+
+/// A local HTTP server standing in for a Discord or generic webhook, so that the webhook executor
+/// can be tested offline.
+///
+/// Every accepted JSON body is recorded, and the server can be told to rate limit requests like
+/// Discord does (HTTP 429 with a `retry_after` field). The server shuts down when dropped.
+pub struct MockWebhook {
+    /// Address the server is listening on.
+    address: SocketAddr,
+    /// Shared with the server.
+    state: Arc<Mutex<WebhookState>>,
+    /// Stops the server.
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl MockWebhook {
+    /// Spawn the webhook on a random local port (must be called from within a tokio runtime).
+    pub fn spawn() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+
+        let state = Arc::new(Mutex::new(WebhookState::default()));
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    handle_request(request, state.clone())
+                }))
+            }
+        });
+
+        let (shutdown, shutdown_receiver) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                shutdown_receiver.await.ok();
+            });
+        tokio::spawn(server);
+
+        Ok(Self {
+            address,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// Url to use as the webhook url.
+    pub fn url(&self) -> Url {
+        Url::parse(&format!("http://{}", self.address)).expect("valid webhook url")
+    }
+
+    /// Bodies of every accepted request, in the order they were received.
+    pub fn requests(&self) -> Vec<Value> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// How many requests were answered with a 429.
+    pub fn rate_limited(&self) -> usize {
+        self.state.lock().unwrap().rate_limited
+    }
+
+    /// Answer the next `count` requests with a 429 asking to retry after `retry_after` seconds.
+    pub fn rate_limit_next(&self, count: usize, retry_after: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.rate_limit_next = count;
+        state.retry_after = retry_after;
+    }
+}
+
+impl Drop for MockWebhook {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+/// Record the request's body, unless it is rate limited.
+async fn handle_request(
+    request: Request<Body>,
+    state: Arc<Mutex<WebhookState>>,
+) -> Result<Response<Body>, Infallible> {
+    let body = match hyper::body::to_bytes(request.into_body()).await {
+        Ok(body) => body,
+        Err(e) => return Ok(json_response(StatusCode::BAD_REQUEST, json!(e.to_string()))),
+    };
+
+    let mut state = state.lock().unwrap();
+    if state.rate_limit_next > 0 {
+        state.rate_limit_next -= 1;
+        state.rate_limited += 1;
+        return Ok(json_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            json!({ "message": "You are being rate limited.", "retry_after": state.retry_after }),
+        ));
+    }
+
+    match serde_json::from_slice(&body) {
+        Ok(body) => {
+            state.requests.push(body);
+            Ok(Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .expect("valid response"))
+        }
+        Err(e) => Ok(json_response(StatusCode::BAD_REQUEST, json!(e.to_string()))),
+    }
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("valid response")
+}
@@ -1,4 +1,18 @@
 //! Utilities for working with Artemis.
 
+/// This module implements the capture file that events and actions are recorded to.
+pub mod capture;
+/// This module implements a mock node that emulates `eth_callMany` on top of anvil.
+#[cfg(feature = "test-utils")]
+pub mod mock_call_many;
+/// This module implements a mock MEV-Share event stream for testing collectors offline.
+#[cfg(feature = "test-utils")]
+pub mod mock_mev_share;
+/// This module implements a mock Flashbots relay for testing executors offline.
+#[cfg(feature = "test-utils")]
+pub mod mock_relay;
+/// This module implements a mock webhook for testing notifications offline.
+#[cfg(feature = "test-utils")]
+pub mod mock_webhook;
 /// This module implements state overriding middleware.
 pub mod state_override_middleware;
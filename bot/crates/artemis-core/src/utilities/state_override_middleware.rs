@@ -1,12 +1,25 @@
+use std::collections::HashMap;
+
 use async_trait::async_trait;
 use ethers::{
+    abi::{self, Token},
     core::types::{transaction::eip2718::TypedTransaction, BlockId},
     providers::{spoof, CallBuilder, Middleware, MiddlewareError, RawCall},
-    types::{Address, Bytes},
+    types::{
+        transaction::eip2930::AccessListWithGasUsed, Address, BlockNumber, Bytes,
+        TransactionRequest, H256, U256, U64,
+    },
+    utils::{id, keccak256},
 };
 use thiserror::Error;
 
+/// How many mapping slots are tried when looking for an ERC20's balance mapping.
+const MAX_BALANCE_SLOT: u64 = 32;
+
 /// This custom middleware performs an ephemeral state override prior to executoring calls.
+///
+/// The overrides it holds apply to every `call`, `estimate_gas` and `create_access_list`, extra
+/// overrides can be scoped to a single request using the `*_with_overrides` methods.
 #[derive(Debug)]
 pub struct StateOverrideMiddleware<M> {
     /// The inner middleware
@@ -27,6 +40,128 @@ where
             state: spoof::state(),
         }
     }
+
+    /// Performs a call with the middleware's overrides and `overrides` on top (for this call only).
+    pub async fn call_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::State,
+    ) -> Result<Bytes, StateOverrideMiddlewareError<M>> {
+        let state = self.merged_state(overrides);
+        let call_builder = CallBuilder::new(self.inner.provider(), tx);
+        let call_builder = match block {
+            Some(block) => call_builder.block(block),
+            None => call_builder,
+        };
+        let call_builder = call_builder.state(&state);
+        call_builder
+            .await
+            .map_err(StateOverrideMiddlewareError::from_provider_err)
+    }
+
+    /// Estimates gas with the middleware's overrides and `overrides` on top (for this request
+    /// only). Requests without any override are passed to the inner middleware untouched.
+    pub async fn estimate_gas_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::State,
+    ) -> Result<U256, StateOverrideMiddlewareError<M>> {
+        let state = self.merged_state(overrides);
+        if state.is_empty() {
+            return self
+                .inner
+                .estimate_gas(tx, block)
+                .await
+                .map_err(StateOverrideMiddlewareError::from_err);
+        }
+
+        self.inner
+            .provider()
+            .request("eth_estimateGas", (tx, block_or_latest(block), state))
+            .await
+            .map_err(StateOverrideMiddlewareError::from_provider_err)
+    }
+
+    /// Creates an access list with the middleware's overrides and `overrides` on top (for this
+    /// request only). Requests without any override are passed to the inner middleware untouched.
+    pub async fn create_access_list_with_overrides(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+        overrides: &spoof::State,
+    ) -> Result<AccessListWithGasUsed, StateOverrideMiddlewareError<M>> {
+        let state = self.merged_state(overrides);
+        if state.is_empty() {
+            return self
+                .inner
+                .create_access_list(tx, block)
+                .await
+                .map_err(StateOverrideMiddlewareError::from_err);
+        }
+
+        self.inner
+            .provider()
+            .request("eth_createAccessList", (tx, block_or_latest(block), state))
+            .await
+            .map_err(StateOverrideMiddlewareError::from_provider_err)
+    }
+
+    /// Overrides `owner`'s balance of the ERC20 `token` by finding which storage slot holds it.
+    ///
+    /// Solidity (`keccak(owner . slot)`) and Vyper (`keccak(slot . owner)`) mappings declared in
+    /// the first `MAX_BALANCE_SLOT` slots are tried, a slot is kept once `balanceOf(owner)`
+    /// returns a probe value (one more than the current balance) with it overridden, and is then
+    /// overridden with `amount`.
+    pub async fn set_erc20_balance(
+        &mut self,
+        token: Address,
+        owner: Address,
+        amount: U256,
+        block: Option<BlockId>,
+    ) -> Result<H256, StateOverrideMiddlewareError<M>> {
+        let mut calldata = id("balanceOf(address)").to_vec();
+        calldata.extend(abi::encode(&[Token::Address(owner)]));
+        let tx: TypedTransaction = TransactionRequest::new().to(token).data(calldata).into();
+
+        // probing with `amount` would match any slot if it's already the owner's balance
+        let balance = self
+            .call_with_overrides(&tx, block, &spoof::state())
+            .await?;
+        if balance.len() != 32 {
+            return Err(StateOverrideMiddlewareError::BalanceSlotNotFound(token));
+        }
+        let probe = U256::from_big_endian(&balance)
+            .overflowing_add(U256::one())
+            .0;
+        let probe_value = H256::from_uint(&probe);
+
+        for slot in 0..MAX_BALANCE_SLOT {
+            let solidity_key = [Token::Address(owner), Token::Uint(slot.into())];
+            let vyper_key = [Token::Uint(slot.into()), Token::Address(owner)];
+
+            for key in [solidity_key, vyper_key] {
+                let storage_slot = H256::from(keccak256(abi::encode(&key)));
+
+                let mut overrides = spoof::state();
+                overrides.account(token).store(storage_slot, probe_value);
+
+                // tokens that don't use this slot may revert or return another balance
+                let balance = match self.call_with_overrides(&tx, block, &overrides).await {
+                    Ok(output) if output.len() == 32 => U256::from_big_endian(&output),
+                    _ => continue,
+                };
+
+                if balance == probe {
+                    self.add_storage(token, storage_slot, H256::from_uint(&amount));
+                    return Ok(storage_slot);
+                }
+            }
+        }
+
+        Err(StateOverrideMiddlewareError::BalanceSlotNotFound(token))
+    }
 }
 
 #[async_trait]
@@ -48,15 +183,27 @@ where
         tx: &TypedTransaction,
         block: Option<BlockId>,
     ) -> Result<Bytes, Self::Error> {
-        let call_builder = CallBuilder::new(self.inner.provider(), tx);
-        let call_builder = match block {
-            Some(block) => call_builder.block(block),
-            None => call_builder,
-        };
-        let call_builder = call_builder.state(&self.state);
-        call_builder
+        self.call_with_overrides(tx, block, &spoof::state()).await
+    }
+
+    /// Estimates gas with the state override.
+    async fn estimate_gas(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<U256, Self::Error> {
+        self.estimate_gas_with_overrides(tx, block, &spoof::state())
+            .await
+    }
+
+    /// Creates an access list with the state override.
+    async fn create_access_list(
+        &self,
+        tx: &TypedTransaction,
+        block: Option<BlockId>,
+    ) -> Result<AccessListWithGasUsed, Self::Error> {
+        self.create_access_list_with_overrides(tx, block, &spoof::state())
             .await
-            .map_err(StateOverrideMiddlewareError::from_provider_err)
     }
 }
 
@@ -72,6 +219,67 @@ impl<M> StateOverrideMiddleware<M> {
         self.state.account(address).code(code);
         address
     }
+
+    /// Adds a balance override at a given address.
+    pub fn add_balance(&mut self, address: Address, balance: U256) {
+        self.state.account(address).balance(balance);
+    }
+
+    /// Adds a nonce override at a given address.
+    pub fn add_nonce(&mut self, address: Address, nonce: u64) {
+        self.state.account(address).nonce(U64::from(nonce));
+    }
+
+    /// Replaces the whole storage of a given address, slots that aren't in `storage` read as zero.
+    pub fn add_state(&mut self, address: Address, storage: HashMap<H256, H256>) {
+        self.state.account(address).storage = Some(spoof::Storage::Replace(storage));
+    }
+
+    /// Overrides a single storage slot at a given address, other slots keep their value.
+    pub fn add_storage(&mut self, address: Address, slot: H256, value: H256) {
+        self.state.account(address).store(slot, value);
+    }
+
+    /// The overrides applied to every request.
+    pub fn state(&self) -> &spoof::State {
+        &self.state
+    }
+
+    /// Removes every override.
+    pub fn clear(&mut self) {
+        self.state = spoof::state();
+    }
+
+    /// The middleware's overrides with `overrides` applied on top.
+    fn merged_state(&self, overrides: &spoof::State) -> spoof::State {
+        let mut state = self.state.clone();
+
+        for (address, account) in overrides.iter() {
+            let merged = state.account(*address);
+            merged.nonce = account.nonce.or(merged.nonce);
+            merged.balance = account.balance.or(merged.balance);
+            merged.code = account.code.clone().or(merged.code.take());
+            merged.storage = match (merged.storage.take(), account.storage.clone()) {
+                (Some(spoof::Storage::Diff(mut base)), Some(spoof::Storage::Diff(slots))) => {
+                    base.extend(slots);
+                    Some(spoof::Storage::Diff(base))
+                }
+                (Some(spoof::Storage::Replace(mut base)), Some(spoof::Storage::Diff(slots))) => {
+                    base.extend(slots);
+                    Some(spoof::Storage::Replace(base))
+                }
+                (base, None) => base,
+                (_, storage) => storage,
+            };
+        }
+
+        state
+    }
+}
+
+/// Raw requests need an explicit block to be able to pass the overrides after it.
+fn block_or_latest(block: Option<BlockId>) -> BlockId {
+    block.unwrap_or(BlockId::Number(BlockNumber::Latest))
 }
 
 #[derive(Error, Debug)]
@@ -79,6 +287,9 @@ pub enum StateOverrideMiddlewareError<M: Middleware> {
     /// Thrown when the internal middleware errors
     #[error("{0}")]
     MiddlewareError(M::Error),
+    /// Thrown when none of the probed slots holds the token's balances
+    #[error("balance mapping slot of token {0:?} not found")]
+    BalanceSlotNotFound(Address),
 }
 
 impl<M: Middleware> MiddlewareError for StateOverrideMiddlewareError<M> {
@@ -91,6 +302,7 @@ impl<M: Middleware> MiddlewareError for StateOverrideMiddlewareError<M> {
     fn as_inner(&self) -> Option<&Self::Inner> {
         match self {
             StateOverrideMiddlewareError::MiddlewareError(e) => Some(e),
+            StateOverrideMiddlewareError::BalanceSlotNotFound(_) => None,
         }
     }
 }
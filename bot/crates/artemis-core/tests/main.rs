@@ -1,14 +1,60 @@
 use artemis_core::{
-    collectors::{block_collector::BlockCollector, mempool_collector::MempoolCollector},
-    types::Collector,
+    collectors::{
+        block_collector::BlockCollector,
+        broadcast_collector::BroadcastCollector,
+        capture_collector::CaptureCollector,
+        mempool_collector::MempoolCollector,
+        mev_share_collector::{MevShareCollector, MevShareEvent, MevShareLog, MevShareTx},
+        multi_mempool_collector::{
+            MempoolEvent, MempoolSource, MultiMempoolCollector, PendingTxSubscription,
+        },
+        reconnecting_collector::{ReconnectingCollector, StreamEvent},
+        recording_collector::RecordingCollector,
+        replay_collector::{FileBlockSource, ReplayCollector, ReplaySpeed, RpcBlockSource},
+    },
+    engine::{BackpressurePolicy, ChannelStats, Engine},
+    executors::{
+        bundle_simulator::{
+            BundleExpectation, BundleSimulation, BundleSimulator, RelayBundleSimulator,
+        },
+        flashbots_executor::{BundleSubmission, FlashbotsExecutor, SubmissionStatus},
+        mev_share_executor::{MevShareBundle, MevShareExecutor},
+        multi_builder_executor::{BuilderConfig, MultiBuilderExecutor},
+        paper_trading_executor::{PaperTradingExecutor, PaperTradingStats},
+        recorder_executor::RecorderExecutor,
+        webhook_executor::{Notification, WebhookConfig, WebhookExecutor, WebhookFormat},
+    },
+    types::{Collector, CollectorStream, Events, Executor, Strategy},
+    utilities::{
+        capture::{read_capture, CaptureRecord, CaptureWriter},
+        mock_mev_share::MockMevShare,
+        mock_relay::MockRelay,
+        mock_webhook::MockWebhook,
+        state_override_middleware::StateOverrideMiddleware,
+    },
 };
+use async_trait::async_trait;
 use ethers::providers::StreamExt;
 use ethers::{
     providers::{Middleware, Provider, Ws},
-    types::{BlockNumber, TransactionRequest, U256},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, Block, BlockNumber, Bytes,
+        Eip1559TransactionRequest, Transaction, TransactionRequest, H256, I256, U256, U64,
+    },
     utils::{Anvil, AnvilInstance},
 };
-use std::{sync::Arc, time::Duration};
+use ethers_flashbots::BundleRequest;
+use reqwest::Url;
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::broadcast;
+use uuid::Uuid;
 
 /// Spawns Anvil and instantiates an Http provider.
 pub async fn spawn_anvil() -> (Provider<Ws>, AnvilInstance) {
@@ -20,6 +66,26 @@ pub async fn spawn_anvil() -> (Provider<Ws>, AnvilInstance) {
     (provider, anvil)
 }
 
+/// Signs a bundle containing a single transfer from `searcher` targeting block 100.
+fn signed_bundle(searcher: &LocalWallet, nonce: u64) -> (BundleRequest, Bytes) {
+    let tx: TypedTransaction = Eip1559TransactionRequest::new()
+        .to(Address::random())
+        .nonce(nonce)
+        .gas(21000)
+        .max_fee_per_gas(1)
+        .max_priority_fee_per_gas(1)
+        .chain_id(1)
+        .into();
+    let signature = searcher.sign_transaction_sync(&tx).unwrap();
+    let raw = tx.rlp_signed(&signature);
+
+    let bundle = BundleRequest::new()
+        .push_transaction(raw.clone())
+        .set_block(U64::from(100))
+        .set_simulation_block(U64::from(99));
+    (bundle, raw)
+}
+
 /// Test that block collector correctly emits blocks.
 #[tokio::test]
 async fn test_block_collector_sends_blocks() {
@@ -57,3 +123,1244 @@ async fn test_mempool_collector_sends_txs() {
     let tx = mempool_stream.into_future().await.0.unwrap();
     assert_eq!(tx.value, value.into());
 }
+
+/// Test that the multi mempool collector emits each tx once, whichever source sees it.
+#[tokio::test]
+async fn test_multi_mempool_collector_deduplicates_txs() {
+    let (provider, anvil) = spawn_anvil().await;
+    let provider = Arc::new(provider);
+    let other_provider = Provider::<Ws>::connect(anvil.ws_endpoint()).await.unwrap();
+    let sources = vec![
+        MempoolSource {
+            name: "a".to_string(),
+            provider: provider.clone(),
+            subscription: PendingTxSubscription::Hashes,
+        },
+        MempoolSource {
+            name: "b".to_string(),
+            provider: Arc::new(other_provider),
+            subscription: PendingTxSubscription::Hashes,
+        },
+    ];
+    let mempool_collector = MultiMempoolCollector::new(sources);
+    let first_seen_counts = mempool_collector.first_seen_counts();
+    let mut mempool_stream = mempool_collector.get_event_stream().await.unwrap();
+
+    let account = provider.get_accounts().await.unwrap()[0];
+    let mut hashes = vec![];
+    for value in 1..=2u64 {
+        let tx = TransactionRequest::new()
+            .to(account)
+            .from(account)
+            .value(value);
+        hashes.push(*provider.send_transaction(tx, None).await.unwrap());
+    }
+
+    let mut seen = vec![];
+    for _ in 0..2 {
+        let seen_tx = match mempool_stream.next().await.unwrap() {
+            MempoolEvent::Transaction(seen_tx) => seen_tx,
+            event => panic!("unexpected event: {:?}", event),
+        };
+        assert!(seen_tx.source == "a" || seen_tx.source == "b");
+        seen.push(seen_tx.tx.hash);
+    }
+    seen.sort();
+    hashes.sort();
+    assert_eq!(seen, hashes);
+
+    // the other source's copies are dropped
+    let duplicate = tokio::time::timeout(Duration::from_secs(1), mempool_stream.next()).await;
+    assert!(duplicate.is_err());
+    assert_eq!(first_seen_counts.lock().unwrap().values().sum::<u64>(), 2);
+}
+
+/// Test that txs mined by the time their hash is looked up aren't emitted as pending.
+#[tokio::test]
+async fn test_multi_mempool_collector_skips_mined_txs() {
+    let anvil = Anvil::new().arg("--no-mining").spawn();
+    let provider = Provider::<Ws>::connect(anvil.ws_endpoint())
+        .await
+        .unwrap()
+        .interval(Duration::from_millis(50u64));
+    let provider = Arc::new(provider);
+    let sources = vec![MempoolSource {
+        name: "a".to_string(),
+        provider: provider.clone(),
+        subscription: PendingTxSubscription::Hashes,
+    }];
+    let mempool_collector = MultiMempoolCollector::new(sources);
+    let mut mempool_stream = mempool_collector.get_event_stream().await.unwrap();
+
+    // the first tx is mined before the stream gets to look it up
+    let account = provider.get_accounts().await.unwrap()[0];
+    let mut hashes = vec![];
+    for value in 1..=2u64 {
+        let tx = TransactionRequest::new()
+            .to(account)
+            .from(account)
+            .value(value);
+        hashes.push(*provider.send_transaction(tx, None).await.unwrap());
+        if value == 1 {
+            provider
+                .request::<_, U256>("evm_mine", None::<()>)
+                .await
+                .unwrap();
+        }
+    }
+
+    let seen_tx = match mempool_stream.next().await.unwrap() {
+        MempoolEvent::Transaction(seen_tx) => seen_tx,
+        event => panic!("unexpected event: {:?}", event),
+    };
+    assert_eq!(seen_tx.tx.hash, hashes[1]);
+    let mined = tokio::time::timeout(Duration::from_secs(1), mempool_stream.next()).await;
+    assert!(mined.is_err());
+}
+
+/// Test that blocks recorded from an RPC are replayed from file in order, each block's txs first.
+#[tokio::test]
+async fn test_replay_collector_replays_recorded_blocks() {
+    let (provider, _anvil) = spawn_anvil().await;
+    let provider = Arc::new(provider);
+
+    let account = provider.get_accounts().await.unwrap()[0];
+    let mut receipts = vec![];
+    for value in 1..=2u64 {
+        let tx = TransactionRequest::new()
+            .to(account)
+            .from(account)
+            .value(value);
+        let pending_tx = provider.send_transaction(tx, None).await.unwrap();
+        receipts.push(pending_tx.await.unwrap().unwrap());
+    }
+    let to_block = provider.get_block_number().await.unwrap();
+
+    let path = std::env::temp_dir().join("artemis_replay_blocks.jsonl");
+    let recorded = RpcBlockSource::new(provider.clone(), U64::one(), to_block)
+        .record(&path)
+        .await
+        .unwrap();
+    assert_eq!(recorded as u64, to_block.as_u64());
+
+    let collector = ReplayCollector::new(Box::new(FileBlockSource::new(&path)))
+        .with_speed(ReplaySpeed::Interval(Duration::from_millis(100)))
+        .with_repeat(true);
+    let started = Instant::now();
+    let events: Vec<Events> = collector.get_event_stream().await.unwrap().collect().await;
+    assert!(started.elapsed() >= Duration::from_millis(100) * (recorded as u32 - 1));
+    assert!(collector.finished().is_cancelled());
+
+    let mut blocks = vec![];
+    let mut pending = vec![];
+    for event in events {
+        match event {
+            Events::Transaction(tx) => {
+                assert!(tx.block_number.is_none());
+                pending.push(tx.hash);
+            }
+            Events::NewBlock(block) => {
+                // txs are emitted right before the block that includes them
+                let included: Vec<H256> = receipts
+                    .iter()
+                    .filter(|receipt| receipt.block_number == Some(block.number))
+                    .map(|receipt| receipt.transaction_hash)
+                    .collect();
+                assert_eq!(pending, included);
+                pending.clear();
+                blocks.push(block.number.as_u64());
+            }
+        }
+    }
+    assert_eq!(blocks, (1..=to_block.as_u64()).collect::<Vec<_>>());
+}
+
+/// Test that the MEV-Share collector decodes hints and skips keep alives and malformed events.
+#[tokio::test]
+async fn test_mev_share_collector_receives_hints() {
+    let mev_share = MockMevShare::spawn().unwrap();
+    let mev_share_collector = MevShareCollector::new(mev_share.url());
+    let mut mev_share_stream = mev_share_collector.get_event_stream().await.unwrap();
+    assert_eq!(mev_share.clients(), 1);
+
+    let event = MevShareEvent {
+        hash: H256::random(),
+        logs: vec![MevShareLog {
+            address: Address::random(),
+            topics: vec![H256::random()],
+            data: Bytes::from(vec![1u8; 128]),
+        }],
+        txs: Some(vec![MevShareTx {
+            to: Some(Address::random()),
+            function_selector: Some(Bytes::from(vec![0x02, 0x2c, 0x0d, 0x9f])),
+            call_data: None,
+        }]),
+    };
+    mev_share.send_raw(": ping\n\n");
+    mev_share.send_raw("data: {oops}\n\n");
+    mev_share.send(&event);
+    // hints without logs or txs shared
+    mev_share.send_raw(&format!("data: {{\"hash\":\"{:?}\"}}\n\n", H256::zero()));
+
+    assert_eq!(mev_share_stream.next().await.unwrap(), event);
+    let hash_only = mev_share_stream.next().await.unwrap();
+    assert_eq!(hash_only.hash, H256::zero());
+    assert!(hash_only.logs.is_empty() && hash_only.txs.is_none());
+
+    // the stream ends once the server closes it
+    mev_share.disconnect_clients();
+    let end = tokio::time::timeout(Duration::from_secs(5), mev_share_stream.next()).await;
+    assert!(end.unwrap().is_none());
+}
+
+/// Test that backrun bundles are sent with `mev_sendBundle`, behind the hinted tx.
+#[tokio::test]
+async fn test_mev_share_executor_against_mock_relay() {
+    let relay = MockRelay::spawn().unwrap();
+    let (provider, _mock) = Provider::mocked();
+    let auth_signer: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000002"
+            .parse()
+            .unwrap();
+    let searcher: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    let executor = MevShareExecutor::new(Arc::new(provider), auth_signer.clone(), relay.url());
+
+    let victim_hash = H256::random();
+    let (_, raw) = signed_bundle(&searcher, 0);
+    executor
+        .execute(MevShareBundle {
+            victim_hash,
+            txs: vec![raw.clone()],
+            block: U64::from(100),
+            max_block: U64::from(102),
+        })
+        .await
+        .unwrap();
+
+    let bundles = relay.mev_share_bundles();
+    assert_eq!(bundles.len(), 1);
+    assert_eq!(bundles[0].signer, auth_signer.address());
+    assert_eq!(bundles[0].block_number, U64::from(100));
+    assert_eq!(bundles[0].txs, vec![raw]);
+    assert_eq!(
+        bundles[0].transactions().unwrap()[0].from,
+        searcher.address()
+    );
+    assert_eq!(
+        bundles[0].params["body"][0]["hash"],
+        serde_json::json!(victim_hash)
+    );
+    assert_eq!(bundles[0].params["inclusion"]["maxBlock"], "0x66");
+    assert!(relay.bundles().is_empty());
+}
+
+/// Test that simulations are only accepted when within tolerance of the expectation.
+#[test]
+fn test_bundle_expectation_tolerance() {
+    let expectation = BundleExpectation {
+        searcher: Address::random(),
+        coinbase_payment: U256::from(10_000),
+        profit: I256::from(-100),
+    };
+
+    let simulation = BundleSimulation {
+        coinbase_payment: U256::from(10_050),
+        profit: None,
+    };
+    assert!(expectation.check(&simulation, 100).is_ok());
+    assert!(expectation.check(&simulation, 10).is_err());
+
+    let simulation = BundleSimulation {
+        coinbase_payment: U256::from(10_000),
+        profit: Some(I256::from(-200)),
+    };
+    assert!(expectation.check(&simulation, 100).is_err());
+}
+
+/// Test that the mock relay keeps the latest bundle per uuid and drops cancelled bundles.
+#[tokio::test]
+async fn test_mock_relay_replaces_and_cancels_bundles() {
+    let relay = MockRelay::spawn().unwrap();
+    let (provider, _mock) = Provider::mocked();
+    let auth_signer: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000002"
+            .parse()
+            .unwrap();
+    let searcher: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    let executor = FlashbotsExecutor::new(Arc::new(provider), auth_signer.clone(), relay.url());
+
+    let uuid = Uuid::new_v4();
+    let mut replacement = None;
+    for nonce in 0..2 {
+        let (request, raw) = signed_bundle(&searcher, nonce);
+        replacement = Some(raw);
+        executor
+            .execute(BundleSubmission {
+                request,
+                expectation: None,
+                replacement_uuid: Some(uuid),
+            })
+            .await
+            .unwrap();
+    }
+
+    let bundles = relay.bundles();
+    assert_eq!(bundles.len(), 2);
+    assert!(bundles.iter().all(|b| b.signer == auth_signer.address()));
+    assert!(bundles.iter().all(|b| b.block_number == U64::from(100)));
+
+    let live_bundles = relay.live_bundles();
+    assert_eq!(live_bundles.len(), 1);
+    assert_eq!(live_bundles[0].txs, vec![replacement.unwrap()]);
+    assert_eq!(
+        live_bundles[0].transactions().unwrap()[0].from,
+        searcher.address()
+    );
+
+    executor.execute(uuid).await.unwrap();
+    assert_eq!(relay.cancellations(), vec![uuid]);
+    assert!(relay.live_bundles().is_empty());
+    assert_eq!(relay.rejected(), 0);
+}
+
+/// Test that the mock relay refuses requests without a valid signature.
+#[tokio::test]
+async fn test_mock_relay_rejects_unsigned_requests() {
+    let relay = MockRelay::spawn().unwrap();
+    let client = reqwest::Client::new();
+    let body = r#"{"jsonrpc":"2.0","id":1,"method":"eth_cancelBundle","params":[{"replacementUuid":"00000000-0000-0000-0000-000000000000"}]}"#;
+
+    let unsigned = client.post(relay.url()).body(body).send().await.unwrap();
+    assert_eq!(unsigned.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let forged = client
+        .post(relay.url())
+        .header(
+            "X-Flashbots-Signature",
+            format!("{:?}:0x{}", Address::random(), "00".repeat(65)),
+        )
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(forged.status(), reqwest::StatusCode::FORBIDDEN);
+
+    assert_eq!(relay.rejected(), 2);
+    assert!(relay.cancellations().is_empty());
+}
+
+/// Test that relay simulations read the coinbase payment of the searcher's txs.
+#[tokio::test]
+async fn test_relay_simulator_against_mock_relay() {
+    let relay = MockRelay::spawn().unwrap();
+    let (provider, _mock) = Provider::mocked();
+    let searcher: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    let simulator = RelayBundleSimulator::new(
+        Arc::new(provider),
+        "0x0000000000000000000000000000000000000000000000000000000000000002"
+            .parse::<LocalWallet>()
+            .unwrap(),
+        relay.url(),
+    );
+    let (bundle, _) = signed_bundle(&searcher, 0);
+
+    // default simulation pays nothing to the coinbase
+    let simulation = simulator
+        .simulate(&bundle, searcher.address())
+        .await
+        .unwrap();
+    assert_eq!(simulation.coinbase_payment, U256::zero());
+    assert_eq!(relay.simulations().len(), 1);
+
+    let tx = relay.simulations()[0].transactions().unwrap().remove(0);
+    relay.set_call_bundle_response(serde_json::json!({
+        "bundleHash": relay.simulations()[0].bundle_hash(),
+        "bundleGasPrice": "1",
+        "coinbaseDiff": "5000",
+        "ethSentToCoinbase": "5000",
+        "gasFees": "21000",
+        "stateBlockNumber": 99,
+        "totalGasUsed": 21000,
+        "results": [{
+            "txHash": tx.hash,
+            "fromAddress": tx.from,
+            "toAddress": tx.to,
+            "gasUsed": 21000,
+            "gasPrice": "1",
+            "gasFees": "21000",
+            "coinbaseDiff": "5000",
+            "ethSentToCoinbase": "5000",
+            "value": "0x",
+        }],
+    }));
+    let simulation = simulator
+        .simulate(&bundle, searcher.address())
+        .await
+        .unwrap();
+    assert_eq!(simulation.coinbase_payment, U256::from(5000));
+}
+
+/// Simulator that reports the same outcome for every bundle.
+struct FixedSimulator(BundleSimulation);
+
+#[async_trait]
+impl BundleSimulator for FixedSimulator {
+    async fn simulate(
+        &self,
+        _bundle: &BundleRequest,
+        _searcher: Address,
+    ) -> anyhow::Result<BundleSimulation> {
+        Ok(self.0.clone())
+    }
+}
+
+/// Test that the flashbots executor only sends bundles whose simulation is within tolerance.
+#[tokio::test]
+async fn test_flashbots_executor_drops_bundles_out_of_tolerance() {
+    let relay = MockRelay::spawn().unwrap();
+    let searcher: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    let submission = |nonce| BundleSubmission {
+        request: signed_bundle(&searcher, nonce).0,
+        expectation: Some(BundleExpectation {
+            searcher: searcher.address(),
+            coinbase_payment: U256::from(10_000),
+            profit: I256::from(100),
+        }),
+        replacement_uuid: Some(Uuid::new_v4()),
+    };
+
+    let executor = |simulation| {
+        let (provider, _mock) = Provider::mocked();
+        FlashbotsExecutor::new(
+            Arc::new(provider),
+            "0x0000000000000000000000000000000000000000000000000000000000000002"
+                .parse::<LocalWallet>()
+                .unwrap(),
+            relay.url(),
+        )
+        .with_simulator(Box::new(FixedSimulator(simulation)), 100)
+    };
+
+    // pays 2% less than expected
+    let short = executor(BundleSimulation {
+        coinbase_payment: U256::from(9_800),
+        profit: Some(I256::from(100)),
+    });
+    short.execute(submission(0)).await.unwrap();
+    assert!(relay.bundles().is_empty());
+
+    // bundles without an expectation are never simulated
+    short
+        .execute(BundleSubmission {
+            expectation: None,
+            ..submission(1)
+        })
+        .await
+        .unwrap();
+    assert_eq!(relay.bundles().len(), 1);
+
+    // within 1% of the expectation
+    let close = executor(BundleSimulation {
+        coinbase_payment: U256::from(10_050),
+        profit: Some(I256::from(100)),
+    });
+    close.execute(submission(2)).await.unwrap();
+    assert_eq!(relay.bundles().len(), 2);
+}
+
+/// Builder config signing with the test auth key.
+fn builder_config(name: &str, url: Url, timeout: Duration) -> BuilderConfig<LocalWallet> {
+    BuilderConfig {
+        name: name.to_string(),
+        url,
+        signer: "0x0000000000000000000000000000000000000000000000000000000000000002"
+            .parse()
+            .unwrap(),
+        timeout,
+        enabled: true,
+    }
+}
+
+/// Test that bundles are sent to every enabled builder, and that each builder's outcome is
+/// recorded in its own stats.
+#[tokio::test]
+async fn test_multi_builder_executor_fans_out_bundles() {
+    let (first, second, disabled) = (
+        MockRelay::spawn().unwrap(),
+        MockRelay::spawn().unwrap(),
+        MockRelay::spawn().unwrap(),
+    );
+    // accepts connections but never answers
+    let unresponsive = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let unresponsive_url =
+        Url::parse(&format!("http://{}", unresponsive.local_addr().unwrap())).unwrap();
+    // nothing listens on the port once the listener is dropped
+    let closed_url = {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap()
+    };
+
+    let timeout = Duration::from_secs(2);
+    let (provider, _mock) = Provider::mocked();
+    let executor = MultiBuilderExecutor::new(
+        Arc::new(provider),
+        vec![
+            builder_config("first", first.url(), timeout),
+            builder_config("second", second.url(), timeout),
+            builder_config("unresponsive", unresponsive_url, Duration::from_millis(200)),
+            builder_config("closed", closed_url, timeout),
+            BuilderConfig {
+                enabled: false,
+                ..builder_config("disabled", disabled.url(), timeout)
+            },
+        ],
+    );
+    let stats = executor.stats();
+    let (reports, _) = broadcast::channel(16);
+    let report_collector = BroadcastCollector::new(reports.clone());
+    let mut report_stream = report_collector.get_event_stream().await.unwrap();
+    let executor = executor.with_reports(reports);
+
+    let searcher: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    let started = Instant::now();
+    for nonce in 0..2 {
+        executor
+            .execute(BundleSubmission {
+                request: signed_bundle(&searcher, nonce).0,
+                expectation: None,
+                replacement_uuid: Some(Uuid::new_v4()),
+            })
+            .await
+            .unwrap();
+    }
+    // builders are sent to concurrently, the slow one only holds up each bundle by its own timeout
+    assert!(started.elapsed() < Duration::from_secs(2));
+
+    assert_eq!(first.bundles().len(), 2);
+    assert_eq!(second.bundles().len(), 2);
+    assert!(disabled.bundles().is_empty());
+
+    let stats = stats.lock().unwrap().clone();
+    assert!(!stats.contains_key("disabled"));
+    for name in ["first", "second"] {
+        assert_eq!(stats[name].submitted, 2);
+        assert_eq!(stats[name].accepted, 2);
+        assert_eq!(stats[name].rejected + stats[name].timed_out, 0);
+    }
+    assert_eq!(stats["unresponsive"].submitted, 2);
+    assert_eq!(stats["unresponsive"].timed_out, 2);
+    assert_eq!(stats["unresponsive"].accepted, 0);
+    assert_eq!(stats["unresponsive"].average_latency(), Duration::ZERO);
+    assert_eq!(stats["closed"].submitted, 2);
+    assert_eq!(stats["closed"].rejected, 2);
+
+    // sent as long as one builder accepted it
+    for _ in 0..2 {
+        let report = report_stream.next().await.unwrap();
+        assert_eq!(report.status, SubmissionStatus::Sent);
+        assert_eq!(report.transaction_hashes.len(), 1);
+    }
+}
+
+/// Test that a bundle failing simulation is sent to none of the builders.
+#[tokio::test]
+async fn test_multi_builder_executor_simulates_once_for_all_builders() {
+    let (first, second) = (MockRelay::spawn().unwrap(), MockRelay::spawn().unwrap());
+    let (provider, _mock) = Provider::mocked();
+    let executor = MultiBuilderExecutor::new(
+        Arc::new(provider),
+        vec![
+            builder_config("first", first.url(), Duration::from_secs(2)),
+            builder_config("second", second.url(), Duration::from_secs(2)),
+        ],
+    )
+    .with_simulator(
+        Box::new(FixedSimulator(BundleSimulation {
+            coinbase_payment: U256::zero(),
+            profit: None,
+        })),
+        100,
+    );
+    let stats = executor.stats();
+    let (reports, _) = broadcast::channel(16);
+    let report_collector = BroadcastCollector::new(reports.clone());
+    let mut report_stream = report_collector.get_event_stream().await.unwrap();
+    let executor = executor.with_reports(reports);
+
+    let searcher: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    executor
+        .execute(BundleSubmission {
+            request: signed_bundle(&searcher, 0).0,
+            expectation: Some(BundleExpectation {
+                searcher: searcher.address(),
+                coinbase_payment: U256::from(10_000),
+                profit: I256::from(100),
+            }),
+            replacement_uuid: Some(Uuid::new_v4()),
+        })
+        .await
+        .unwrap();
+
+    assert!(first.bundles().is_empty());
+    assert!(second.bundles().is_empty());
+    assert!(stats
+        .lock()
+        .unwrap()
+        .values()
+        .all(|stats| stats.submitted == 0));
+
+    let report = report_stream.next().await.unwrap();
+    assert!(matches!(report.status, SubmissionStatus::Dropped(_)));
+}
+
+/// Test that paper trading only simulates the latest live bundle per uuid once its block arrives.
+#[tokio::test]
+async fn test_paper_trading_skips_replaced_and_cancelled_bundles() {
+    let (provider, mock) = Provider::mocked();
+    let executor = PaperTradingExecutor::new(
+        Arc::new(provider),
+        Box::new(FixedSimulator(BundleSimulation {
+            coinbase_payment: U256::from(10),
+            profit: Some(I256::from(100)),
+        })),
+    );
+    let stats = executor.stats();
+
+    let submission = |uuid| BundleSubmission {
+        request: BundleRequest::new().set_block(U64::from(100)),
+        expectation: Some(BundleExpectation {
+            searcher: Address::random(),
+            coinbase_payment: U256::from(10),
+            profit: I256::from(100),
+        }),
+        replacement_uuid: Some(uuid),
+    };
+    let (replaced, cancelled) = (Uuid::new_v4(), Uuid::new_v4());
+    for uuid in [replaced, replaced, cancelled] {
+        executor.execute(submission(uuid)).await.unwrap();
+    }
+    executor.execute(cancelled).await.unwrap();
+
+    // target block is mined, it is looked up once for all three bundles (responses are popped
+    // last in first out)
+    let block = Block::<H256> {
+        number: Some(U64::from(100)),
+        ..Default::default()
+    };
+    mock.push::<Block<H256>, _>(block).unwrap();
+    mock.push::<U64, _>(U64::from(100)).unwrap();
+
+    let stats = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let stats = stats.lock().unwrap().clone();
+            if stats.simulated + stats.replaced == 3 {
+                return stats;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(stats.simulated, 1);
+    assert_eq!(stats.replaced, 2);
+    assert_eq!(stats.failed, 0);
+    assert_eq!(stats.profit, I256::from(100));
+    assert_eq!(stats.coinbase_payment, U256::from(10));
+}
+
+/// Keeps every bundle it is asked to simulate, along with the searcher.
+struct RecordingSimulator(Arc<Mutex<Vec<(BundleRequest, Address)>>>);
+
+#[async_trait]
+impl BundleSimulator for RecordingSimulator {
+    async fn simulate(
+        &self,
+        bundle: &BundleRequest,
+        searcher: Address,
+    ) -> anyhow::Result<BundleSimulation> {
+        self.0.lock().unwrap().push((bundle.clone(), searcher));
+        Ok(BundleSimulation {
+            coinbase_payment: U256::from(10),
+            profit: Some(I256::from(100)),
+        })
+    }
+}
+
+/// Test that paper traded MEV-Share backruns are simulated behind their hinted tx once it is mined,
+/// and skipped if it never is.
+#[tokio::test]
+async fn test_paper_trading_simulates_backruns_behind_their_hint() {
+    let (provider, mock) = Provider::mocked();
+    let simulated = Arc::new(Mutex::new(vec![]));
+    let executor = PaperTradingExecutor::new(
+        Arc::new(provider),
+        Box::new(RecordingSimulator(simulated.clone())),
+    );
+    let stats = executor.stats();
+    let searcher: LocalWallet =
+        "0x0000000000000000000000000000000000000000000000000000000000000001"
+            .parse()
+            .unwrap();
+    let (_, raw) = signed_bundle(&searcher, 0);
+    let backrun = |block: u64| MevShareBundle {
+        victim_hash: H256::from_low_u64_be(block),
+        txs: vec![raw.clone()],
+        block: U64::from(block),
+        max_block: U64::from(block),
+    };
+    let wait_for = |done: fn(&PaperTradingStats) -> bool| {
+        let stats = stats.clone();
+        tokio::time::timeout(Duration::from_secs(10), async move {
+            while !done(&stats.lock().unwrap()) {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        })
+    };
+
+    // the hinted tx is mined in the target block (responses are popped last in first out)
+    let victim = Transaction {
+        hash: H256::from_low_u64_be(100),
+        block_number: Some(U64::from(100)),
+        ..Default::default()
+    };
+    mock.push::<Transaction, _>(victim).unwrap();
+    mock.push::<Block<H256>, _>(Block::<H256> {
+        number: Some(U64::from(100)),
+        ..Default::default()
+    })
+    .unwrap();
+    mock.push::<U64, _>(U64::from(100)).unwrap();
+    executor.execute(backrun(100)).await.unwrap();
+    wait_for(|stats| stats.simulated == 1).await.unwrap();
+
+    // the next hinted tx is never mined
+    mock.push::<Option<Transaction>, _>(None).unwrap();
+    mock.push::<Block<H256>, _>(Block::<H256> {
+        number: Some(U64::from(101)),
+        ..Default::default()
+    })
+    .unwrap();
+    mock.push::<U64, _>(U64::from(101)).unwrap();
+    executor.execute(backrun(101)).await.unwrap();
+    wait_for(|stats| stats.unmatched == 1).await.unwrap();
+
+    let stats = stats.lock().unwrap().clone();
+    assert_eq!((stats.simulated, stats.unmatched), (1, 1));
+    assert_eq!(stats.profit, I256::from(100));
+
+    let simulated = simulated.lock().unwrap();
+    let (request, simulated_searcher) = &simulated[0];
+    assert_eq!(*simulated_searcher, searcher.address());
+    assert_eq!(request.transaction_hashes().len(), 2);
+    assert_eq!(request.transaction_hashes()[0], H256::from_low_u64_be(100));
+    assert_eq!(request.simulation_block(), Some(U64::from(99)));
+}
+
+/// Emits `0..count` as fast as it can.
+struct CountingCollector(u64);
+
+#[async_trait]
+impl Collector<u64> for CountingCollector {
+    async fn get_event_stream(&self) -> anyhow::Result<CollectorStream<'_, u64>> {
+        Ok(Box::pin(futures::stream::iter(0..self.0)))
+    }
+}
+
+/// Records every event it receives, taking a millisecond per event.
+struct SlowStrategy(Arc<Mutex<Vec<u64>>>);
+
+#[async_trait]
+impl Strategy<u64, u64> for SlowStrategy {
+    async fn sync_state(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn process_event(&mut self, event: u64) -> Vec<u64> {
+        tokio::time::sleep(Duration::from_millis(1)).await;
+        self.0.lock().unwrap().push(event);
+        vec![]
+    }
+}
+
+/// Run 100 events through a slow strategy with a channel of 4, returns the events the strategy
+/// received and its channel counters.
+async fn run_slow_strategy(policy: BackpressurePolicy<u64>) -> (Vec<u64>, ChannelStats) {
+    let received = Arc::new(Mutex::new(vec![]));
+    let mut engine: Engine<u64, u64> = Engine::new()
+        .with_event_channel_capacity(4)
+        .with_event_backpressure(policy);
+    engine.add_collector(Box::new(CountingCollector(100)));
+    engine.add_strategy(Box::new(SlowStrategy(received.clone())));
+    let metrics = engine.metrics();
+    let _set = engine.run().await.unwrap();
+
+    let stats = tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            let stats = metrics.strategies()[0];
+            if stats.received + stats.lagged == 100
+                && received.lock().unwrap().len() as u64 == stats.received
+            {
+                return stats;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    let received = received.lock().unwrap().clone();
+    (received, stats)
+}
+
+/// Test that events a lagging strategy skips are counted.
+#[tokio::test]
+async fn test_engine_counts_lagged_events() {
+    let (received, stats) = run_slow_strategy(BackpressurePolicy::DropOldest).await;
+    assert!(stats.lagged > 0);
+    assert_eq!(stats.blocked, 0);
+    assert_eq!(received.len() as u64, stats.received);
+}
+
+/// Test that blocking collectors delivers every event in order.
+#[tokio::test]
+async fn test_engine_blocks_collectors() {
+    let (received, stats) = run_slow_strategy(BackpressurePolicy::Block).await;
+    assert_eq!(received, (0..100).collect::<Vec<_>>());
+    assert_eq!(stats.lagged, 0);
+    assert!(stats.blocked > 0);
+}
+
+/// Test that prioritised events are never dropped.
+#[tokio::test]
+async fn test_engine_prioritises_events() {
+    let (received, stats) =
+        run_slow_strategy(BackpressurePolicy::Prioritise(|event| event % 10 == 0)).await;
+    assert!(stats.lagged > 0);
+    let prioritised: Vec<u64> = received.into_iter().filter(|e| e % 10 == 0).collect();
+    assert_eq!(prioritised, (0..100).step_by(10).collect::<Vec<_>>());
+}
+
+/// Fails to start the first time, then emits `0..` every millisecond.
+struct FlakyCollector(AtomicBool);
+
+#[async_trait]
+impl Collector<u64> for FlakyCollector {
+    async fn get_event_stream(&self) -> anyhow::Result<CollectorStream<'_, u64>> {
+        if !self.0.swap(true, Ordering::Relaxed) {
+            return Err(anyhow::anyhow!("not ready"));
+        }
+        let stream = futures::stream::iter(0..).then(|event| async move {
+            tokio::time::sleep(Duration::from_millis(1)).await;
+            event
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Echoes every event as an action, flushes `u64::MAX`.
+struct EchoStrategy;
+
+#[async_trait]
+impl Strategy<u64, u64> for EchoStrategy {
+    async fn sync_state(&mut self) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    async fn process_event(&mut self, event: u64) -> Vec<u64> {
+        vec![event]
+    }
+
+    async fn flush(&mut self) -> Vec<u64> {
+        vec![u64::MAX]
+    }
+}
+
+/// Records every action and whether it was flushed.
+struct RecordingExecutor {
+    actions: Arc<Mutex<Vec<u64>>>,
+    flushed: Arc<AtomicBool>,
+}
+
+#[async_trait]
+impl Executor<u64> for RecordingExecutor {
+    async fn execute(&self, action: u64) -> anyhow::Result<()> {
+        self.actions.lock().unwrap().push(action);
+        Ok(())
+    }
+
+    async fn flush(&self) -> anyhow::Result<()> {
+        self.flushed.store(true, Ordering::Relaxed);
+        Ok(())
+    }
+}
+
+/// Test that a failed collector is restarted and that shutting down flushes every component.
+#[tokio::test]
+async fn test_engine_restarts_collectors_and_shuts_down_gracefully() {
+    let actions = Arc::new(Mutex::new(vec![]));
+    let flushed = Arc::new(AtomicBool::new(false));
+    let mut engine: Engine<u64, u64> =
+        Engine::new().with_restart_backoff(Duration::from_millis(10), Duration::from_millis(10));
+    engine.add_collector(Box::new(FlakyCollector(AtomicBool::new(false))));
+    engine.add_strategy(Box::new(EchoStrategy));
+    engine.add_executor(Box::new(RecordingExecutor {
+        actions: actions.clone(),
+        flushed: flushed.clone(),
+    }));
+    let shutdown = engine.shutdown_token();
+    let mut set = engine.run().await.unwrap();
+
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while actions.lock().unwrap().len() < 10 {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    shutdown.cancel();
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while let Some(res) = set.join_next().await {
+            res.unwrap();
+        }
+    })
+    .await
+    .unwrap();
+
+    let actions = actions.lock().unwrap();
+    let (last, events) = actions.split_last().unwrap();
+    assert_eq!(*last, u64::MAX);
+    assert_eq!(events, (0..events.len() as u64).collect::<Vec<_>>());
+    assert!(flushed.load(Ordering::Relaxed));
+}
+
+/// Every other subscription fails, the others emit three events then end.
+struct DroppingCollector(AtomicU64);
+
+#[async_trait]
+impl Collector<u64> for DroppingCollector {
+    async fn get_event_stream(&self) -> anyhow::Result<CollectorStream<'_, u64>> {
+        let subscription = self.0.fetch_add(1, Ordering::Relaxed);
+        if subscription % 2 == 1 {
+            return Err(anyhow::anyhow!("connection refused"));
+        }
+        let first = subscription / 2 * 3;
+        Ok(Box::pin(futures::stream::iter(first..first + 3)))
+    }
+}
+
+/// Test that a collector is resubscribed after its stream ends, with a gap in between.
+#[tokio::test]
+async fn test_reconnecting_collector_resubscribes() {
+    let collector = ReconnectingCollector::new(Box::new(DroppingCollector(AtomicU64::new(0))))
+        .with_backoff(Duration::from_millis(10), Duration::from_millis(100));
+    let stream = collector.get_event_stream().await.unwrap();
+
+    let events: Vec<Option<u64>> = stream
+        .take(8)
+        .map(|event| match event {
+            StreamEvent::Event(event) => Some(event),
+            StreamEvent::Gap(_) => None,
+        })
+        .collect()
+        .await;
+    assert_eq!(
+        events,
+        vec![
+            Some(0),
+            Some(1),
+            Some(2),
+            None,
+            Some(3),
+            Some(4),
+            Some(5),
+            None
+        ]
+    );
+}
+
+/// Test that recorded events and actions are read back in order, and that the events are replayed
+/// from the capture file.
+#[tokio::test]
+async fn test_capture_records_and_replays_events() {
+    use std::io::Write;
+
+    let path = std::env::temp_dir().join(format!("artemis_capture_{}.jsonl", Uuid::new_v4()));
+    let writer = CaptureWriter::open(&path).unwrap();
+
+    let collector = RecordingCollector::new(
+        Box::new(DroppingCollector(AtomicU64::new(0))),
+        writer.clone(),
+    );
+    let events: Vec<u64> = collector.get_event_stream().await.unwrap().collect().await;
+    assert_eq!(events, vec![0, 1, 2]);
+    let recorder = RecorderExecutor::new(writer);
+    recorder.execute("bundle".to_string()).await.unwrap();
+
+    // records are buffered until flushed
+    Executor::<String>::flush(&recorder).await.unwrap();
+
+    // a crash mid write leaves a truncated last line
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .unwrap()
+        .write_all(b"{\"kind\":\"ev")
+        .unwrap();
+
+    let records = read_capture::<u64>(&path).unwrap();
+    assert_eq!(records.len(), 4);
+    assert!(matches!(records[2], CaptureRecord::Event { event: 2, .. }));
+    assert!(matches!(&records[3], CaptureRecord::Action { action, .. } if *action == "bundle"));
+    assert!(records.windows(2).all(|pair| pair[0].at() <= pair[1].at()));
+
+    let capture = CaptureCollector::<u64>::new(&path);
+    let mut stream = capture.get_event_stream().await.unwrap();
+    let mut replayed = vec![];
+    for _ in 0..3 {
+        replayed.push(stream.next().await.unwrap());
+    }
+    assert_eq!(replayed, vec![0, 1, 2]);
+
+    // the stream stays open so that the engine doesn't replay the capture again
+    let next = tokio::time::timeout(Duration::from_millis(100), stream.next()).await;
+    assert!(next.is_err());
+    assert!(capture.finished().is_cancelled());
+}
+
+/// Test that balance, storage and ERC20 balance overrides apply to calls, and that per call
+/// overrides don't leak into later calls.
+#[tokio::test]
+async fn test_state_override_middleware_overrides() {
+    let (provider, _anvil) = spawn_anvil().await;
+    let mut middleware = StateOverrideMiddleware::new(provider);
+    let call = |to: Address, data: Vec<u8>| -> TypedTransaction {
+        TransactionRequest::new()
+            .from(Address::repeat_byte(0x11))
+            .to(to)
+            .data(data)
+            .into()
+    };
+    let as_u256 = |output: Bytes| U256::from_big_endian(&output);
+
+    // returns the caller's balance
+    let balance_of_caller = middleware.add_code(Bytes::from(vec![
+        0x33, 0x31, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+    ]));
+    middleware.add_balance(Address::repeat_byte(0x11), U256::from(1337));
+    let output = middleware
+        .call(&call(balance_of_caller, vec![]), None)
+        .await
+        .unwrap();
+    assert_eq!(as_u256(output), U256::from(1337));
+
+    // returns slot 0
+    let slot_zero = middleware.add_code(Bytes::from(vec![
+        0x60, 0x00, 0x54, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+    ]));
+    middleware.add_storage(slot_zero, H256::zero(), H256::from_low_u64_be(42));
+    let tx = call(slot_zero, vec![]);
+    assert_eq!(
+        as_u256(middleware.call(&tx, None).await.unwrap()),
+        U256::from(42)
+    );
+
+    let mut overrides = ethers::providers::spoof::state();
+    overrides
+        .account(slot_zero)
+        .store(H256::zero(), H256::from_low_u64_be(7));
+    let output = middleware
+        .call_with_overrides(&tx, None, &overrides)
+        .await
+        .unwrap();
+    assert_eq!(as_u256(output), U256::from(7));
+    assert_eq!(
+        as_u256(middleware.call(&tx, None).await.unwrap()),
+        U256::from(42)
+    );
+
+    // `balanceOf(address)` reading a solidity mapping declared at slot 3
+    let token = middleware.add_code(Bytes::from(vec![
+        0x60, 0x04, 0x35, 0x60, 0x00, 0x52, 0x60, 0x03, 0x60, 0x20, 0x52, 0x60, 0x40, 0x60, 0x00,
+        0x20, 0x54, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+    ]));
+    let owner = Address::random();
+    middleware
+        .set_erc20_balance(token, owner, U256::from(1234), None)
+        .await
+        .unwrap();
+    let mut calldata = ethers::utils::id("balanceOf(address)").to_vec();
+    calldata.extend(ethers::abi::encode(&[ethers::abi::Token::Address(owner)]));
+    let output = middleware
+        .call(&call(token, calldata.clone()), None)
+        .await
+        .unwrap();
+    assert_eq!(as_u256(output), U256::from(1234));
+
+    // overriding with the current balance still finds the mapping's slot
+    let slot = middleware
+        .set_erc20_balance(token, owner, U256::from(1234), None)
+        .await
+        .unwrap();
+    let mapping_key = [
+        ethers::abi::Token::Address(owner),
+        ethers::abi::Token::Uint(3.into()),
+    ];
+    assert_eq!(
+        slot,
+        H256::from(ethers::utils::keccak256(ethers::abi::encode(&mapping_key)))
+    );
+    let output = middleware.call(&call(token, calldata), None).await.unwrap();
+    assert_eq!(as_u256(output), U256::from(1234));
+}
+
+/// Test that overrides also apply to gas estimates and access lists, and that per request
+/// overrides don't leak into later requests.
+#[tokio::test]
+async fn test_state_override_middleware_estimates_with_overrides() {
+    let (provider, _anvil) = spawn_anvil().await;
+    let mut middleware = StateOverrideMiddleware::new(provider);
+    let call = |to: Address| -> TypedTransaction {
+        TransactionRequest::new()
+            .from(Address::repeat_byte(0x11))
+            .to(to)
+            .into()
+    };
+    // returns slot 0, costing more than a plain transfer
+    let code = Bytes::from(vec![
+        0x60, 0x00, 0x54, 0x60, 0x00, 0x52, 0x60, 0x20, 0x60, 0x00, 0xf3,
+    ]);
+
+    let contract = middleware.add_code(code.clone());
+    let gas = middleware
+        .estimate_gas(&call(contract), None)
+        .await
+        .unwrap();
+    assert!(gas > U256::from(21000));
+    let access_list = middleware
+        .create_access_list(&call(contract), None)
+        .await
+        .unwrap();
+    assert!(access_list.gas_used > U256::from(21000));
+
+    let scoped = Address::random();
+    let mut overrides = ethers::providers::spoof::state();
+    overrides.account(scoped).code(code);
+    let gas = middleware
+        .estimate_gas_with_overrides(&call(scoped), None, &overrides)
+        .await
+        .unwrap();
+    assert!(gas > U256::from(21000));
+    let access_list = middleware
+        .create_access_list_with_overrides(&call(scoped), None, &overrides)
+        .await
+        .unwrap();
+    assert!(access_list.gas_used > U256::from(21000));
+
+    let gas = middleware.estimate_gas(&call(scoped), None).await.unwrap();
+    assert_eq!(gas, U256::from(21000));
+    let access_list = middleware
+        .create_access_list(&call(scoped), None)
+        .await
+        .unwrap();
+    assert_eq!(access_list.gas_used, U256::from(21000));
+}
+
+/// Test that notifications are batched, spaced out, and split across posts.
+#[tokio::test]
+async fn test_webhook_executor_batches_notifications() {
+    let webhook = MockWebhook::spawn().unwrap();
+    let config = WebhookConfig {
+        batch_interval: Duration::from_millis(200),
+        max_batch_size: 3,
+        min_interval: Duration::from_millis(300),
+        ..WebhookConfig::new(webhook.url(), WebhookFormat::Generic)
+    };
+    let executor = WebhookExecutor::new(config);
+    let stats = executor.stats();
+
+    let start = Instant::now();
+    for i in 0..4 {
+        executor
+            .execute(Notification::info("Sandwich landed", format!("#{}", i)))
+            .await
+            .unwrap();
+    }
+    executor.flush().await.unwrap();
+
+    // first batch is full, the remaining notification is posted once the min interval is over
+    let requests = webhook.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0]["notifications"].as_array().unwrap().len(), 3);
+    assert_eq!(requests[0]["notifications"][0]["level"], "info");
+    assert_eq!(requests[0]["notifications"][0]["title"], "Sandwich landed");
+    assert_eq!(requests[1]["notifications"][0]["message"], "#3");
+    assert!(start.elapsed() >= Duration::from_millis(300));
+
+    let stats = stats.lock().unwrap().clone();
+    assert_eq!(stats.sent, 4);
+    assert_eq!(stats.posts, 2);
+    assert_eq!(stats.dropped + stats.failed, 0);
+}
+
+/// Test that Discord posts are retried after being rate limited, and that long batches are split
+/// to fit Discord's message limit.
+#[tokio::test]
+async fn test_webhook_executor_retries_rate_limited_discord_posts() {
+    let webhook = MockWebhook::spawn().unwrap();
+    webhook.rate_limit_next(1, 0.05);
+    let config = WebhookConfig {
+        batch_interval: Duration::from_millis(100),
+        min_interval: Duration::ZERO,
+        ..WebhookConfig::new(webhook.url(), WebhookFormat::Discord)
+    };
+    let executor = WebhookExecutor::new(config);
+    let stats = executor.stats();
+
+    executor
+        .execute(Notification::warning("Block stream down", "for 12s"))
+        .await
+        .unwrap();
+    executor
+        .execute(Notification::error("Bundle outbid", "x".repeat(1990)))
+        .await
+        .unwrap();
+    executor.flush().await.unwrap();
+
+    assert_eq!(webhook.rate_limited(), 1);
+    let requests = webhook.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(
+        requests[0]["content"],
+        "**[warning] Block stream down** for 12s"
+    );
+    assert!(requests[1]["content"]
+        .as_str()
+        .unwrap()
+        .starts_with("**[error] Bundle outbid** xxx"));
+
+    let stats = stats.lock().unwrap().clone();
+    assert_eq!(stats.sent, 2);
+    assert_eq!(stats.rate_limited, 1);
+    assert_eq!(stats.posts, 3);
+    assert_eq!(stats.failed, 0);
+}
@@ -0,0 +1,226 @@
+use std::io::Write;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, Transaction, H256, U256, U64},
+};
+use log::info;
+use serde::Serialize;
+
+use crate::{log_error, log_info_cyan, types::BlockInfo};
+
+use super::{weth_ingredients, SandoBot};
+
+/// Outcome of evaluating a historical victim against one of the pools it touched
+#[derive(Debug, Clone, Serialize)]
+pub struct BacktestResult {
+    pub block: u64,
+    pub victim_hash: H256,
+    pub pool: Address,
+    pub optimal_input: Option<U256>,
+    pub revenue: Option<U256>,
+    pub frontrun_gas_used: Option<u64>,
+    pub backrun_gas_used: Option<u64>,
+    /// Why the victim was not sandwichable
+    pub error: Option<String>,
+}
+
+/// Totals across all replayed blocks
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BacktestTotals {
+    pub blocks: u64,
+    pub victims_evaluated: u64,
+    pub sandwichable: u64,
+    pub revenue: U256,
+}
+
+/// Per victim results and totals of a backtest
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BacktestReport {
+    pub results: Vec<BacktestResult>,
+    pub totals: BacktestTotals,
+}
+
+impl BacktestReport {
+    /// Add the results of one replayed block
+    pub fn add_block(&mut self, results: Vec<BacktestResult>) {
+        self.totals.blocks += 1;
+        for result in &results {
+            self.totals.victims_evaluated += 1;
+            if let Some(revenue) = result.revenue {
+                self.totals.sandwichable += 1;
+                self.totals.revenue += revenue;
+            }
+        }
+        self.results.extend(results);
+    }
+
+    /// Write results and totals as pretty printed json
+    pub fn write_json<W: Write>(&self, writer: W) -> Result<()> {
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Write one csv row per result, amounts are in wei
+    pub fn write_csv<W: Write>(&self, writer: W) -> Result<()> {
+        let mut csv = csv::Writer::from_writer(writer);
+        csv.write_record([
+            "block",
+            "victim_hash",
+            "pool",
+            "optimal_input",
+            "revenue",
+            "frontrun_gas_used",
+            "backrun_gas_used",
+            "error",
+        ])?;
+
+        let opt = |v: Option<String>| v.unwrap_or_default();
+        for result in &self.results {
+            csv.write_record([
+                result.block.to_string(),
+                format!("{:?}", result.victim_hash),
+                format!("{:?}", result.pool),
+                opt(result.optimal_input.map(|v| v.to_string())),
+                opt(result.revenue.map(|v| v.to_string())),
+                opt(result.frontrun_gas_used.map(|v| v.to_string())),
+                opt(result.backrun_gas_used.map(|v| v.to_string())),
+                opt(result.error.clone()),
+            ])?;
+        }
+
+        csv.flush()?;
+        Ok(())
+    }
+}
+
+impl<M: Middleware + 'static> SandoBot<M> {
+    /// Evaluate every tx in `block_number` as if it were pending at the previous block
+    ///
+    /// Pools must be synced first (see `sync_state`)
+    pub async fn backtest_block(&self, block_number: U64) -> Result<Vec<BacktestResult>> {
+        let block = self
+            .provider
+            .get_block_with_txs(BlockNumber::Number(block_number))
+            .await
+            .map_err(|e| anyhow!("Failed to get block {}: {}", block_number, e))?
+            .ok_or(anyhow!("Block {} not found", block_number))?;
+
+        // victims are treated as pending while the parent block is the latest block
+        let parent_block = BlockNumber::Number(block_number - 1);
+        let parent_info: BlockInfo = self
+            .provider
+            .get_block(parent_block)
+            .await
+            .map_err(|e| anyhow!("Failed to get block {}: {}", block_number - 1, e))?
+            .ok_or(anyhow!("Block {} not found", block_number - 1))?
+            .try_into()?;
+        let target_block = parent_info.get_next_block();
+
+        let mut results = vec![];
+        for victim_tx in block.transactions {
+            results.extend(
+                self.evaluate_victim(&victim_tx, &parent_info, target_block)
+                    .await,
+            );
+        }
+
+        log_info_cyan!(
+            "[BACKTEST] block {} evaluated {} victims",
+            block_number,
+            results.len()
+        );
+
+        Ok(results)
+    }
+
+    /// Evaluate a single tx as if it were pending: a mined tx is evaluated at the block before
+    /// its own, a pending one on top of the latest block
+    ///
+    /// Pools must be synced first (see `sync_state`)
+    pub async fn simulate_tx(&self, tx_hash: H256) -> Result<Vec<BacktestResult>> {
+        let victim_tx = self
+            .provider
+            .get_transaction(tx_hash)
+            .await
+            .map_err(|e| anyhow!("Failed to get tx {:?}: {}", tx_hash, e))?
+            .ok_or(anyhow!("Tx {:?} not found", tx_hash))?;
+
+        let parent_block = match victim_tx.block_number {
+            Some(block_number) => BlockNumber::Number(block_number - 1),
+            None => BlockNumber::Latest,
+        };
+        let parent_info: BlockInfo = self
+            .provider
+            .get_block(parent_block)
+            .await
+            .map_err(|e| anyhow!("Failed to get block {:?}: {}", parent_block, e))?
+            .ok_or(anyhow!("Block {:?} not found", parent_block))?
+            .try_into()?;
+        let target_block = parent_info.get_next_block();
+
+        Ok(self
+            .evaluate_victim(&victim_tx, &parent_info, target_block)
+            .await)
+    }
+
+    /// Evaluate `victim_tx` against each pool it touches, while `parent_info` is the latest block
+    async fn evaluate_victim(
+        &self,
+        victim_tx: &Transaction,
+        parent_info: &BlockInfo,
+        target_block: BlockInfo,
+    ) -> Vec<BacktestResult> {
+        let touched_pools = match self
+            .pool_manager
+            .get_touched_sandwichable_pools(victim_tx, parent_info, self.provider.clone())
+            .await
+        {
+            Ok(pools) => pools,
+            Err(e) => {
+                log_error!(
+                    "Failed to get touched pools for {:?}: {}",
+                    victim_tx.hash,
+                    e
+                );
+                return vec![];
+            }
+        };
+
+        let mut results = vec![];
+
+        for pool in touched_pools {
+            let ingredients = match weth_ingredients(victim_tx, pool) {
+                Some(ingredients) => ingredients,
+                None => continue,
+            };
+
+            let mut result = BacktestResult {
+                block: target_block.number.as_u64(),
+                victim_hash: victim_tx.hash,
+                pool: pool.address(),
+                optimal_input: None,
+                revenue: None,
+                frontrun_gas_used: None,
+                backrun_gas_used: None,
+                error: None,
+            };
+
+            match self.is_sandwichable(ingredients, target_block).await {
+                Ok(recipe) => {
+                    result.optimal_input = Some(recipe.get_optimal_input());
+                    result.revenue = Some(recipe.get_revenue());
+                    result.frontrun_gas_used = Some(recipe.get_frontrun_gas_used());
+                    result.backrun_gas_used = Some(recipe.get_backrun_gas_used());
+                }
+                Err(e) => result.error = Some(e.to_string()),
+            }
+
+            results.push(result);
+        }
+
+        results
+    }
+}
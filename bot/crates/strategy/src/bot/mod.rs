@@ -1,24 +1,58 @@
-use anyhow::Result;
-use artemis_core::{collectors::block_collector::NewBlock, types::Strategy};
+use anyhow::{ensure, Result};
+use artemis_core::{
+    collectors::{block_collector::NewBlock, mev_share_collector::MevShareEvent},
+    executors::{
+        flashbots_executor::{SubmissionReport, SubmissionStatus},
+        webhook_executor::Notification,
+    },
+    types::Strategy,
+};
 use async_trait::async_trait;
-use cfmms::pool::Pool::{UniswapV2, UniswapV3};
+use cfmms::pool::Pool::{self, UniswapV2, UniswapV3};
 use colored::Colorize;
-use ethers::{providers::Middleware, types::Transaction};
-use foundry_evm::executor::fork::{BlockchainDb, BlockchainDbMeta, SharedBackend};
+use ethers::{
+    providers::Middleware,
+    types::{Address, BlockNumber, Transaction, H256, I256, U256, U64},
+    utils::keccak256,
+};
 use log::{error, info};
-use std::{collections::BTreeSet, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
 
 use crate::{
     constants::WETH_ADDRESS,
     log_error, log_info_cyan, log_new_block_info, log_not_sandwichable, log_opportunity,
     managers::{
-        block_manager::BlockManager, pool_manager::PoolManager,
+        block_manager::BlockManager,
+        bundle_manager::BundleManager,
+        inclusion_tracker::{InclusionTracker, TrackedBundle},
+        pool_manager::{weth_pair_token, PoolManager},
         sando_state_manager::SandoStateManager,
     },
-    simulator::{huff_sando::create_recipe, lil_router::find_optimal_input},
-    types::{Action, BlockInfo, Event, RawIngredients, SandoRecipe, StratConfig},
+    simulator::{
+        backend::{LocalBackend, SimulationBackend},
+        huff_sando::{create_backrun_recipe, create_recipe},
+        lil_router::{find_optimal_backrun_input, find_optimal_input},
+        setup_shared_backend, ForkDb,
+    },
+    storage::{
+        bundle_hash, writer::StoreWriter, OpportunityRecord, OpportunityStatus, OpportunityStore,
+    },
+    types::{
+        Action, BackrunIngredients, BlockInfo, BribePolicy, BundleOutcome, EnvPerturbation, Event,
+        PnlTotals, RawIngredients, RiskLimits, SalmonellaPolicy, SandoRecipe, StratConfig,
+        TxSource,
+    },
 };
 
+/// Replays historical blocks through the strategy
+pub mod backtest;
+
+/// Notifications posted to webhooks
+mod notifications;
+
 pub struct SandoBot<M> {
     /// Ethers client
     provider: Arc<M>,
@@ -26,25 +60,79 @@ pub struct SandoBot<M> {
     pool_manager: PoolManager<M>,
     /// Block manager
     block_manager: BlockManager,
+    /// Keeps track of sent bundles for replacement & cancellation
+    bundle_manager: BundleManager,
+    /// Watches blocks for our bundles and keeps track of pnl
+    inclusion_tracker: InclusionTracker,
+    /// Records evaluated opportunities and bundles, off the strategy loop
+    store: Option<StoreWriter>,
     /// Keeps track of weth inventory & token dust
     sando_state_manager: SandoStateManager,
+    /// Decides which opcodes/contracts fail the salmonella check
+    salmonella_policy: SalmonellaPolicy,
+    /// Environments that sandwiches are replayed under (empty if differential check is disabled)
+    env_perturbations: Vec<EnvPerturbation>,
+    /// Ignore public mempool txs, only backrun MEV-Share hints
+    backrun_only: bool,
+    /// Simulates frontrun inputs instead of the local fork (if set)
+    simulation_backend: Option<Arc<dyn SimulationBackend>>,
+    /// How much revenue is bribed
+    bribe_policy: BribePolicy,
+    /// Caps inputs and skips small opportunities
+    risk_limits: RiskLimits,
 }
 
 impl<M: Middleware + 'static> SandoBot<M> {
-    /// Create a new instance
+    /// Create a new instance (from within a tokio runtime if a store is configured, as its writes
+    /// are applied by a blocking task)
     pub fn new(client: Arc<M>, config: StratConfig) -> Self {
         Self {
-            pool_manager: PoolManager::new(client.clone()),
+            pool_manager: PoolManager::new(client.clone(), config.dexes)
+                .with_local_state_diffs(config.local_state_diffs)
+                .with_checkpoint(config.pool_checkpoints),
             provider: client,
             block_manager: BlockManager::new(),
+            bundle_manager: BundleManager::new(),
+            inclusion_tracker: InclusionTracker::new(config.sando_address),
+            store: config.store.map(StoreWriter::spawn),
             sando_state_manager: SandoStateManager::new(
                 config.sando_address,
                 config.searcher_signer,
                 config.sando_inception_block,
             ),
+            salmonella_policy: config.salmonella_policy,
+            env_perturbations: match config.differential_check {
+                true => EnvPerturbation::defaults(),
+                false => vec![],
+            },
+            backrun_only: config.backrun_only,
+            simulation_backend: config.simulation_backend,
+            bribe_policy: config.bribe_policy,
+            risk_limits: config.risk_limits,
         }
     }
 
+    /// Realised pnl across all bundles
+    pub fn get_pnl(&self) -> &PnlTotals {
+        self.inclusion_tracker.get_pnl()
+    }
+
+    /// Realised pnl keyed by the token held during the sandwich
+    pub fn get_token_pnl(&self) -> &HashMap<Address, PnlTotals> {
+        self.inclusion_tracker.get_token_pnl()
+    }
+
+    /// Realised pnl keyed by pool
+    pub fn get_pool_pnl(&self) -> &HashMap<Address, PnlTotals> {
+        self.inclusion_tracker.get_pool_pnl()
+    }
+
+    /// Sync every pool from scratch, rebuilding the dex set's checkpoint that later runs start
+    /// from, returns how many pools were synced
+    pub async fn resync_pools(&mut self) -> Result<usize> {
+        self.pool_manager.resync().await
+    }
+
     /// Main logic for the strategy
     /// Checks if the passed `RawIngredients` is sandwichable
     pub async fn is_sandwichable(
@@ -53,52 +141,128 @@ impl<M: Middleware + 'static> SandoBot<M> {
         target_block: BlockInfo,
     ) -> Result<SandoRecipe> {
         // setup shared backend
-        let shared_backend = SharedBackend::spawn_backend_thread(
-            self.provider.clone(),
-            BlockchainDb::new(
-                BlockchainDbMeta {
-                    cfg_env: Default::default(),
-                    block_env: Default::default(),
-                    hosts: BTreeSet::from(["".to_string()]),
-                },
-                None,
-            ), /* default because not accounting for this atm */
-            Some((target_block.number - 1).into()),
-        );
-
-        let weth_inventory = if cfg!(feature = "debug") {
-            // spoof weth balance when the debug feature is active
-            (*crate::constants::WETH_FUND_AMT).into()
-        } else {
-            self.sando_state_manager.get_weth_inventory()
+        let shared_backend = setup_shared_backend(self.provider.clone(), target_block.number - 1);
+        let simulation_backend: Arc<dyn SimulationBackend> = match &self.simulation_backend {
+            Some(simulation_backend) => simulation_backend.clone(),
+            None => Arc::new(LocalBackend::new(shared_backend.clone())),
         };
+        self.sandwich_with(
+            ingredients,
+            target_block,
+            shared_backend,
+            simulation_backend,
+        )
+        .await
+    }
+
+    /// Same as `is_sandwichable` but simulates against `fork_db` (e.g. a `FixtureDb` loaded from a
+    /// `SimulationFixture`) instead of forking from the provider
+    pub async fn is_sandwichable_with_backend<D: ForkDb>(
+        &self,
+        ingredients: RawIngredients,
+        target_block: BlockInfo,
+        fork_db: D,
+    ) -> Result<SandoRecipe> {
+        let simulation_backend = Arc::new(LocalBackend::new(fork_db.clone()));
+        self.sandwich_with(ingredients, target_block, fork_db, simulation_backend)
+            .await
+    }
+
+    /// Search for the optimal input through `simulation_backend`, then build the recipe against
+    /// `fork_db`
+    async fn sandwich_with<D: ForkDb>(
+        &self,
+        ingredients: RawIngredients,
+        target_block: BlockInfo,
+        fork_db: D,
+        simulation_backend: Arc<dyn SimulationBackend>,
+    ) -> Result<SandoRecipe> {
+        let weth_inventory = self.get_weth_inventory();
 
         let optimal_input = find_optimal_input(
             &ingredients,
             &target_block,
+            self.risk_limits.cap_input(weth_inventory),
+            simulation_backend,
+        )
+        .await?;
+
+        let recipe = create_recipe(
+            &ingredients,
+            &target_block,
+            optimal_input,
             weth_inventory,
+            self.sando_state_manager.get_searcher_address(),
+            self.sando_state_manager.get_sando_address(),
+            &self.salmonella_policy,
+            &self.env_perturbations,
+            fork_db,
+        )?;
+        ensure!(
+            recipe.get_revenue() >= self.risk_limits.min_revenue,
+            "revenue below the risk limit's minimum"
+        );
+
+        log_opportunity!(
+            ingredients.print_meats(),
+            optimal_input.as_u128() as f64 / 1e18,
+            recipe.get_revenue().as_u128() as f64 / 1e18
+        );
+
+        Ok(recipe)
+    }
+
+    /// Checks if arbing the passed `BackrunIngredients` behind the hinted swap is profitable
+    pub async fn is_backrunnable(
+        &self,
+        ingredients: BackrunIngredients,
+        target_block: BlockInfo,
+    ) -> Result<SandoRecipe> {
+        let shared_backend = setup_shared_backend(self.provider.clone(), target_block.number - 1);
+        let weth_inventory = self.get_weth_inventory();
+
+        let optimal_input = find_optimal_backrun_input(
+            &ingredients,
+            &target_block,
+            self.risk_limits.cap_input(weth_inventory),
             shared_backend.clone(),
         )
         .await?;
+        ensure!(!optimal_input.is_zero(), "no profitable backrun");
 
-        let recipe = create_recipe(
+        let recipe = create_backrun_recipe(
             &ingredients,
             &target_block,
             optimal_input,
             weth_inventory,
             self.sando_state_manager.get_searcher_address(),
             self.sando_state_manager.get_sando_address(),
+            &self.salmonella_policy,
             shared_backend,
         )?;
+        ensure!(
+            recipe.get_revenue() >= self.risk_limits.min_revenue,
+            "revenue below the risk limit's minimum"
+        );
 
         log_opportunity!(
-            ingredients.print_meats(),
+            format!("{:?}", ingredients.get_victim_hash()),
             optimal_input.as_u128() as f64 / 1e18,
             recipe.get_revenue().as_u128() as f64 / 1e18
         );
 
         Ok(recipe)
     }
+
+    /// Weth the sando contract can trade with
+    fn get_weth_inventory(&self) -> U256 {
+        if cfg!(feature = "debug") {
+            // spoof weth balance when the debug feature is active
+            (*crate::constants::WETH_FUND_AMT).into()
+        } else {
+            self.sando_state_manager.get_weth_inventory()
+        }
+    }
 }
 
 #[async_trait]
@@ -114,99 +278,360 @@ impl<M: Middleware + 'static> Strategy<Event, Action> for SandoBot<M> {
     }
 
     /// Process incoming events
-    async fn process_event(&mut self, event: Event) -> Option<Action> {
+    async fn process_event(&mut self, event: Event) -> Vec<Action> {
         match event {
-            Event::NewBlock(block) => match self.process_new_block(block).await {
-                Ok(_) => None,
-                Err(e) => {
-                    panic!("strategy is out of sync {}", e);
+            Event::NewBlock(block) => {
+                // blocks can be skipped when the websocket reconnects by itself
+                let mut actions = self
+                    .backfill_missed_blocks(Some(block.number.saturating_sub(U64::one())))
+                    .await;
+                match self.process_new_block(block).await {
+                    Ok(new_actions) => actions.extend(new_actions),
+                    Err(e) => {
+                        panic!("strategy is out of sync {}", e);
+                    }
                 }
-            },
-            Event::NewTransaction(tx) => self.process_new_tx(tx).await,
+                actions
+            }
+            Event::NewTransaction(_) | Event::TaggedTransaction(..) if self.backrun_only => vec![],
+            Event::NewTransaction(tx) => self.process_new_tx(tx, None).await,
+            Event::TaggedTransaction(tx, source) => self.process_new_tx(tx, Some(source)).await,
+            Event::BlockGap(gap) => {
+                log_error!("Block subscription was down for {:?}", gap.downtime);
+                let mut actions = vec![Action::Notify(notifications::stream_gap(
+                    "Block",
+                    gap.downtime,
+                ))];
+                actions.extend(self.backfill_missed_blocks(None).await);
+                actions
+            }
+            Event::MempoolGap(gap) => {
+                log_error!(
+                    "Mempool subscription was down for {:?}, txs sent in between were missed",
+                    gap.downtime
+                );
+                vec![Action::Notify(notifications::stream_gap(
+                    "Mempool",
+                    gap.downtime,
+                ))]
+            }
+            Event::MempoolSourceGap(source, gap) => {
+                log_error!(
+                    "Mempool source {} was down for {:?}, txs only it saw in between were missed",
+                    source,
+                    gap.downtime
+                );
+                vec![Action::Notify(notifications::stream_gap(
+                    &format!("Mempool source {}", source),
+                    gap.downtime,
+                ))]
+            }
+            Event::MevShareEvent(event) => self.process_mev_share_event(event).await,
+            Event::MevShareGap(gap) => {
+                log_error!(
+                    "MEV-Share event stream was down for {:?}, hints sent in between were missed",
+                    gap.downtime
+                );
+                vec![Action::Notify(notifications::stream_gap(
+                    "MEV-Share",
+                    gap.downtime,
+                ))]
+            }
+            Event::SubmissionReport(report) => {
+                self.process_submission_report(report).into_iter().collect()
+            }
+        }
+    }
+
+    /// Cancel stale bundles that haven't been cancelled yet, and wait for the store to catch up
+    /// before shutting down
+    async fn flush(&mut self) -> Vec<Action> {
+        let actions = self.take_cancellations();
+        if let Some(store) = &self.store {
+            if let Err(e) = store.flush().await {
+                log_error!("Failed to flush opportunity store: {}", e);
+            }
         }
+        actions
     }
 }
 
 impl<M: Middleware + 'static> SandoBot<M> {
     /// Process new blocks as they come in
-    async fn process_new_block(&mut self, event: NewBlock) -> Result<()> {
+    async fn process_new_block(&mut self, event: NewBlock) -> Result<Vec<Action>> {
         log_new_block_info!(event);
+        let block_number = event.number;
         self.block_manager.update_block_info(event);
-        Ok(())
+
+        // only fetch mined txs when there are bundles waiting on them
+        let mut block = None;
+        if self.bundle_manager.has_bundles_after(block_number)
+            || self.inclusion_tracker.has_pending(block_number)
+        {
+            match self
+                .provider
+                .get_block_with_txs(BlockNumber::Number(block_number))
+                .await
+            {
+                Ok(Some(b)) => block = Some(b),
+                Ok(None) => log_error!("Block {} not found", block_number),
+                Err(e) => log_error!("Failed to get block {}: {}", block_number, e),
+            }
+        }
+
+        let mined_txs: HashSet<H256> = block
+            .iter()
+            .flat_map(|b| b.transactions.iter().map(|tx| tx.hash))
+            .collect();
+        self.bundle_manager.on_new_block(block_number, &mined_txs);
+
+        let mut actions = vec![];
+        if let Some(block) = block {
+            match self
+                .inclusion_tracker
+                .on_new_block(&block, self.provider.clone())
+                .await
+            {
+                Ok(outcomes) => {
+                    let mut landed = false;
+                    for (bundle, outcome) in outcomes {
+                        let profit = match outcome {
+                            BundleOutcome::Landed { profit } => Some(profit),
+                            _ => None,
+                        };
+                        landed |= profit.is_some();
+                        self.update_status(bundle.bundle_hash, (&outcome).into(), profit);
+                        if let Some(notification) = notifications::bundle_outcome(&bundle, &outcome)
+                        {
+                            actions.push(Action::Notify(notification));
+                        }
+                    }
+
+                    // landed bundles change how much weth we can put into the next sandwich
+                    if landed {
+                        actions.extend(self.refresh_weth_inventory().await);
+                    }
+                }
+                Err(e) => {
+                    log_error!("Failed to track bundle inclusion: {}", e);
+                    actions.push(Action::Notify(Notification::error(
+                        "Failed to track bundle inclusion",
+                        e.to_string(),
+                    )));
+                }
+            }
+        }
+
+        actions.extend(self.take_cancellations());
+        Ok(actions)
+    }
+
+    /// Refetch the weth inventory, returns a notification if it changed
+    async fn refresh_weth_inventory(&mut self) -> Option<Action> {
+        match self
+            .sando_state_manager
+            .refresh_weth_inventory(self.provider.clone())
+            .await
+        {
+            Ok(previous) => {
+                let current = self.sando_state_manager.get_weth_inventory();
+                (previous != current)
+                    .then(|| Action::Notify(notifications::inventory_change(previous, current)))
+            }
+            Err(e) => {
+                log_error!("Failed to refresh weth inventory: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Process the blocks mined after the latest block we know of (up to `to`, the chain head if
+    /// `None`) that were never received
+    async fn backfill_missed_blocks(&mut self, to: Option<U64>) -> Vec<Action> {
+        if let Some(to) = to {
+            if to <= self.block_manager.get_latest_block().number {
+                return vec![];
+            }
+        }
+
+        let blocks = match self
+            .block_manager
+            .get_missed_blocks(to, self.provider.clone())
+            .await
+        {
+            Ok(blocks) => blocks,
+            Err(e) => {
+                log_error!("Failed to backfill missed blocks: {}", e);
+                return vec![Action::Notify(Notification::error(
+                    "Failed to backfill missed blocks",
+                    e.to_string(),
+                ))];
+            }
+        };
+
+        let mut actions = vec![];
+        if let (Some(first), Some(last)) = (blocks.first(), blocks.last()) {
+            log_info_cyan!(
+                "Resyncing missed blocks {} to {}",
+                first.number,
+                last.number
+            );
+            actions.push(Action::Notify(notifications::resync(
+                first.number,
+                last.number,
+            )));
+        }
+        for block in blocks {
+            match self.process_new_block(block).await {
+                Ok(new_actions) => actions.extend(new_actions),
+                Err(e) => {
+                    panic!("strategy is out of sync {}", e);
+                }
+            }
+        }
+        actions
+    }
+
+    /// Forget bundles that never reached a builder, so that they don't hold back later bundles
+    /// and aren't counted as missed, returns a notification for them
+    fn process_submission_report(&mut self, report: SubmissionReport) -> Option<Action> {
+        let hash = bundle_hash(&report.transaction_hashes);
+        let notification = notifications::submission_failure(hash, &report.status);
+
+        let uuid = match report.replacement_uuid {
+            Some(uuid) => uuid,
+            None => return notification.map(Action::Notify),
+        };
+
+        match report.status {
+            SubmissionStatus::Sent => {
+                self.bundle_manager.on_sent(uuid, hash);
+                self.inclusion_tracker.on_sent(&uuid, hash);
+            }
+            SubmissionStatus::Dropped(reason) | SubmissionStatus::Failed(reason) => {
+                log_error!("Bundle {:?} was not sent: {}", hash, reason);
+                self.bundle_manager.on_dropped(uuid, hash);
+                self.update_status(hash, OpportunityStatus::Dropped, None);
+                if let Some(restored) = self.inclusion_tracker.on_dropped(&uuid, hash) {
+                    self.update_status(restored.bundle_hash, OpportunityStatus::Submitted, None);
+                }
+            }
+        }
+
+        notification.map(Action::Notify)
+    }
+
+    /// Returns a cancellation for each stale bundle
+    fn take_cancellations(&mut self) -> Vec<Action> {
+        let cancellations = self.bundle_manager.take_cancellations();
+        for uuid in &cancellations {
+            if let Some(bundle) = self.inclusion_tracker.untrack(uuid) {
+                self.update_status(bundle.bundle_hash, OpportunityStatus::Cancelled, None);
+            }
+        }
+
+        cancellations
+            .into_iter()
+            .map(Action::CancelBundle)
+            .collect()
+    }
+
+    /// Record an opportunity if a store is configured
+    fn record_opportunity(&self, record: &OpportunityRecord) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.record_opportunity(record) {
+                log_error!("Failed to record opportunity: {}", e);
+            }
+        }
+    }
+
+    /// Update a bundle's status if a store is configured
+    fn update_status(
+        &self,
+        bundle_hash: H256,
+        status: OpportunityStatus,
+        realised_profit: Option<I256>,
+    ) {
+        if let Some(store) = &self.store {
+            if let Err(e) = store.update_status(bundle_hash, status, realised_profit) {
+                log_error!("Failed to update status of bundle {:?}: {}", bundle_hash, e);
+            }
+        }
     }
 
     /// Process new txs as they come in
     #[allow(unused_mut)]
-    async fn process_new_tx(&mut self, victim_tx: Transaction) -> Option<Action> {
+    async fn process_new_tx(
+        &mut self,
+        victim_tx: Transaction,
+        source: Option<TxSource>,
+    ) -> Vec<Action> {
         // setup variables for processing tx
         let next_block = self.block_manager.get_next_block();
         let latest_block = self.block_manager.get_latest_block();
 
+        // bundles sandwiching a tx that is being replaced are stale
+        self.bundle_manager.on_new_tx(&victim_tx);
+
         // ignore txs that we can't include in next block
         // enhancement: simulate all txs regardless, store result, and use result when tx can included
         if victim_tx.max_fee_per_gas.unwrap_or_default() < next_block.base_fee_per_gas {
             log_info_cyan!("{:?} mf<nbf", victim_tx.hash);
-            return None;
+            return self.take_cancellations();
         }
 
         // check if tx is a swap
         let touched_pools = self
             .pool_manager
-            .get_touched_sandwichable_pools(
-                &victim_tx,
-                latest_block.number.into(),
-                self.provider.clone(),
-            )
+            .get_touched_sandwichable_pools(&victim_tx, &latest_block, self.provider.clone())
             .await
             .map_err(|e| {
                 log_error!("Failed to get touched sandwichable pools: {}", e);
                 e
             })
-            .ok()?;
+            .ok()
+            .unwrap_or_default();
 
         // no touched pools = no sandwich opps
         if touched_pools.is_empty() {
             info!("{:?}", victim_tx.hash);
-            return None;
+            return self.take_cancellations();
         }
 
         let mut sando_bundles = vec![];
 
         for pool in touched_pools {
-            let (token_a, token_b) = match pool {
-                UniswapV2(p) => (p.token_a, p.token_b),
-                UniswapV3(p) => (p.token_a, p.token_b),
+            let ingredients = match weth_ingredients(&victim_tx, pool) {
+                Some(ingredients) => ingredients,
+                None => continue,
             };
-
-            if token_a != *WETH_ADDRESS && token_b != *WETH_ADDRESS {
-                // contract can only sandwich weth pools
-                continue;
-            }
-
-            // token that we use as frontrun input and backrun output
-            let start_end_token = *WETH_ADDRESS;
-
-            // token that we use as frontrun output and backrun input
-            let intermediary_token = if token_a == start_end_token {
-                token_b
-            } else {
-                token_a
-            };
-
-            let ingredients = RawIngredients::new(
-                vec![victim_tx.clone()],
-                start_end_token,
-                intermediary_token,
-                pool,
-            );
+            let intermediary_token = ingredients.get_intermediary_token();
+            let pool_address = pool.address();
 
             match self.is_sandwichable(ingredients, next_block.clone()).await {
                 Ok(s) => {
-                    let _bundle = match s
+                    let revenue = s.get_revenue();
+                    let mut record = OpportunityRecord {
+                        victim_hash: victim_tx.hash,
+                        target_block: next_block.number,
+                        pool: pool_address,
+                        optimal_input: Some(s.get_optimal_input()),
+                        revenue: Some(revenue),
+                        frontrun_gas_used: Some(s.get_frontrun_gas_used()),
+                        backrun_gas_used: Some(s.get_backrun_gas_used()),
+                        bribe: None,
+                        bundle_hash: None,
+                        status: OpportunityStatus::NotSandwichable,
+                        reason: None,
+                        victim_source: source.clone(),
+                    };
+
+                    let mut _bundle = match s
                         .to_fb_bundle(
                             self.sando_state_manager.get_sando_address(),
                             self.sando_state_manager.get_searcher_signer(),
                             false,
+                            &self.bribe_policy,
                             self.provider.clone(),
                         )
                         .await
@@ -214,21 +639,238 @@ impl<M: Middleware + 'static> SandoBot<M> {
                         Ok(b) => b,
                         Err(e) => {
                             log_not_sandwichable!("{:?}", e);
+                            record.reason = Some(e.to_string());
+                            self.record_opportunity(&record);
+                            continue;
+                        }
+                    };
+
+                    let tx_hashes = _bundle.request.transaction_hashes();
+                    record.bribe = _bundle.expectation.as_ref().map(|e| e.coinbase_payment);
+                    record.bundle_hash = Some(bundle_hash(&tx_hashes));
+
+                    // replace the live bundle for this opportunity only if we make more
+                    _bundle.replacement_uuid = match self.bundle_manager.register(
+                        pool_address,
+                        next_block.number,
+                        bundle_hash(&tx_hashes),
+                        revenue,
+                        vec![victim_tx.clone()],
+                    ) {
+                        Some(uuid) => Some(uuid),
+                        None => {
+                            log_not_sandwichable!("{:?} live bundle is better", victim_tx.hash);
+                            record.status = OpportunityStatus::Outcompeted;
+                            self.record_opportunity(&record);
                             continue;
                         }
                     };
 
                     #[cfg(not(feature = "debug"))]
                     {
+                        record.status = OpportunityStatus::Submitted;
+                        self.record_opportunity(&record);
+
+                        if let (Some(uuid), Some(frontrun_hash), Some(backrun_hash)) = (
+                            _bundle.replacement_uuid,
+                            tx_hashes.first(),
+                            tx_hashes.last(),
+                        ) {
+                            let replaced = self.inclusion_tracker.track(
+                                uuid,
+                                TrackedBundle {
+                                    bundle_hash: bundle_hash(&tx_hashes),
+                                    frontrun_hash: *frontrun_hash,
+                                    backrun_hash: *backrun_hash,
+                                    victims: vec![victim_tx.hash],
+                                    pool: pool_address,
+                                    intermediary_token,
+                                    target_block: next_block.number,
+                                    expected_revenue: revenue,
+                                    bribe: record.bribe.unwrap_or_default(),
+                                },
+                            );
+                            if let Some(replaced) = replaced {
+                                self.update_status(
+                                    replaced.bundle_hash,
+                                    OpportunityStatus::Replaced,
+                                    None,
+                                );
+                            }
+                        }
                         sando_bundles.push(_bundle);
                     }
                 }
                 Err(e) => {
-                    log_not_sandwichable!("{:?} {:?}", victim_tx.hash, e)
+                    log_not_sandwichable!("{:?} {:?}", victim_tx.hash, e);
+                    self.record_opportunity(&OpportunityRecord {
+                        victim_source: source.clone(),
+                        ..OpportunityRecord::not_sandwichable(
+                            victim_tx.hash,
+                            next_block.number,
+                            pool_address,
+                            e.to_string(),
+                        )
+                    });
                 }
             };
         }
 
-        Some(Action::SubmitToFlashbots(sando_bundles))
+        // cancel stale bundles before sending new ones
+        let mut actions = self.take_cancellations();
+        actions.extend(sando_bundles.into_iter().map(Action::SubmitToFlashbots));
+        actions
     }
+
+    /// Process MEV-Share hints as they come in, backrunning each hinted swap by arbing the swap's
+    /// pool against the other pools of the same token
+    #[allow(unused_mut)]
+    async fn process_mev_share_event(&mut self, event: MevShareEvent) -> Vec<Action> {
+        let next_block = self.block_manager.get_next_block();
+
+        let hinted_swaps = self.pool_manager.get_hinted_swaps(&event);
+        if hinted_swaps.is_empty() {
+            return vec![];
+        }
+
+        let mut backrun_bundles = vec![];
+
+        for swap in hinted_swaps {
+            let intermediary_token = match weth_pair_token(&swap.pool) {
+                Some(token) => token,
+                None => continue,
+            };
+
+            // find the most profitable pool to arb against
+            let mut best_backrun: Option<(SandoRecipe, Address)> = None;
+            for pool in self.pool_manager.get_weth_pools(intermediary_token) {
+                if pool.address() == swap.pool.address() {
+                    continue;
+                }
+
+                // weth in pushes the token's price up on the hinted pool, buy it elsewhere and
+                // sell it there (and the other way around)
+                let (buy_pool, sell_pool) = match swap.weth_in {
+                    true => (pool, swap.pool),
+                    false => (swap.pool, pool),
+                };
+                let ingredients = BackrunIngredients::new(
+                    event.hash,
+                    swap,
+                    intermediary_token,
+                    buy_pool,
+                    sell_pool,
+                );
+
+                match self.is_backrunnable(ingredients, next_block).await {
+                    Ok(recipe) => {
+                        let is_best = match &best_backrun {
+                            Some((best, _)) => recipe.get_revenue() > best.get_revenue(),
+                            None => true,
+                        };
+                        if is_best {
+                            best_backrun = Some((recipe, pool.address()));
+                        }
+                    }
+                    Err(e) => {
+                        log_not_sandwichable!("{:?} {:?}", event.hash, e);
+                        self.record_opportunity(&OpportunityRecord::not_sandwichable(
+                            event.hash,
+                            next_block.number,
+                            pool.address(),
+                            e.to_string(),
+                        ));
+                    }
+                }
+            }
+
+            let (recipe, pool_address) = match best_backrun {
+                Some(best_backrun) => best_backrun,
+                None => continue,
+            };
+
+            let mut record = OpportunityRecord {
+                victim_hash: event.hash,
+                target_block: next_block.number,
+                pool: pool_address,
+                optimal_input: Some(recipe.get_optimal_input()),
+                revenue: Some(recipe.get_revenue()),
+                frontrun_gas_used: Some(recipe.get_frontrun_gas_used()),
+                backrun_gas_used: Some(recipe.get_backrun_gas_used()),
+                bribe: None,
+                bundle_hash: None,
+                status: OpportunityStatus::NotSandwichable,
+                reason: None,
+                victim_source: None,
+            };
+
+            let _bundle = match recipe
+                .to_mev_share_bundle(
+                    event.hash,
+                    self.sando_state_manager.get_sando_address(),
+                    self.sando_state_manager.get_searcher_signer(),
+                    false,
+                    &self.bribe_policy,
+                    self.provider.clone(),
+                )
+                .await
+            {
+                Ok(b) => b,
+                Err(e) => {
+                    log_not_sandwichable!("{:?}", e);
+                    record.reason = Some(e.to_string());
+                    self.record_opportunity(&record);
+                    continue;
+                }
+            };
+
+            #[cfg(not(feature = "debug"))]
+            {
+                let tx_hashes: Vec<H256> = _bundle
+                    .txs
+                    .iter()
+                    .map(|tx| H256::from(keccak256(tx)))
+                    .collect();
+                record.bundle_hash = Some(bundle_hash(&tx_hashes));
+                record.status = OpportunityStatus::Submitted;
+                self.record_opportunity(&record);
+                backrun_bundles.push(_bundle);
+            }
+        }
+
+        backrun_bundles
+            .into_iter()
+            .map(Action::SubmitToMevShare)
+            .collect()
+    }
+}
+
+/// Ingredients for sandwiching `victim_tx` on `pool`, `None` if the pool doesn't trade weth
+pub(crate) fn weth_ingredients(victim_tx: &Transaction, pool: Pool) -> Option<RawIngredients> {
+    let (token_a, token_b) = match pool {
+        UniswapV2(p) => (p.token_a, p.token_b),
+        UniswapV3(p) => (p.token_a, p.token_b),
+    };
+
+    if token_a != *WETH_ADDRESS && token_b != *WETH_ADDRESS {
+        // contract can only sandwich weth pools
+        return None;
+    }
+
+    // token that we use as frontrun input and backrun output
+    let start_end_token = *WETH_ADDRESS;
+
+    // token that we use as frontrun output and backrun input
+    let intermediary_token = if token_a == start_end_token {
+        token_b
+    } else {
+        token_a
+    };
+
+    Some(RawIngredients::new(
+        vec![victim_tx.clone()],
+        start_end_token,
+        intermediary_token,
+        pool,
+    ))
 }
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use artemis_core::executors::{
+    flashbots_executor::SubmissionStatus, webhook_executor::Notification,
+};
+use ethers::{
+    types::{H256, U256, U64},
+    utils::format_ether,
+};
+
+use crate::{managers::inclusion_tracker::TrackedBundle, types::BundleOutcome};
+
+/// Notification for a resolved bundle, `None` for missed bundles (most bundles miss)
+pub fn bundle_outcome(bundle: &TrackedBundle, outcome: &BundleOutcome) -> Option<Notification> {
+    let details = format!(
+        "victims {:?}, pool {:?}, revenue {} weth, bribe {} eth",
+        bundle.victims,
+        bundle.pool,
+        format_ether(bundle.expected_revenue),
+        format_ether(bundle.bribe)
+    );
+
+    match outcome {
+        BundleOutcome::Landed { profit } if profit.is_negative() => Some(Notification::warning(
+            "Sandwich landed at a loss",
+            format!("{}, profit {} weth", details, format_ether(*profit)),
+        )),
+        BundleOutcome::Landed { profit } => Some(Notification::info(
+            "Sandwich landed",
+            format!("{}, profit {} weth", details, format_ether(*profit)),
+        )),
+        BundleOutcome::Outbid { competitor } => Some(Notification::warning(
+            "Sandwich outbid",
+            format!("{}, sandwiched by {:?}", details, competitor),
+        )),
+        BundleOutcome::Missed => None,
+    }
+}
+
+/// Notification for a bundle that no builder received, `None` for sent bundles
+pub fn submission_failure(bundle_hash: H256, status: &SubmissionStatus) -> Option<Notification> {
+    match status {
+        SubmissionStatus::Sent => None,
+        SubmissionStatus::Dropped(reason) => Some(Notification::warning(
+            "Bundle dropped by simulation",
+            format!("bundle {:?}, {}", bundle_hash, reason),
+        )),
+        SubmissionStatus::Failed(reason) => Some(Notification::error(
+            "Bundle not accepted by any builder",
+            format!("bundle {:?}, {}", bundle_hash, reason),
+        )),
+    }
+}
+
+/// Notification for a change of the sando contract's weth inventory
+pub fn inventory_change(previous: U256, current: U256) -> Notification {
+    Notification::info(
+        "Weth inventory changed",
+        format!(
+            "{} -> {} weth",
+            format_ether(previous),
+            format_ether(current)
+        ),
+    )
+}
+
+/// Notification for a collector whose subscription dropped
+pub fn stream_gap(stream: &str, downtime: Duration) -> Notification {
+    Notification::warning(
+        format!("{} subscription dropped", stream),
+        format!("reconnected after {:?}", downtime),
+    )
+}
+
+/// Notification for blocks that were missed and then processed late
+pub fn resync(from: U64, to: U64) -> Notification {
+    Notification::warning(
+        "Strategy resynced",
+        format!("processed missed blocks {} to {}", from, to),
+    )
+}
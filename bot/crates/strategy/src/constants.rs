@@ -28,6 +28,28 @@ pub static LIL_ROUTER_CODE: Lazy<Bytes> = Lazy::new(|| {
         .unwrap()
 });
 
+// could generate random address to use at runtime
+pub static LIL_ROUTER_SWEEPER_ADDRESS: Lazy<rAddress> = Lazy::new(|| {
+    "0x5eeB5eeB5eeB5eeB5eeB5eeB5eeB5eeB5eeB5eeB"
+        .parse()
+        .unwrap()
+});
+
+// forwards `abi.encode(token, lilRouter) ++ lilRouterCalldata` to the lilRouter with `amountIn`
+// replaced by `token.balanceOf(lilRouter)`, lets a backrun sell whatever the frontrun bought when
+// the amount isn't known ahead of time (bubbles up the lilRouter's output/revert)
+//
+//   mstore(0, shl(224, 0x70a08231)) mstore(4, calldataload(32))
+//   if iszero(staticcall(gas(), calldataload(0), 0, 36, 0, 32)) { bubble revert }
+//   calldatacopy(64, 64, sub(calldatasize(), 64)) mstore(68, mload(0))
+//   success := call(gas(), calldataload(32), 0, 64, sub(calldatasize(), 64), 0, 0)
+//   returndatacopy(0, 0, returndatasize()) then return/revert(0, returndatasize())
+pub static LIL_ROUTER_SWEEPER_CODE: Lazy<Bytes> = Lazy::new(|| {
+    "0x6370a0823160e01b60005260203560045260206000602460006000355afa15604b576040360360406040376000516044526000600060403603604060006020355af13d600060003e6056575b3d600060003e3d6000fd5b3d6000f3"
+        .parse()
+        .unwrap()
+});
+
 // funciton signature for getting reserves
 pub static GET_RESERVES_SIG: Lazy<Bytes> = Lazy::new(|| "0x0902f1ac".parse().unwrap());
 
@@ -37,6 +59,18 @@ pub static ERC20_TRANSFER_EVENT_SIG: Lazy<H256> = Lazy::new(|| {
         .unwrap()
 });
 
+pub static UNISWAP_V2_SWAP_EVENT_SIG: Lazy<H256> = Lazy::new(|| {
+    H256::from(ethers::utils::keccak256(
+        "Swap(address,uint256,uint256,uint256,uint256,address)",
+    ))
+});
+
+pub static UNISWAP_V3_SWAP_EVENT_SIG: Lazy<H256> = Lazy::new(|| {
+    H256::from(ethers::utils::keccak256(
+        "Swap(address,address,int256,int256,uint160,uint128,int24)",
+    ))
+});
+
 pub static WETH_ADDRESS: Lazy<Address> = Lazy::new(|| {
     "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"
         .parse()
@@ -57,4 +91,11 @@ pub static COINBASE: Lazy<rAddress> = Lazy::new(|| {
         .unwrap()
 });
 
+// used in place of `COINBASE` to check if a sandwich depends on the block's coinbase
+pub static ALT_COINBASE: Lazy<rAddress> = Lazy::new(|| {
+    "0xBadC0ffee0ddF00dBadC0ffee0ddF00dBadC0ffe"
+        .parse()
+        .unwrap()
+});
+
 pub static DUST_OVERPAY: Lazy<U256> = Lazy::new(|| ethers::utils::parse_ether("0.00015").unwrap());
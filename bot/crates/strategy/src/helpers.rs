@@ -61,6 +61,29 @@ pub fn access_list_to_revm(access_list: AccessList) -> Vec<(B160, Vec<rU256>)> {
         .collect()
 }
 
+/// (De)serialize a tx as its signed rlp encoding, a fraction of the size of its json encoding. The
+/// sender is recovered from the signature when deserializing.
+pub mod signed_rlp {
+    use ethers::{
+        types::{Bytes, Transaction},
+        utils::rlp,
+    };
+    use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(tx: &Transaction, serializer: S) -> Result<S::Ok, S::Error> {
+        tx.rlp().serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Transaction, D::Error> {
+        let raw = Bytes::deserialize(deserializer)?;
+        let mut tx: Transaction = rlp::decode(&raw).map_err(D::Error::custom)?;
+        tx.recover_from_mut().map_err(D::Error::custom)?;
+        Ok(tx)
+    }
+}
+
 //
 // -- Logging Macros --
 //
@@ -0,0 +1,109 @@
+use anyhow::{anyhow, Result};
+use ethers::{
+    providers::Middleware,
+    types::{Address, Bytes, Eip1559TransactionRequest, U256, U64},
+};
+use std::{collections::BTreeSet, sync::Arc};
+
+use crate::{
+    abi::Erc20, constants::WETH_ADDRESS, managers::sando_state_manager::find_token_dust,
+    tx_utils::huff_sando_interface::common::get_jump_dest_from_sig,
+};
+
+/// Balances held by the sando contract
+#[derive(Debug, Clone, Default)]
+pub struct SandoInventory {
+    pub eth: U256,
+    /// Weth that sandwiches are funded with
+    pub weth: U256,
+    /// Balance of each token the sando contract has traded, backruns leave some behind so that
+    /// the next swap of the token doesn't write to an empty slot
+    pub dust: Vec<(Address, U256)>,
+}
+
+impl SandoInventory {
+    /// Fetch the sando contract's balances, dust tokens are found by scanning its transfers since
+    /// `sando_inception_block`
+    pub async fn fetch<M: Middleware + 'static>(
+        provider: Arc<M>,
+        sando_address: Address,
+        sando_inception_block: U64,
+    ) -> Result<Self> {
+        let eth = provider.get_balance(sando_address, None).await?;
+        let weth = weth_balance(provider.clone(), sando_address).await?;
+
+        let tokens: BTreeSet<Address> =
+            find_token_dust(&provider, sando_address, sando_inception_block)
+                .await?
+                .into_iter()
+                .filter(|token| *token != *WETH_ADDRESS)
+                .collect();
+
+        let mut dust = vec![];
+        for token in tokens {
+            let balance = Erc20::new(token, provider.clone())
+                .balance_of(sando_address)
+                .call()
+                .await?;
+            dust.push((token, balance));
+        }
+
+        Ok(Self { eth, weth, dust })
+    }
+}
+
+/// Weth held by `owner`
+pub async fn weth_balance<M: Middleware + 'static>(
+    provider: Arc<M>,
+    owner: Address,
+) -> Result<U256> {
+    Ok(Erc20::new(*WETH_ADDRESS, provider)
+        .balance_of(owner)
+        .call()
+        .await?)
+}
+
+/// What is swept out of the sando contract, the contract can only transfer out eth and weth
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Withdrawal {
+    /// Transfer this much weth
+    Weth(U256),
+    /// Transfer all eth
+    Eth,
+}
+
+/// Tx sweeping `withdrawal` from the sando contract to `searcher` (the only caller the contract
+/// accepts) on chain `chain_id`, gas and fees are left to be filled in
+///
+/// Weth can only be withdrawn on mainnet, the contract reads the amount at calldata offset
+/// `chainid`, which is right after the jump dest only when the chain id is 1
+pub fn withdraw_tx(
+    sando_address: Address,
+    searcher: Address,
+    withdrawal: Withdrawal,
+    chain_id: u64,
+) -> Result<Eip1559TransactionRequest> {
+    let data = match withdrawal {
+        Withdrawal::Weth(_) if chain_id != 1 => {
+            return Err(anyhow!(
+                "weth can only be withdrawn on mainnet, the sando contract reads the amount at \
+                 calldata offset chainid ({})",
+                chain_id
+            ))
+        }
+        Withdrawal::Weth(amount) => {
+            let mut data = vec![get_jump_dest_from_sig("recoverWeth")];
+            let mut amount_bytes = [0u8; 32];
+            amount.to_big_endian(&mut amount_bytes);
+            data.extend(amount_bytes);
+            data
+        }
+        Withdrawal::Eth => vec![get_jump_dest_from_sig("recoverEth")],
+    };
+
+    Ok(Eip1559TransactionRequest::new()
+        .from(searcher)
+        .to(sando_address)
+        .data(Bytes::from(data))
+        .chain_id(chain_id))
+}
@@ -1,7 +1,9 @@
 mod abi;
 mod constants;
 mod helpers;
-mod simulator;
+
+/// Module contains evm simulations of sandwiches and bundles
+pub mod simulator;
 
 /// Module contains logic to manage info on onchain pools
 mod managers;
@@ -9,6 +11,12 @@ mod managers;
 /// Module contains logic related to transaction building
 mod tx_utils;
 
+/// Module contains persistent storage of opportunities and bundles
+pub mod storage;
+
+/// Module contains the sando contract's balances and withdrawals
+pub mod inventory;
+
 /// Module contains core strategy implementation
 pub mod bot;
 
@@ -1,5 +1,9 @@
 use anyhow::{anyhow, Result};
-use ethers::{providers::Middleware, types::BlockNumber};
+use artemis_core::collectors::block_collector::NewBlock;
+use ethers::{
+    providers::Middleware,
+    types::{BlockNumber, U64},
+};
 use log::info;
 use std::sync::Arc;
 
@@ -7,6 +11,10 @@ use colored::Colorize;
 
 use crate::{startup_info_log, types::BlockInfo};
 
+/// Max number of missed blocks to backfill, bundles only target the next block so older blocks
+/// don't matter
+const MAX_BACKFILL_BLOCKS: u64 = 64;
+
 pub struct BlockManager {
     latest_block: BlockInfo,
     next_block: BlockInfo,
@@ -44,6 +52,47 @@ impl BlockManager {
         self.latest_block
     }
 
+    /// Fetch the blocks mined after the latest block we know of, up to and including `to` (the
+    /// chain head if `None`)
+    pub async fn get_missed_blocks<M: Middleware + 'static>(
+        &self,
+        to: Option<U64>,
+        provider: Arc<M>,
+    ) -> Result<Vec<NewBlock>> {
+        let to = match to {
+            Some(to) => to.as_u64(),
+            None => provider
+                .get_block_number()
+                .await
+                .map_err(|e| anyhow!("Failed to get current block number: {}", e))?
+                .as_u64(),
+        };
+        let from =
+            (self.latest_block.number.as_u64() + 1).max(to.saturating_sub(MAX_BACKFILL_BLOCKS - 1));
+
+        let mut blocks = vec![];
+        for number in from..=to {
+            let block = provider
+                .get_block(number)
+                .await
+                .map_err(|e| anyhow!("Failed to get block {}: {}", number, e))?
+                .ok_or(anyhow!("Block {} not found", number))?;
+
+            blocks.push(NewBlock {
+                number: U64::from(number),
+                gas_used: block.gas_used,
+                gas_limit: block.gas_limit,
+                base_fee_per_gas: block.base_fee_per_gas.unwrap_or_default(),
+                timestamp: block.timestamp,
+            });
+        }
+
+        if !blocks.is_empty() {
+            info!("Backfilling blocks {} to {}", from, to);
+        }
+        Ok(blocks)
+    }
+
     /// Updates internal state with the latest mined block and next block
     pub fn update_block_info<T: Into<BlockInfo>>(&mut self, latest_block: T) {
         let latest_block: BlockInfo = latest_block.into();
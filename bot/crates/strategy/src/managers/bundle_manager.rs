@@ -0,0 +1,352 @@
+use std::collections::{HashMap, HashSet};
+
+use ethers::types::{Address, Transaction, H256, U256, U64};
+use uuid::Uuid;
+
+/// A bundle handed to the executor for a (pool, block) opportunity
+#[derive(Debug, Clone)]
+struct SentBundle {
+    bundle_hash: H256,
+    /// Revenue found during simulation
+    revenue: U256,
+    /// Victim txs sandwiched by the bundle
+    victims: Vec<Transaction>,
+}
+
+/// The bundles sent under an opportunity's replacement uuid
+struct LiveBundle {
+    /// Uuid that the bundle and its replacements are sent under
+    replacement_uuid: Uuid,
+    /// Latest bundle a builder accepted
+    sent: Option<SentBundle>,
+    /// Bundles handed to the executor that haven't been reported on yet, oldest first
+    pending: Vec<SentBundle>,
+}
+
+impl LiveBundle {
+    /// Bundle that later bundles have to beat
+    fn best(&self) -> Option<&SentBundle> {
+        self.pending.last().or(self.sent.as_ref())
+    }
+
+    fn victims(&self) -> impl Iterator<Item = &Transaction> {
+        self.sent
+            .iter()
+            .chain(self.pending.iter())
+            .flat_map(|bundle| bundle.victims.iter())
+    }
+}
+
+/// Keeps track of sent bundles so that better bundles replace worse ones and stale bundles get
+/// cancelled
+pub struct BundleManager {
+    /// Live bundles keyed by (pool, target block)
+    live_bundles: HashMap<(Address, U64), LiveBundle>,
+    /// Replacement uuids waiting to be cancelled
+    pending_cancellations: Vec<Uuid>,
+}
+
+impl BundleManager {
+    pub fn new() -> Self {
+        Self {
+            live_bundles: HashMap::new(),
+            pending_cancellations: Vec::new(),
+        }
+    }
+
+    /// Register a bundle for the (pool, block) opportunity
+    ///
+    /// Returns the replacement uuid to send the bundle under, or `None` if the live bundle for the
+    /// same opportunity makes more revenue
+    pub fn register(
+        &mut self,
+        pool: Address,
+        target_block: U64,
+        bundle_hash: H256,
+        revenue: U256,
+        victims: Vec<Transaction>,
+    ) -> Option<Uuid> {
+        let bundle = SentBundle {
+            bundle_hash,
+            revenue,
+            victims,
+        };
+
+        match self.live_bundles.get_mut(&(pool, target_block)) {
+            Some(live) if live.best().map_or(false, |best| best.revenue >= revenue) => None,
+            Some(live) => {
+                live.pending.push(bundle);
+                Some(live.replacement_uuid)
+            }
+            None => {
+                let replacement_uuid = Uuid::new_v4();
+                self.live_bundles.insert(
+                    (pool, target_block),
+                    LiveBundle {
+                        replacement_uuid,
+                        sent: None,
+                        pending: vec![bundle],
+                    },
+                );
+                Some(replacement_uuid)
+            }
+        }
+    }
+
+    /// A bundle reached a builder, it replaced every bundle sent before it
+    pub fn on_sent(&mut self, replacement_uuid: Uuid, bundle_hash: H256) {
+        if let Some((_, live)) = self.find(replacement_uuid) {
+            if let Some(i) = live
+                .pending
+                .iter()
+                .position(|bundle| bundle.bundle_hash == bundle_hash)
+            {
+                live.sent = live.pending.drain(..=i).last();
+            }
+        }
+    }
+
+    /// A bundle never reached a builder (dropped by the simulator or rejected by every builder),
+    /// later bundles are compared against the bundles that did
+    pub fn on_dropped(&mut self, replacement_uuid: Uuid, bundle_hash: H256) {
+        let key = match self.find(replacement_uuid) {
+            Some((key, live)) => {
+                live.pending
+                    .retain(|bundle| bundle.bundle_hash != bundle_hash);
+                if live.best().is_some() {
+                    return;
+                }
+                key
+            }
+            None => return,
+        };
+        self.live_bundles.remove(&key);
+    }
+
+    /// Cancel live bundles whose victim is replaced by `tx` (same sender and nonce)
+    pub fn on_new_tx(&mut self, tx: &Transaction) {
+        self.cancel_where(|victim| {
+            victim.from == tx.from && victim.nonce == tx.nonce && victim.hash != tx.hash
+        });
+    }
+
+    /// Forget bundles targeting blocks up to `block_number`, and cancel bundles for later blocks
+    /// whose victims were mined in `mined_txs`
+    pub fn on_new_block(&mut self, block_number: U64, mined_txs: &HashSet<H256>) {
+        self.live_bundles
+            .retain(|(_, target_block), _| *target_block > block_number);
+        self.cancel_where(|victim| mined_txs.contains(&victim.hash));
+    }
+
+    /// Returns true if a live bundle targets a block after `block_number`
+    pub fn has_bundles_after(&self, block_number: U64) -> bool {
+        self.live_bundles
+            .keys()
+            .any(|(_, target_block)| *target_block > block_number)
+    }
+
+    /// Drain the replacement uuids waiting to be cancelled
+    pub fn take_cancellations(&mut self) -> Vec<Uuid> {
+        std::mem::take(&mut self.pending_cancellations)
+    }
+
+    fn find(&mut self, replacement_uuid: Uuid) -> Option<((Address, U64), &mut LiveBundle)> {
+        self.live_bundles
+            .iter_mut()
+            .find(|(_, live)| live.replacement_uuid == replacement_uuid)
+            .map(|(key, live)| (*key, live))
+    }
+
+    /// Remove and queue cancellation of live bundles containing a victim matching `is_stale`
+    fn cancel_where<F: Fn(&Transaction) -> bool>(&mut self, is_stale: F) {
+        let pending_cancellations = &mut self.pending_cancellations;
+        self.live_bundles.retain(|_, live| {
+            let stale = live.victims().any(&is_stale);
+            if stale {
+                pending_cancellations.push(live.replacement_uuid);
+            }
+            !stale
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn victim(from: Address, nonce: u64) -> Transaction {
+        Transaction {
+            hash: H256::random(),
+            from,
+            nonce: nonce.into(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn register_keeps_the_best_bundle_per_opportunity() {
+        let mut manager = BundleManager::new();
+        let (pool, block) = (Address::random(), U64::from(10));
+
+        let uuid = manager
+            .register(pool, block, H256::random(), 100.into(), vec![])
+            .unwrap();
+        assert_eq!(
+            manager.register(pool, block, H256::random(), 100.into(), vec![]),
+            None
+        );
+        assert_eq!(
+            manager.register(pool, block, H256::random(), 150.into(), vec![]),
+            Some(uuid)
+        );
+        assert_eq!(
+            manager.register(pool, block, H256::random(), 120.into(), vec![]),
+            None
+        );
+
+        // other pools and blocks are separate opportunities
+        let other_pool =
+            manager.register(Address::random(), block, H256::random(), 1.into(), vec![]);
+        let next_block = manager.register(pool, block + 1, H256::random(), 1.into(), vec![]);
+        assert!(other_pool.is_some() && other_pool != Some(uuid));
+        assert!(next_block.is_some() && next_block != Some(uuid));
+    }
+
+    #[test]
+    fn dropped_bundle_does_not_block_replacements() {
+        let mut manager = BundleManager::new();
+        let (pool, block) = (Address::random(), U64::from(10));
+        let (sent, dropped) = (H256::random(), H256::random());
+
+        let uuid = manager
+            .register(pool, block, sent, 100.into(), vec![])
+            .unwrap();
+        manager.on_sent(uuid, sent);
+        manager.register(pool, block, dropped, 200.into(), vec![]);
+        manager.on_dropped(uuid, dropped);
+
+        // compared against the sent bundle again
+        assert_eq!(
+            manager.register(pool, block, H256::random(), 150.into(), vec![]),
+            Some(uuid)
+        );
+        assert_eq!(
+            manager.register(pool, block, H256::random(), 90.into(), vec![]),
+            None
+        );
+    }
+
+    #[test]
+    fn dropped_first_bundle_frees_the_opportunity() {
+        let mut manager = BundleManager::new();
+        let (pool, block) = (Address::random(), U64::from(10));
+        let dropped = H256::random();
+
+        let uuid = manager
+            .register(pool, block, dropped, 200.into(), vec![])
+            .unwrap();
+        manager.on_dropped(uuid, dropped);
+
+        assert!(!manager.has_bundles_after(block - 1));
+        assert!(manager
+            .register(pool, block, H256::random(), 50.into(), vec![])
+            .is_some());
+    }
+
+    #[test]
+    fn sent_replacement_forgets_the_replaced_bundle() {
+        let mut manager = BundleManager::new();
+        let (pool, block) = (Address::random(), U64::from(10));
+        let (first, second, third) = (H256::random(), H256::random(), H256::random());
+
+        let uuid = manager
+            .register(pool, block, first, 100.into(), vec![])
+            .unwrap();
+        manager.register(pool, block, second, 200.into(), vec![]);
+        manager.on_sent(uuid, second);
+        manager.register(pool, block, third, 300.into(), vec![]);
+        manager.on_dropped(uuid, third);
+
+        // falls back to the second bundle, not the first
+        assert_eq!(
+            manager.register(pool, block, H256::random(), 150.into(), vec![]),
+            None
+        );
+    }
+
+    #[test]
+    fn on_new_tx_cancels_bundles_whose_victim_is_replaced() {
+        let mut manager = BundleManager::new();
+        let sender = Address::random();
+        let victim = victim(sender, 7);
+
+        let uuid = manager
+            .register(
+                Address::random(),
+                10.into(),
+                H256::random(),
+                100.into(),
+                vec![victim.clone()],
+            )
+            .unwrap();
+        let other = manager
+            .register(
+                Address::random(),
+                10.into(),
+                H256::random(),
+                100.into(),
+                vec![self::victim(Address::random(), 7)],
+            )
+            .unwrap();
+
+        // the victim itself and other nonces of the sender don't make the bundle stale
+        manager.on_new_tx(&victim);
+        manager.on_new_tx(&self::victim(sender, 8));
+        assert!(manager.take_cancellations().is_empty());
+
+        manager.on_new_tx(&self::victim(sender, 7));
+        assert_eq!(manager.take_cancellations(), vec![uuid]);
+        assert!(manager.take_cancellations().is_empty());
+        assert_ne!(uuid, other);
+    }
+
+    #[test]
+    fn on_new_block_prunes_past_bundles_and_cancels_mined_victims() {
+        let mut manager = BundleManager::new();
+        let mined = victim(Address::random(), 0);
+
+        manager.register(
+            Address::random(),
+            10.into(),
+            H256::random(),
+            1.into(),
+            vec![],
+        );
+        let stale = manager
+            .register(
+                Address::random(),
+                12.into(),
+                H256::random(),
+                1.into(),
+                vec![mined.clone()],
+            )
+            .unwrap();
+        manager.register(
+            Address::random(),
+            12.into(),
+            H256::random(),
+            1.into(),
+            vec![],
+        );
+
+        manager.on_new_block(10.into(), &HashSet::from([mined.hash]));
+
+        // the bundle for block 10 is forgotten without being cancelled
+        assert_eq!(manager.take_cancellations(), vec![stale]);
+        assert!(manager.has_bundles_after(11.into()));
+
+        manager.on_new_block(12.into(), &HashSet::new());
+        assert!(!manager.has_bundles_after(11.into()));
+        assert!(manager.take_cancellations().is_empty());
+    }
+}
@@ -0,0 +1,612 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use ethers::{
+    providers::Middleware,
+    types::{Address, Block, Transaction, TransactionReceipt, H256, I256, U256, U64},
+};
+use log::info;
+use uuid::Uuid;
+
+use crate::{
+    constants::{ERC20_TRANSFER_EVENT_SIG, WETH_ADDRESS},
+    log_error, log_info_cyan,
+};
+
+/// A sandwich bundle that was sent and is waiting for its target block
+#[derive(Debug, Clone)]
+pub struct TrackedBundle {
+    pub bundle_hash: H256,
+    pub frontrun_hash: H256,
+    pub backrun_hash: H256,
+    pub victims: Vec<H256>,
+    pub pool: Address,
+    /// Token held for the duration of the sandwich
+    pub intermediary_token: Address,
+    pub target_block: U64,
+    /// Revenue found during simulation
+    pub expected_revenue: U256,
+    /// Paid to the builder
+    pub bribe: U256,
+}
+
+/// What happened to a bundle once its target block was mined
+#[derive(Debug, Clone)]
+pub enum BundleOutcome {
+    /// Frontrun and backrun were included, profit is realised from receipts (after gas)
+    Landed { profit: I256 },
+    /// Bundle was not included
+    Missed,
+    /// A competitor sandwiched our victim
+    Outbid { competitor: Address },
+}
+
+/// Running totals of bundle outcomes
+#[derive(Debug, Clone, Default)]
+pub struct PnlTotals {
+    pub landed: u64,
+    pub missed: u64,
+    pub outbid: u64,
+    /// Realised profit in wei (weth gained minus gas paid)
+    pub profit: I256,
+}
+
+impl PnlTotals {
+    fn record(&mut self, outcome: &BundleOutcome) {
+        match outcome {
+            BundleOutcome::Landed { profit } => {
+                self.landed += 1;
+                self.profit += *profit;
+            }
+            BundleOutcome::Missed => self.missed += 1,
+            BundleOutcome::Outbid { .. } => self.outbid += 1,
+        }
+    }
+}
+
+/// The bundles sent under a replacement uuid
+#[derive(Debug, Default)]
+struct TrackedBundles {
+    /// Latest bundle a builder accepted
+    sent: Option<TrackedBundle>,
+    /// Bundles waiting on a submission report, oldest first
+    unreported: Vec<TrackedBundle>,
+}
+
+impl TrackedBundles {
+    /// Bundle the builders are (assumed to be) holding
+    fn latest(&self) -> Option<&TrackedBundle> {
+        self.unreported.last().or(self.sent.as_ref())
+    }
+}
+
+/// Watches new blocks for our bundles and keeps track of realised pnl
+pub struct InclusionTracker {
+    sando_contract: Address,
+    /// Bundles waiting for their target block, keyed by replacement uuid
+    pending: HashMap<Uuid, TrackedBundles>,
+    total: PnlTotals,
+    per_token: HashMap<Address, PnlTotals>,
+    per_pool: HashMap<Address, PnlTotals>,
+}
+
+impl InclusionTracker {
+    pub fn new(sando_contract: Address) -> Self {
+        Self {
+            sando_contract,
+            pending: HashMap::new(),
+            total: PnlTotals::default(),
+            per_token: HashMap::new(),
+            per_pool: HashMap::new(),
+        }
+    }
+
+    /// Start tracking a bundle handed to the executor, returns the bundle previously sent under
+    /// the same uuid
+    pub fn track(
+        &mut self,
+        replacement_uuid: Uuid,
+        bundle: TrackedBundle,
+    ) -> Option<TrackedBundle> {
+        let bundles = self.pending.entry(replacement_uuid).or_default();
+        let replaced = bundles.latest().cloned();
+        bundles.unreported.push(bundle);
+        replaced
+    }
+
+    /// Stop tracking a cancelled bundle
+    pub fn untrack(&mut self, replacement_uuid: &Uuid) -> Option<TrackedBundle> {
+        self.pending
+            .remove(replacement_uuid)
+            .and_then(|bundles| bundles.latest().cloned())
+    }
+
+    /// A bundle reached a builder, it replaced every bundle sent before it
+    pub fn on_sent(&mut self, replacement_uuid: &Uuid, bundle_hash: H256) {
+        if let Some(bundles) = self.pending.get_mut(replacement_uuid) {
+            if let Some(i) = bundles
+                .unreported
+                .iter()
+                .position(|bundle| bundle.bundle_hash == bundle_hash)
+            {
+                bundles.sent = bundles.unreported.drain(..=i).last();
+            }
+        }
+    }
+
+    /// A bundle never reached a builder so it can't land, returns the bundle that is live again
+    /// if the dropped bundle was the latest
+    pub fn on_dropped(
+        &mut self,
+        replacement_uuid: &Uuid,
+        bundle_hash: H256,
+    ) -> Option<TrackedBundle> {
+        let bundles = self.pending.get_mut(replacement_uuid)?;
+        let was_latest = bundles
+            .latest()
+            .map_or(false, |latest| latest.bundle_hash == bundle_hash);
+        bundles
+            .unreported
+            .retain(|bundle| bundle.bundle_hash != bundle_hash);
+
+        let restored = bundles.latest().cloned();
+        if restored.is_none() {
+            self.pending.remove(replacement_uuid);
+        }
+        restored.filter(|_| was_latest)
+    }
+
+    /// Returns true if a tracked bundle targets `block_number` or earlier
+    pub fn has_pending(&self, block_number: U64) -> bool {
+        self.pending
+            .values()
+            .filter_map(TrackedBundles::latest)
+            .any(|bundle| bundle.target_block <= block_number)
+    }
+
+    /// Resolve all tracked bundles targeting `block` or earlier, returning their outcomes
+    ///
+    /// Bundles targeting an earlier block (e.g. when a block was missed) are resolved against
+    /// their own target block, which is fetched from `provider`
+    pub async fn on_new_block<M: Middleware + 'static>(
+        &mut self,
+        block: &Block<Transaction>,
+        provider: Arc<M>,
+    ) -> Result<Vec<(TrackedBundle, BundleOutcome)>> {
+        let block_number = block
+            .number
+            .ok_or(anyhow!("Field block number does not exist on block"))?;
+
+        let is_due = |bundles: &TrackedBundles| {
+            bundles
+                .latest()
+                .map_or(true, |bundle| bundle.target_block <= block_number)
+        };
+        let (resolved, pending) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|(_, bundles)| is_due(bundles));
+        self.pending = pending;
+
+        let resolved: Vec<TrackedBundle> = resolved
+            .into_values()
+            .filter_map(|bundles: TrackedBundles| bundles.latest().cloned())
+            .collect();
+        if resolved.is_empty() {
+            return Ok(vec![]);
+        }
+
+        // fetch the earlier target blocks once
+        let mut earlier_blocks = HashMap::new();
+        for target_block in resolved.iter().map(|bundle| bundle.target_block) {
+            if target_block == block_number || earlier_blocks.contains_key(&target_block) {
+                continue;
+            }
+            match provider.get_block_with_txs(target_block).await {
+                Ok(Some(block)) => {
+                    earlier_blocks.insert(target_block, block);
+                }
+                Ok(None) => log_error!("Target block {} not found", target_block),
+                Err(e) => log_error!("Failed to get target block {}: {}", target_block, e),
+            }
+        }
+
+        let mut outcomes = vec![];
+
+        for bundle in resolved {
+            let target_block = match bundle.target_block == block_number {
+                true => block,
+                false => match earlier_blocks.get(&bundle.target_block) {
+                    Some(block) => block,
+                    None => continue,
+                },
+            };
+
+            let outcome = match self.resolve(&bundle, target_block, provider.clone()).await {
+                Ok(outcome) => outcome,
+                Err(e) => {
+                    log_error!("Failed to resolve bundle {:?}: {}", bundle.frontrun_hash, e);
+                    continue;
+                }
+            };
+
+            log_info_cyan!(
+                "[BUNDLE {:?}] pool {:?} expected {} got {:?}",
+                bundle.frontrun_hash,
+                bundle.pool,
+                bundle.expected_revenue,
+                outcome
+            );
+
+            self.total.record(&outcome);
+            self.per_token
+                .entry(bundle.intermediary_token)
+                .or_default()
+                .record(&outcome);
+            self.per_pool
+                .entry(bundle.pool)
+                .or_default()
+                .record(&outcome);
+
+            outcomes.push((bundle, outcome));
+        }
+
+        log_info_cyan!(
+            "[PNL] landed {} missed {} outbid {} profit {} wei",
+            self.total.landed,
+            self.total.missed,
+            self.total.outbid,
+            self.total.profit
+        );
+
+        Ok(outcomes)
+    }
+
+    /// Find out what happened to `bundle` in `block`
+    async fn resolve<M: Middleware + 'static>(
+        &self,
+        bundle: &TrackedBundle,
+        block: &Block<Transaction>,
+        provider: Arc<M>,
+    ) -> Result<BundleOutcome> {
+        let position = |hash: H256| block.transactions.iter().position(|tx| tx.hash == hash);
+
+        if position(bundle.frontrun_hash).is_some() && position(bundle.backrun_hash).is_some() {
+            let mut profit = I256::zero();
+            for hash in [bundle.frontrun_hash, bundle.backrun_hash] {
+                let receipt = provider
+                    .get_transaction_receipt(hash)
+                    .await
+                    .map_err(|e| anyhow!("Failed to get receipt {:?}: {}", hash, e))?
+                    .ok_or(anyhow!("Receipt {:?} not found", hash))?;
+                profit += self.realised_profit(&receipt);
+            }
+            return Ok(BundleOutcome::Landed { profit });
+        }
+
+        // victim got sandwiched by someone else if the txs either side share a sender
+        for victim in &bundle.victims {
+            if let Some(i) = position(*victim) {
+                let victim = &block.transactions[i];
+                let before = i.checked_sub(1).and_then(|i| block.transactions.get(i));
+                let after = block.transactions.get(i + 1);
+                if let (Some(before), Some(after)) = (before, after) {
+                    let same_contract =
+                        before.to.is_some() && before.to == after.to && before.to != victim.to;
+                    if before.from == after.from || same_contract {
+                        return Ok(BundleOutcome::Outbid {
+                            competitor: before.to.unwrap_or(before.from),
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(BundleOutcome::Missed)
+    }
+
+    /// Weth gained by the sando contract in `receipt` minus the gas paid for it
+    fn realised_profit(&self, receipt: &TransactionReceipt) -> I256 {
+        let mut profit = I256::zero();
+
+        for log in &receipt.logs {
+            if log.address != *WETH_ADDRESS
+                || log.topics.len() != 3
+                || log.topics[0] != *ERC20_TRANSFER_EVENT_SIG
+            {
+                continue;
+            }
+
+            let from = Address::from(log.topics[1]);
+            let to = Address::from(log.topics[2]);
+            let value = I256::from_raw(U256::from_big_endian(&log.data));
+
+            if to == self.sando_contract {
+                profit += value;
+            }
+            if from == self.sando_contract {
+                profit -= value;
+            }
+        }
+
+        let gas_cost =
+            receipt.gas_used.unwrap_or_default() * receipt.effective_gas_price.unwrap_or_default();
+
+        profit - I256::from_raw(gas_cost)
+    }
+
+    /// Totals across all bundles
+    pub fn get_pnl(&self) -> &PnlTotals {
+        &self.total
+    }
+
+    /// Totals keyed by the token held during the sandwich
+    pub fn get_token_pnl(&self) -> &HashMap<Address, PnlTotals> {
+        &self.per_token
+    }
+
+    /// Totals keyed by pool
+    pub fn get_pool_pnl(&self) -> &HashMap<Address, PnlTotals> {
+        &self.per_pool
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ethers::{
+        providers::Provider,
+        types::{Log, TransactionReceipt},
+    };
+
+    use super::*;
+
+    fn tx(from: Address, to: Address) -> Transaction {
+        Transaction {
+            hash: H256::random(),
+            from,
+            to: Some(to),
+            ..Default::default()
+        }
+    }
+
+    fn block(number: u64, transactions: Vec<Transaction>) -> Block<Transaction> {
+        Block {
+            number: Some(number.into()),
+            transactions,
+            ..Default::default()
+        }
+    }
+
+    /// Bundle sandwiching `victim` in `target_block`, our txs are sent to `sando`
+    fn bundle(
+        sando: Address,
+        victim: &Transaction,
+        target_block: u64,
+    ) -> (TrackedBundle, Vec<Transaction>) {
+        let searcher = Address::random();
+        let (frontrun, backrun) = (tx(searcher, sando), tx(searcher, sando));
+        let bundle = TrackedBundle {
+            bundle_hash: H256::random(),
+            frontrun_hash: frontrun.hash,
+            backrun_hash: backrun.hash,
+            victims: vec![victim.hash],
+            pool: Address::random(),
+            intermediary_token: Address::random(),
+            target_block: target_block.into(),
+            expected_revenue: 100.into(),
+            bribe: 10.into(),
+        };
+        (bundle, vec![frontrun, victim.clone(), backrun])
+    }
+
+    /// Receipt of a tx moving `amount` weth from `from` to `to`, paying `gas_cost`
+    fn receipt(
+        hash: H256,
+        from: Address,
+        to: Address,
+        amount: u64,
+        gas_cost: u64,
+    ) -> TransactionReceipt {
+        TransactionReceipt {
+            transaction_hash: hash,
+            logs: vec![Log {
+                address: *WETH_ADDRESS,
+                topics: vec![*ERC20_TRANSFER_EVENT_SIG, from.into(), to.into()],
+                data: H256::from_low_u64_be(amount).as_bytes().to_vec().into(),
+                ..Default::default()
+            }],
+            gas_used: Some(gas_cost.into()),
+            effective_gas_price: Some(1.into()),
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn landed_bundle_realises_weth_gained_minus_gas() {
+        let sando = Address::random();
+        let (provider, mock) = Provider::mocked();
+        let mut tracker = InclusionTracker::new(sando);
+        let pool = Address::random();
+
+        let victim = tx(Address::random(), Address::random());
+        let (tracked, txs) = bundle(sando, &victim, 10);
+        let tracked = TrackedBundle { pool, ..tracked };
+        tracker.track(Uuid::new_v4(), tracked.clone());
+
+        // frontrun spends 100 weth, backrun gets 150 back, 20 wei of gas
+        mock.push(receipt(tracked.frontrun_hash, sando, pool, 100, 10))
+            .unwrap();
+        mock.push(receipt(tracked.backrun_hash, pool, sando, 150, 10))
+            .unwrap();
+
+        let outcomes = tracker
+            .on_new_block(&block(10, txs), Arc::new(provider))
+            .await
+            .unwrap();
+
+        assert_eq!(outcomes.len(), 1);
+        match outcomes[0].1 {
+            BundleOutcome::Landed { profit } => assert_eq!(profit, I256::from(30)),
+            ref outcome => panic!("expected landed, got {:?}", outcome),
+        }
+        assert!(!tracker.has_pending(U64::from(10)));
+    }
+
+    #[tokio::test]
+    async fn missed_and_outbid_bundles() {
+        let sando = Address::random();
+        let (provider, _mock) = Provider::mocked();
+        let provider = Arc::new(provider);
+        let mut tracker = InclusionTracker::new(sando);
+
+        let (missed_victim, outbid_victim) = (
+            tx(Address::random(), Address::random()),
+            tx(Address::random(), Address::random()),
+        );
+        let (missed, _) = bundle(sando, &missed_victim, 10);
+        let (outbid, _) = bundle(sando, &outbid_victim, 10);
+        tracker.track(Uuid::new_v4(), missed);
+        tracker.track(Uuid::new_v4(), outbid.clone());
+
+        // someone else's contract wraps the victim
+        let competitor = Address::random();
+        let txs = vec![
+            tx(Address::random(), competitor),
+            outbid_victim,
+            tx(Address::random(), competitor),
+        ];
+        let outcomes = tracker
+            .on_new_block(&block(10, txs), provider)
+            .await
+            .unwrap();
+
+        assert_eq!(outcomes.len(), 2);
+        for (bundle, outcome) in outcomes {
+            match bundle.bundle_hash == outbid.bundle_hash {
+                true => assert!(
+                    matches!(outcome, BundleOutcome::Outbid { competitor: c } if c == competitor)
+                ),
+                false => assert!(matches!(outcome, BundleOutcome::Missed)),
+            }
+        }
+
+        let pnl = tracker.get_pnl();
+        assert_eq!((pnl.landed, pnl.missed, pnl.outbid), (0, 1, 1));
+        assert_eq!(pnl.profit, I256::zero());
+    }
+
+    #[tokio::test]
+    async fn totals_are_kept_per_token_and_pool() {
+        let sando = Address::random();
+        let (provider, _mock) = Provider::mocked();
+        let provider = Arc::new(provider);
+        let mut tracker = InclusionTracker::new(sando);
+        let (token, pool) = (Address::random(), Address::random());
+
+        for target_block in [10, 11] {
+            let victim = tx(Address::random(), Address::random());
+            let (tracked, _) = bundle(sando, &victim, target_block);
+            tracker.track(
+                Uuid::new_v4(),
+                TrackedBundle {
+                    intermediary_token: token,
+                    pool,
+                    ..tracked
+                },
+            );
+        }
+        let victim = tx(Address::random(), Address::random());
+        tracker.track(Uuid::new_v4(), bundle(sando, &victim, 11).0);
+
+        tracker
+            .on_new_block(&block(10, vec![]), provider.clone())
+            .await
+            .unwrap();
+        assert!(tracker.has_pending(U64::from(11)));
+        tracker
+            .on_new_block(&block(11, vec![]), provider)
+            .await
+            .unwrap();
+        assert!(!tracker.has_pending(U64::from(11)));
+
+        assert_eq!(tracker.get_pnl().missed, 3);
+        assert_eq!(tracker.get_token_pnl()[&token].missed, 2);
+        assert_eq!(tracker.get_pool_pnl()[&pool].missed, 2);
+        assert_eq!(tracker.get_token_pnl().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn bundles_are_resolved_against_their_own_target_block() {
+        let sando = Address::random();
+        let (provider, mock) = Provider::mocked();
+        let mut tracker = InclusionTracker::new(sando);
+
+        let victim = tx(Address::random(), Address::random());
+        let (tracked, txs) = bundle(sando, &victim, 10);
+        tracker.track(Uuid::new_v4(), tracked.clone());
+
+        // block 10 was missed, our bundle landed in it
+        mock.push(receipt(
+            tracked.frontrun_hash,
+            sando,
+            Address::random(),
+            100,
+            0,
+        ))
+        .unwrap();
+        mock.push(receipt(
+            tracked.backrun_hash,
+            Address::random(),
+            sando,
+            100,
+            0,
+        ))
+        .unwrap();
+        mock.push(block(10, txs)).unwrap();
+
+        let outcomes = tracker
+            .on_new_block(&block(11, vec![]), Arc::new(provider))
+            .await
+            .unwrap();
+
+        assert_eq!(outcomes.len(), 1);
+        assert!(matches!(outcomes[0].1, BundleOutcome::Landed { .. }));
+    }
+
+    #[tokio::test]
+    async fn dropped_bundles_are_not_counted() {
+        let sando = Address::random();
+        let (provider, _mock) = Provider::mocked();
+        let provider = Arc::new(provider);
+        let mut tracker = InclusionTracker::new(sando);
+        let uuid = Uuid::new_v4();
+
+        let victim = tx(Address::random(), Address::random());
+        let (sent, _) = bundle(sando, &victim, 10);
+        let (dropped, _) = bundle(sando, &victim, 10);
+        tracker.track(uuid, sent.clone());
+        tracker.on_sent(&uuid, sent.bundle_hash);
+        assert_eq!(
+            tracker.track(uuid, dropped.clone()).map(|b| b.bundle_hash),
+            Some(sent.bundle_hash)
+        );
+
+        // the bundle it replaced is live again
+        let restored = tracker.on_dropped(&uuid, dropped.bundle_hash);
+        assert_eq!(restored.map(|b| b.bundle_hash), Some(sent.bundle_hash));
+
+        // a lone dropped bundle is forgotten
+        let (lone, _) = bundle(sando, &victim, 10);
+        let lone_uuid = Uuid::new_v4();
+        tracker.track(lone_uuid, lone.clone());
+        assert!(tracker.on_dropped(&lone_uuid, lone.bundle_hash).is_none());
+
+        let outcomes = tracker
+            .on_new_block(&block(10, vec![]), provider)
+            .await
+            .unwrap();
+        assert_eq!(outcomes.len(), 1);
+        assert_eq!(outcomes[0].0.bundle_hash, sent.bundle_hash);
+        assert_eq!(tracker.get_pnl().missed, 1);
+    }
+}
@@ -1,3 +1,5 @@
 pub(crate) mod block_manager;
+pub(crate) mod bundle_manager;
+pub(crate) mod inclusion_tracker;
 pub(crate) mod pool_manager;
 pub(crate) mod sando_state_manager;
@@ -1,21 +1,29 @@
 use anyhow::{anyhow, Result};
+use artemis_core::collectors::mev_share_collector::{MevShareEvent, MevShareLog};
 use cfmms::{
     checkpoint::sync_pools_from_checkpoint,
     dex::{Dex, DexVariant},
-    pool::Pool,
+    pool::Pool::{self, UniswapV2, UniswapV3},
     sync::sync_pairs,
 };
 use colored::Colorize;
 use dashmap::DashMap;
 use ethers::{
-    abi,
+    abi::{self, ParamType},
     providers::Middleware,
-    types::{Address, BlockNumber, Diff, TraceType, Transaction, H160, H256, U256},
+    types::{
+        AccountDiff, Address, BlockNumber, Diff, TraceType, Transaction, H160, H256, I256, U256,
+    },
 };
 use log::info;
-use std::{path::Path, str::FromStr, sync::Arc};
+use std::{collections::BTreeMap, path::Path, str::FromStr, sync::Arc};
 
-use crate::{constants::WETH_ADDRESS, startup_info_log};
+use crate::{
+    constants::{UNISWAP_V2_SWAP_EVENT_SIG, UNISWAP_V3_SWAP_EVENT_SIG, WETH_ADDRESS},
+    simulator::{get_storage_diffs, setup_shared_backend, StorageDiffs},
+    startup_info_log,
+    types::{BlockInfo, HintedSwap},
+};
 
 pub(crate) struct PoolManager<M> {
     /// Provider
@@ -24,26 +32,25 @@ pub(crate) struct PoolManager<M> {
     pools: DashMap<Address, Pool>,
     /// Which dexes to monitor
     dexes: Vec<Dex>,
+    /// Where synced pools are cached between runs (nothing is cached if `None`)
+    checkpoint_path: Option<String>,
+    /// Find a victim's state diffs by simulating it locally instead of with `trace_call`
+    local_state_diffs: bool,
 }
 
 impl<M: Middleware + 'static> PoolManager<M> {
     /// Gets state of all pools
     pub async fn setup(&mut self) -> Result<()> {
-        let checkpoint_path = ".cfmms-checkpoint.json";
-
-        let checkpoint_exists = Path::new(checkpoint_path).exists();
-
-        let pools = if checkpoint_exists {
-            let (_, pools) =
-                sync_pools_from_checkpoint(checkpoint_path, 100000, self.provider.clone()).await?;
-            pools
-        } else {
-            sync_pairs(
-                self.dexes.clone(),
-                self.provider.clone(),
-                Some(checkpoint_path),
-            )
-            .await?
+        let pools = match self.checkpoint_path.as_deref() {
+            Some(checkpoint_path) if Path::new(checkpoint_path).exists() => {
+                let (_, pools) =
+                    sync_pools_from_checkpoint(checkpoint_path, 100000, self.provider.clone())
+                        .await?;
+                pools
+            }
+            checkpoint_path => {
+                sync_pairs(self.dexes.clone(), self.provider.clone(), checkpoint_path).await?
+            }
         };
 
         for pool in pools {
@@ -55,21 +62,48 @@ impl<M: Middleware + 'static> PoolManager<M> {
         Ok(())
     }
 
+    /// Sync every pool from the dexes' creation blocks, replacing the checkpoint (if any)
+    pub async fn resync(&mut self) -> Result<usize> {
+        if let Some(checkpoint_path) = &self.checkpoint_path {
+            if Path::new(checkpoint_path).exists() {
+                std::fs::remove_file(checkpoint_path)?;
+            }
+        }
+
+        self.pools.clear();
+        self.setup().await?;
+
+        Ok(self.pools.len())
+    }
+
     /// Return a tx's touched pools
     // enhancement: record stable coin pairs to sandwich as well here
     pub async fn get_touched_sandwichable_pools(
         &self,
         victim_tx: &Transaction,
-        latest_block: BlockNumber,
+        latest_block: &BlockInfo,
         provider: Arc<M>,
     ) -> Result<Vec<Pool>> {
         // get victim tx state diffs
-        let state_diffs = provider
-            .trace_call(victim_tx, vec![TraceType::StateDiff], Some(latest_block))
-            .await?
-            .state_diff
-            .ok_or(anyhow!("not sandwichable, no state diffs produced"))?
-            .0;
+        let state_diffs = match self.local_state_diffs {
+            true => get_storage_diffs(
+                victim_tx,
+                &latest_block.get_next_block(),
+                setup_shared_backend(provider.clone(), latest_block.number),
+            )?,
+            false => storage_diffs_from_trace(
+                provider
+                    .trace_call(
+                        victim_tx,
+                        vec![TraceType::StateDiff],
+                        Some(BlockNumber::Number(latest_block.number)),
+                    )
+                    .await?
+                    .state_diff
+                    .ok_or(anyhow!("not sandwichable, no state diffs produced"))?
+                    .0,
+            ),
+        };
 
         // capture all addresses that have a state change and are also a `WETH` pool
         let touched_pools: Vec<Pool> = state_diffs
@@ -87,10 +121,9 @@ impl<M: Middleware + 'static> PoolManager<M> {
         }
 
         // find trade direction
-        let weth_state_diff = &state_diffs
+        let weth_state_diff = state_diffs
             .get(&WETH_ADDRESS)
-            .ok_or(anyhow!("Missing WETH state diffs"))?
-            .storage;
+            .ok_or(anyhow!("Missing WETH state diffs"))?;
 
         let mut sandwichable_pools = vec![];
 
@@ -102,10 +135,7 @@ impl<M: Middleware + 'static> PoolManager<M> {
             ])));
 
             // in reality we also want to check stable coin pools
-            if let Some(Diff::Changed(c)) = weth_state_diff.get(&storage_key) {
-                let from = U256::from(c.from.to_fixed_bytes());
-                let to = U256::from(c.to.to_fixed_bytes());
-
+            if let Some((from, to)) = weth_state_diff.get(&storage_key) {
                 // right now bot can only sandwich `weth->token` trades
                 // enhancement: add support for `token->weth` trades (using longtail or flashswaps sandos)
                 if to > from {
@@ -117,69 +147,249 @@ impl<M: Middleware + 'static> PoolManager<M> {
         Ok(sandwichable_pools)
     }
 
-    pub fn new(provider: Arc<M>) -> Self {
-        let dexes_data = [
-            (
-                // Uniswap v2
-                "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
-                DexVariant::UniswapV2,
-                10000835u64,
-            ),
-            (
-                // Sushiswap
-                "0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac",
-                DexVariant::UniswapV2,
-                10794229u64,
-            ),
-            (
-                // Crypto.com swap
-                "0x9DEB29c9a4c7A88a3C0257393b7f3335338D9A9D",
-                DexVariant::UniswapV2,
-                10828414u64,
-            ),
-            (
-                // Convergence swap
-                "0x4eef5746ED22A2fD368629C1852365bf5dcb79f1",
-                DexVariant::UniswapV2,
-                12385067u64,
-            ),
-            (
-                // Pancakeswap
-                "0x1097053Fd2ea711dad45caCcc45EfF7548fCB362",
-                DexVariant::UniswapV2,
-                15614590u64,
-            ),
-            (
-                // ShibaSwap
-                "0x115934131916C8b277DD010Ee02de363c09d037c",
-                DexVariant::UniswapV2,
-                12771526u64,
-            ),
-            (
-                // Saitaswap
-                "0x35113a300ca0D7621374890ABFEAC30E88f214b1",
-                DexVariant::UniswapV2,
-                15210780u64,
-            ),
-            (
-                // Uniswap v3
-                "0x1F98431c8aD98523631AE4a59f267346ea31F984",
-                DexVariant::UniswapV3,
-                12369621u64,
-            ),
-        ];
-
-        let dexes = dexes_data
-            .into_iter()
-            .map(|(address, variant, number)| {
-                Dex::new(H160::from_str(address).unwrap(), variant, number, Some(300))
+    /// Return the swaps on our weth pools revealed by a MEV-Share hint's logs (only logs whose
+    /// data was shared can be decoded)
+    pub fn get_hinted_swaps(&self, event: &MevShareEvent) -> Vec<HintedSwap> {
+        event
+            .logs
+            .iter()
+            .filter_map(|log| {
+                let pool = *self.pools.get(&log.address)?;
+                decode_swap_log(pool, log)
             })
-            .collect();
+            .collect()
+    }
+
+    /// Return every pool trading `token` against weth
+    pub fn get_weth_pools(&self, token: Address) -> Vec<Pool> {
+        self.pools
+            .iter()
+            .map(|pool| *pool.value())
+            .filter(|pool| weth_pair_token(pool) == Some(token))
+            .collect()
+    }
+
+    /// Monitor `dexes`, or the default mainnet dexes if `None`
+    pub fn new(provider: Arc<M>, dexes: Option<Vec<Dex>>) -> Self {
+        let (dexes, checkpoint_path) = match dexes {
+            Some(dexes) => {
+                let checkpoint_path = dexes_checkpoint_path(&dexes);
+                (dexes, checkpoint_path)
+            }
+            None => (mainnet_dexes(), DEFAULT_CHECKPOINT_PATH.to_string()),
+        };
 
         Self {
             pools: DashMap::new(),
             provider,
             dexes,
+            checkpoint_path: Some(checkpoint_path),
+            local_state_diffs: false,
+        }
+    }
+
+    /// Cache synced pools between runs (on by default), turned off for short lived chains whose
+    /// state doesn't outlive the run (e.g. anvil)
+    pub fn with_checkpoint(mut self, checkpoint: bool) -> Self {
+        if !checkpoint {
+            self.checkpoint_path = None;
+        }
+        self
+    }
+
+    /// Simulate victims locally to find the pools they touch, for nodes that don't support
+    /// `trace_call` (e.g. anvil)
+    pub fn with_local_state_diffs(mut self, local_state_diffs: bool) -> Self {
+        self.local_state_diffs = local_state_diffs;
+        self
+    }
+}
+
+/// Checkpoint of the default mainnet dexes
+const DEFAULT_CHECKPOINT_PATH: &str = ".cfmms-checkpoint.json";
+
+/// Checkpoint of a configured dex set, named after a hash of the dexes so that each set gets its
+/// own checkpoint and changing the set starts a new one
+fn dexes_checkpoint_path(dexes: &[Dex]) -> String {
+    let encoded = serde_json::to_vec(dexes).expect("dexes are serializable");
+    let hash = ethers::utils::keccak256(encoded);
+    format!(
+        ".cfmms-checkpoint-{}.json",
+        ethers::utils::hex::encode(&hash[..8])
+    )
+}
+
+/// Dexes monitored when none are configured
+fn mainnet_dexes() -> Vec<Dex> {
+    let dexes_data = [
+        (
+            // Uniswap v2
+            "0x5C69bEe701ef814a2B6a3EDD4B1652CB9cc5aA6f",
+            DexVariant::UniswapV2,
+            10000835u64,
+        ),
+        (
+            // Sushiswap
+            "0xC0AEe478e3658e2610c5F7A4A2E1777cE9e4f2Ac",
+            DexVariant::UniswapV2,
+            10794229u64,
+        ),
+        (
+            // Crypto.com swap
+            "0x9DEB29c9a4c7A88a3C0257393b7f3335338D9A9D",
+            DexVariant::UniswapV2,
+            10828414u64,
+        ),
+        (
+            // Convergence swap
+            "0x4eef5746ED22A2fD368629C1852365bf5dcb79f1",
+            DexVariant::UniswapV2,
+            12385067u64,
+        ),
+        (
+            // Pancakeswap
+            "0x1097053Fd2ea711dad45caCcc45EfF7548fCB362",
+            DexVariant::UniswapV2,
+            15614590u64,
+        ),
+        (
+            // ShibaSwap
+            "0x115934131916C8b277DD010Ee02de363c09d037c",
+            DexVariant::UniswapV2,
+            12771526u64,
+        ),
+        (
+            // Saitaswap
+            "0x35113a300ca0D7621374890ABFEAC30E88f214b1",
+            DexVariant::UniswapV2,
+            15210780u64,
+        ),
+        (
+            // Uniswap v3
+            "0x1F98431c8aD98523631AE4a59f267346ea31F984",
+            DexVariant::UniswapV3,
+            12369621u64,
+        ),
+    ];
+
+    dexes_data
+        .into_iter()
+        .map(|(address, variant, number)| {
+            Dex::new(H160::from_str(address).unwrap(), variant, number, Some(300))
+        })
+        .collect()
+}
+
+/// Keep the changed storage slots of a `trace_call` state diff
+fn storage_diffs_from_trace(state_diffs: BTreeMap<Address, AccountDiff>) -> StorageDiffs {
+    state_diffs
+        .into_iter()
+        .map(|(address, account_diff)| {
+            let storage = account_diff
+                .storage
+                .into_iter()
+                .filter_map(|(slot, diff)| match diff {
+                    Diff::Changed(c) => Some((
+                        slot,
+                        (
+                            U256::from(c.from.to_fixed_bytes()),
+                            U256::from(c.to.to_fixed_bytes()),
+                        ),
+                    )),
+                    _ => None,
+                })
+                .collect();
+            (address, storage)
+        })
+        .collect()
+}
+
+/// Token traded against weth by `pool`, `None` if the pool doesn't trade weth
+pub(crate) fn weth_pair_token(pool: &Pool) -> Option<Address> {
+    let (token_a, token_b) = match pool {
+        UniswapV2(p) => (p.token_a, p.token_b),
+        UniswapV3(p) => (p.token_a, p.token_b),
+    };
+
+    match (token_a == *WETH_ADDRESS, token_b == *WETH_ADDRESS) {
+        (true, false) => Some(token_b),
+        (false, true) => Some(token_a),
+        _ => None,
+    }
+}
+
+/// Decode a UniswapV2/V3 `Swap` log emitted by `pool` into the amount paid into the pool
+fn decode_swap_log(pool: Pool, log: &MevShareLog) -> Option<HintedSwap> {
+    let token = weth_pair_token(&pool)?;
+
+    let (amount_0_in, amount_1_in) = match pool {
+        UniswapV2(_) if log.topics.first() == Some(&*UNISWAP_V2_SWAP_EVENT_SIG) => {
+            let amounts = abi::decode(&vec![ParamType::Uint(256); 4], &log.data).ok()?;
+            (
+                amounts[0].clone().into_uint()?,
+                amounts[1].clone().into_uint()?,
+            )
+        }
+        UniswapV3(_) if log.topics.first() == Some(&*UNISWAP_V3_SWAP_EVENT_SIG) => {
+            let amounts = abi::decode(
+                &[
+                    ParamType::Int(256),
+                    ParamType::Int(256),
+                    ParamType::Uint(160),
+                    ParamType::Uint(128),
+                    ParamType::Int(24),
+                ],
+                &log.data,
+            )
+            .ok()?;
+            // positive amounts are paid into the pool
+            let amount_in = |amount: I256| match amount.is_positive() {
+                true => amount.into_raw(),
+                false => U256::zero(),
+            };
+            (
+                amount_in(I256::from_raw(amounts[0].clone().into_int()?)),
+                amount_in(I256::from_raw(amounts[1].clone().into_int()?)),
+            )
         }
+        _ => return None,
+    };
+
+    let (weth_in, token_in) = match *WETH_ADDRESS < token {
+        true => (amount_0_in, amount_1_in),
+        false => (amount_1_in, amount_0_in),
+    };
+
+    if !weth_in.is_zero() {
+        Some(HintedSwap {
+            pool,
+            weth_in: true,
+            amount_in: weth_in,
+        })
+    } else if !token_in.is_zero() {
+        Some(HintedSwap {
+            pool,
+            weth_in: false,
+            amount_in: token_in,
+        })
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_dex_set_gets_its_own_checkpoint() {
+        let dex = |factory: Address| Dex::new(factory, DexVariant::UniswapV2, 0, Some(300));
+        let (first, second) = (Address::random(), Address::random());
+
+        let path = dexes_checkpoint_path(&[dex(first)]);
+        assert_eq!(path, dexes_checkpoint_path(&[dex(first)]));
+        assert_ne!(path, dexes_checkpoint_path(&[dex(second)]));
+        assert_ne!(path, dexes_checkpoint_path(&[dex(first), dex(second)]));
+        assert_ne!(path, DEFAULT_CHECKPOINT_PATH);
     }
 }
@@ -45,40 +45,8 @@ impl SandoStateManager {
         self.weth_inventory = weth_balance;
 
         // find weth dust
-        let step = 10000;
-
-        let latest_block = provider
-            .get_block(BlockNumber::Latest)
-            .await
-            .map_err(|_| anyhow!("Failed to get latest block"))?
-            .ok_or(anyhow!("Failed to get latest block"))?
-            .number
-            .ok_or(anyhow!("Field block number does not exist on latest block"))?
-            .as_u64();
-
-        let mut token_dust = vec![];
-
-        let start_block = self.sando_inception_block.as_u64();
-
-        // for each block within the range, get all transfer events asynchronously
-        for from_block in (start_block..=latest_block).step_by(step) {
-            let to_block = from_block + step as u64;
-
-            // check for all incoming and outgoing txs within step range
-            let transfer_logs = provider
-                .get_logs(
-                    &Filter::new()
-                        .topic0(*ERC20_TRANSFER_EVENT_SIG)
-                        .topic1(self.sando_contract)
-                        .from_block(BlockNumber::Number(U64([from_block])))
-                        .to_block(BlockNumber::Number(U64([to_block]))),
-                )
-                .await?;
-
-            for log in transfer_logs {
-                token_dust.push(log.address);
-            }
-        }
+        let token_dust =
+            find_token_dust(&provider, self.sando_contract, self.sando_inception_block).await?;
 
         startup_info_log!("token dust found : {}", token_dust.len());
         self.token_dust = token_dust;
@@ -101,4 +69,60 @@ impl SandoStateManager {
     pub fn get_weth_inventory(&self) -> U256 {
         self.weth_inventory
     }
+
+    /// Refetch the weth inventory (it changes when bundles land or weth is withdrawn), returns the
+    /// previous inventory
+    pub async fn refresh_weth_inventory<M: Middleware + 'static>(
+        &mut self,
+        provider: Arc<M>,
+    ) -> Result<U256> {
+        let weth = Erc20::new(*WETH_ADDRESS, provider);
+        let weth_balance = weth.balance_of(self.sando_contract).call().await?;
+        Ok(std::mem::replace(&mut self.weth_inventory, weth_balance))
+    }
+}
+
+/// Tokens that `sando_contract` sent out since `sando_inception_block` (once per transfer, so
+/// tokens can repeat)
+pub(crate) async fn find_token_dust<M: Middleware + 'static>(
+    provider: &Arc<M>,
+    sando_contract: Address,
+    sando_inception_block: U64,
+) -> Result<Vec<Address>> {
+    let step = 10000;
+
+    let latest_block = provider
+        .get_block(BlockNumber::Latest)
+        .await
+        .map_err(|_| anyhow!("Failed to get latest block"))?
+        .ok_or(anyhow!("Failed to get latest block"))?
+        .number
+        .ok_or(anyhow!("Field block number does not exist on latest block"))?
+        .as_u64();
+
+    let mut token_dust = vec![];
+
+    let start_block = sando_inception_block.as_u64();
+
+    // for each block within the range, get all transfer events asynchronously
+    for from_block in (start_block..=latest_block).step_by(step) {
+        let to_block = from_block + step as u64;
+
+        // check for all incoming and outgoing txs within step range
+        let transfer_logs = provider
+            .get_logs(
+                &Filter::new()
+                    .topic0(*ERC20_TRANSFER_EVENT_SIG)
+                    .topic1(sando_contract)
+                    .from_block(BlockNumber::Number(U64([from_block])))
+                    .to_block(BlockNumber::Number(U64([to_block]))),
+            )
+            .await?;
+
+        for log in transfer_logs {
+            token_dust.push(log.address);
+        }
+    }
+
+    Ok(token_dust)
 }
@@ -0,0 +1,47 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use ethers::types::U256;
+
+use crate::types::{BlockInfo, RawIngredients};
+
+use super::{lil_router::evaluate_sandwich_revenue, ForkDb};
+
+/// Where the frontrun inputs tried while searching for the optimal input are simulated
+#[async_trait]
+pub trait SimulationBackend: Debug + Send + Sync {
+    /// Revenue of sandwiching `ingredients` in `next_block` with a frontrun of `frontrun_in` weth,
+    /// measured as the lilRouter's weth balance after the backrun minus `WETH_FUND_AMT`
+    async fn sandwich_revenue(
+        &self,
+        frontrun_in: U256,
+        next_block: BlockInfo,
+        ingredients: RawIngredients,
+    ) -> Result<U256>;
+}
+
+/// Simulates in an in-process revm on top of `fork_db` (e.g. a `SharedBackend` that pulls state
+/// slot by slot from a node)
+#[derive(Debug, Clone)]
+pub struct LocalBackend<D> {
+    fork_db: D,
+}
+
+impl<D: ForkDb> LocalBackend<D> {
+    pub fn new(fork_db: D) -> Self {
+        Self { fork_db }
+    }
+}
+
+#[async_trait]
+impl<D: ForkDb> SimulationBackend for LocalBackend<D> {
+    async fn sandwich_revenue(
+        &self,
+        frontrun_in: U256,
+        next_block: BlockInfo,
+        ingredients: RawIngredients,
+    ) -> Result<U256> {
+        evaluate_sandwich_revenue(frontrun_in, next_block, self.fork_db.clone(), ingredients).await
+    }
+}
@@ -0,0 +1,156 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, ensure, Result};
+use artemis_core::executors::bundle_simulator::{BundleSimulation, BundleSimulator};
+use async_trait::async_trait;
+use ethers::{
+    providers::Middleware,
+    types::{Address, Transaction, I256, U256},
+    utils::rlp::{Decodable, Rlp},
+};
+use ethers_flashbots::{BundleRequest, BundleTransaction};
+use foundry_evm::{
+    executor::fork::SharedBackend,
+    revm::{db::CacheDB, Database, EVM},
+    utils::ru256_to_u256,
+};
+
+use crate::{
+    constants::{COINBASE, WETH_ADDRESS},
+    helpers::access_list_to_revm,
+    types::BlockInfo,
+};
+
+use super::{
+    huff_sando::get_erc20_balance, setup_block_state, setup_meat_tx_env, setup_shared_backend,
+};
+
+/// Simulates bundles locally with revm on a fork of the bundle's simulation block
+pub struct LocalBundleSimulator<M> {
+    /// Ethers client used to fork state
+    provider: Arc<M>,
+    /// Contract that holds our weth inventory
+    sando_address: Address,
+}
+
+impl<M: Middleware + 'static> LocalBundleSimulator<M> {
+    pub fn new(provider: Arc<M>, sando_address: Address) -> Self {
+        Self {
+            provider,
+            sando_address,
+        }
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> BundleSimulator for LocalBundleSimulator<M> {
+    /// Execute bundle txs in order, profit is measured as the change in the sando contract's weth
+    /// balance minus the gas paid by the searcher
+    async fn simulate(
+        &self,
+        bundle: &BundleRequest,
+        searcher: Address,
+    ) -> Result<BundleSimulation> {
+        let simulation_block = bundle
+            .simulation_block()
+            .ok_or(anyhow!("bundle has no simulation block"))?;
+
+        let parent_block: BlockInfo = self
+            .provider
+            .get_block(simulation_block)
+            .await
+            .map_err(|e| anyhow!("Failed to get simulation block {:?}", e))?
+            .ok_or(anyhow!("Failed to get simulation block"))?
+            .try_into()?;
+        let target_block = parent_block.get_next_block();
+
+        let txs = bundle
+            .transactions()
+            .iter()
+            .map(decode_bundle_transaction)
+            .collect::<Result<Vec<_>>>()?;
+
+        let shared_backend = setup_shared_backend(self.provider.clone(), simulation_block);
+        let mut evm = EVM::new();
+        evm.database(CacheDB::new(shared_backend));
+        setup_block_state(&mut evm, &target_block);
+
+        let start_balance =
+            get_erc20_balance(*WETH_ADDRESS, self.sando_address, &target_block, &mut evm)?;
+
+        let mut coinbase_payment = U256::zero();
+        let mut gas_cost = U256::zero();
+
+        for tx in txs {
+            evm.env.tx.gas_priority_fee = None;
+            setup_meat_tx_env(&tx, &mut evm);
+            evm.env.tx.access_list =
+                access_list_to_revm(tx.access_list.clone().unwrap_or_default());
+
+            let coinbase_before = get_coinbase_balance(&mut evm)?;
+            let result = evm
+                .transact_commit()
+                .map_err(|e| anyhow!("[EVM ERROR] {:?}: {:?}", tx.hash, e))?;
+
+            if tx.from != searcher {
+                continue;
+            }
+
+            ensure!(
+                result.is_success(),
+                "tx {:?} failed in simulation: {:?}",
+                tx.hash,
+                result
+            );
+
+            coinbase_payment += get_coinbase_balance(&mut evm)? - coinbase_before;
+            gas_cost += U256::from(result.gas_used())
+                * effective_gas_price(&tx, target_block.base_fee_per_gas);
+        }
+
+        let end_balance =
+            get_erc20_balance(*WETH_ADDRESS, self.sando_address, &target_block, &mut evm)?;
+
+        let profit =
+            I256::from_raw(end_balance) - I256::from_raw(start_balance) - I256::from_raw(gas_cost);
+
+        Ok(BundleSimulation {
+            coinbase_payment,
+            profit: Some(profit),
+        })
+    }
+}
+
+/// Decode a bundle tx and recover its sender
+fn decode_bundle_transaction(bundle_tx: &BundleTransaction) -> Result<Transaction> {
+    let mut tx = match bundle_tx {
+        BundleTransaction::Signed(tx) => *tx.clone(),
+        BundleTransaction::Raw(raw) => Transaction::decode(&Rlp::new(raw))
+            .map_err(|e| anyhow!("Failed to decode bundle tx: {:?}", e))?,
+    };
+
+    tx.recover_from_mut()
+        .map_err(|e| anyhow!("Failed to recover bundle tx sender: {:?}", e))?;
+
+    Ok(tx)
+}
+
+/// Price per gas paid by `tx` when included in a block with `base_fee`
+fn effective_gas_price(tx: &Transaction, base_fee: U256) -> U256 {
+    match (tx.max_fee_per_gas, tx.max_priority_fee_per_gas) {
+        (Some(max_fee), Some(max_priority_fee)) => max_fee.min(base_fee + max_priority_fee),
+        _ => tx.gas_price.unwrap_or_default(),
+    }
+}
+
+fn get_coinbase_balance(evm: &mut EVM<CacheDB<SharedBackend>>) -> Result<U256> {
+    let db = evm.db.as_mut().ok_or(anyhow!("evm has no database"))?;
+
+    let balance = db
+        .basic(*COINBASE)
+        .map_err(|e| anyhow!("Failed to read coinbase balance: {:?}", e))?
+        .map(|info| info.balance)
+        .unwrap_or_default();
+
+    Ok(ru256_to_u256(balance))
+}
@@ -0,0 +1,286 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{self, Debug},
+    fs::File,
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use cfmms::pool::Pool;
+use ethers::{
+    providers::Middleware,
+    types::{Address, Bytes, Transaction, H256, U256, U64},
+};
+use foundry_evm::{
+    executor::{
+        backend::DatabaseError,
+        fork::{BlockchainDb, SharedBackend},
+        Bytecode,
+    },
+    revm::{
+        db::DatabaseRef,
+        primitives::{AccountInfo, B160, B256, U256 as rU256},
+    },
+    utils::{b160_to_h160, b256_to_h256, h160_to_b160, h256_to_b256, ru256_to_u256, u256_to_ru256},
+};
+use serde::{Deserialize, Serialize};
+
+use super::{new_blockchain_db, setup_shared_backend_with_db};
+use crate::types::{BlockInfo, RawIngredients};
+
+/// Account state touched by a simulation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixtureAccount {
+    pub balance: U256,
+    pub nonce: u64,
+    pub code: Bytes,
+}
+
+/// Everything needed to replay a simulation without network access: the opportunity, the
+/// block it targeted, the revenue found, and all state the simulation fetched from the fork block
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulationFixture {
+    pub fork_block: U64,
+    pub target_block: BlockInfo,
+    /// Revenue of the recorded simulation, a replay is expected to find the same
+    pub revenue: U256,
+    pub victims: Vec<Transaction>,
+    pub start_end_token: Address,
+    pub intermediary_token: Address,
+    pub pool: Pool,
+    pub accounts: BTreeMap<Address, FixtureAccount>,
+    pub storage: BTreeMap<Address, BTreeMap<U256, U256>>,
+    pub block_hashes: BTreeMap<U256, H256>,
+}
+
+impl SimulationFixture {
+    /// Snapshot the state cached in `db` (see `FixtureRecorder`)
+    fn snapshot(
+        db: &BlockchainDb,
+        fork_block: U64,
+        ingredients: &RawIngredients,
+        target_block: BlockInfo,
+        revenue: U256,
+    ) -> Self {
+        let accounts = db
+            .accounts()
+            .read()
+            .iter()
+            .map(|(address, info)| {
+                let code = info
+                    .code
+                    .as_ref()
+                    .map(|code| Bytes::from(code.original_bytes().to_vec()))
+                    .unwrap_or_default();
+                let account = FixtureAccount {
+                    balance: ru256_to_u256(info.balance),
+                    nonce: info.nonce,
+                    code,
+                };
+                (b160_to_h160(*address), account)
+            })
+            .collect();
+
+        let storage = db
+            .storage()
+            .read()
+            .iter()
+            .map(|(address, slots)| {
+                let slots = slots
+                    .iter()
+                    .map(|(slot, value)| (ru256_to_u256(*slot), ru256_to_u256(*value)))
+                    .collect();
+                (b160_to_h160(*address), slots)
+            })
+            .collect();
+
+        let block_hashes = db
+            .block_hashes()
+            .read()
+            .iter()
+            .map(|(number, hash)| (ru256_to_u256(*number), b256_to_h256(*hash)))
+            .collect();
+
+        Self {
+            fork_block,
+            target_block,
+            revenue,
+            victims: ingredients.get_meats_ref().clone(),
+            start_end_token: ingredients.get_start_end_token(),
+            intermediary_token: ingredients.get_intermediary_token(),
+            pool: ingredients.get_target_pool(),
+            accounts,
+            storage,
+            block_hashes,
+        }
+    }
+
+    /// Read a fixture from a json file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    /// Write fixture to a json file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        serde_json::to_writer(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    /// Ingredients of the recorded opportunity
+    pub fn ingredients(&self) -> RawIngredients {
+        RawIngredients::new(
+            self.victims.clone(),
+            self.start_end_token,
+            self.intermediary_token,
+            self.pool,
+        )
+    }
+
+    /// Load fixture state into a `BlockchainDb`
+    pub fn to_blockchain_db(&self) -> BlockchainDb {
+        let db = new_blockchain_db();
+
+        let mut accounts = db.accounts().write();
+        for (address, account) in &self.accounts {
+            let info = AccountInfo::new(
+                u256_to_ru256(account.balance),
+                account.nonce,
+                Bytecode::new_raw(account.code.0.clone()),
+            );
+            accounts.insert(h160_to_b160(*address), info);
+        }
+        drop(accounts);
+
+        let mut storage = db.storage().write();
+        for (address, slots) in &self.storage {
+            let slots = slots
+                .iter()
+                .map(|(slot, value)| (u256_to_ru256(*slot), u256_to_ru256(*value)))
+                .collect();
+            storage.insert(h160_to_b160(*address), slots);
+        }
+        drop(storage);
+
+        let mut block_hashes = db.block_hashes().write();
+        for (number, hash) in &self.block_hashes {
+            block_hashes.insert(u256_to_ru256(*number), h256_to_b256(*hash));
+        }
+        drop(block_hashes);
+
+        db
+    }
+
+    /// Database that serves the fixture's state without a provider
+    pub fn db(&self) -> FixtureDb {
+        FixtureDb {
+            db: self.to_blockchain_db(),
+        }
+    }
+}
+
+/// Serves a fixture's state, reading state that wasn't recorded errors instead of defaulting so
+/// that an incomplete fixture can't silently change a simulation's outcome
+#[derive(Clone)]
+pub struct FixtureDb {
+    db: BlockchainDb,
+}
+
+impl Debug for FixtureDb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FixtureDb").finish_non_exhaustive()
+    }
+}
+
+impl DatabaseRef for FixtureDb {
+    type Error = DatabaseError;
+
+    fn basic(&self, address: B160) -> Result<Option<AccountInfo>, Self::Error> {
+        match self.db.accounts().read().get(&address) {
+            Some(info) => Ok(Some(info.clone())),
+            None => Err(DatabaseError::msg(format!(
+                "account {:?} is missing from the fixture",
+                address
+            ))),
+        }
+    }
+
+    fn code_by_hash(&self, code_hash: B256) -> Result<Bytecode, Self::Error> {
+        self.db
+            .accounts()
+            .read()
+            .values()
+            .find(|info| info.code_hash == code_hash)
+            .and_then(|info| info.code.clone())
+            .ok_or_else(|| {
+                DatabaseError::msg(format!("code {:?} is missing from the fixture", code_hash))
+            })
+    }
+
+    fn storage(&self, address: B160, index: rU256) -> Result<rU256, Self::Error> {
+        match self
+            .db
+            .storage()
+            .read()
+            .get(&address)
+            .and_then(|slots| slots.get(&index))
+        {
+            Some(value) => Ok(*value),
+            None => Err(DatabaseError::msg(format!(
+                "storage slot {:?} of {:?} is missing from the fixture",
+                index, address
+            ))),
+        }
+    }
+
+    fn block_hash(&self, number: rU256) -> Result<B256, Self::Error> {
+        match self.db.block_hashes().read().get(&number) {
+            Some(hash) => Ok(*hash),
+            None => Err(DatabaseError::msg(format!(
+                "hash of block {} is missing from the fixture",
+                number
+            ))),
+        }
+    }
+}
+
+/// Backend that keeps a copy of all state fetched through it so that it can be saved as a
+/// fixture
+pub struct FixtureRecorder {
+    db: BlockchainDb,
+    fork_block: U64,
+    backend: SharedBackend,
+}
+
+impl FixtureRecorder {
+    pub fn new<M: Middleware + 'static>(provider: Arc<M>, fork_block: U64) -> Self {
+        let db = new_blockchain_db();
+        let backend = setup_shared_backend_with_db(provider, fork_block, db.clone());
+        Self {
+            db,
+            fork_block,
+            backend,
+        }
+    }
+
+    /// Backend to run simulations against
+    pub fn backend(&self) -> SharedBackend {
+        self.backend.clone()
+    }
+
+    /// Fixture holding all state fetched so far
+    pub fn finish(
+        &self,
+        ingredients: &RawIngredients,
+        target_block: BlockInfo,
+        revenue: U256,
+    ) -> SimulationFixture {
+        SimulationFixture::snapshot(
+            &self.db,
+            self.fork_block,
+            ingredients,
+            target_block,
+            revenue,
+        )
+    }
+}
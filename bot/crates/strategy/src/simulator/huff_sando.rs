@@ -1,58 +1,48 @@
 use anvil::eth::util::get_precompiles_for;
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, ensure, Result};
 use cfmms::pool::Pool::{UniswapV2, UniswapV3};
 use cfmms::pool::UniswapV2Pool;
 use ethers::abi::{self, parse_abi, Address, ParamType};
 use ethers::prelude::BaseContract;
 use ethers::types::{Bytes, U256};
 use foundry_evm::executor::TxEnv;
-use foundry_evm::executor::{
-    fork::SharedBackend, inspector::AccessListTracer, ExecutionResult, Output, TransactTo,
-};
-use foundry_evm::revm::{
-    db::CacheDB,
-    primitives::{Address as rAddress, U256 as rU256},
-    EVM,
-};
+use foundry_evm::executor::{inspector::AccessListTracer, ExecutionResult, Output, TransactTo};
+use foundry_evm::revm::{db::CacheDB, primitives::U256 as rU256, EVM};
 
 use crate::constants::{GET_RESERVES_SIG, SUGAR_DADDY, WETH_ADDRESS};
 use crate::helpers::access_list_to_revm;
-use crate::simulator::setup_block_state;
+use crate::simulator::{setup_block_state, setup_meat_tx_env, EnvPerturbation, ForkDb};
 use crate::tx_utils::huff_sando_interface::common::five_byte_encoder::FiveByteMetaData;
 use crate::tx_utils::huff_sando_interface::{
     common::weth_encoder::WethEncoder,
     v2::{v2_create_backrun_payload, v2_create_frontrun_payload},
     v3::{v3_create_backrun_payload, v3_create_frontrun_payload},
 };
-use crate::types::{BlockInfo, RawIngredients, SandoRecipe};
+use crate::types::{BackrunIngredients, BlockInfo, RawIngredients, SandoRecipe};
 
-use super::salmonella_inspector::{IsSandoSafu, SalmonellaInspectoooor};
+use super::lil_router::{inject_lil_router_code, replay_hinted_swap};
+use super::salmonella_inspector::{IsSandoSafu, SalmonellaInspectoooor, SalmonellaPolicy};
 
 /// finds if sandwich is profitable + salmonella free
-pub fn create_recipe(
+#[allow(clippy::too_many_arguments)]
+pub fn create_recipe<D: ForkDb>(
     ingredients: &RawIngredients,
     next_block: &BlockInfo,
     optimal_in: U256,
     sando_start_bal: U256,
     searcher: Address,
     sando_address: Address,
-    shared_backend: SharedBackend,
+    salmonella_policy: &SalmonellaPolicy,
+    env_perturbations: &[EnvPerturbation],
+    fork_db: D,
 ) -> Result<SandoRecipe> {
-    #[allow(unused_mut)]
-    let mut fork_db = CacheDB::new(shared_backend);
-
-    #[cfg(feature = "debug")]
-    {
-        inject_huff_sando(
-            &mut fork_db,
-            sando_address.0.into(),
-            searcher.0.into(),
-            sando_start_bal,
-        );
-    }
-    let mut evm = EVM::new();
-    evm.database(fork_db);
-    setup_block_state(&mut evm, &next_block);
+    let mut evm = setup_sando_evm(
+        next_block,
+        searcher,
+        sando_address,
+        sando_start_bal,
+        fork_db.clone(),
+    );
 
     // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
     // *                    FRONTRUN TRANSACTION                    */
@@ -86,91 +76,23 @@ pub fn create_recipe(
         ),
     };
 
-    // setup evm for frontrun transaction
-    let mut frontrun_tx_env = TxEnv {
-        caller: searcher.0.into(),
-        gas_limit: 700000,
-        gas_price: next_block.base_fee_per_gas.into(),
-        gas_priority_fee: None,
-        transact_to: TransactTo::Call(sando_address.0.into()),
-        value: frontrun_value.into(),
-        data: frontrun_data.clone().into(),
-        chain_id: None,
-        nonce: None,
-        access_list: Default::default(),
-    };
-    evm.env.tx = frontrun_tx_env.clone();
-
-    // get access list
-    let mut access_list_inspector = AccessListTracer::new(
-        Default::default(),
+    let (frontrun_tx_env, frontrun_gas_used) = run_sando_tx(
+        frontrun_data,
+        frontrun_value,
+        "frontrun",
+        next_block,
         searcher,
         sando_address,
-        get_precompiles_for(evm.env.cfg.spec_id),
-    );
-    evm.inspect_ref(&mut access_list_inspector)
-        .map_err(|e| anyhow!("[EVM ERROR] frontrun: {:?}", (e)))?;
-    let frontrun_access_list = access_list_inspector.access_list();
-
-    frontrun_tx_env.access_list = access_list_to_revm(frontrun_access_list);
-    evm.env.tx = frontrun_tx_env.clone();
-
-    // run again but now with access list (so that we get accurate gas used)
-    // run with a salmonella inspector to flag `suspicious` opcodes
-    let mut salmonella_inspector = SalmonellaInspectoooor::new();
-    let frontrun_result = match evm.inspect_commit(&mut salmonella_inspector) {
-        Ok(result) => result,
-        Err(e) => return Err(anyhow!("[huffsando: EVM ERROR] frontrun: {:?}", e)),
-    };
-    match frontrun_result {
-        ExecutionResult::Success { .. } => { /* continue operation */ }
-        ExecutionResult::Revert { output, .. } => {
-            return Err(anyhow!("[huffsando: REVERT] frontrun: {:?}", output));
-        }
-        ExecutionResult::Halt { reason, .. } => {
-            return Err(anyhow!("[huffsando: HALT] frontrun: {:?}", reason));
-        }
-    };
-    match salmonella_inspector.is_sando_safu() {
-        IsSandoSafu::Safu => { /* continue operation */ }
-        IsSandoSafu::NotSafu(not_safu_opcodes) => {
-            return Err(anyhow!(
-                "[huffsando: FrontrunNotSafu] {:?}",
-                not_safu_opcodes
-            ))
-        }
-    }
-
-    let frontrun_gas_used = frontrun_result.gas_used();
+        salmonella_policy,
+        &mut evm,
+    )?;
 
     // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
     // *                     MEAT TRANSACTION/s                     */
     // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
     let mut is_meat_good = Vec::new();
     for meat in ingredients.get_meats_ref().iter() {
-        evm.env.tx.caller = rAddress::from_slice(&meat.from.0);
-        evm.env.tx.transact_to =
-            TransactTo::Call(rAddress::from_slice(&meat.to.unwrap_or_default().0));
-        evm.env.tx.data = meat.input.0.clone();
-        evm.env.tx.value = meat.value.into();
-        evm.env.tx.chain_id = meat.chain_id.map(|id| id.as_u64());
-        //evm.env.tx.nonce = Some(meat.nonce.as_u64());
-        evm.env.tx.gas_limit = meat.gas.as_u64();
-        match meat.transaction_type {
-            Some(ethers::types::U64([0])) => {
-                // legacy tx
-                evm.env.tx.gas_price = meat.gas_price.unwrap_or_default().into();
-            }
-            Some(_) => {
-                // type 2 tx
-                evm.env.tx.gas_priority_fee = meat.max_priority_fee_per_gas.map(|mpf| mpf.into());
-                evm.env.tx.gas_price = meat.max_fee_per_gas.unwrap_or_default().into();
-            }
-            None => {
-                // legacy tx
-                evm.env.tx.gas_price = meat.gas_price.unwrap().into();
-            }
-        }
+        setup_meat_tx_env(meat, &mut evm);
 
         // keep track of which meat transactions are successful to filter reverted meats at end
         // remove reverted meats because mempool tx/s gas costs are accounted for by fb
@@ -223,22 +145,203 @@ pub fn create_recipe(
         ),
     };
 
-    // setup evm for backrun transaction
-    let mut backrun_tx_env = TxEnv {
+    let (backrun_tx_env, backrun_gas_used) = run_sando_tx(
+        backrun_data,
+        backrun_value,
+        "backrun",
+        next_block,
+        searcher,
+        sando_address,
+        salmonella_policy,
+        &mut evm,
+    )?;
+
+    // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
+    // *                      GENERATE REPORTS                      */
+    // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
+    // caluclate revenue from balance change
+    let post_sando_bal = get_erc20_balance(backrun_token_out, sando_address, next_block, &mut evm)?;
+
+    let revenue = post_sando_bal
+        .checked_sub(sando_start_bal)
+        .unwrap_or_default();
+
+    // replay sandwich under perturbed environments to catch salmonella that the opcode check missed
+    if !env_perturbations.is_empty() {
+        check_env_invariance(
+            ingredients,
+            next_block,
+            &frontrun_tx_env,
+            &backrun_tx_env,
+            searcher,
+            sando_address,
+            sando_start_bal,
+            env_perturbations,
+            fork_db,
+        )?;
+    }
+
+    // filter only passing meat txs
+    let good_meats_only = ingredients
+        .get_meats_ref()
+        .iter()
+        .zip(is_meat_good.iter())
+        .filter(|&(_, &b)| b)
+        .map(|(s, _)| s.to_owned())
+        .collect();
+
+    Ok(SandoRecipe::new(
+        optimal_in,
+        frontrun_tx_env,
+        frontrun_gas_used,
+        good_meats_only,
+        backrun_tx_env,
+        backrun_gas_used,
+        revenue,
+        *next_block,
+    ))
+}
+
+/// finds if backrunning a hinted swap is profitable + salmonella free, the hinted swap is replayed
+/// before the backrun's buy (`frontrun`) and sell (`backrun`) legs
+#[allow(clippy::too_many_arguments)]
+pub fn create_backrun_recipe<D: ForkDb>(
+    ingredients: &BackrunIngredients,
+    next_block: &BlockInfo,
+    optimal_in: U256,
+    sando_start_bal: U256,
+    searcher: Address,
+    sando_address: Address,
+    salmonella_policy: &SalmonellaPolicy,
+    fork_db: D,
+) -> Result<SandoRecipe> {
+    let mut evm = setup_sando_evm(
+        next_block,
+        searcher,
+        sando_address,
+        sando_start_bal,
+        fork_db,
+    );
+
+    // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
+    // *                        HINTED SWAP                         */
+    // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
+    if let Some(db) = evm.db.as_mut() {
+        inject_lil_router_code(db);
+    }
+    replay_hinted_swap(&ingredients.get_victim_swap(), next_block, &mut evm)?;
+
+    // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
+    // *                       BUY TRANSACTION                      */
+    // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
+    let intermediary_token = ingredients.get_intermediary_token();
+    let buy_in = WethEncoder::decode(WethEncoder::encode(optimal_in));
+
+    let (buy_data, buy_value) = match ingredients.get_buy_pool() {
+        UniswapV2(p) => {
+            evm.env.tx.gas_price = next_block.base_fee_per_gas.into();
+            evm.env.tx.gas_limit = 700000;
+            evm.env.tx.value = rU256::ZERO;
+            let buy_out = v2_get_amount_out(buy_in, p, true, &mut evm)?;
+            v2_create_frontrun_payload(p, intermediary_token, buy_in, buy_out)
+        }
+        UniswapV3(p) => v3_create_frontrun_payload(p, intermediary_token, buy_in.as_u128().into()),
+    };
+
+    let (buy_tx_env, buy_gas_used) = run_sando_tx(
+        buy_data,
+        buy_value,
+        "backrun buy",
+        next_block,
+        searcher,
+        sando_address,
+        salmonella_policy,
+        &mut evm,
+    )?;
+
+    // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
+    // *                      SELL TRANSACTION                      */
+    // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
+    // keep some dust
+    let sell_in = get_erc20_balance(intermediary_token, sando_address, next_block, &mut evm)?;
+
+    let (sell_data, sell_value) = match ingredients.get_sell_pool() {
+        UniswapV2(p) => {
+            let mut sell_in_encoded = FiveByteMetaData::encode(sell_in, 1);
+            sell_in_encoded.decrement_four_bytes();
+            let sell_in = sell_in_encoded.decode();
+            let sell_out = v2_get_amount_out(sell_in, p, false, &mut evm)?;
+            v2_create_backrun_payload(p, intermediary_token, sell_in, sell_out)
+        }
+        UniswapV3(p) => {
+            let sell_in = FiveByteMetaData::encode(sell_in, 1).decode();
+            (
+                v3_create_backrun_payload(p, intermediary_token, sell_in),
+                U256::zero(),
+            )
+        }
+    };
+
+    let (sell_tx_env, sell_gas_used) = run_sando_tx(
+        sell_data,
+        sell_value,
+        "backrun sell",
+        next_block,
+        searcher,
+        sando_address,
+        salmonella_policy,
+        &mut evm,
+    )?;
+
+    // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
+    // *                      GENERATE REPORTS                      */
+    // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
+    let post_backrun_bal = get_erc20_balance(*WETH_ADDRESS, sando_address, next_block, &mut evm)?;
+
+    let revenue = post_backrun_bal
+        .checked_sub(sando_start_bal)
+        .unwrap_or_default();
+
+    Ok(SandoRecipe::new(
+        optimal_in,
+        buy_tx_env,
+        buy_gas_used,
+        vec![],
+        sell_tx_env,
+        sell_gas_used,
+        revenue,
+        *next_block,
+    ))
+}
+
+/// Runs a tx against the sando contract with an access list attached (so that gas used is
+/// accurate), returns the tx env and gas used if the tx succeeds and is salmonella free
+#[allow(clippy::too_many_arguments)]
+fn run_sando_tx<D: ForkDb>(
+    data: Bytes,
+    value: U256,
+    label: &str,
+    next_block: &BlockInfo,
+    searcher: Address,
+    sando_address: Address,
+    salmonella_policy: &SalmonellaPolicy,
+    evm: &mut EVM<CacheDB<D>>,
+) -> Result<(TxEnv, u64)> {
+    let mut tx_env = TxEnv {
         caller: searcher.0.into(),
         gas_limit: 700000,
         gas_price: next_block.base_fee_per_gas.into(),
         gas_priority_fee: None,
         transact_to: TransactTo::Call(sando_address.0.into()),
-        value: backrun_value.into(),
-        data: backrun_data.clone().into(),
+        value: value.into(),
+        data: data.0,
         chain_id: None,
         nonce: None,
         access_list: Default::default(),
     };
-    evm.env.tx = backrun_tx_env.clone();
+    evm.env.tx = tx_env.clone();
 
-    // create access list
+    // get access list
     let mut access_list_inspector = AccessListTracer::new(
         Default::default(),
         searcher,
@@ -246,76 +349,201 @@ pub fn create_recipe(
         get_precompiles_for(evm.env.cfg.spec_id),
     );
     evm.inspect_ref(&mut access_list_inspector)
-        .map_err(|e| anyhow!("[huffsando: EVM ERROR] frontrun: {:?}", e))
-        .unwrap();
-    let backrun_access_list = access_list_inspector.access_list();
-    backrun_tx_env.access_list = access_list_to_revm(backrun_access_list);
-    evm.env.tx = backrun_tx_env.clone();
+        .map_err(|e| anyhow!("[huffsando: EVM ERROR] {}: {:?}", label, e))?;
+    tx_env.access_list = access_list_to_revm(access_list_inspector.access_list());
+    evm.env.tx = tx_env.clone();
 
     // run again but now with access list (so that we get accurate gas used)
     // run with a salmonella inspector to flag `suspicious` opcodes
-    let mut salmonella_inspector = SalmonellaInspectoooor::new();
-    let backrun_result = match evm.inspect_commit(&mut salmonella_inspector) {
+    let mut salmonella_inspector = SalmonellaInspectoooor::new(salmonella_policy);
+    let result = match evm.inspect_commit(&mut salmonella_inspector) {
         Ok(result) => result,
-        Err(e) => return Err(anyhow!("[huffsando: EVM ERROR] backrun: {:?}", e)),
+        Err(e) => return Err(anyhow!("[huffsando: EVM ERROR] {}: {:?}", label, e)),
     };
-    match backrun_result {
-        ExecutionResult::Success { .. } => { /* continue */ }
+    match result {
+        ExecutionResult::Success { .. } => { /* continue operation */ }
         ExecutionResult::Revert { output, .. } => {
-            return Err(anyhow!("[huffsando: REVERT] backrun: {:?}", output));
+            return Err(anyhow!("[huffsando: REVERT] {}: {:?}", label, output));
         }
         ExecutionResult::Halt { reason, .. } => {
-            return Err(anyhow!("[huffsando: HALT] backrun: {:?}", reason))
+            return Err(anyhow!("[huffsando: HALT] {}: {:?}", label, reason));
         }
     };
     match salmonella_inspector.is_sando_safu() {
         IsSandoSafu::Safu => { /* continue operation */ }
+        IsSandoSafu::SafuWithWarnings(warning_opcodes) => {
+            log::warn!("[huffsando: WARNING] {}: {:?}", label, warning_opcodes);
+        }
         IsSandoSafu::NotSafu(not_safu_opcodes) => {
             return Err(anyhow!(
-                "[huffsando: BACKRUN_NOT_SAFU] bad_opcodes->{:?}",
+                "[huffsando: NOT_SAFU] {}: {:?}",
+                label,
                 not_safu_opcodes
             ))
         }
     }
 
-    let backrun_gas_used = backrun_result.gas_used();
+    Ok((tx_env, result.gas_used()))
+}
 
-    // *´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
-    // *                      GENERATE REPORTS                      */
-    // *.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
-    // caluclate revenue from balance change
-    let post_sando_bal = get_erc20_balance(backrun_token_out, sando_address, next_block, &mut evm)?;
+/// Sando contract's balances after replaying a sandwich
+#[derive(Debug, PartialEq, Eq)]
+struct SandoOutcome {
+    start_end_balance: U256,
+    intermediary_balance: U256,
+    is_meat_good: Vec<bool>,
+}
 
-    let revenue = post_sando_bal
-        .checked_sub(sando_start_bal)
-        .unwrap_or_default();
+/// Replays the sandwich under each perturbed environment and errors if the outcome differs from
+/// an unperturbed replay
+#[allow(clippy::too_many_arguments)]
+fn check_env_invariance<D: ForkDb>(
+    ingredients: &RawIngredients,
+    next_block: &BlockInfo,
+    frontrun_tx_env: &TxEnv,
+    backrun_tx_env: &TxEnv,
+    searcher: Address,
+    sando_address: Address,
+    sando_start_bal: U256,
+    env_perturbations: &[EnvPerturbation],
+    fork_db: D,
+) -> Result<()> {
+    let replay = |perturbation: Option<&EnvPerturbation>| {
+        let mut evm = setup_sando_evm(
+            next_block,
+            searcher,
+            sando_address,
+            sando_start_bal,
+            fork_db.clone(),
+        );
+        replay_sandwich(
+            ingredients,
+            next_block,
+            frontrun_tx_env,
+            backrun_tx_env,
+            sando_address,
+            perturbation,
+            &mut evm,
+        )
+    };
 
-    // filter only passing meat txs
-    let good_meats_only = ingredients
-        .get_meats_ref()
-        .iter()
-        .zip(is_meat_good.iter())
-        .filter(|&(_, &b)| b)
-        .map(|(s, _)| s.to_owned())
-        .collect();
+    let baseline = replay(None)?;
+
+    for perturbation in env_perturbations {
+        let outcome = replay(Some(perturbation)).map_err(|e| {
+            anyhow!(
+                "[huffsando: ENV_DEPENDENT] {} replay failed: {:?}",
+                perturbation.name,
+                e
+            )
+        })?;
+
+        ensure!(
+            outcome == baseline,
+            "[huffsando: ENV_DEPENDENT] {} changed outcome: expected {:?}, got {:?}",
+            perturbation.name,
+            baseline,
+            outcome
+        );
+    }
 
-    Ok(SandoRecipe::new(
-        frontrun_tx_env,
-        frontrun_gas_used,
-        good_meats_only,
-        backrun_tx_env,
-        backrun_gas_used,
-        revenue,
-        *next_block,
-    ))
+    Ok(())
+}
+
+/// Runs frontrun, meats, and backrun on `evm` and reports the sando contract's balances
+fn replay_sandwich<D: ForkDb>(
+    ingredients: &RawIngredients,
+    next_block: &BlockInfo,
+    frontrun_tx_env: &TxEnv,
+    backrun_tx_env: &TxEnv,
+    sando_address: Address,
+    perturbation: Option<&EnvPerturbation>,
+    evm: &mut EVM<CacheDB<D>>,
+) -> Result<SandoOutcome> {
+    let perturb_tx = |tx: &TxEnv| match perturbation {
+        Some(perturbation) => perturbation.apply_to_tx(tx),
+        None => tx.clone(),
+    };
+
+    if let Some(perturbation) = perturbation {
+        perturbation.apply_to_block(evm);
+        perturbation.fund_gas_price_bump(frontrun_tx_env, evm)?;
+        perturbation.fund_gas_price_bump(backrun_tx_env, evm)?;
+    }
+
+    evm.env.tx = perturb_tx(frontrun_tx_env);
+    match evm.transact_commit() {
+        Ok(result) if result.is_success() => { /* continue operation */ }
+        Ok(result) => return Err(anyhow!("[huffsando: REPLAY_FAILED] frontrun: {:?}", result)),
+        Err(e) => return Err(anyhow!("[huffsando: EVM ERROR] frontrun: {:?}", e)),
+    }
+
+    let mut is_meat_good = Vec::new();
+    for meat in ingredients.get_meats_ref().iter() {
+        setup_meat_tx_env(meat, evm);
+        match evm.transact_commit() {
+            Ok(result) => is_meat_good.push(result.is_success()),
+            Err(e) => return Err(anyhow!("[huffsando: EVM ERROR] meat: {:?}", e)),
+        }
+    }
+
+    evm.env.tx = perturb_tx(backrun_tx_env);
+    match evm.transact_commit() {
+        Ok(result) if result.is_success() => { /* continue operation */ }
+        Ok(result) => return Err(anyhow!("[huffsando: REPLAY_FAILED] backrun: {:?}", result)),
+        Err(e) => return Err(anyhow!("[huffsando: EVM ERROR] backrun: {:?}", e)),
+    }
+
+    Ok(SandoOutcome {
+        start_end_balance: get_erc20_balance(
+            ingredients.get_start_end_token(),
+            sando_address,
+            next_block,
+            evm,
+        )?,
+        intermediary_balance: get_erc20_balance(
+            ingredients.get_intermediary_token(),
+            sando_address,
+            next_block,
+            evm,
+        )?,
+        is_meat_good,
+    })
+}
+
+/// Create an evm forked at `next_block` that our frontrun/backrun can be ran on
+#[allow(unused_variables)]
+fn setup_sando_evm<D: ForkDb>(
+    next_block: &BlockInfo,
+    searcher: Address,
+    sando_address: Address,
+    sando_start_bal: U256,
+    fork_db: D,
+) -> EVM<CacheDB<D>> {
+    #[allow(unused_mut)]
+    let mut cache_db = CacheDB::new(fork_db);
+
+    #[cfg(feature = "debug")]
+    {
+        inject_huff_sando(
+            &mut cache_db,
+            sando_address.0.into(),
+            searcher.0.into(),
+            sando_start_bal,
+        );
+    }
+    let mut evm = EVM::new();
+    evm.database(cache_db);
+    setup_block_state(&mut evm, next_block);
+    evm
 }
 
 /// Get the balance of a token in an evm (account for tax)
-pub fn get_erc20_balance(
+pub fn get_erc20_balance<D: ForkDb>(
     token: Address,
     owner: Address,
     block: &BlockInfo,
-    evm: &mut EVM<CacheDB<SharedBackend>>,
+    evm: &mut EVM<CacheDB<D>>,
 ) -> Result<U256> {
     let erc20 = BaseContract::from(
         parse_abi(&["function balanceOf(address) external returns (uint)"]).unwrap(),
@@ -369,11 +597,11 @@ pub fn get_erc20_balance(
 // Returns:
 // Ok(U256): amount out
 // Err(SimulationError): if error during caluclation
-pub fn v2_get_amount_out(
+pub fn v2_get_amount_out<D: ForkDb>(
     amount_in: U256,
     target_pool: UniswapV2Pool,
     is_frontrun: bool,
-    evm: &mut EVM<CacheDB<SharedBackend>>,
+    evm: &mut EVM<CacheDB<D>>,
 ) -> Result<U256> {
     // get reserves
     evm.env.tx.transact_to = TransactTo::Call(target_pool.address().0.into());
@@ -438,8 +666,8 @@ pub fn v2_get_amount_out(
 }
 
 #[cfg(feature = "debug")]
-fn inject_huff_sando(
-    db: &mut CacheDB<SharedBackend>,
+fn inject_huff_sando<D: ForkDb>(
+    db: &mut CacheDB<D>,
     huff_sando_addy: foundry_evm::executor::B160,
     searcher: foundry_evm::executor::B160,
     sando_start_bal: U256,
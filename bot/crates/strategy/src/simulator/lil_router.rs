@@ -1,11 +1,16 @@
+use std::{future::Future, sync::Arc};
+
 use anyhow::{anyhow, Result};
-use cfmms::pool::Pool::{UniswapV2, UniswapV3};
-use ethers::{abi, types::U256};
+use cfmms::pool::Pool::{self, UniswapV2, UniswapV3};
+use ethers::{
+    abi,
+    types::{Address, Bytes, U256},
+};
 use foundry_evm::{
-    executor::{fork::SharedBackend, Bytecode, ExecutionResult, Output, TransactTo},
+    executor::{Bytecode, ExecutionResult, Output, TransactTo},
     revm::{
         db::CacheDB,
-        primitives::{keccak256, AccountInfo, Address as rAddress, U256 as rU256},
+        primitives::{keccak256, AccountInfo, U256 as rU256},
         EVM,
     },
 };
@@ -14,22 +19,64 @@ use crate::{
     constants::{
         LIL_ROUTER_ADDRESS, LIL_ROUTER_CODE, LIL_ROUTER_CONTROLLER, WETH_ADDRESS, WETH_FUND_AMT,
     },
+    managers::pool_manager::weth_pair_token,
     tx_utils::lil_router_interface::{
         build_swap_v2_data, build_swap_v3_data, decode_swap_v2_result, decode_swap_v3_result,
     },
-    types::{BlockInfo, RawIngredients},
+    types::{BackrunIngredients, BlockInfo, HintedSwap, RawIngredients},
 };
 
-use super::{eth_to_wei, setup_block_state};
+use super::{
+    backend::SimulationBackend, eth_to_wei, huff_sando::get_erc20_balance, set_erc20_balance,
+    setup_block_state, setup_meat_tx_env, ForkDb,
+};
 
-// Juiced implementation of https://research.ijcaonline.org/volume65/number14/pxc3886165.pdf
-// splits range in more intervals, search intervals concurrently, compare, repeat till termination
+/// Find the sandwich frontrun input that produces the highest revenue, each input is evaluated
+/// through `simulation_backend`
 pub async fn find_optimal_input(
     ingredients: &RawIngredients,
     target_block: &BlockInfo,
     weth_inventory: U256,
-    shared_backend: SharedBackend,
+    simulation_backend: Arc<dyn SimulationBackend>,
+) -> Result<U256> {
+    search_optimal_input(weth_inventory, |frontrun_in| {
+        let simulation_backend = simulation_backend.clone();
+        let ingredients = ingredients.clone();
+        let target_block = *target_block;
+        async move {
+            simulation_backend
+                .sandwich_revenue(frontrun_in, target_block, ingredients)
+                .await
+        }
+    })
+    .await
+}
+
+/// Find the backrun input that produces the highest revenue once the hinted swap has landed
+pub async fn find_optimal_backrun_input<D: ForkDb>(
+    ingredients: &BackrunIngredients,
+    target_block: &BlockInfo,
+    weth_inventory: U256,
+    fork_db: D,
 ) -> Result<U256> {
+    search_optimal_input(weth_inventory, |backrun_in| {
+        evaluate_backrun_revenue(
+            backrun_in,
+            *target_block,
+            fork_db.clone(),
+            ingredients.clone(),
+        )
+    })
+    .await
+}
+
+// Juiced implementation of https://research.ijcaonline.org/volume65/number14/pxc3886165.pdf
+// splits range in more intervals, search intervals concurrently, compare, repeat till termination
+async fn search_optimal_input<F, Fut>(weth_inventory: U256, evaluate_revenue: F) -> Result<U256>
+where
+    F: Fn(U256) -> Fut,
+    Fut: Future<Output = Result<U256>> + Send + 'static,
+{
     //
     //            [EXAMPLE WITH 10 BOUND INTERVALS]
     //
@@ -106,12 +153,7 @@ pub async fn find_optimal_input(
         // calculate revenue at each interval concurrently
         let mut revenues = Vec::new();
         for bound in &intervals {
-            let sim = tokio::task::spawn(evaluate_sandwich_revenue(
-                *bound,
-                target_block.clone(),
-                shared_backend.clone(),
-                ingredients.clone(),
-            ));
+            let sim = tokio::task::spawn(evaluate_revenue(*bound));
             revenues.push(sim);
         }
 
@@ -164,115 +206,142 @@ pub async fn find_optimal_input(
     Ok(highest_sando_input)
 }
 
-async fn evaluate_sandwich_revenue(
+/// Simulate the sandwich locally, returns the lilRouter's weth balance after the backrun minus
+/// `WETH_FUND_AMT`
+pub(crate) async fn evaluate_sandwich_revenue<D: ForkDb>(
     frontrun_in: U256,
     next_block: BlockInfo,
-    shared_backend: SharedBackend,
+    fork_db: D,
     ingredients: RawIngredients,
 ) -> Result<U256> {
-    let mut fork_db = CacheDB::new(shared_backend);
-    inject_lil_router_code(&mut fork_db);
-
-    let mut evm = EVM::new();
-    evm.database(fork_db);
-    setup_block_state(&mut evm, &next_block);
+    let mut evm = setup_lil_router_evm(&next_block, fork_db);
 
     /*´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
     /*                    FRONTRUN TRANSACTION                    */
     /*.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
-    let frontrun_data = match ingredients.get_target_pool() {
-        UniswapV2(pool) => build_swap_v2_data(frontrun_in, pool, true),
-        UniswapV3(pool) => build_swap_v3_data(frontrun_in.as_u128().into(), pool, true),
-    };
-
-    evm.env.tx.caller = *LIL_ROUTER_CONTROLLER;
-    evm.env.tx.transact_to = TransactTo::Call(*LIL_ROUTER_ADDRESS);
-    evm.env.tx.data = frontrun_data.0;
-    evm.env.tx.gas_limit = 700000;
-    evm.env.tx.gas_price = next_block.base_fee_per_gas.into();
-    evm.env.tx.value = rU256::ZERO;
-
-    let result = match evm.transact_commit() {
-        Ok(result) => result,
-        Err(e) => return Err(anyhow!("[lilRouter: EVM ERROR] frontrun: {:?}", e)),
-    };
-    let output = match result {
-        ExecutionResult::Success { output, .. } => match output {
-            Output::Call(o) => o,
-            Output::Create(o, _) => o,
-        },
-        ExecutionResult::Revert { output, .. } => {
-            return Err(anyhow!("[lilRouter: REVERT] frontrun: {:?}", output))
-        }
-        ExecutionResult::Halt { reason, .. } => {
-            return Err(anyhow!("[lilRouter: HALT] frontrun: {:?}", reason))
-        }
-    };
-    let (_frontrun_out, backrun_in) = match ingredients.get_target_pool() {
-        UniswapV2(_) => match decode_swap_v2_result(output.into()) {
-            Ok(output) => output,
-            Err(e) => {
-                return Err(anyhow!(
-                    "[lilRouter: FailedToDecodeOutput] frontrun: {:?}",
-                    e
-                ))
-            }
-        },
-        UniswapV3(_) => match decode_swap_v3_result(output.into()) {
-            Ok(output) => output,
-            Err(e) => return Err(anyhow!("lilRouter: FailedToDecodeOutput: {:?}", e)),
-        },
-    };
+    let (_frontrun_out, backrun_in) = lil_router_swap(
+        frontrun_in,
+        ingredients.get_target_pool(),
+        true,
+        &next_block,
+        &mut evm,
+    )
+    .map_err(|e| anyhow!("{} frontrun", e))?;
 
     /*´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
     /*                     MEAT TRANSACTION/s                     */
     /*.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
     for meat in ingredients.get_meats_ref().iter() {
-        evm.env.tx.caller = rAddress::from_slice(&meat.from.0);
-        evm.env.tx.transact_to =
-            TransactTo::Call(rAddress::from_slice(&meat.to.unwrap_or_default().0));
-        evm.env.tx.data = meat.input.0.clone();
-        evm.env.tx.value = meat.value.into();
-        evm.env.tx.chain_id = meat.chain_id.map(|id| id.as_u64());
-        // evm.env.tx.nonce = Some(meat.nonce.as_u64()); /** ignore nonce check for now **/
-        evm.env.tx.gas_limit = meat.gas.as_u64();
-        match meat.transaction_type {
-            Some(ethers::types::U64([0])) => {
-                // legacy tx
-                evm.env.tx.gas_price = meat.gas_price.unwrap_or_default().into();
-            }
-            Some(_) => {
-                // type 2 tx
-                evm.env.tx.gas_priority_fee = meat.max_priority_fee_per_gas.map(|mpf| mpf.into());
-                evm.env.tx.gas_price = meat.max_fee_per_gas.unwrap_or_default().into();
-            }
-            None => {
-                // legacy tx
-                evm.env.tx.gas_price = meat.gas_price.unwrap_or_default().into();
-            }
-        }
-
+        setup_meat_tx_env(meat, &mut evm);
         let _res = evm.transact_commit();
     }
 
     /*´:°•.°+.*•´.*:˚.°*.˚•´.°:°•.°•.*•´.*:˚.°*.˚•´.°:°•.°+.*•´.*:*/
     /*                    BACKRUN TRANSACTION                     */
     /*.•°:°.´+˚.*°.˚:*.´•*.+°.•°:´*.´•*.•°.•°:°.´:•˚°.*°.˚:*.´+°.•*/
-    let backrun_data = match ingredients.get_target_pool() {
-        UniswapV2(pool) => build_swap_v2_data(backrun_in, pool, false),
-        UniswapV3(pool) => build_swap_v3_data(backrun_in.as_u128().into(), pool, false),
+    let (_backrun_out, post_sandwich_balance) = lil_router_swap(
+        backrun_in,
+        ingredients.get_target_pool(),
+        false,
+        &next_block,
+        &mut evm,
+    )
+    .map_err(|e| anyhow!("{} backrun", e))?;
+
+    let revenue = post_sandwich_balance
+        .checked_sub((*WETH_FUND_AMT).into())
+        .unwrap_or_default();
+
+    Ok(revenue)
+}
+
+async fn evaluate_backrun_revenue<D: ForkDb>(
+    backrun_in: U256,
+    next_block: BlockInfo,
+    fork_db: D,
+    ingredients: BackrunIngredients,
+) -> Result<U256> {
+    let mut evm = setup_lil_router_evm(&next_block, fork_db);
+
+    replay_hinted_swap(&ingredients.get_victim_swap(), &next_block, &mut evm)?;
+
+    let weth_balance = get_erc20_balance(
+        *WETH_ADDRESS,
+        (*LIL_ROUTER_ADDRESS).into(),
+        &next_block,
+        &mut evm,
+    )?;
+
+    // buy the intermediary token where it is cheaper
+    let (_buy_out, intermediary_balance) = lil_router_swap(
+        backrun_in,
+        ingredients.get_buy_pool(),
+        true,
+        &next_block,
+        &mut evm,
+    )
+    .map_err(|e| anyhow!("{} backrun buy", e))?;
+
+    // and sell it where it is more expensive
+    let (_sell_out, post_backrun_balance) = lil_router_swap(
+        intermediary_balance,
+        ingredients.get_sell_pool(),
+        false,
+        &next_block,
+        &mut evm,
+    )
+    .map_err(|e| anyhow!("{} backrun sell", e))?;
+
+    Ok(post_backrun_balance
+        .checked_sub(weth_balance)
+        .unwrap_or_default())
+}
+
+/// Replay a hinted swap through the lilRouter, the hinted tx itself is unknown so only its effect
+/// on the pool is reproduced
+pub(crate) fn replay_hinted_swap<D: ForkDb>(
+    swap: &HintedSwap,
+    next_block: &BlockInfo,
+    evm: &mut EVM<CacheDB<D>>,
+) -> Result<()> {
+    let token_in = match swap.weth_in {
+        true => *WETH_ADDRESS,
+        false => weth_pair_token(&swap.pool)
+            .ok_or(anyhow!("[lilRouter] hinted swap isn't on a weth pool"))?,
     };
+    let router: Address = (*LIL_ROUTER_ADDRESS).into();
+
+    // fund the lilRouter with the swap's input on top of what it already holds
+    let balance = get_erc20_balance(token_in, router, next_block, evm)?;
+    set_erc20_balance(token_in, router, balance + swap.amount_in, next_block, evm)?;
+
+    lil_router_swap(swap.amount_in, swap.pool, swap.weth_in, next_block, evm)
+        .map_err(|e| anyhow!("{} hinted swap", e))?;
+
+    Ok(())
+}
+
+/// Swap on `pool` through the lilRouter, `is_weth_in` trades WETH -> TOKEN (TOKEN -> WETH
+/// otherwise), returns the amount out and the lilRouter's balance of the output token
+fn lil_router_swap<D: ForkDb>(
+    amount_in: U256,
+    pool: Pool,
+    is_weth_in: bool,
+    next_block: &BlockInfo,
+    evm: &mut EVM<CacheDB<D>>,
+) -> Result<(U256, U256)> {
+    let data = build_swap_data(amount_in, pool, is_weth_in);
 
     evm.env.tx.caller = *LIL_ROUTER_CONTROLLER;
     evm.env.tx.transact_to = TransactTo::Call(*LIL_ROUTER_ADDRESS);
-    evm.env.tx.data = backrun_data.0;
+    evm.env.tx.data = data.0;
     evm.env.tx.gas_limit = 700000;
     evm.env.tx.gas_price = next_block.base_fee_per_gas.into();
     evm.env.tx.value = rU256::ZERO;
 
     let result = match evm.transact_commit() {
         Ok(result) => result,
-        Err(e) => return Err(anyhow!("[lilRouter: EVM ERROR] backrun: {:?}", e)),
+        Err(e) => return Err(anyhow!("[lilRouter: EVM ERROR] {:?}", e)),
     };
     let output = match result {
         ExecutionResult::Success { output, .. } => match output {
@@ -280,32 +349,48 @@ async fn evaluate_sandwich_revenue(
             Output::Create(o, _) => o,
         },
         ExecutionResult::Revert { output, .. } => {
-            return Err(anyhow!("[lilRouter: REVERT] backrun: {:?}", output))
+            return Err(anyhow!("[lilRouter: REVERT] {:?}", output))
         }
         ExecutionResult::Halt { reason, .. } => {
-            return Err(anyhow!("[lilRouter: HALT] backrun: {:?}", reason))
+            return Err(anyhow!("[lilRouter: HALT] {:?}", reason))
         }
     };
-    let (_backrun_out, post_sandwich_balance) = match ingredients.get_target_pool() {
-        UniswapV2(_) => match decode_swap_v2_result(output.into()) {
-            Ok(output) => output,
-            Err(e) => return Err(anyhow!("[lilRouter: FailedToDecodeOutput] {:?}", e)),
-        },
-        UniswapV3(_) => match decode_swap_v3_result(output.into()) {
-            Ok(output) => output,
-            Err(e) => return Err(anyhow!("[lilRouter: FailedToDecodeOutput] {:?}", e)),
-        },
-    };
 
-    let revenue = post_sandwich_balance
-        .checked_sub((*WETH_FUND_AMT).into())
-        .unwrap_or_default();
+    decode_swap_result(pool, output.into())
+}
 
-    Ok(revenue)
+/// Calldata of a lilRouter swap on `pool`, `is_weth_in` trades WETH -> TOKEN (TOKEN -> WETH
+/// otherwise)
+pub(crate) fn build_swap_data(amount_in: U256, pool: Pool, is_weth_in: bool) -> Bytes {
+    match pool {
+        UniswapV2(pool) => build_swap_v2_data(amount_in, pool, is_weth_in),
+        UniswapV3(pool) => build_swap_v3_data(amount_in.as_u128().into(), pool, is_weth_in),
+    }
+}
+
+/// Decode a lilRouter swap's output into the amount out and the lilRouter's balance of the output
+/// token
+pub(crate) fn decode_swap_result(pool: Pool, output: Bytes) -> Result<(U256, U256)> {
+    match pool {
+        UniswapV2(_) => decode_swap_v2_result(output),
+        UniswapV3(_) => decode_swap_v3_result(output),
+    }
+    .map_err(|e| anyhow!("[lilRouter: FailedToDecodeOutput] {:?}", e))
+}
+
+/// Create an evm forked at `next_block` with the lilRouter injected
+fn setup_lil_router_evm<D: ForkDb>(next_block: &BlockInfo, fork_db: D) -> EVM<CacheDB<D>> {
+    let mut cache_db = CacheDB::new(fork_db);
+    inject_lil_router_code(&mut cache_db);
+
+    let mut evm = EVM::new();
+    evm.database(cache_db);
+    setup_block_state(&mut evm, next_block);
+    evm
 }
 
 /// Inserts custom minimal router contract into evm instance for simulations
-fn inject_lil_router_code(db: &mut CacheDB<SharedBackend>) {
+pub(crate) fn inject_lil_router_code<D: ForkDb>(db: &mut CacheDB<D>) {
     // insert lilRouter bytecode
     let lil_router_info = AccountInfo::new(
         rU256::ZERO,
@@ -1,18 +1,84 @@
+pub mod backend;
+pub mod bundle_simulator;
+pub mod fixture;
 pub mod huff_sando;
 pub(crate) mod lil_router;
+pub mod rpc_backend;
 pub(crate) mod salmonella_inspector;
 
+use std::{
+    collections::{BTreeSet, HashMap},
+    fmt::Debug,
+    sync::Arc,
+};
+
+use anyhow::{anyhow, Result};
+use ethers::{
+    abi,
+    providers::Middleware,
+    types::{Address, Transaction, H256, U256, U64},
+};
 use foundry_evm::{
-    executor::fork::SharedBackend,
-    revm::{db::CacheDB, primitives::U256 as rU256, EVM},
+    executor::{
+        backend::DatabaseError,
+        fork::{BlockchainDb, BlockchainDbMeta, SharedBackend},
+        TransactTo, TxEnv,
+    },
+    revm::{
+        db::{CacheDB, DatabaseRef},
+        primitives::{keccak256, Address as rAddress, U256 as rU256},
+        Database, EVM,
+    },
+    utils::{b160_to_h160, ru256_to_u256},
 };
 
 use crate::{
-    constants::{COINBASE, ONE_ETHER_IN_WEI},
+    constants::{ALT_COINBASE, COINBASE, ONE_ETHER_IN_WEI},
     types::BlockInfo,
 };
 
-fn setup_block_state(evm: &mut EVM<CacheDB<SharedBackend>>, next_block: &BlockInfo) {
+/// State that simulations fork from, either a `SharedBackend` that lazily fetches from a node or
+/// a `FixtureDb` that only serves recorded state
+pub trait ForkDb:
+    DatabaseRef<Error = DatabaseError> + Clone + Debug + Send + Sync + 'static
+{
+}
+
+impl<T> ForkDb for T where
+    T: DatabaseRef<Error = DatabaseError> + Clone + Debug + Send + Sync + 'static
+{
+}
+
+/// Spawn a backend that lazily fetches state at `fork_block` from `provider`
+pub(crate) fn setup_shared_backend<M: Middleware + 'static>(
+    provider: Arc<M>,
+    fork_block: U64,
+) -> SharedBackend {
+    setup_shared_backend_with_db(provider, fork_block, new_blockchain_db())
+}
+
+/// Spawn a backend that serves state from `db`, fetching missing state from `provider`
+fn setup_shared_backend_with_db<M: Middleware + 'static>(
+    provider: Arc<M>,
+    fork_block: U64,
+    db: BlockchainDb,
+) -> SharedBackend {
+    SharedBackend::spawn_backend_thread(provider, db, Some(fork_block.into()))
+}
+
+/// Empty in memory state cache
+fn new_blockchain_db() -> BlockchainDb {
+    BlockchainDb::new(
+        BlockchainDbMeta {
+            cfg_env: Default::default(),
+            block_env: Default::default(),
+            hosts: BTreeSet::from(["".to_string()]),
+        },
+        None,
+    ) /* default because not accounting for this atm */
+}
+
+fn setup_block_state<D: ForkDb>(evm: &mut EVM<CacheDB<D>>, next_block: &BlockInfo) {
     evm.env.block.number = rU256::from(next_block.number.as_u64());
     evm.env.block.timestamp = next_block.timestamp.into();
     evm.env.block.basefee = next_block.base_fee_per_gas.into();
@@ -20,6 +86,306 @@ fn setup_block_state(evm: &mut EVM<CacheDB<SharedBackend>>, next_block: &BlockIn
     evm.env.block.coinbase = *COINBASE;
 }
 
+/// Setup evm's tx env to execute a victim tx
+pub(crate) fn setup_meat_tx_env<D: ForkDb>(meat: &Transaction, evm: &mut EVM<CacheDB<D>>) {
+    evm.env.tx.caller = rAddress::from_slice(&meat.from.0);
+    evm.env.tx.transact_to = TransactTo::Call(rAddress::from_slice(&meat.to.unwrap_or_default().0));
+    evm.env.tx.data = meat.input.0.clone();
+    evm.env.tx.value = meat.value.into();
+    evm.env.tx.chain_id = meat.chain_id.map(|id| id.as_u64());
+    //evm.env.tx.nonce = Some(meat.nonce.as_u64());
+    evm.env.tx.gas_limit = meat.gas.as_u64();
+    match meat.transaction_type {
+        Some(ethers::types::U64([0])) => {
+            // legacy tx
+            evm.env.tx.gas_price = meat.gas_price.unwrap_or_default().into();
+        }
+        Some(_) => {
+            // type 2 tx
+            evm.env.tx.gas_priority_fee = meat.max_priority_fee_per_gas.map(|mpf| mpf.into());
+            evm.env.tx.gas_price = meat.max_fee_per_gas.unwrap_or_default().into();
+        }
+        None => {
+            // legacy tx
+            evm.env.tx.gas_price = meat.gas_price.unwrap_or_default().into();
+        }
+    }
+}
+
+/// Storage slots changed by a tx as `(from, to)`, keyed by account and slot
+pub(crate) type StorageDiffs = HashMap<Address, HashMap<H256, (U256, U256)>>;
+
+/// Run `tx` locally on top of `next_block` and collect the storage slots that it changes
+pub(crate) fn get_storage_diffs(
+    tx: &Transaction,
+    next_block: &BlockInfo,
+    shared_backend: SharedBackend,
+) -> Result<StorageDiffs> {
+    let mut evm = EVM::new();
+    evm.database(CacheDB::new(shared_backend));
+    setup_block_state(&mut evm, next_block);
+    setup_meat_tx_env(tx, &mut evm);
+
+    let state = match evm.transact() {
+        Ok(result) => result.state,
+        Err(e) => return Err(anyhow!("[EVM ERROR] state diffs: {:?}", e)),
+    };
+
+    Ok(state
+        .into_iter()
+        .map(|(address, account)| {
+            let storage: HashMap<H256, (U256, U256)> = account
+                .storage
+                .into_iter()
+                .filter(|(_, value)| value.original_value != value.present_value)
+                .map(|(slot, value)| {
+                    (
+                        H256::from_uint(&ru256_to_u256(slot)),
+                        (
+                            ru256_to_u256(value.original_value),
+                            ru256_to_u256(value.present_value),
+                        ),
+                    )
+                })
+                .collect();
+            (b160_to_h160(address), storage)
+        })
+        .filter(|(_, storage)| !storage.is_empty())
+        .collect())
+}
+
+/// How many storage slots are probed for a token's `balanceOf` mapping
+const MAX_BALANCE_SLOT: u64 = 32;
+
+/// Set `owner`'s balance of `token` by probing for the slot of the token's `balanceOf` mapping
+/// (assumes solidity's mapping layout), slots are probed with a value other than the current
+/// balance so that an unrelated slot can't match when the owner already holds `amount`
+pub(crate) fn set_erc20_balance<D: ForkDb>(
+    token: Address,
+    owner: Address,
+    amount: U256,
+    block: &BlockInfo,
+    evm: &mut EVM<CacheDB<D>>,
+) -> Result<()> {
+    let current = huff_sando::get_erc20_balance(token, owner, block, evm)?;
+    let probe = current.overflowing_add(U256::one()).0;
+
+    for slot in 0..MAX_BALANCE_SLOT {
+        let key: rU256 = keccak256(&abi::encode(&[
+            abi::Token::Address(owner),
+            abi::Token::Uint(U256::from(slot)),
+        ]))
+        .into();
+
+        let db = evm.db.as_mut().ok_or(anyhow!("evm has no database"))?;
+        let original = db
+            .storage(token.0.into(), key)
+            .map_err(|e| anyhow!("[set_erc20_balance: DatabaseError] {:?}", e))?;
+        db.insert_account_storage(token.0.into(), key, probe.into())
+            .map_err(|e| anyhow!("[set_erc20_balance: DatabaseError] {:?}", e))?;
+
+        let balance = huff_sando::get_erc20_balance(token, owner, block, evm).ok();
+        if balance == Some(probe) {
+            let db = evm.db.as_mut().ok_or(anyhow!("evm has no database"))?;
+            db.insert_account_storage(token.0.into(), key, amount.into())
+                .map_err(|e| anyhow!("[set_erc20_balance: DatabaseError] {:?}", e))?;
+            return Ok(());
+        }
+
+        // not the balance mapping, put the slot back
+        if let Some(db) = evm.db.as_mut() {
+            db.insert_account_storage(token.0.into(), key, original)
+                .map_err(|e| anyhow!("[set_erc20_balance: DatabaseError] {:?}", e))?;
+        }
+    }
+
+    Err(anyhow!(
+        "[set_erc20_balance] balance slot of {:?} not found",
+        token
+    ))
+}
+
 pub fn eth_to_wei(amt: u128) -> rU256 {
     rU256::from(amt).checked_mul(*ONE_ETHER_IN_WEI).unwrap()
 }
+
+/// Alternative block/tx context that a sandwich is replayed under, salmonella tokens can branch on
+/// these values to behave differently onchain compared to our simulations
+#[derive(Debug, Clone)]
+pub struct EnvPerturbation {
+    /// Used when logging why a sandwich was rejected
+    pub name: &'static str,
+    /// Replaces `COINBASE` as the block's coinbase
+    pub coinbase: Option<rAddress>,
+    /// Added as a priority fee on top of our frontrun/backrun gas price
+    pub gas_price_bump: U256,
+    /// Seconds added to the block's timestamp
+    pub timestamp_offset: u64,
+    /// Replaces the block's gas limit
+    pub gas_limit: Option<u64>,
+}
+
+impl EnvPerturbation {
+    /// One perturbation per context value so that a rejection points at the value being read
+    pub fn defaults() -> Vec<EnvPerturbation> {
+        let unperturbed = EnvPerturbation {
+            name: "",
+            coinbase: None,
+            gas_price_bump: U256::zero(),
+            timestamp_offset: 0,
+            gas_limit: None,
+        };
+
+        vec![
+            EnvPerturbation {
+                name: "coinbase",
+                coinbase: Some(*ALT_COINBASE),
+                ..unperturbed.clone()
+            },
+            EnvPerturbation {
+                name: "gas_price",
+                gas_price_bump: U256::from(2_000_000_000u64), // 2 gwei
+                ..unperturbed.clone()
+            },
+            EnvPerturbation {
+                name: "timestamp",
+                timestamp_offset: 24,
+                ..unperturbed.clone()
+            },
+            EnvPerturbation {
+                name: "gas_limit",
+                gas_limit: Some(30_000_000),
+                ..unperturbed
+            },
+        ]
+    }
+
+    /// Apply perturbation to the evm's block env
+    fn apply_to_block<D: ForkDb>(&self, evm: &mut EVM<CacheDB<D>>) {
+        if let Some(coinbase) = self.coinbase {
+            evm.env.block.coinbase = coinbase;
+        }
+        evm.env.block.timestamp += rU256::from(self.timestamp_offset);
+        if let Some(gas_limit) = self.gas_limit {
+            evm.env.block.gas_limit = rU256::from(gas_limit);
+        }
+    }
+
+    /// Apply perturbation to one of our own txs
+    fn apply_to_tx(&self, tx: &TxEnv) -> TxEnv {
+        let mut tx = tx.clone();
+        if !self.gas_price_bump.is_zero() {
+            let gas_price_bump: rU256 = self.gas_price_bump.into();
+            tx.gas_price += gas_price_bump;
+            tx.gas_priority_fee = Some(gas_price_bump);
+        }
+        tx
+    }
+
+    /// Fund the caller of one of our own txs for the extra gas the bump costs, otherwise a lightly
+    /// funded searcher fails the perturbed replay and a good sandwich looks env dependent
+    fn fund_gas_price_bump<D: ForkDb>(&self, tx: &TxEnv, evm: &mut EVM<CacheDB<D>>) -> Result<()> {
+        if self.gas_price_bump.is_zero() {
+            return Ok(());
+        }
+
+        let gas_price_bump: rU256 = self.gas_price_bump.into();
+        let db = evm.db.as_mut().ok_or(anyhow!("evm has no database"))?;
+        let mut info = db
+            .basic(tx.caller)
+            .map_err(|e| anyhow!("[fund_gas_price_bump: DatabaseError] {:?}", e))?
+            .unwrap_or_default();
+        info.balance += rU256::from(tx.gas_limit) * gas_price_bump;
+        db.insert_account_info(tx.caller, info);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use foundry_evm::{
+        executor::Bytecode,
+        revm::primitives::{AccountInfo, B160, B256},
+    };
+
+    use super::*;
+
+    /// State where every account and slot is empty
+    #[derive(Debug, Clone)]
+    struct EmptyForkDb;
+
+    impl DatabaseRef for EmptyForkDb {
+        type Error = DatabaseError;
+
+        fn basic(&self, _address: B160) -> Result<Option<AccountInfo>, Self::Error> {
+            Ok(None)
+        }
+
+        fn code_by_hash(&self, _code_hash: B256) -> Result<Bytecode, Self::Error> {
+            Ok(Bytecode::default())
+        }
+
+        fn storage(&self, _address: B160, _index: rU256) -> Result<rU256, Self::Error> {
+            Ok(rU256::ZERO)
+        }
+
+        fn block_hash(&self, _number: rU256) -> Result<B256, Self::Error> {
+            Ok(B256::zero())
+        }
+    }
+
+    /// Token whose `balanceOf` (any selector) returns the `balances` mapping at storage slot 3
+    const TOKEN_CODE: [u8; 25] = [
+        0x60, 0x04, 0x35, // calldataload(4), the owner
+        0x60, 0x00, 0x52, // mstore(0, owner)
+        0x60, 0x03, 0x60, 0x20, 0x52, // mstore(32, 3)
+        0x60, 0x40, 0x60, 0x00, 0x20, 0x54, // sload(keccak256(0, 64))
+        0x60, 0x00, 0x52, // mstore(0, balance)
+        0x60, 0x20, 0x60, 0x00, 0xf3, // return(0, 32)
+    ];
+
+    #[test]
+    fn set_erc20_balance_overrides_the_balance_slot_when_the_owner_holds_amount() {
+        let (token, owner, amount) = (Address::random(), Address::random(), U256::from(1234));
+        let balance_key = |slot: u64| -> rU256 {
+            keccak256(&abi::encode(&[
+                abi::Token::Address(owner),
+                abi::Token::Uint(U256::from(slot)),
+            ]))
+            .into()
+        };
+        let block = BlockInfo {
+            number: 1.into(),
+            base_fee_per_gas: U256::zero(),
+            timestamp: U256::zero(),
+            gas_used: None,
+            gas_limit: None,
+        };
+
+        let mut db = CacheDB::new(EmptyForkDb);
+        let code = ethers::types::Bytes::from(TOKEN_CODE.to_vec());
+        db.insert_account_info(
+            token.0.into(),
+            AccountInfo::new(rU256::ZERO, 0, Bytecode::new_raw(code.0)),
+        );
+        db.insert_account_storage(token.0.into(), balance_key(3), amount.into())
+            .unwrap();
+        let mut evm = EVM::new();
+        evm.database(db);
+
+        set_erc20_balance(token, owner, amount, &block, &mut evm).unwrap();
+
+        assert_eq!(
+            huff_sando::get_erc20_balance(token, owner, &block, &mut evm).unwrap(),
+            amount
+        );
+        let db = evm.db.as_mut().unwrap();
+        for slot in (0..MAX_BALANCE_SLOT).filter(|slot| *slot != 3) {
+            assert_eq!(
+                db.storage(token.0.into(), balance_key(slot)).unwrap(),
+                rU256::ZERO
+            );
+        }
+    }
+}
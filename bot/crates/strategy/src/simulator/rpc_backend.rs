@@ -0,0 +1,177 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use ethers::{
+    abi,
+    providers::{spoof, Middleware},
+    types::{Address, BlockNumber, Bytes, TransactionRequest, H256, U256, U64},
+    utils::keccak256,
+};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::{
+    constants::{
+        COINBASE, LIL_ROUTER_ADDRESS, LIL_ROUTER_CODE, LIL_ROUTER_CONTROLLER,
+        LIL_ROUTER_SWEEPER_ADDRESS, LIL_ROUTER_SWEEPER_CODE, WETH_ADDRESS, WETH_FUND_AMT,
+    },
+    types::{BlockInfo, RawIngredients},
+};
+
+use super::{
+    backend::SimulationBackend,
+    eth_to_wei,
+    lil_router::{build_swap_data, decode_swap_result},
+};
+
+/// Outcome of one call in an `eth_callMany` response
+#[derive(Debug, Deserialize)]
+struct CallManyResult {
+    value: Option<Bytes>,
+    error: Option<String>,
+}
+
+impl CallManyResult {
+    fn into_output(self) -> Result<Bytes> {
+        match (self.value, self.error) {
+            (_, Some(error)) => Err(anyhow!("[lilRouter: REVERT] {}", error)),
+            (Some(value), None) => Ok(value),
+            (None, None) => Err(anyhow!("[lilRouter: REVERT] no output")),
+        }
+    }
+}
+
+/// Simulates each frontrun input with a single `eth_callMany` request (frontrun, meats and backrun
+/// as one bundle on top of the previous block), the lilRouter is injected with state overrides
+///
+/// The node must support `eth_callMany` with state overrides (e.g. reth or erigon)
+#[derive(Debug)]
+pub struct RpcBackend<M> {
+    provider: Arc<M>,
+}
+
+impl<M: Middleware> RpcBackend<M> {
+    pub fn new(provider: Arc<M>) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl<M: Middleware + 'static> SimulationBackend for RpcBackend<M> {
+    async fn sandwich_revenue(
+        &self,
+        frontrun_in: U256,
+        next_block: BlockInfo,
+        ingredients: RawIngredients,
+    ) -> Result<U256> {
+        let pool = ingredients.get_target_pool();
+        let lil_router: Address = (*LIL_ROUTER_ADDRESS).into();
+
+        let mut transactions = vec![lil_router_tx(
+            lil_router,
+            build_swap_data(frontrun_in, pool, true),
+        )];
+
+        transactions.extend(
+            ingredients
+                .get_meats_ref()
+                .iter()
+                .map(|meat| TransactionRequest {
+                    from: Some(meat.from),
+                    to: meat.to.map(Into::into),
+                    gas: Some(meat.gas),
+                    value: Some(meat.value),
+                    data: Some(meat.input.clone()),
+                    ..Default::default()
+                }),
+        );
+
+        // the backrun's input is only known once the frontrun has landed, the sweeper fills it in
+        let mut backrun_data = abi::encode(&[
+            abi::Token::Address(ingredients.get_intermediary_token()),
+            abi::Token::Address(lil_router),
+        ]);
+        backrun_data.extend(build_swap_data(U256::zero(), pool, false).0);
+        transactions.push(lil_router_tx(
+            (*LIL_ROUTER_SWEEPER_ADDRESS).into(),
+            backrun_data.into(),
+        ));
+
+        let bundle = json!({
+            "transactions": transactions,
+            "blockOverride": {
+                "number": next_block.number,
+                "time": U64::from(next_block.timestamp.as_u64()),
+                "baseFee": next_block.base_fee_per_gas,
+                "coinbase": Address::from(*COINBASE),
+            },
+        });
+        let state_context = json!({
+            "blockNumber": BlockNumber::Number(next_block.number - 1),
+            "transactionIndex": -1,
+        });
+
+        let mut results: Vec<Vec<CallManyResult>> = self
+            .provider
+            .provider()
+            .request(
+                "eth_callMany",
+                (vec![bundle], state_context, lil_router_state()),
+            )
+            .await
+            .map_err(|e| anyhow!("[eth_callMany] {:?}", e))?;
+
+        let mut results = results
+            .pop()
+            .filter(|results| results.len() == transactions.len())
+            .ok_or(anyhow!("[eth_callMany] missing results"))?;
+
+        let backrun_output = results.pop().unwrap().into_output();
+        let frontrun_output = results.swap_remove(0).into_output();
+
+        decode_swap_result(pool, frontrun_output?).map_err(|e| anyhow!("{} frontrun", e))?;
+        let (_backrun_out, post_sandwich_balance) =
+            decode_swap_result(pool, backrun_output?).map_err(|e| anyhow!("{} backrun", e))?;
+
+        let revenue = post_sandwich_balance
+            .checked_sub((*WETH_FUND_AMT).into())
+            .unwrap_or_default();
+
+        Ok(revenue)
+    }
+}
+
+/// Call made by the lilRouter's controller
+fn lil_router_tx(to: Address, data: Bytes) -> TransactionRequest {
+    TransactionRequest::new()
+        .from(Address::from(*LIL_ROUTER_CONTROLLER))
+        .to(to)
+        .data(data)
+        .gas(700000)
+}
+
+/// Same state as `inject_lil_router_code` sets up locally, plus the sweeper
+fn lil_router_state() -> spoof::State {
+    let lil_router: Address = (*LIL_ROUTER_ADDRESS).into();
+    let mut state = spoof::state();
+
+    state.account(lil_router).code(LIL_ROUTER_CODE.clone());
+    state
+        .account((*LIL_ROUTER_SWEEPER_ADDRESS).into())
+        .code(LIL_ROUTER_SWEEPER_CODE.clone());
+    state
+        .account((*LIL_ROUTER_CONTROLLER).into())
+        .balance((*WETH_FUND_AMT).into());
+
+    // fund lilRouter with 200 weth
+    let slot = keccak256(abi::encode(&[
+        abi::Token::Address(lil_router),
+        abi::Token::Uint(U256::from(3)),
+    ]));
+    state
+        .account(*WETH_ADDRESS)
+        .store(slot.into(), H256::from_uint(&U256::from(eth_to_wei(200))));
+
+    state
+}
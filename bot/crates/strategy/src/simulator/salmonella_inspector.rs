@@ -1,3 +1,7 @@
+use std::collections::HashSet;
+
+use anyhow::{anyhow, Result};
+use ethers::types::{Address, H256};
 use foundry_evm::{
     executor::InstructionResult,
     revm::{
@@ -8,6 +12,8 @@ use foundry_evm::{
 
 pub enum IsSandoSafu {
     Safu,
+    /// Only opcodes marked as warnings by the policy were hit
+    SafuWithWarnings(Vec<OpCode>),
     NotSafu(Vec<OpCode>),
 }
 
@@ -35,17 +41,119 @@ impl OpCode {
     }
 }
 
-pub struct SalmonellaInspectoooor {
+/// Decides which opcodes are fatal, which are only warnings, and which contracts are exempt
+/// from salmonella checks
+#[derive(Debug, Clone)]
+pub struct SalmonellaPolicy {
+    /// Opcodes that reject the sandwich when executed
+    fatal_opcodes: HashSet<u8>,
+    /// Opcodes that are logged but do not reject the sandwich
+    warning_opcodes: HashSet<u8>,
+    /// Contracts whose execution is never inspected
+    exempt_addresses: HashSet<Address>,
+    /// Contract codehashes whose execution is never inspected
+    exempt_codehashes: HashSet<H256>,
+}
+
+impl Default for SalmonellaPolicy {
+    fn default() -> Self {
+        // these opcodes can be used to divert execution flow when ran locally vs on mainnet
+        // extra safe version, can easily ignore half of these checks if ur up for it
+        let fatal_opcodes = [
+            opcode::BALANCE,
+            opcode::GASPRICE,
+            opcode::EXTCODEHASH,
+            opcode::BLOCKHASH,
+            opcode::COINBASE,
+            opcode::DIFFICULTY,
+            opcode::GASLIMIT,
+            opcode::SELFBALANCE,
+            opcode::BASEFEE,
+            opcode::CREATE,
+            opcode::CREATE2,
+            opcode::SELFDESTRUCT,
+        ];
+
+        Self {
+            fatal_opcodes: HashSet::from(fatal_opcodes),
+            warning_opcodes: HashSet::new(),
+            exempt_addresses: HashSet::new(),
+            exempt_codehashes: HashSet::new(),
+        }
+    }
+}
+
+impl SalmonellaPolicy {
+    /// Create a policy with no fatal opcodes, warnings, or exemptions
+    pub fn empty() -> Self {
+        Self {
+            fatal_opcodes: HashSet::new(),
+            warning_opcodes: HashSet::new(),
+            exempt_addresses: HashSet::new(),
+            exempt_codehashes: HashSet::new(),
+        }
+    }
+
+    /// Mark opcode as fatal (removes it from warnings)
+    pub fn add_fatal_opcode(&mut self, code: u8) {
+        self.warning_opcodes.remove(&code);
+        self.fatal_opcodes.insert(code);
+    }
+
+    /// Mark opcode as a warning (removes it from fatal)
+    pub fn add_warning_opcode(&mut self, code: u8) {
+        self.fatal_opcodes.remove(&code);
+        self.warning_opcodes.insert(code);
+    }
+
+    /// Stop inspecting execution inside `address`
+    pub fn exempt_address(&mut self, address: Address) {
+        self.exempt_addresses.insert(address);
+    }
+
+    /// Stop inspecting execution of contracts whose code hashes to `codehash`
+    pub fn exempt_codehash(&mut self, codehash: H256) {
+        self.exempt_codehashes.insert(codehash);
+    }
+
+    pub fn is_fatal(&self, code: u8) -> bool {
+        self.fatal_opcodes.contains(&code)
+    }
+
+    pub fn is_warning(&self, code: u8) -> bool {
+        self.warning_opcodes.contains(&code)
+    }
+
+    pub fn is_exempt(&self, address: Address, codehash: H256) -> bool {
+        self.exempt_addresses.contains(&address) || self.exempt_codehashes.contains(&codehash)
+    }
+
+    /// Find numeric opcode from its mnemonic (case insensitive), e.g. `"SELFBALANCE"` -> `0x47`
+    pub fn parse_opcode(name: &str) -> Result<u8> {
+        let name = name.trim();
+        opcode::OPCODE_JUMPMAP
+            .iter()
+            .position(|op| matches!(op, Some(op) if op.eq_ignore_ascii_case(name)))
+            .map(|code| code as u8)
+            .ok_or(anyhow!("unknown opcode \"{}\"", name))
+    }
+}
+
+pub struct SalmonellaInspectoooor<'a> {
+    policy: &'a SalmonellaPolicy,
     suspicious_opcodes: Vec<OpCode>,
+    warning_opcodes: Vec<OpCode>,
     gas_opcode_counter: u64,
     call_opcode_counter: u64,
 }
 
-impl SalmonellaInspectoooor {
-    // create new salmonella inspector
-    pub fn new() -> Self {
+impl<'a> SalmonellaInspectoooor<'a> {
+    // create new salmonella inspector that flags opcodes according to `policy`
+    pub fn new(policy: &'a SalmonellaPolicy) -> Self {
         Self {
+            policy,
             suspicious_opcodes: Vec::new(),
+            warning_opcodes: Vec::new(),
             gas_opcode_counter: 0,
             call_opcode_counter: 0,
         }
@@ -57,7 +165,7 @@ impl SalmonellaInspectoooor {
     // `self`: consumes self during calculation
     //
     // Returns:
-    // IsSandoSafu: enum that is either Safu or NotSafu
+    // IsSandoSafu: enum that is either Safu, SafuWithWarnings, or NotSafu
     pub fn is_sando_safu(self) -> IsSandoSafu {
         // if more gas opcodes used then call then we know that the contract is checking gas_used
         let mut suspicious_opcodes = self.suspicious_opcodes.clone();
@@ -66,14 +174,18 @@ impl SalmonellaInspectoooor {
             suspicious_opcodes.insert(0, gas_opcode);
         }
 
-        match self.suspicious_opcodes.len() == 0 {
+        if !self.suspicious_opcodes.is_empty() {
+            return IsSandoSafu::NotSafu(suspicious_opcodes);
+        }
+
+        match self.warning_opcodes.is_empty() {
             true => IsSandoSafu::Safu,
-            false => IsSandoSafu::NotSafu(suspicious_opcodes),
+            false => IsSandoSafu::SafuWithWarnings(self.warning_opcodes),
         }
     }
 }
 
-impl<DB: Database> Inspector<DB> for SalmonellaInspectoooor {
+impl<DB: Database> Inspector<DB> for SalmonellaInspectoooor<'_> {
     // get opcode by calling `interp.contract.opcode(interp.program_counter())`.
     // all other information can be obtained from interp.
     fn step(
@@ -82,39 +194,31 @@ impl<DB: Database> Inspector<DB> for SalmonellaInspectoooor {
         _data: &mut EVMData<'_, DB>,
         _is_static: bool,
     ) -> InstructionResult {
-        let executed_opcode = interp.current_opcode();
-
-        let mut add_suspicious = |opcode: OpCode| self.suspicious_opcodes.push(opcode);
-        let mut increment_call_counter = || self.call_opcode_counter += 1;
-
-        let executed_opcode = OpCode::new_from_code(executed_opcode);
+        let executed_opcode = OpCode::new_from_code(interp.current_opcode());
 
+        // the gas check in `is_sando_safu` counts every call and gas opcode, whatever the policy
         match executed_opcode.code {
-            // these opcodes can be used to divert execution flow when ran locally vs on mainnet
-            // extra safe version, can easily ignore half of these checks if ur up for it
-            opcode::BALANCE => add_suspicious(executed_opcode.clone()),
-            opcode::GASPRICE => add_suspicious(executed_opcode.clone()),
-            opcode::EXTCODEHASH => add_suspicious(executed_opcode.clone()),
-            opcode::BLOCKHASH => add_suspicious(executed_opcode.clone()),
-            opcode::COINBASE => add_suspicious(executed_opcode.clone()),
-            opcode::DIFFICULTY => add_suspicious(executed_opcode.clone()),
-            opcode::GASLIMIT => add_suspicious(executed_opcode.clone()),
-            opcode::SELFBALANCE => add_suspicious(executed_opcode.clone()),
-            opcode::BASEFEE => add_suspicious(executed_opcode.clone()),
-            opcode::CREATE => add_suspicious(executed_opcode.clone()),
-            opcode::CREATE2 => add_suspicious(executed_opcode.clone()),
-            opcode::SELFDESTRUCT => add_suspicious(executed_opcode.clone()),
-            // add one to call counter
-            opcode::CALL => increment_call_counter(),
-            opcode::DELEGATECALL => increment_call_counter(),
-            opcode::STATICCALL => increment_call_counter(),
-            // add one to gas opcode counter
+            opcode::CALL | opcode::DELEGATECALL | opcode::STATICCALL => {
+                self.call_opcode_counter += 1
+            }
             opcode::GAS => self.gas_opcode_counter += 1,
-            _ => { /* this opcode is safu */ }
+            _ => {}
+        }
+
+        // skip contracts that have been vetted
+        let address = Address::from(interp.contract.address.0);
+        let codehash = H256::from(interp.contract.hash.0);
+        if self.policy.is_exempt(address, codehash) {
+            return InstructionResult::Continue;
         }
 
-        if &executed_opcode.name == "UNKNOWN" {
-            add_suspicious(executed_opcode);
+        match executed_opcode.code {
+            code if self.policy.is_fatal(code) => self.suspicious_opcodes.push(executed_opcode),
+            code if self.policy.is_warning(code) => self.warning_opcodes.push(executed_opcode),
+            _ if &executed_opcode.name == "UNKNOWN" => {
+                self.suspicious_opcodes.push(executed_opcode)
+            }
+            _ => { /* this opcode is safu */ }
         }
 
         InstructionResult::Continue
@@ -0,0 +1,130 @@
+use std::fmt::Debug;
+
+use anyhow::Result;
+use ethers::{
+    types::{Address, H256, I256, U256, U64},
+    utils::keccak256,
+};
+
+use crate::types::{BundleOutcome, TxSource};
+
+pub mod sqlite;
+pub mod writer;
+
+/// Where an evaluated opportunity ended up
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpportunityStatus {
+    /// Simulation or bundle creation failed
+    NotSandwichable,
+    /// A better bundle was already live for the same pool and block
+    Outcompeted,
+    /// Bundle was sent
+    Submitted,
+    /// Bundle was replaced by a better one for the same pool and block
+    Replaced,
+    /// Bundle was cancelled because it went stale
+    Cancelled,
+    /// Bundle failed simulation or no builder accepted it
+    Dropped,
+    Landed,
+    Missed,
+    Outbid,
+}
+
+impl OpportunityStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OpportunityStatus::NotSandwichable => "not_sandwichable",
+            OpportunityStatus::Outcompeted => "outcompeted",
+            OpportunityStatus::Submitted => "submitted",
+            OpportunityStatus::Replaced => "replaced",
+            OpportunityStatus::Cancelled => "cancelled",
+            OpportunityStatus::Dropped => "dropped",
+            OpportunityStatus::Landed => "landed",
+            OpportunityStatus::Missed => "missed",
+            OpportunityStatus::Outbid => "outbid",
+        }
+    }
+}
+
+impl From<&BundleOutcome> for OpportunityStatus {
+    fn from(outcome: &BundleOutcome) -> Self {
+        match outcome {
+            BundleOutcome::Landed { .. } => OpportunityStatus::Landed,
+            BundleOutcome::Missed => OpportunityStatus::Missed,
+            BundleOutcome::Outbid { .. } => OpportunityStatus::Outbid,
+        }
+    }
+}
+
+/// A victim evaluated against a pool, fields are `None` when evaluation stopped before they
+/// were known
+#[derive(Debug, Clone)]
+pub struct OpportunityRecord {
+    pub victim_hash: H256,
+    pub target_block: U64,
+    pub pool: Address,
+    pub optimal_input: Option<U256>,
+    pub revenue: Option<U256>,
+    pub frontrun_gas_used: Option<u64>,
+    pub backrun_gas_used: Option<u64>,
+    /// Amount paid to coinbase by the backrun
+    pub bribe: Option<U256>,
+    pub bundle_hash: Option<H256>,
+    pub status: OpportunityStatus,
+    /// Why the opportunity was not submitted
+    pub reason: Option<String>,
+    /// Mempool source that saw the victim first, `None` for MEV-Share hints and untagged txs
+    pub victim_source: Option<TxSource>,
+}
+
+impl OpportunityRecord {
+    /// Record for a victim that could not be sandwiched on `pool`
+    pub fn not_sandwichable(
+        victim_hash: H256,
+        target_block: U64,
+        pool: Address,
+        reason: String,
+    ) -> Self {
+        Self {
+            victim_hash,
+            target_block,
+            pool,
+            optimal_input: None,
+            revenue: None,
+            frontrun_gas_used: None,
+            backrun_gas_used: None,
+            bribe: None,
+            bundle_hash: None,
+            status: OpportunityStatus::NotSandwichable,
+            reason: Some(reason),
+            victim_source: None,
+        }
+    }
+}
+
+/// Storage layer for a queryable history of opportunities and bundles
+pub trait OpportunityStore: Debug + Send + Sync {
+    /// Record an evaluated opportunity
+    fn record_opportunity(&self, opportunity: &OpportunityRecord) -> Result<()>;
+
+    /// Record how a builder handled a bundle
+    fn record_submission(&self, bundle_hash: H256, builder: &str, outcome: &str) -> Result<()>;
+
+    /// Update the status of a submitted bundle, `realised_profit` is set for landed bundles
+    fn update_status(
+        &self,
+        bundle_hash: H256,
+        status: OpportunityStatus,
+        realised_profit: Option<I256>,
+    ) -> Result<()>;
+}
+
+/// Bundle hash as computed by relays, keccak256 of the bundle's concatenated tx hashes
+pub fn bundle_hash(tx_hashes: &[H256]) -> H256 {
+    let concatenated: Vec<u8> = tx_hashes
+        .iter()
+        .flat_map(|hash| hash.as_bytes().to_vec())
+        .collect();
+    H256::from(keccak256(concatenated))
+}
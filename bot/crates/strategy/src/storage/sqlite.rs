@@ -0,0 +1,197 @@
+use std::{
+    path::Path,
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{anyhow, Result};
+use ethers::types::{H256, I256};
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{OpportunityRecord, OpportunityStatus, OpportunityStore};
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS opportunities (
+    id                INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at        INTEGER NOT NULL,
+    victim_hash       TEXT NOT NULL,
+    target_block      INTEGER NOT NULL,
+    pool              TEXT NOT NULL,
+    optimal_input     TEXT,
+    revenue           TEXT,
+    frontrun_gas_used INTEGER,
+    backrun_gas_used  INTEGER,
+    bribe             TEXT,
+    bundle_hash       TEXT,
+    status            TEXT NOT NULL,
+    reason            TEXT,
+    realised_profit   TEXT,
+    victim_source     TEXT,
+    victim_first_seen INTEGER
+);
+CREATE INDEX IF NOT EXISTS opportunities_bundle_hash ON opportunities (bundle_hash);
+CREATE INDEX IF NOT EXISTS opportunities_victim_hash ON opportunities (victim_hash);
+
+CREATE TABLE IF NOT EXISTS submissions (
+    id          INTEGER PRIMARY KEY AUTOINCREMENT,
+    created_at  INTEGER NOT NULL,
+    bundle_hash TEXT NOT NULL,
+    builder     TEXT NOT NULL,
+    outcome     TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS submissions_bundle_hash ON submissions (bundle_hash);
+";
+
+/// Columns added to `opportunities` after it was first released, added to older databases on open
+const ADDED_COLUMNS: [(&str, &str); 2] =
+    [("victim_source", "TEXT"), ("victim_first_seen", "INTEGER")];
+
+/// Opportunity store backed by an embedded SQLite database
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open (or create) the database at `path`
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Create a database that only lives in memory
+    pub fn in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+        for (column, kind) in ADDED_COLUMNS {
+            let exists: bool = conn.query_row(
+                "SELECT COUNT(*) > 0 FROM pragma_table_info('opportunities') WHERE name = ?1",
+                params![column],
+                |row| row.get(0),
+            )?;
+            if !exists {
+                conn.execute_batch(&format!(
+                    "ALTER TABLE opportunities ADD COLUMN {} {}",
+                    column, kind
+                ))?;
+            }
+        }
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Status and realised profit of the opportunity that produced `bundle_hash`
+    pub fn get_status(&self, bundle_hash: H256) -> Result<Option<(String, Option<String>)>> {
+        let conn = self.conn()?;
+        let row = conn
+            .query_row(
+                "SELECT status, realised_profit FROM opportunities WHERE bundle_hash = ?1",
+                params![format!("{:?}", bundle_hash)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(row)
+    }
+
+    /// Mempool source that first saw the victim of the opportunities recorded for `victim_hash`,
+    /// and when (in milliseconds since unix epoch)
+    pub fn get_victim_source(&self, victim_hash: H256) -> Result<Option<(String, i64)>> {
+        let conn = self.conn()?;
+        let row = conn
+            .query_row(
+                "SELECT victim_source, victim_first_seen FROM opportunities
+                WHERE victim_hash = ?1 AND victim_source IS NOT NULL",
+                params![format!("{:?}", victim_hash)],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        Ok(row)
+    }
+
+    /// Number of builders that `bundle_hash` was sent to
+    pub fn count_submissions(&self, bundle_hash: H256) -> Result<u64> {
+        let conn = self.conn()?;
+        let count: i64 = conn.query_row(
+            "SELECT COUNT(*) FROM submissions WHERE bundle_hash = ?1",
+            params![format!("{:?}", bundle_hash)],
+            |row| row.get(0),
+        )?;
+        Ok(count as u64)
+    }
+
+    fn conn(&self) -> Result<std::sync::MutexGuard<'_, Connection>> {
+        self.conn
+            .lock()
+            .map_err(|_| anyhow!("sqlite connection lock poisoned"))
+    }
+}
+
+impl OpportunityStore for SqliteStore {
+    fn record_opportunity(&self, opportunity: &OpportunityRecord) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO opportunities (
+                created_at, victim_hash, target_block, pool, optimal_input, revenue,
+                frontrun_gas_used, backrun_gas_used, bribe, bundle_hash, status, reason,
+                victim_source, victim_first_seen
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
+            params![
+                now(),
+                format!("{:?}", opportunity.victim_hash),
+                opportunity.target_block.as_u64() as i64,
+                format!("{:?}", opportunity.pool),
+                opportunity.optimal_input.map(|v| v.to_string()),
+                opportunity.revenue.map(|v| v.to_string()),
+                opportunity.frontrun_gas_used.map(|v| v as i64),
+                opportunity.backrun_gas_used.map(|v| v as i64),
+                opportunity.bribe.map(|v| v.to_string()),
+                opportunity.bundle_hash.map(|v| format!("{:?}", v)),
+                opportunity.status.as_str(),
+                opportunity.reason,
+                opportunity.victim_source.as_ref().map(|s| s.name.clone()),
+                opportunity
+                    .victim_source
+                    .as_ref()
+                    .and_then(|s| s.first_seen.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_millis() as i64),
+            ],
+        )?;
+        Ok(())
+    }
+
+    fn record_submission(&self, bundle_hash: H256, builder: &str, outcome: &str) -> Result<()> {
+        self.conn()?.execute(
+            "INSERT INTO submissions (created_at, bundle_hash, builder, outcome)
+            VALUES (?1, ?2, ?3, ?4)",
+            params![now(), format!("{:?}", bundle_hash), builder, outcome],
+        )?;
+        Ok(())
+    }
+
+    fn update_status(
+        &self,
+        bundle_hash: H256,
+        status: OpportunityStatus,
+        realised_profit: Option<I256>,
+    ) -> Result<()> {
+        self.conn()?.execute(
+            "UPDATE opportunities SET status = ?1, realised_profit = ?2 WHERE bundle_hash = ?3",
+            params![
+                status.as_str(),
+                realised_profit.map(|v| v.to_string()),
+                format!("{:?}", bundle_hash)
+            ],
+        )?;
+        Ok(())
+    }
+}
+
+/// Seconds since unix epoch
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,115 @@
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use colored::Colorize;
+use ethers::types::{H256, I256};
+use log::error;
+use tokio::sync::{mpsc, oneshot};
+
+use super::{OpportunityRecord, OpportunityStatus, OpportunityStore};
+use crate::log_error;
+
+/// A write waiting to be applied to the wrapped store
+#[derive(Debug)]
+enum Write {
+    Opportunity(OpportunityRecord),
+    Submission {
+        bundle_hash: H256,
+        builder: String,
+        outcome: String,
+    },
+    Status {
+        bundle_hash: H256,
+        status: OpportunityStatus,
+        realised_profit: Option<I256>,
+    },
+    /// Answered once every earlier write is applied
+    Flush(oneshot::Sender<()>),
+}
+
+/// Applies writes to the wrapped store from a blocking task, so that the strategy loop never waits
+/// on disk I/O
+///
+/// Writes are applied in the order they are sent, failed writes are logged and skipped
+#[derive(Debug, Clone)]
+pub struct StoreWriter {
+    sender: mpsc::UnboundedSender<Write>,
+}
+
+impl StoreWriter {
+    /// Spawn the writer task (must be called from within a tokio runtime), it stops once every
+    /// handle is dropped
+    pub fn spawn(store: Arc<dyn OpportunityStore>) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+
+        tokio::task::spawn_blocking(move || {
+            while let Some(write) = receiver.blocking_recv() {
+                let result = match write {
+                    Write::Opportunity(opportunity) => store.record_opportunity(&opportunity),
+                    Write::Submission {
+                        bundle_hash,
+                        builder,
+                        outcome,
+                    } => store.record_submission(bundle_hash, &builder, &outcome),
+                    Write::Status {
+                        bundle_hash,
+                        status,
+                        realised_profit,
+                    } => store.update_status(bundle_hash, status, realised_profit),
+                    Write::Flush(done) => {
+                        let _ = done.send(());
+                        Ok(())
+                    }
+                };
+                if let Err(e) = result {
+                    log_error!("Failed to write to opportunity store: {}", e);
+                }
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// Wait until every write sent so far is applied
+    pub async fn flush(&self) -> Result<()> {
+        let (done, flushed) = oneshot::channel();
+        self.send(Write::Flush(done))?;
+        flushed
+            .await
+            .map_err(|_| anyhow!("Store writer has stopped"))
+    }
+
+    fn send(&self, write: Write) -> Result<()> {
+        self.sender
+            .send(write)
+            .map_err(|_| anyhow!("Store writer has stopped"))
+    }
+}
+
+impl OpportunityStore for StoreWriter {
+    /// Queue the record, errors only if the writer task has stopped
+    fn record_opportunity(&self, opportunity: &OpportunityRecord) -> Result<()> {
+        self.send(Write::Opportunity(opportunity.clone()))
+    }
+
+    fn record_submission(&self, bundle_hash: H256, builder: &str, outcome: &str) -> Result<()> {
+        self.send(Write::Submission {
+            bundle_hash,
+            builder: builder.to_string(),
+            outcome: outcome.to_string(),
+        })
+    }
+
+    fn update_status(
+        &self,
+        bundle_hash: H256,
+        status: OpportunityStatus,
+        realised_profit: Option<I256>,
+    ) -> Result<()> {
+        self.send(Write::Status {
+            bundle_hash,
+            status,
+            realised_profit,
+        })
+    }
+}
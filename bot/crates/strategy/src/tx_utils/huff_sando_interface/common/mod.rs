@@ -7,7 +7,7 @@ pub mod five_byte_encoder;
 pub mod weth_encoder;
 
 // Declare the array as static
-static FUNCTION_NAMES: [&str; 8] = [
+static FUNCTION_NAMES: [&str; 11] = [
     "v2_backrun0",
     "v2_frontrun0",
     "v2_backrun1",
@@ -16,6 +16,9 @@ static FUNCTION_NAMES: [&str; 8] = [
     "v3_frontrun0",
     "v3_backrun1",
     "v3_frontrun1",
+    "seppuku",
+    "recoverEth",
+    "recoverWeth",
 ];
 
 pub fn get_jump_dest_from_sig(function_name: &str) -> u8 {
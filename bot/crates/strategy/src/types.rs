@@ -1,35 +1,82 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::SystemTime};
 
 use anyhow::ensure;
 use anyhow::{anyhow, Result};
 use artemis_core::{
-    collectors::block_collector::NewBlock, executors::flashbots_executor::FlashbotsBundle,
+    collectors::{
+        block_collector::NewBlock, mev_share_collector::MevShareEvent,
+        reconnecting_collector::StreamGap,
+    },
+    executors::{
+        bundle_simulator::BundleExpectation,
+        flashbots_executor::{
+            BundleSubmission, FlashbotsBundle, FlashbotsCancellation, SubmissionReport,
+        },
+        mev_share_executor::MevShareBundle,
+        webhook_executor::Notification,
+    },
 };
-use cfmms::pool::Pool;
+use cfmms::{dex::Dex, pool::Pool};
 use ethers::providers::Middleware;
 use ethers::signers::LocalWallet;
 use ethers::signers::Signer;
 use ethers::types::{
-    Address, Block, Bytes, Eip1559TransactionRequest, Transaction, H256, U256, U64,
+    Address, Block, Bytes, Eip1559TransactionRequest, Transaction, H256, I256, U256, U64,
 };
 use ethers_flashbots::BundleRequest;
 use foundry_evm::executor::TxEnv;
+use serde::{Deserialize, Serialize};
 
 use crate::constants::DUST_OVERPAY;
 use crate::helpers::access_list_to_ethers;
 use crate::helpers::sign_eip1559;
+use crate::simulator::backend::SimulationBackend;
+use crate::storage::OpportunityStore;
+
+pub use crate::managers::inclusion_tracker::{BundleOutcome, PnlTotals};
+pub use crate::simulator::{salmonella_inspector::SalmonellaPolicy, EnvPerturbation};
 
 /// Core Event enum for current strategy
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
     NewBlock(NewBlock),
-    NewTransaction(Transaction),
+    /// Captured as its signed rlp to keep capture files compact
+    NewTransaction(#[serde(with = "crate::helpers::signed_rlp")] Transaction),
+    /// Pending tx from the merged mempool sources, tagged with the source that saw it first
+    TaggedTransaction(
+        #[serde(with = "crate::helpers::signed_rlp")] Transaction,
+        TxSource,
+    ),
+    /// Block subscription dropped, blocks mined in between were missed
+    BlockGap(StreamGap),
+    /// Mempool subscription dropped, txs sent in between were missed
+    MempoolGap(StreamGap),
+    /// One of the merged mempool sources dropped, txs that only it saw in between were missed
+    MempoolSourceGap(String, StreamGap),
+    /// Hint about a pending tx from the MEV-Share event stream
+    MevShareEvent(MevShareEvent),
+    /// MEV-Share event stream dropped, hints sent in between were missed
+    MevShareGap(StreamGap),
+    /// What the executor did with one of our bundles
+    SubmissionReport(SubmissionReport),
+}
+
+/// Mempool source that saw a pending tx first
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TxSource {
+    pub name: String,
+    /// When the tx (or its hash) was first seen
+    pub first_seen: SystemTime,
 }
 
 /// Core Action enum for current strategy
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub enum Action {
     SubmitToFlashbots(FlashbotsBundle),
+    CancelBundle(FlashbotsCancellation),
+    SubmitToMevShare(MevShareBundle),
+    /// Landed sandwiches, failures, inventory changes, and health alerts
+    Notify(Notification),
 }
 
 /// Configuration for variables needed for sandwiches
@@ -38,6 +85,75 @@ pub struct StratConfig {
     pub sando_address: Address,
     pub sando_inception_block: U64,
     pub searcher_signer: LocalWallet,
+    /// Which opcodes/contracts are flagged when checking for salmonella
+    pub salmonella_policy: SalmonellaPolicy,
+    /// Reject sandwiches whose outcome changes when replayed under perturbed block/tx envs
+    pub differential_check: bool,
+    /// Where evaluated opportunities and bundles are recorded (nothing is recorded if `None`)
+    pub store: Option<Arc<dyn OpportunityStore>>,
+    /// Which dexes' pools are synced and sandwiched (mainnet dexes are used if `None`)
+    pub dexes: Option<Vec<Dex>>,
+    /// Ignore public mempool txs and only backrun the swaps revealed by MEV-Share hints
+    pub backrun_only: bool,
+    /// Where frontrun inputs are simulated when searching for the optimal input (a local revm
+    /// fork if `None`)
+    pub simulation_backend: Option<Arc<dyn SimulationBackend>>,
+    /// How much of each opportunity's revenue is paid to the builder
+    pub bribe_policy: BribePolicy,
+    /// Bounds on the opportunities that are taken
+    pub risk_limits: RiskLimits,
+    /// Find the pools a victim touches by simulating it locally instead of with `trace_call`, for
+    /// nodes without the trace api (e.g. anvil)
+    pub local_state_diffs: bool,
+    /// Cache synced pools in a checkpoint file per dex set, so that restarts only sync the blocks
+    /// since the last run (off for chains that don't outlive the run, e.g. anvil)
+    pub pool_checkpoints: bool,
+}
+
+/// How much of an opportunity's revenue is paid to the builder (as the backrun's priority fee)
+#[derive(Debug, Clone, Copy)]
+pub struct BribePolicy {
+    /// Share of the revenue left after the frontrun's base fee that is bribed, in billionths
+    pub revenue_share_ppb: u64,
+    /// Bribed on top of the whole revenue while the sando contract holds no dust, so that the
+    /// swaps leave some behind
+    pub dust_overpay: U256,
+}
+
+impl BribePolicy {
+    /// Bribe paid out of `revenue`
+    pub fn bribe(&self, revenue: U256) -> U256 {
+        revenue * self.revenue_share_ppb / 1_000_000_000u64
+    }
+}
+
+impl Default for BribePolicy {
+    fn default() -> Self {
+        Self {
+            // bribe away 99.9999999% of revenue lmeow
+            revenue_share_ppb: 999_999_999,
+            dust_overpay: *DUST_OVERPAY,
+        }
+    }
+}
+
+/// Bounds on the opportunities that are taken
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RiskLimits {
+    /// Most weth put into a single opportunity (the whole inventory if `None`)
+    pub max_input: Option<U256>,
+    /// Opportunities with less revenue are skipped
+    pub min_revenue: U256,
+}
+
+impl RiskLimits {
+    /// Largest input to search for out of `weth_inventory`
+    pub fn cap_input(&self, weth_inventory: U256) -> U256 {
+        match self.max_input {
+            Some(max_input) => weth_inventory.min(max_input),
+            None => weth_inventory,
+        }
+    }
 }
 
 /// Information on potential sandwichable opportunity
@@ -99,7 +215,72 @@ impl RawIngredients {
     }
 }
 
-#[derive(Default, Clone, Copy)]
+/// Swap revealed by a MEV-Share hint's logs
+#[derive(Debug, Clone, Copy)]
+pub struct HintedSwap {
+    /// Pool the swap is made on
+    pub pool: Pool,
+    /// Whether weth is paid into the pool (the swap buys the pool's other token)
+    pub weth_in: bool,
+    /// Amount paid into the pool
+    pub amount_in: U256,
+}
+
+/// Information on potential backrun opportunity, buying the intermediary token on one pool and
+/// selling it on another once the hinted swap has moved the price
+#[derive(Clone)]
+pub struct BackrunIngredients {
+    /// Hash of the hinted tx
+    victim_hash: H256,
+    /// Replayed before the backrun, since the hinted tx itself is unknown
+    victim_swap: HintedSwap,
+    /// Which token do we hold for duration of the backrun
+    intermediary_token: Address,
+    /// Pool the intermediary token is bought on
+    buy_pool: Pool,
+    /// Pool the intermediary token is sold on
+    sell_pool: Pool,
+}
+
+impl BackrunIngredients {
+    pub fn new(
+        victim_hash: H256,
+        victim_swap: HintedSwap,
+        intermediary_token: Address,
+        buy_pool: Pool,
+        sell_pool: Pool,
+    ) -> Self {
+        Self {
+            victim_hash,
+            victim_swap,
+            intermediary_token,
+            buy_pool,
+            sell_pool,
+        }
+    }
+
+    pub fn get_victim_hash(&self) -> H256 {
+        self.victim_hash
+    }
+
+    pub fn get_victim_swap(&self) -> HintedSwap {
+        self.victim_swap
+    }
+
+    pub fn get_intermediary_token(&self) -> Address {
+        self.intermediary_token
+    }
+
+    pub fn get_buy_pool(&self) -> Pool {
+        self.buy_pool
+    }
+
+    pub fn get_sell_pool(&self) -> Pool {
+        self.sell_pool
+    }
+}
+
+#[derive(Default, Clone, Copy, Debug, Serialize, Deserialize)]
 pub struct BlockInfo {
     pub number: U64,
     pub base_fee_per_gas: U256,
@@ -186,6 +367,7 @@ fn calculate_next_block_base_fee(block: &BlockInfo) -> U256 {
 
 /// All details for capturing a sando opp
 pub struct SandoRecipe {
+    optimal_input: U256,
     frontrun: TxEnv,
     frontrun_gas_used: u64,
     meats: Vec<Transaction>,
@@ -196,7 +378,9 @@ pub struct SandoRecipe {
 }
 
 impl SandoRecipe {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
+        optimal_input: U256,
         frontrun: TxEnv,
         frontrun_gas_used: u64,
         meats: Vec<Transaction>,
@@ -206,6 +390,7 @@ impl SandoRecipe {
         target_block: BlockInfo,
     ) -> Self {
         Self {
+            optimal_input,
             frontrun,
             frontrun_gas_used,
             meats,
@@ -220,16 +405,92 @@ impl SandoRecipe {
         self.revenue
     }
 
+    pub fn get_optimal_input(&self) -> U256 {
+        self.optimal_input
+    }
+
+    pub fn get_frontrun_gas_used(&self) -> u64 {
+        self.frontrun_gas_used
+    }
+
+    pub fn get_backrun_gas_used(&self) -> u64 {
+        self.backrun_gas_used
+    }
+
     /// turn recipe into a signed bundle that can be sumbitted to flashbots
     pub async fn to_fb_bundle<M: Middleware>(
         self,
         sando_address: Address,
         searcher: &LocalWallet,
         has_dust: bool,
+        bribe_policy: &BribePolicy,
+        provider: Arc<M>,
+    ) -> Result<BundleSubmission> {
+        let signed = self
+            .sign(sando_address, searcher, has_dust, bribe_policy, provider)
+            .await?;
+
+        let signed_meat_txs: Vec<Bytes> = self.meats.into_iter().map(|meat| meat.rlp()).collect();
+
+        // construct bundle
+        let mut bundled_transactions: Vec<Bytes> = vec![signed.frontrun];
+        bundled_transactions.append(&mut signed_meat_txs.clone());
+        bundled_transactions.push(signed.backrun);
+
+        let mut bundle_request = BundleRequest::new();
+        for tx in bundled_transactions {
+            bundle_request = bundle_request.push_transaction(tx);
+        }
+
+        bundle_request = bundle_request
+            .set_block(self.target_block.number)
+            .set_simulation_block(self.target_block.number - 1)
+            .set_simulation_timestamp(self.target_block.timestamp.as_u64());
+
+        Ok(BundleSubmission {
+            request: bundle_request,
+            expectation: Some(signed.expectation),
+            replacement_uuid: None,
+        })
+    }
+
+    /// turn backrun recipe (without meats) into a signed bundle that lands behind the hinted tx
+    pub async fn to_mev_share_bundle<M: Middleware>(
+        self,
+        victim_hash: H256,
+        sando_address: Address,
+        searcher: &LocalWallet,
+        has_dust: bool,
+        bribe_policy: &BribePolicy,
+        provider: Arc<M>,
+    ) -> Result<MevShareBundle> {
+        let signed = self
+            .sign(sando_address, searcher, has_dust, bribe_policy, provider)
+            .await?;
+
+        Ok(MevShareBundle {
+            victim_hash,
+            txs: vec![signed.frontrun, signed.backrun],
+            block: self.target_block.number,
+            max_block: self.target_block.number,
+        })
+    }
+
+    /// sign frontrun and backrun, bribes are paid in the backrun
+    async fn sign<M: Middleware>(
+        &self,
+        sando_address: Address,
+        searcher: &LocalWallet,
+        has_dust: bool,
+        bribe_policy: &BribePolicy,
         provider: Arc<M>,
-    ) -> Result<BundleRequest> {
+    ) -> Result<SignedLegs> {
+        // nonce once the block before the target block is mined (nodes reject future blocks)
         let nonce = provider
-            .get_transaction_count(searcher.address(), Some(self.target_block.number.into()))
+            .get_transaction_count(
+                searcher.address(),
+                Some((self.target_block.number - 1).into()),
+            )
             .await
             .map_err(|e| anyhow!("FAILED TO CREATE BUNDLE: Failed to get nonce {:?}", e))?;
 
@@ -237,16 +498,14 @@ impl SandoRecipe {
             to: Some(sando_address.into()),
             gas: Some((U256::from(self.frontrun_gas_used) * 10) / 7),
             value: Some(self.frontrun.value.into()),
-            data: Some(self.frontrun.data.into()),
+            data: Some(self.frontrun.data.clone().into()),
             nonce: Some(nonce),
-            access_list: access_list_to_ethers(self.frontrun.access_list),
+            access_list: access_list_to_ethers(self.frontrun.access_list.clone()),
             max_fee_per_gas: Some(self.target_block.base_fee_per_gas.into()),
             ..Default::default()
         };
         let signed_frontrun = sign_eip1559(frontrun_tx, &searcher).await?;
 
-        let signed_meat_txs: Vec<Bytes> = self.meats.into_iter().map(|meat| meat.rlp()).collect();
-
         // calc bribe (bribes paid in backrun)
         let revenue_minus_frontrun_tx_fee = self
             .revenue
@@ -257,10 +516,9 @@ impl SandoRecipe {
 
         // eat a loss (overpay) to get dust onto the sando contract (more: https://twitter.com/libevm/status/1474870661373779969)
         let bribe_amount = if !has_dust {
-            revenue_minus_frontrun_tx_fee + *DUST_OVERPAY
+            revenue_minus_frontrun_tx_fee + bribe_policy.dust_overpay
         } else {
-            // bribe away 99.9999999% of revenue lmeow
-            revenue_minus_frontrun_tx_fee * 999999999 / 1000000000
+            bribe_policy.bribe(revenue_minus_frontrun_tx_fee)
         };
 
         let max_fee = bribe_amount / self.backrun_gas_used;
@@ -270,41 +528,45 @@ impl SandoRecipe {
             "[FAILED TO CREATE BUNDLE] backrun maxfee less than basefee"
         );
 
-        let effective_miner_tip = max_fee.checked_sub(self.target_block.base_fee_per_gas);
-
-        ensure!(
-            effective_miner_tip.is_none(),
-            "[FAILED TO CREATE BUNDLE] negative miner tip"
-        );
+        let effective_miner_tip = max_fee
+            .checked_sub(self.target_block.base_fee_per_gas)
+            .ok_or_else(|| anyhow!("[FAILED TO CREATE BUNDLE] negative miner tip"))?;
 
         let backrun_tx = Eip1559TransactionRequest {
             to: Some(sando_address.into()),
             gas: Some((U256::from(self.backrun_gas_used) * 10) / 7),
             value: Some(self.backrun.value.into()),
-            data: Some(self.backrun.data.into()),
+            data: Some(self.backrun.data.clone().into()),
             nonce: Some(nonce+1),
-            access_list: access_list_to_ethers(self.backrun.access_list),
+            access_list: access_list_to_ethers(self.backrun.access_list.clone()),
             max_priority_fee_per_gas: Some(max_fee),
             max_fee_per_gas: Some(max_fee),
             ..Default::default()
         };
         let signed_backrun = sign_eip1559(backrun_tx, &searcher).await?;
 
-        // construct bundle
-        let mut bundled_transactions: Vec<Bytes> = vec![signed_frontrun];
-        bundled_transactions.append(&mut signed_meat_txs.clone());
-        bundled_transactions.push(signed_backrun);
-
-        let mut bundle_request = BundleRequest::new();
-        for tx in bundled_transactions {
-            bundle_request = bundle_request.push_transaction(tx);
-        }
-
-        bundle_request = bundle_request
-            .set_block(self.target_block.number)
-            .set_simulation_block(self.target_block.number - 1)
-            .set_simulation_timestamp(self.target_block.timestamp.as_u64());
+        // what we expect to see if the bundle is simulated before being sent
+        let frontrun_fee = U256::from(self.frontrun_gas_used) * self.target_block.base_fee_per_gas;
+        let backrun_fee = U256::from(self.backrun_gas_used) * max_fee;
+        let expectation = BundleExpectation {
+            searcher: searcher.address(),
+            coinbase_payment: U256::from(self.backrun_gas_used) * effective_miner_tip,
+            profit: I256::from_raw(self.revenue)
+                - I256::from_raw(frontrun_fee)
+                - I256::from_raw(backrun_fee),
+        };
 
-        Ok(bundle_request)
+        Ok(SignedLegs {
+            frontrun: signed_frontrun,
+            backrun: signed_backrun,
+            expectation,
+        })
     }
 }
+
+/// Signed frontrun and backrun of a recipe
+struct SignedLegs {
+    frontrun: Bytes,
+    backrun: Bytes,
+    expectation: BundleExpectation,
+}
@@ -0,0 +1,599 @@
+//! Local anvil chain with the huff sando contract and mock UniswapV2 contracts deployed, used to run
+//! `SandoBot` end to end without a mainnet node
+//!
+//! Requires `anvil`, `forge`, and `huffc` to be installed
+
+use std::{path::PathBuf, process::Command, str::FromStr, sync::Arc, time::Duration};
+
+use anyhow::{anyhow, Result};
+use artemis_core::{
+    collectors::{block_collector::BlockCollector, mev_share_collector::MevShareCollector},
+    engine::Engine,
+    executors::{flashbots_executor::BundleSubmission, mev_share_executor::MevShareExecutor},
+    types::{Collector, CollectorMap, CollectorStream, Executor, ExecutorMap},
+};
+use async_trait::async_trait;
+use cfmms::dex::{Dex, DexVariant};
+use ethers::{
+    abi::{self, parse_abi},
+    prelude::{BaseContract, Lazy},
+    providers::{Middleware, Provider, Url, Ws},
+    signers::{LocalWallet, Signer},
+    types::{
+        Address, Bytes, Eip1559TransactionRequest, Transaction, TransactionReceipt,
+        TransactionRequest, H256, U256, U64,
+    },
+    utils::{
+        parse_ether,
+        rlp::{Decodable, Rlp},
+        Anvil, AnvilInstance,
+    },
+};
+use ethers_flashbots::BundleTransaction;
+use futures::StreamExt;
+use strategy::{
+    bot::SandoBot,
+    types::{Action, BribePolicy, Event, RiskLimits, SalmonellaPolicy, StratConfig},
+};
+use tokio::{
+    sync::mpsc::{self, UnboundedReceiver, UnboundedSender},
+    task::JoinSet,
+};
+
+use super::WETH_ADDRESS;
+
+// -- consts --
+pub static SANDO_ADDRESS: Lazy<Address> = Lazy::new(|| {
+    "0xaAaAaAaaAaAaAaaAaAAAAAAAAaaaAaAaAaaAaaAa"
+        .parse()
+        .unwrap()
+});
+
+// huff sando only accepts calls from this key's address
+static SEARCHER_PRIVATE_KEY: &str =
+    "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+// simulations spoof calls from this address so it needs eth on the local chain too
+static SUGAR_DADDY: Lazy<Address> = Lazy::new(|| {
+    "0xd8dA6BF26964aF9D7eEd9e03E53415D37aA96045"
+        .parse()
+        .unwrap()
+});
+
+static MOCK_ABI: Lazy<BaseContract> = Lazy::new(|| {
+    BaseContract::from(
+        parse_abi(&[
+            "function deposit() external payable",
+            "function transfer(address,uint256) external returns (bool)",
+            "function approve(address,uint256) external returns (bool)",
+            "function balanceOf(address) external view returns (uint256)",
+            "function createPair(address,address) external returns (address)",
+            "function getPair(address,address) external view returns (address)",
+            "function sync() external",
+            "function swapExactTokensForTokens(address,address,uint256,uint256,address) external returns (uint256)",
+        ])
+        .unwrap(),
+    )
+});
+
+/// Anvil chain where the harness' mocks have been deployed and funded
+pub struct Harness {
+    pub provider: Arc<Provider<Ws>>,
+    pub searcher: LocalWallet,
+    pub factory: Address,
+    pub pair: Address,
+    /// Second factory with its own WETH/token pair, hinted swaps are backrun against it
+    pub second_factory: Address,
+    pub second_pair: Address,
+    pub token: Address,
+    pub router: Address,
+    /// Funds the pool and deploys the mocks
+    deployer: Address,
+    /// Sends the swap that gets sandwiched
+    victim: Address,
+    anvil: AnvilInstance,
+}
+
+impl Harness {
+    /// Spawn anvil (without automining) and deploy WETH, the sando contract, and a funded
+    /// WETH/token pair on two factories
+    pub async fn spawn() -> Self {
+        // bundles are signed for mainnet and huff sando uses `push0`
+        let anvil = Anvil::new()
+            .chain_id(1u64)
+            .args(["--no-mining", "--hardfork", "shanghai"])
+            .spawn();
+        let provider = Arc::new(
+            Provider::<Ws>::connect(anvil.ws_endpoint())
+                .await
+                .unwrap()
+                .interval(Duration::from_millis(50u64)),
+        );
+
+        let mut harness = Harness {
+            provider,
+            searcher: SEARCHER_PRIVATE_KEY.parse().unwrap(),
+            factory: Address::zero(),
+            pair: Address::zero(),
+            second_factory: Address::zero(),
+            second_pair: Address::zero(),
+            token: Address::zero(),
+            router: Address::zero(),
+            deployer: anvil.addresses()[0],
+            victim: anvil.addresses()[1],
+            anvil,
+        };
+
+        // sando and WETH addresses are hardcoded in the huff contract, so etch them in place
+        let weth_code = forge_bytecode("MockWETH", "deployedBytecode");
+        harness
+            .anvil_request("anvil_setCode", (*WETH_ADDRESS, weth_code))
+            .await;
+        // constructor isn't ran when etching, set `decimals` (slot 2) by hand
+        harness
+            .anvil_request(
+                "anvil_setStorageAt",
+                (
+                    *WETH_ADDRESS,
+                    H256::from_low_u64_be(2),
+                    H256::from_low_u64_be(18),
+                ),
+            )
+            .await;
+        harness
+            .anvil_request("anvil_setCode", (*SANDO_ADDRESS, huff_sando_bytecode()))
+            .await;
+
+        let searcher = harness.searcher.address();
+        for account in [searcher, *SUGAR_DADDY] {
+            harness
+                .anvil_request("anvil_setBalance", (account, parse_ether(1000).unwrap()))
+                .await;
+        }
+
+        // deploy mocks
+        let mut token_code = forge_bytecode("MockERC20", "bytecode").to_vec();
+        token_code.extend(abi::encode(&[
+            abi::Token::String("Harness Token".to_string()),
+            abi::Token::String("HT".to_string()),
+            abi::Token::Uint(parse_ether(1_000_000).unwrap()),
+        ]));
+        harness.token = harness.deploy(token_code.into()).await;
+        harness.factory = harness
+            .deploy(forge_bytecode("MockUniswapV2Factory", "bytecode"))
+            .await;
+        harness.second_factory = harness
+            .deploy(forge_bytecode("MockUniswapV2Factory", "bytecode"))
+            .await;
+        harness.router = harness
+            .deploy(forge_bytecode("MockUniswapV2Router", "bytecode"))
+            .await;
+
+        let (deployer, weth) = (harness.deployer, *WETH_ADDRESS);
+        harness.call(deployer, weth, "deposit", (), 300).await;
+        harness.pair = harness.create_funded_pair(harness.factory).await;
+        harness.second_pair = harness.create_funded_pair(harness.second_factory).await;
+
+        // sando's weth inventory
+        harness
+            .call(
+                deployer,
+                weth,
+                "transfer",
+                (*SANDO_ADDRESS, parse_ether(50).unwrap()),
+                0,
+            )
+            .await;
+
+        // victim holds weth that the router can spend
+        let (victim, router) = (harness.victim, harness.router);
+        harness.call(victim, weth, "deposit", (), 20).await;
+        harness
+            .call(victim, weth, "approve", (router, U256::MAX), 0)
+            .await;
+
+        harness
+    }
+
+    /// Strategy config that points the bot at the harness' sando contract and mock factories
+    pub fn strat_config(&self) -> StratConfig {
+        StratConfig {
+            sando_address: *SANDO_ADDRESS,
+            sando_inception_block: U64::zero(),
+            searcher_signer: self.searcher.clone(),
+            salmonella_policy: SalmonellaPolicy::default(),
+            differential_check: true,
+            backrun_only: false,
+            store: None,
+            dexes: Some(
+                [self.factory, self.second_factory]
+                    .into_iter()
+                    .map(|factory| Dex::new(factory, DexVariant::UniswapV2, 0, Some(300)))
+                    .collect(),
+            ),
+            simulation_backend: None,
+            bribe_policy: BribePolicy::default(),
+            risk_limits: RiskLimits::default(),
+            // anvil doesn't support `trace_call`
+            local_state_diffs: true,
+            // a checkpoint wouldn't match the next run's fresh anvil chain
+            pool_checkpoints: false,
+        }
+    }
+
+    /// Run the engine in backrun only mode, hints come from `mev_share_url` and bundles are
+    /// sent to `relay_url`
+    pub async fn run_backrun_engine(&self, mev_share_url: Url, relay_url: Url) -> JoinSet<()> {
+        let mut engine: Engine<Event, Action> = Engine::default();
+
+        let block_collector = Box::new(BlockCollector::new(self.provider.clone()));
+        let block_collector = CollectorMap::new(block_collector, Event::NewBlock);
+        engine.add_collector(Box::new(block_collector));
+
+        let mev_share_collector = Box::new(MevShareCollector::new(mev_share_url));
+        let mev_share_collector = CollectorMap::new(mev_share_collector, Event::MevShareEvent);
+        engine.add_collector(Box::new(mev_share_collector));
+
+        let strategy = SandoBot::new(
+            self.provider.clone(),
+            StratConfig {
+                backrun_only: true,
+                ..self.strat_config()
+            },
+        );
+        engine.add_strategy(Box::new(strategy));
+
+        let executor =
+            MevShareExecutor::new(self.provider.clone(), self.searcher.clone(), relay_url);
+        let executor = ExecutorMap::new(Box::new(executor), |action| match action {
+            Action::SubmitToMevShare(bundle) => Some(bundle),
+            _ => None,
+        });
+        engine.add_executor(Box::new(executor));
+
+        engine.run().await.unwrap()
+    }
+
+    /// Run the full engine (block + mempool collectors, `SandoBot`, mock relay executor), the
+    /// receiver gets every bundle the bot submits
+    pub async fn run_engine(&self) -> (JoinSet<()>, UnboundedReceiver<BundleSubmission>) {
+        let mut engine: Engine<Event, Action> = Engine::default();
+
+        let block_collector = Box::new(BlockCollector::new(self.provider.clone()));
+        let block_collector = CollectorMap::new(block_collector, Event::NewBlock);
+        engine.add_collector(Box::new(block_collector));
+
+        let mempool_collector = Box::new(PendingTxCollector::new(self.provider.clone()));
+        let mempool_collector = CollectorMap::new(mempool_collector, Event::NewTransaction);
+        engine.add_collector(Box::new(mempool_collector));
+
+        let strategy = SandoBot::new(self.provider.clone(), self.strat_config());
+        engine.add_strategy(Box::new(strategy));
+
+        let (executor, bundles) = MockRelayExecutor::new();
+        engine.add_executor(Box::new(executor));
+
+        let engine = engine.run().await.unwrap();
+        // give the collectors time to subscribe before anything is sent to the mempool
+        tokio::time::sleep(Duration::from_secs(1)).await;
+
+        (engine, bundles)
+    }
+
+    /// Send a WETH->token swap through the router and leave it pending in anvil's mempool
+    pub async fn submit_victim_swap(&self, amount_in: U256) -> Transaction {
+        let tx = Eip1559TransactionRequest::new()
+            .from(self.victim)
+            .to(self.router)
+            .data(
+                MOCK_ABI
+                    .encode(
+                        "swapExactTokensForTokens",
+                        (
+                            self.pair,
+                            *WETH_ADDRESS,
+                            amount_in,
+                            U256::zero(),
+                            self.victim,
+                        ),
+                    )
+                    .unwrap(),
+            )
+            .gas(300000)
+            .max_fee_per_gas(U256::from(100_000_000_000u64))
+            .max_priority_fee_per_gas(U256::from(2_000_000_000u64));
+
+        let tx_hash = *self.provider.send_transaction(tx, None).await.unwrap();
+        self.provider
+            .get_transaction(tx_hash)
+            .await
+            .unwrap()
+            .expect("victim tx not in anvil's mempool")
+    }
+
+    /// Mine a bundle's txs in order, one tx per block, returning their receipts
+    ///
+    /// The victim is dropped from anvil's mempool first so that it is mined between our
+    /// frontrun and backrun
+    pub async fn mine_bundle(&self, bundle: &[Transaction]) -> Vec<TransactionReceipt> {
+        for tx in bundle {
+            self.drop_pending(tx.hash).await;
+        }
+
+        let mut receipts = vec![];
+        for tx in bundle {
+            self.provider.send_raw_transaction(tx.rlp()).await.unwrap();
+            self.mine().await;
+            receipts.push(
+                self.provider
+                    .get_transaction_receipt(tx.hash)
+                    .await
+                    .unwrap()
+                    .expect("bundle tx was not mined"),
+            );
+        }
+        receipts
+    }
+
+    /// Remove a tx from anvil's mempool
+    pub async fn drop_pending(&self, tx_hash: H256) {
+        self.anvil_request("anvil_dropTransaction", [tx_hash]).await;
+    }
+
+    /// Swap the harness token's code for `contract`'s (its storage is kept, so `contract` must
+    /// share MockERC20's layout), then write `storage` on top as `(slot, value)` pairs
+    pub async fn etch_token(&self, contract: &str, storage: &[(u64, U256)]) {
+        let code = forge_bytecode(contract, "deployedBytecode");
+        self.anvil_request("anvil_setCode", (self.token, code))
+            .await;
+        for (slot, value) in storage {
+            let mut value_bytes = [0u8; 32];
+            value.to_big_endian(&mut value_bytes);
+            self.anvil_request(
+                "anvil_setStorageAt",
+                (
+                    self.token,
+                    H256::from_low_u64_be(*slot),
+                    H256::from(value_bytes),
+                ),
+            )
+            .await;
+        }
+    }
+
+    /// Set the eth balance of `account`
+    pub async fn set_balance(&self, account: Address, amount: U256) {
+        self.anvil_request("anvil_setBalance", (account, amount))
+            .await;
+    }
+
+    /// Anvil's http rpc url
+    pub fn endpoint(&self) -> Url {
+        self.anvil.endpoint().parse().unwrap()
+    }
+
+    /// WETH held by `owner`
+    pub async fn weth_balance(&self, owner: Address) -> U256 {
+        let output = self
+            .provider
+            .call(
+                &TransactionRequest::new()
+                    .to(*WETH_ADDRESS)
+                    .data(MOCK_ABI.encode("balanceOf", owner).unwrap())
+                    .into(),
+                None,
+            )
+            .await
+            .unwrap();
+        MOCK_ABI.decode_output("balanceOf", output).unwrap()
+    }
+
+    /// Create `factory`'s WETH/token pair, starting at 100 WETH : 100k tokens
+    async fn create_funded_pair(&self, factory: Address) -> Address {
+        let (deployer, token, weth) = (self.deployer, self.token, *WETH_ADDRESS);
+        self.call(deployer, factory, "createPair", (weth, token), 0)
+            .await;
+        let pair: Address = MOCK_ABI
+            .decode_output(
+                "getPair",
+                self.provider
+                    .call(
+                        &TransactionRequest::new()
+                            .to(factory)
+                            .data(MOCK_ABI.encode("getPair", (weth, token)).unwrap())
+                            .into(),
+                        None,
+                    )
+                    .await
+                    .unwrap(),
+            )
+            .unwrap();
+
+        self.call(
+            deployer,
+            weth,
+            "transfer",
+            (pair, parse_ether(100).unwrap()),
+            0,
+        )
+        .await;
+        self.call(
+            deployer,
+            token,
+            "transfer",
+            (pair, parse_ether(100_000).unwrap()),
+            0,
+        )
+        .await;
+        self.call(deployer, pair, "sync", (), 0).await;
+
+        pair
+    }
+
+    /// Mine a single block
+    pub async fn mine(&self) {
+        self.anvil_request("evm_mine", ()).await;
+    }
+
+    /// Deploy `code` from the deployer account
+    async fn deploy(&self, code: Bytes) -> Address {
+        let tx = TransactionRequest::new().from(self.deployer).data(code);
+        self.send(tx)
+            .await
+            .contract_address
+            .expect("deployment has no contract address")
+    }
+
+    /// Call `function` on `to` from one of anvil's unlocked accounts, sending `ether` along
+    async fn call<T: abi::Tokenize>(
+        &self,
+        from: Address,
+        to: Address,
+        function: &str,
+        args: T,
+        ether: u64,
+    ) -> TransactionReceipt {
+        let tx = TransactionRequest::new()
+            .from(from)
+            .to(to)
+            .value(parse_ether(ether).unwrap())
+            .data(MOCK_ABI.encode(function, args).unwrap());
+        self.send(tx).await
+    }
+
+    /// Send a tx and mine it, panics if it reverts
+    async fn send(&self, tx: TransactionRequest) -> TransactionReceipt {
+        let tx_hash = *self.provider.send_transaction(tx, None).await.unwrap();
+        self.mine().await;
+        let receipt = self
+            .provider
+            .get_transaction_receipt(tx_hash)
+            .await
+            .unwrap()
+            .expect("setup tx was not mined");
+        assert_eq!(receipt.status, Some(U64::one()), "setup tx reverted");
+        receipt
+    }
+
+    /// Call one of anvil's cheat rpc methods, their results aren't needed
+    async fn anvil_request<T>(&self, method: &str, params: T)
+    where
+        T: std::fmt::Debug + serde::Serialize + Send + Sync,
+    {
+        self.provider
+            .request::<_, serde_json::Value>(method, params)
+            .await
+            .unwrap_or_else(|e| panic!("{} failed: {:?}", method, e));
+    }
+}
+
+/// Decode the txs of a bundle (with senders recovered)
+pub fn decode_bundle(bundle: &BundleSubmission) -> Vec<Transaction> {
+    bundle
+        .request
+        .transactions()
+        .iter()
+        .map(|bundle_tx| {
+            let mut tx = match bundle_tx {
+                BundleTransaction::Signed(tx) => *tx.clone(),
+                BundleTransaction::Raw(raw) => Transaction::decode(&Rlp::new(raw)).unwrap(),
+            };
+            tx.recover_from_mut().unwrap();
+            tx
+        })
+        .collect()
+}
+
+/// Anvil doesn't support `newPendingTransactionsWithBody`, so subscribe to pending hashes and
+/// fetch each tx instead
+pub struct PendingTxCollector {
+    provider: Arc<Provider<Ws>>,
+}
+
+impl PendingTxCollector {
+    pub fn new(provider: Arc<Provider<Ws>>) -> Self {
+        Self { provider }
+    }
+}
+
+#[async_trait]
+impl Collector<Transaction> for PendingTxCollector {
+    async fn get_event_stream(&self) -> Result<CollectorStream<'_, Transaction>> {
+        let stream = self
+            .provider
+            .subscribe_pending_txs()
+            .await
+            .map_err(|_| anyhow!("Failed to create pending tx stream"))?;
+        let stream = stream.filter_map(move |tx_hash| async move {
+            self.provider.get_transaction(tx_hash).await.ok().flatten()
+        });
+        Ok(Box::pin(stream))
+    }
+}
+
+/// Stands in for the builders, forwards every submitted bundle to the test
+pub struct MockRelayExecutor {
+    bundles: UnboundedSender<BundleSubmission>,
+}
+
+impl MockRelayExecutor {
+    pub fn new() -> (Self, UnboundedReceiver<BundleSubmission>) {
+        let (bundles, receiver) = mpsc::unbounded_channel();
+        (Self { bundles }, receiver)
+    }
+}
+
+#[async_trait]
+impl Executor<Action> for MockRelayExecutor {
+    async fn execute(&self, action: Action) -> Result<()> {
+        if let Action::SubmitToFlashbots(bundle) = action {
+            self.bundles
+                .send(bundle)
+                .map_err(|_| anyhow!("bundle receiver dropped"))?;
+        }
+        Ok(())
+    }
+}
+
+/// Root of the `contract` directory
+fn contract_dir() -> PathBuf {
+    let git_root = Command::new("git")
+        .arg("rev-parse")
+        .arg("--show-toplevel")
+        .output()
+        .expect("Failed to execute git command")
+        .stdout;
+    let mut contract_dir = PathBuf::from(std::str::from_utf8(&git_root).unwrap().trim());
+    contract_dir.push("contract");
+    contract_dir
+}
+
+/// Compile a contract from `contract/test/misc/MockUniswapV2.sol` and return its `bytecode` or
+/// `deployedBytecode`
+fn forge_bytecode(contract: &str, field: &str) -> Bytes {
+    let output = Command::new("forge")
+        .arg("inspect")
+        .arg(contract)
+        .arg(field)
+        .current_dir(contract_dir())
+        .output()
+        .expect("Failed to run forge");
+
+    assert!(output.status.success(), "Failed to compile {}", contract);
+
+    Bytes::from_str(std::str::from_utf8(&output.stdout).unwrap().trim()).unwrap()
+}
+
+/// Compile the huff sando contract's runtime bytecode
+fn huff_sando_bytecode() -> Bytes {
+    let output = Command::new("huffc")
+        .arg("--bin-runtime")
+        .arg("sando.huff")
+        .current_dir(contract_dir().join("src"))
+        .output()
+        .expect("Failed to compile huff sando contract");
+
+    assert!(output.status.success(), "Command execution failed");
+
+    Bytes::from_str(std::str::from_utf8(&output.stdout).unwrap().trim()).unwrap()
+}
@@ -1,15 +1,56 @@
-use std::{str::FromStr, sync::Arc};
+mod harness;
 
+use std::{
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use artemis_core::{
+    collectors::mev_share_collector::{MevShareEvent, MevShareLog},
+    executors::{
+        bundle_simulator::{BundleExpectation, BundleSimulator},
+        flashbots_executor::{SubmissionReport, SubmissionStatus},
+    },
+    types::Strategy,
+    utilities::{
+        mock_call_many::MockCallMany, mock_mev_share::MockMevShare, mock_relay::MockRelay,
+    },
+};
 use cfmms::pool::{Pool, UniswapV2Pool, UniswapV3Pool};
 use ethers::{
+    abi,
     prelude::Lazy,
-    providers::{Middleware, Provider, Ws},
-    types::{Address, Transaction, TxHash, U64},
+    providers::{Http, Middleware, Provider, Ws},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Eip1559TransactionRequest,
+        Transaction, TxHash, H256, I256, U256, U64,
+    },
+    utils::{keccak256, parse_ether, rlp},
 };
+use harness::{decode_bundle, Harness, SANDO_ADDRESS};
 use strategy::{
-    bot::SandoBot,
-    types::{BlockInfo, RawIngredients, StratConfig},
+    bot::{
+        backtest::{BacktestReport, BacktestResult},
+        SandoBot,
+    },
+    inventory::{withdraw_tx, SandoInventory, Withdrawal},
+    simulator::{
+        bundle_simulator::LocalBundleSimulator,
+        fixture::{FixtureRecorder, SimulationFixture},
+        rpc_backend::RpcBackend,
+    },
+    storage::{
+        bundle_hash, sqlite::SqliteStore, writer::StoreWriter, OpportunityRecord,
+        OpportunityStatus, OpportunityStore,
+    },
+    types::{
+        Action, BlockInfo, BribePolicy, Event, RawIngredients, RiskLimits, SalmonellaPolicy,
+        StratConfig, TxSource,
+    },
 };
+use uuid::Uuid;
 
 // -- consts --
 static WSS_RPC: &str = "ws://localhost:8545";
@@ -28,10 +69,8 @@ fn setup_logger() {
         .apply();
 }
 
-async fn setup_bot(provider: Arc<Provider<Ws>>) -> SandoBot<Provider<Ws>> {
-    setup_logger();
-
-    let strat_config = StratConfig {
+fn strat_config() -> StratConfig {
+    StratConfig {
         sando_address: "0xaAaAaAaaAaAaAaaAaAAAAAAAAaaaAaAaAaaAaaAa"
             .parse()
             .unwrap(),
@@ -39,9 +78,22 @@ async fn setup_bot(provider: Arc<Provider<Ws>>) -> SandoBot<Provider<Ws>> {
         searcher_signer: "0x0000000000000000000000000000000000000000000000000000000000000001"
             .parse()
             .unwrap(),
-    };
+        salmonella_policy: SalmonellaPolicy::default(),
+        differential_check: false,
+        backrun_only: false,
+        store: None,
+        dexes: None,
+        simulation_backend: None,
+        bribe_policy: BribePolicy::default(),
+        risk_limits: RiskLimits::default(),
+        local_state_diffs: false,
+        pool_checkpoints: true,
+    }
+}
 
-    SandoBot::new(provider, strat_config)
+async fn setup_bot<M: Middleware + 'static>(provider: Arc<M>) -> SandoBot<M> {
+    setup_logger();
+    SandoBot::new(provider, strat_config())
 }
 
 async fn block_num_to_info(block_num: u64, provider: Arc<Provider<Ws>>) -> BlockInfo {
@@ -102,6 +154,137 @@ async fn can_sandwich_uni_v2() {
         .unwrap();
 }
 
+/// testing that the uni v2 sandwich above gives the same outcome when replayed under perturbed envs
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn differential_check_accepts_uni_v2_sandwich() {
+    let client = Arc::new(Provider::new(Ws::connect(WSS_RPC).await.unwrap()));
+
+    setup_logger();
+    let bot = SandoBot::new(
+        client.clone(),
+        StratConfig {
+            differential_check: true,
+            ..strat_config()
+        },
+    );
+
+    let ingredients = RawIngredients::new(
+        vec![
+            victim_tx_hash(
+                "0xfecf2c78d1418e6905c18a6a6301c9d39b14e5320e345adce52baaecf805580d",
+                client.clone(),
+            )
+            .await,
+        ],
+        *WETH_ADDRESS,
+        hex_to_address("0x3642Cf76c5894B4aB51c1080B2c4F5B9eA734106"),
+        hex_to_univ2_pool("0x5d1dd0661E1D22697943C1F50Cc726eA3143329b", client.clone()).await,
+    );
+
+    let target_block = block_num_to_info(17754167, client.clone()).await;
+
+    let _ = bot
+        .is_sandwichable(ingredients, target_block)
+        .await
+        .unwrap();
+}
+
+/// Fixture of the uniswap v2 sandwich on block 17754167, recorded by `record_uni_v2_fixture`
+static UNI_V2_FIXTURE: &str = concat!(
+    env!("CARGO_MANIFEST_DIR"),
+    "/tests/fixtures/uni_v2_sandwich.json"
+);
+
+/// (re)records `UNI_V2_FIXTURE` from `WSS_RPC`, run with `cargo test record_uni_v2_fixture --
+/// --ignored` and commit the output
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+#[ignore = "needs an archive node at WSS_RPC, rewrites tests/fixtures/uni_v2_sandwich.json"]
+async fn record_uni_v2_fixture() {
+    let client = Arc::new(Provider::new(Ws::connect(WSS_RPC).await.unwrap()));
+
+    let bot = setup_bot(client.clone()).await;
+
+    let ingredients = RawIngredients::new(
+        vec![
+            victim_tx_hash(
+                "0xfecf2c78d1418e6905c18a6a6301c9d39b14e5320e345adce52baaecf805580d",
+                client.clone(),
+            )
+            .await,
+        ],
+        *WETH_ADDRESS,
+        hex_to_address("0x3642Cf76c5894B4aB51c1080B2c4F5B9eA734106"),
+        hex_to_univ2_pool("0x5d1dd0661E1D22697943C1F50Cc726eA3143329b", client.clone()).await,
+    );
+
+    let target_block = block_num_to_info(17754167, client.clone()).await;
+
+    // record all state touched while simulating
+    let recorder = FixtureRecorder::new(client.clone(), target_block.number - 1);
+    let recipe = bot
+        .is_sandwichable_with_backend(ingredients.clone(), target_block, recorder.backend())
+        .await
+        .unwrap();
+
+    recorder
+        .finish(&ingredients, target_block, recipe.get_revenue())
+        .save(UNI_V2_FIXTURE)
+        .unwrap();
+}
+
+/// testing that a committed fixture replays the recorded sandwich without a provider
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn fixture_replays_sandwich_offline() {
+    let fixture = SimulationFixture::load(UNI_V2_FIXTURE)
+        .expect("fixture missing, record it with `cargo test record_uni_v2_fixture -- --ignored`");
+
+    // provider that can't answer any request
+    let (offline, _) = Provider::mocked();
+    let bot = setup_bot(Arc::new(offline)).await;
+
+    let recipe = bot
+        .is_sandwichable_with_backend(fixture.ingredients(), fixture.target_block, fixture.db())
+        .await
+        .unwrap();
+
+    assert_eq!(recipe.get_revenue(), fixture.revenue);
+}
+
+/// testing that bundles which never reached a builder are notified
+#[tokio::test]
+async fn submission_failures_are_notified() {
+    let (offline, _) = Provider::mocked();
+    let mut bot = setup_bot(Arc::new(offline)).await;
+    let report = |replacement_uuid, status| {
+        Event::SubmissionReport(SubmissionReport {
+            replacement_uuid,
+            transaction_hashes: vec![H256::random(), H256::random()],
+            status,
+        })
+    };
+
+    let actions = bot
+        .process_event(report(Some(Uuid::new_v4()), SubmissionStatus::Sent))
+        .await;
+    assert!(actions.is_empty());
+
+    let dropped = SubmissionStatus::Dropped("backrun reverted".to_string());
+    let actions = bot
+        .process_event(report(Some(Uuid::new_v4()), dropped))
+        .await;
+    assert!(matches!(
+        &actions[..],
+        [Action::Notify(notification)] if notification.title == "Bundle dropped by simulation"
+    ));
+
+    let failed = SubmissionStatus::Failed("flashbots: timed out".to_string());
+    let actions = bot.process_event(report(None, failed)).await;
+    assert!(matches!(
+        &actions[..],
+        [Action::Notify(notification)] if notification.message.contains("flashbots: timed out")
+    ));
+}
+
 /// testing against: https://eigenphi.io/mev/ethereum/tx/0x056ede919e31be59b7e1e8676b3be1272ce2bbd3d18f42317a26a3d1f2951fc8
 #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
 async fn can_sandwich_sushi_swap() {
@@ -190,3 +373,584 @@ async fn can_sandwich_uni_v3() {
         .await
         .unwrap();
 }
+
+/// testing that opcodes can be moved between fatal and warning by name
+#[test]
+fn salmonella_policy_parses_opcodes() {
+    let selfbalance = SalmonellaPolicy::parse_opcode("selfbalance").unwrap();
+    assert_eq!(selfbalance, 0x47);
+    assert!(SalmonellaPolicy::parse_opcode("NOT_AN_OPCODE").is_err());
+
+    let mut policy = SalmonellaPolicy::default();
+    assert!(policy.is_fatal(selfbalance));
+
+    policy.add_warning_opcode(selfbalance);
+    assert!(!policy.is_fatal(selfbalance));
+    assert!(policy.is_warning(selfbalance));
+
+    // nothing is exempt unless configured
+    let weth_codehash = Default::default();
+    assert!(!policy.is_exempt(*WETH_ADDRESS, weth_codehash));
+    policy.exempt_address(*WETH_ADDRESS);
+    assert!(policy.is_exempt(*WETH_ADDRESS, weth_codehash));
+}
+
+/// testing that a submitted opportunity can be followed through to inclusion
+#[test]
+fn sqlite_store_tracks_bundle_status() {
+    let store = SqliteStore::in_memory().unwrap();
+    let bundle = bundle_hash(&[H256::repeat_byte(1), H256::repeat_byte(2)]);
+
+    let mut record = OpportunityRecord::not_sandwichable(
+        H256::repeat_byte(3),
+        U64::from(17700000),
+        *WETH_ADDRESS,
+        String::new(),
+    );
+    record.bundle_hash = Some(bundle);
+    record.status = OpportunityStatus::Submitted;
+    store.record_opportunity(&record).unwrap();
+    store
+        .record_submission(bundle, "flashbots", "Accepted")
+        .unwrap();
+
+    store
+        .update_status(bundle, OpportunityStatus::Landed, Some(I256::from(-5)))
+        .unwrap();
+    let (status, profit) = store.get_status(bundle).unwrap().unwrap();
+    assert_eq!(status, "landed");
+    assert_eq!(profit, Some("-5".to_string()));
+    assert_eq!(store.count_submissions(bundle).unwrap(), 1);
+}
+
+/// testing that the mempool source a victim was first seen on is recorded with its opportunity
+#[test]
+fn sqlite_store_records_victim_source() {
+    let store = SqliteStore::in_memory().unwrap();
+    let victim = H256::repeat_byte(4);
+    let first_seen = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+
+    let mut record = OpportunityRecord::not_sandwichable(
+        victim,
+        U64::from(17700000),
+        *WETH_ADDRESS,
+        String::new(),
+    );
+    record.victim_source = Some(TxSource {
+        name: "bloxroute".to_string(),
+        first_seen,
+    });
+    store.record_opportunity(&record).unwrap();
+
+    assert_eq!(
+        store.get_victim_source(victim).unwrap(),
+        Some(("bloxroute".to_string(), 1_700_000_000_123))
+    );
+    assert_eq!(store.get_victim_source(H256::zero()).unwrap(), None);
+}
+
+/// testing that writes queued on the store writer are applied in order once flushed
+#[tokio::test]
+async fn store_writer_applies_writes_in_order() {
+    let sqlite = Arc::new(SqliteStore::in_memory().unwrap());
+    let writer = StoreWriter::spawn(sqlite.clone());
+    let bundle = bundle_hash(&[H256::repeat_byte(1)]);
+
+    let mut record = OpportunityRecord::not_sandwichable(
+        H256::repeat_byte(2),
+        U64::from(17700000),
+        *WETH_ADDRESS,
+        String::new(),
+    );
+    record.bundle_hash = Some(bundle);
+    record.status = OpportunityStatus::Submitted;
+    writer.record_opportunity(&record).unwrap();
+    writer
+        .update_status(bundle, OpportunityStatus::Dropped, None)
+        .unwrap();
+    writer
+        .update_status(bundle, OpportunityStatus::Submitted, None)
+        .unwrap();
+    writer
+        .record_submission(bundle, "flashbots", "Accepted")
+        .unwrap();
+
+    writer.flush().await.unwrap();
+    let (status, _) = sqlite.get_status(bundle).unwrap().unwrap();
+    assert_eq!(status, "submitted");
+    assert_eq!(sqlite.count_submissions(bundle).unwrap(), 1);
+}
+
+/// testing that backtest totals only count sandwichable victims
+#[test]
+fn backtest_report_totals() {
+    let result = |revenue: Option<u64>| BacktestResult {
+        block: 17700000,
+        victim_hash: H256::zero(),
+        pool: *WETH_ADDRESS,
+        optimal_input: revenue.map(U256::from),
+        revenue: revenue.map(U256::from),
+        frontrun_gas_used: None,
+        backrun_gas_used: None,
+        error: revenue.is_none().then(|| "not sandwichable".to_string()),
+    };
+
+    let mut report = BacktestReport::default();
+    report.add_block(vec![result(Some(10)), result(None)]);
+    report.add_block(vec![result(Some(5))]);
+
+    assert_eq!(report.totals.blocks, 2);
+    assert_eq!(report.totals.victims_evaluated, 3);
+    assert_eq!(report.totals.sandwichable, 2);
+    assert_eq!(report.totals.revenue, U256::from(15));
+
+    let mut csv = vec![];
+    report.write_csv(&mut csv).unwrap();
+    assert_eq!(String::from_utf8(csv).unwrap().lines().count(), 4);
+}
+
+/// testing that pending txs survive the compact capture encoding, sender included
+#[tokio::test]
+async fn captured_transaction_roundtrips() {
+    let wallet =
+        LocalWallet::from_str("ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80")
+            .unwrap()
+            .with_chain_id(1u64);
+    let tx: TypedTransaction = Eip1559TransactionRequest::new()
+        .to(*WETH_ADDRESS)
+        .value(1)
+        .data(vec![1, 2, 3])
+        .nonce(7)
+        .gas(21000)
+        .max_fee_per_gas(100)
+        .max_priority_fee_per_gas(1)
+        .chain_id(1)
+        .into();
+    let signature = wallet.sign_transaction(&tx).await.unwrap();
+    let mut pending: Transaction = rlp::decode(&tx.rlp_signed(&signature)).unwrap();
+    pending.from = wallet.address();
+
+    let json = serde_json::to_string(&Event::NewTransaction(pending.clone())).unwrap();
+    let decoded = match serde_json::from_str(&json).unwrap() {
+        Event::NewTransaction(decoded) => decoded,
+        event => panic!("decoded the wrong event: {:?}", event),
+    };
+    assert_eq!(decoded, pending);
+    assert_eq!(decoded.hash, tx.hash(&signature));
+
+    let source = TxSource {
+        name: "bloxroute".to_string(),
+        first_seen: SystemTime::now(),
+    };
+    let json =
+        serde_json::to_string(&Event::TaggedTransaction(pending.clone(), source.clone())).unwrap();
+    match serde_json::from_str(&json).unwrap() {
+        Event::TaggedTransaction(decoded, decoded_source) => {
+            assert_eq!(decoded, pending);
+            assert_eq!(decoded_source, source);
+        }
+        event => panic!("decoded the wrong event: {:?}", event),
+    }
+}
+
+/// Run the whole engine against a local anvil chain and mine the bundle that it submits
+#[cfg(not(feature = "debug"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn engine_sandwiches_victim_on_anvil() {
+    setup_logger();
+    let harness = Harness::spawn().await;
+    let (_engine, mut bundles) = harness.run_engine().await;
+
+    let victim_tx = harness.submit_victim_swap(parse_ether(10).unwrap()).await;
+
+    let bundle = tokio::time::timeout(Duration::from_secs(60), bundles.recv())
+        .await
+        .expect("no bundle was submitted")
+        .unwrap();
+    let bundle_txs = decode_bundle(&bundle);
+
+    // frontrun, victim, backrun
+    let searcher = harness.searcher.address();
+    assert_eq!(bundle_txs.len(), 3);
+    assert_eq!(bundle_txs[0].from, searcher);
+    assert_eq!(bundle_txs[0].to, Some(*SANDO_ADDRESS));
+    assert_eq!(bundle_txs[1].hash, victim_tx.hash);
+    assert_eq!(bundle_txs[2].from, searcher);
+    assert_eq!(bundle_txs[2].to, Some(*SANDO_ADDRESS));
+    assert_eq!(bundle_txs[2].nonce, bundle_txs[0].nonce + 1);
+
+    let weth_before = harness.weth_balance(*SANDO_ADDRESS).await;
+    let receipts = harness.mine_bundle(&bundle_txs).await;
+    assert!(receipts
+        .iter()
+        .all(|receipt| receipt.status == Some(U64::one())));
+    let weth_after = harness.weth_balance(*SANDO_ADDRESS).await;
+    assert!(weth_after > weth_before, "sandwich was not profitable");
+}
+
+/// Simulate the engine's bundle with the local bundle simulator, it should match what the strategy
+/// expects from the bundle
+#[cfg(not(feature = "debug"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn local_bundle_simulator_matches_expectation_on_anvil() {
+    setup_logger();
+    let harness = Harness::spawn().await;
+    let (_engine, mut bundles) = harness.run_engine().await;
+
+    harness.submit_victim_swap(parse_ether(10).unwrap()).await;
+
+    let bundle = tokio::time::timeout(Duration::from_secs(60), bundles.recv())
+        .await
+        .expect("no bundle was submitted")
+        .unwrap();
+    let expectation = bundle
+        .expectation
+        .clone()
+        .expect("bundle has no expectation");
+    assert_eq!(expectation.searcher, harness.searcher.address());
+
+    let simulator = LocalBundleSimulator::new(harness.provider.clone(), *SANDO_ADDRESS);
+    let simulation = simulator
+        .simulate(&bundle.request, expectation.searcher)
+        .await
+        .unwrap();
+
+    assert!(!simulation.coinbase_payment.is_zero());
+    assert!(simulation.profit.is_some());
+    expectation.check(&simulation, 100).unwrap();
+
+    // a bundle paying twice the expected bribe is out of tolerance
+    let overpaying = BundleExpectation {
+        coinbase_payment: expectation.coinbase_payment / 2,
+        ..expectation
+    };
+    assert!(overpaying.check(&simulation, 100).is_err());
+}
+
+/// Stream a MEV-Share hint about a pending swap to the engine in backrun only mode and mine the
+/// backrun that it sends to the matchmaker
+#[cfg(not(feature = "debug"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn engine_backruns_mev_share_hint_on_anvil() {
+    setup_logger();
+    let harness = Harness::spawn().await;
+    let relay = MockRelay::spawn().unwrap();
+    let mev_share = MockMevShare::spawn().unwrap();
+    let _engine = harness
+        .run_backrun_engine(mev_share.url(), relay.url())
+        .await;
+
+    // give the collectors time to connect
+    tokio::time::timeout(Duration::from_secs(10), async {
+        while mev_share.clients() == 0 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+    })
+    .await
+    .expect("MEV-Share collector never connected");
+
+    let amount_in = parse_ether(10).unwrap();
+    let victim_tx = harness.submit_victim_swap(amount_in).await;
+
+    // only the amounts paid in are needed to replay the swap
+    let (amount_0_in, amount_1_in) = match *WETH_ADDRESS < harness.token {
+        true => (amount_in, U256::zero()),
+        false => (U256::zero(), amount_in),
+    };
+    mev_share.send(&MevShareEvent {
+        hash: victim_tx.hash,
+        logs: vec![MevShareLog {
+            address: harness.pair,
+            topics: vec![H256::from(keccak256(
+                "Swap(address,uint256,uint256,uint256,uint256,address)",
+            ))],
+            data: abi::encode(&[
+                abi::Token::Uint(amount_0_in),
+                abi::Token::Uint(amount_1_in),
+                abi::Token::Uint(U256::zero()),
+                abi::Token::Uint(U256::zero()),
+            ])
+            .into(),
+        }],
+        txs: None,
+    });
+
+    let bundle = tokio::time::timeout(Duration::from_secs(60), async {
+        loop {
+            if let Some(bundle) = relay.mev_share_bundles().pop() {
+                return bundle;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .expect("no backrun was submitted");
+
+    // hinted tx is referenced by hash, followed by our buy and sell
+    assert_eq!(
+        bundle.params["body"][0]["hash"],
+        serde_json::json!(victim_tx.hash)
+    );
+    let backrun_txs = bundle.transactions().unwrap();
+    let searcher = harness.searcher.address();
+    assert_eq!(backrun_txs.len(), 2);
+    assert!(backrun_txs
+        .iter()
+        .all(|tx| tx.from == searcher && tx.to == Some(*SANDO_ADDRESS)));
+    assert_eq!(backrun_txs[1].nonce, backrun_txs[0].nonce + 1);
+
+    let mut bundle_txs = vec![victim_tx];
+    bundle_txs.extend(backrun_txs);
+
+    let weth_before = harness.weth_balance(*SANDO_ADDRESS).await;
+    let receipts = harness.mine_bundle(&bundle_txs).await;
+    assert!(receipts
+        .iter()
+        .all(|receipt| receipt.status == Some(U64::one())));
+    let weth_after = harness.weth_balance(*SANDO_ADDRESS).await;
+    assert!(weth_after > weth_before, "backrun was not profitable");
+}
+
+/// Search for the optimal input with `eth_callMany` on a stand-in node, it should find the same
+/// sandwich as the local fork
+#[cfg(not(feature = "debug"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn rpc_simulation_matches_local_on_anvil() {
+    setup_logger();
+    let harness = Harness::spawn().await;
+    let node = MockCallMany::spawn(harness.endpoint()).unwrap();
+
+    // the victim is replayed by the simulations, it can't also be pending in anvil's mempool
+    let victim_tx = harness.submit_victim_swap(parse_ether(10).unwrap()).await;
+    harness.drop_pending(victim_tx.hash).await;
+
+    let pool = UniswapV2Pool::new_from_address(harness.pair, harness.provider.clone())
+        .await
+        .unwrap();
+    let ingredients = RawIngredients::new(
+        vec![victim_tx],
+        *WETH_ADDRESS,
+        harness.token,
+        Pool::UniswapV2(pool),
+    );
+    let latest_block = harness
+        .provider
+        .get_block(BlockNumber::Latest)
+        .await
+        .unwrap()
+        .unwrap();
+    let target_block = BlockInfo::try_from(latest_block).unwrap().get_next_block();
+
+    let mut local_bot = SandoBot::new(harness.provider.clone(), harness.strat_config());
+    local_bot.sync_state().await.unwrap();
+    let local_recipe = local_bot
+        .is_sandwichable(ingredients.clone(), target_block)
+        .await
+        .unwrap();
+
+    let node_provider = Arc::new(Provider::<Http>::try_from(node.url().as_str()).unwrap());
+    let mut rpc_bot = SandoBot::new(
+        harness.provider.clone(),
+        StratConfig {
+            simulation_backend: Some(Arc::new(RpcBackend::new(node_provider))),
+            ..harness.strat_config()
+        },
+    );
+    rpc_bot.sync_state().await.unwrap();
+    let rpc_recipe = rpc_bot
+        .is_sandwichable(ingredients, target_block)
+        .await
+        .unwrap();
+
+    assert!(node.call_many_requests() > 0);
+    assert!(!rpc_recipe.get_optimal_input().is_zero());
+    assert_eq!(
+        rpc_recipe.get_optimal_input(),
+        local_recipe.get_optimal_input()
+    );
+}
+
+/// Evaluate a pending victim by its hash (what `simulate --tx` runs)
+#[cfg(not(feature = "debug"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn simulate_tx_finds_pending_victim_on_anvil() {
+    setup_logger();
+    let harness = Harness::spawn().await;
+    let victim_tx = harness.submit_victim_swap(parse_ether(10).unwrap()).await;
+
+    let mut bot = SandoBot::new(harness.provider.clone(), harness.strat_config());
+    bot.sync_state().await.unwrap();
+    let results = bot.simulate_tx(victim_tx.hash).await.unwrap();
+
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].victim_hash, victim_tx.hash);
+    assert_eq!(results[0].pool, harness.pair);
+    assert!(
+        results[0].revenue.unwrap_or_default() > U256::zero(),
+        "victim was not sandwichable: {:?}",
+        results[0].error
+    );
+}
+
+/// Sweep part of the sando contract's weth to the searcher (what `withdraw` sends)
+#[cfg(not(feature = "debug"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn withdraw_sweeps_weth_on_anvil() {
+    setup_logger();
+    let harness = Harness::spawn().await;
+    let searcher = harness.searcher.address();
+
+    let inventory = SandoInventory::fetch(harness.provider.clone(), *SANDO_ADDRESS, U64::zero())
+        .await
+        .unwrap();
+    assert_eq!(inventory.weth, parse_ether(50).unwrap());
+    assert!(inventory.dust.is_empty());
+
+    let mut tx: TypedTransaction = withdraw_tx(
+        *SANDO_ADDRESS,
+        searcher,
+        Withdrawal::Weth(parse_ether(10).unwrap()),
+        1,
+    )
+    .unwrap()
+    .into();
+    harness
+        .provider
+        .fill_transaction(&mut tx, None)
+        .await
+        .unwrap();
+    let signature = harness.searcher.sign_transaction(&tx).await.unwrap();
+    let tx_hash = *harness
+        .provider
+        .send_raw_transaction(tx.rlp_signed(&signature))
+        .await
+        .unwrap();
+    harness.mine().await;
+
+    let receipt = harness
+        .provider
+        .get_transaction_receipt(tx_hash)
+        .await
+        .unwrap()
+        .expect("withdrawal was not mined");
+    assert_eq!(receipt.status, Some(U64::one()));
+    assert_eq!(
+        harness.weth_balance(*SANDO_ADDRESS).await,
+        parse_ether(40).unwrap()
+    );
+    assert_eq!(
+        harness.weth_balance(searcher).await,
+        parse_ether(10).unwrap()
+    );
+}
+
+/// testing that weth withdrawals are refused off mainnet, where the contract reads the amount at
+/// another calldata offset
+#[test]
+fn withdraw_tx_encodes_weth_amount_for_mainnet_only() {
+    let amount = parse_ether(1).unwrap();
+    let tx = withdraw_tx(*SANDO_ADDRESS, Address::zero(), Withdrawal::Weth(amount), 1).unwrap();
+    let data = tx.data.unwrap();
+    assert_eq!(data.len(), 33);
+    assert_eq!(U256::from_big_endian(&data[1..]), amount);
+
+    assert!(withdraw_tx(*SANDO_ADDRESS, Address::zero(), Withdrawal::Weth(amount), 5).is_err());
+    assert!(withdraw_tx(*SANDO_ADDRESS, Address::zero(), Withdrawal::Eth, 5).is_ok());
+}
+
+/// Victim swap on the harness pair (dropped from anvil's mempool so that only the simulations
+/// replay it) and the block it would be sandwiched in
+async fn harness_victim(harness: &Harness) -> (RawIngredients, BlockInfo) {
+    let victim_tx = harness.submit_victim_swap(parse_ether(10).unwrap()).await;
+    harness.drop_pending(victim_tx.hash).await;
+
+    let pool = UniswapV2Pool::new_from_address(harness.pair, harness.provider.clone())
+        .await
+        .unwrap();
+    let ingredients = RawIngredients::new(
+        vec![victim_tx],
+        *WETH_ADDRESS,
+        harness.token,
+        Pool::UniswapV2(pool),
+    );
+    let latest_block = harness
+        .provider
+        .get_block(BlockNumber::Latest)
+        .await
+        .unwrap()
+        .unwrap();
+    let target_block = BlockInfo::try_from(latest_block).unwrap().get_next_block();
+
+    (ingredients, target_block)
+}
+
+/// A token that only misbehaves after a timestamp uses no fatal opcode, so only the perturbed
+/// timestamp replay can catch it
+#[cfg(not(feature = "debug"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn differential_check_rejects_timestamp_dependent_token_on_anvil() {
+    setup_logger();
+    let harness = Harness::spawn().await;
+    let (ingredients, target_block) = harness_victim(&harness).await;
+
+    // taxes start between the target block and the perturbed replay (24s later)
+    let tax_after = target_block.timestamp + 12;
+    harness
+        .etch_token("MockTimeBombERC20", &[(5, tax_after)])
+        .await;
+
+    let mut bot = SandoBot::new(
+        harness.provider.clone(),
+        StratConfig {
+            differential_check: false,
+            ..harness.strat_config()
+        },
+    );
+    bot.sync_state().await.unwrap();
+    bot.is_sandwichable(ingredients.clone(), target_block)
+        .await
+        .expect("opcode check alone should accept the sandwich");
+
+    let mut bot = SandoBot::new(harness.provider.clone(), harness.strat_config());
+    bot.sync_state().await.unwrap();
+    let error = bot
+        .is_sandwichable(ingredients, target_block)
+        .await
+        .expect_err("differential check should reject the sandwich")
+        .to_string();
+    assert!(error.contains("ENV_DEPENDENT"), "{}", error);
+    assert!(error.contains("timestamp"), "{}", error);
+}
+
+/// The gas price perturbation costs the searcher more gas, a searcher holding just enough eth for
+/// the sandwich shouldn't get it rejected as env dependent
+#[cfg(not(feature = "debug"))]
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn differential_check_ignores_searcher_funding_on_anvil() {
+    setup_logger();
+    let harness = Harness::spawn().await;
+    let (ingredients, target_block) = harness_victim(&harness).await;
+
+    let mut bot = SandoBot::new(harness.provider.clone(), harness.strat_config());
+    bot.sync_state().await.unwrap();
+    let recipe = bot
+        .is_sandwichable(ingredients.clone(), target_block)
+        .await
+        .unwrap();
+
+    // frontrun and backrun are simulated with a 700k gas limit, the frontrun sends the encoded
+    // input as value, leave less than the 2 gwei bump would cost on top
+    let balance = (U256::from(700_000) + U256::from(recipe.get_frontrun_gas_used()))
+        * target_block.base_fee_per_gas
+        + recipe.get_optimal_input() / 100_000
+        + parse_ether("0.0005").unwrap();
+    harness
+        .set_balance(harness.searcher.address(), balance)
+        .await;
+
+    let funded_recipe = bot
+        .is_sandwichable(ingredients, target_block)
+        .await
+        .unwrap();
+    assert_eq!(funded_recipe.get_revenue(), recipe.get_revenue());
+}
@@ -0,0 +1,70 @@
+use std::{fs::File, path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use artemis_core::types::Strategy;
+use clap::Args;
+use ethers::{
+    providers::{Provider, Ws},
+    types::U64,
+};
+use log::info;
+use strategy::{
+    bot::{backtest::BacktestReport, SandoBot},
+    types::StratConfig,
+};
+
+use crate::config::Config;
+
+/// Arguments for `backtest <from_block> <to_block> [--csv <path>] [--json <path>]`
+#[derive(Debug, Args)]
+pub struct BacktestArgs {
+    pub from_block: u64,
+    pub to_block: u64,
+    /// Write one row per evaluated victim to this file
+    #[arg(long)]
+    pub csv: Option<PathBuf>,
+    /// Write the results and totals to this file
+    #[arg(long)]
+    pub json: Option<PathBuf>,
+}
+
+/// Replay blocks `from_block..=to_block` through the strategy and report what it would have
+/// captured
+pub async fn run_backtest(config: Config, args: BacktestArgs) -> Result<()> {
+    if args.from_block > args.to_block {
+        return Err(anyhow!("from_block must not be after to_block"));
+    }
+
+    let ws = Ws::connect(config.wss_rpc.clone()).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    let configs = StratConfig {
+        backrun_only: false,
+        ..config.strat_config()?
+    };
+    let mut bot = SandoBot::new(provider, configs);
+    bot.sync_state().await?;
+
+    let mut report = BacktestReport::default();
+    for block_number in args.from_block..=args.to_block {
+        let results = bot.backtest_block(U64::from(block_number)).await?;
+        report.add_block(results);
+    }
+
+    info!(
+        "backtested {} blocks: {} victims evaluated, {} sandwichable, {} wei revenue",
+        report.totals.blocks,
+        report.totals.victims_evaluated,
+        report.totals.sandwichable,
+        report.totals.revenue
+    );
+
+    if let Some(path) = args.csv {
+        report.write_csv(File::create(path)?)?;
+    }
+    if let Some(path) = args.json {
+        report.write_json(File::create(path)?)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,156 @@
+use std::{future::Future, time::Duration};
+
+use anyhow::{anyhow, Result};
+use ethers::{
+    providers::{Http, Middleware, Provider, Ws},
+    signers::Signer,
+    utils::format_ether,
+};
+use log::{error, info};
+use reqwest::Url;
+
+use crate::config::{BundleSimulationMode, Config};
+
+/// How long each endpoint gets to answer
+const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Check that the node, the sando contract, and every configured endpoint are reachable, logging
+/// the outcome of each check (the keys and addresses were already validated when loading `config`)
+pub async fn check_config(config: &Config) -> Result<()> {
+    let mut failed = 0;
+    let mut report = |name: String, outcome: Result<String>| match outcome {
+        Ok(detail) => info!("✓ {}: {}", name, detail),
+        Err(e) => {
+            error!("✗ {}: {}", name, e);
+            failed += 1;
+        }
+    };
+
+    info!("searcher: {:?}", config.searcher_signer.address());
+    info!("flashbots auth: {:?}", config.bundle_signer.address());
+
+    match with_timeout(Ws::connect(config.wss_rpc.as_str())).await {
+        Ok(ws) => {
+            let provider = Provider::new(ws);
+            report("wss rpc".to_string(), check_node(&provider).await);
+            report(
+                "sando contract".to_string(),
+                check_sando(&provider, config).await,
+            );
+            report(
+                "searcher balance".to_string(),
+                with_timeout(provider.get_balance(config.searcher_signer.address(), None))
+                    .await
+                    .map(|balance| format!("{} eth", format_ether(balance))),
+            );
+        }
+        Err(e) => report("wss rpc".to_string(), Err(e)),
+    }
+
+    for source in &config.mempool_sources {
+        let outcome = match with_timeout(Ws::connect(source.wss_rpc.as_str())).await {
+            Ok(ws) => check_node(&Provider::new(ws)).await,
+            Err(e) => Err(e),
+        };
+        report(format!("mempool source {}", source.name), outcome);
+    }
+
+    if let Some(url) = &config.simulation_rpc {
+        let outcome = match Provider::<Http>::try_from(url.as_str()) {
+            Ok(provider) => check_node(&provider).await,
+            Err(e) => Err(e.into()),
+        };
+        report("simulation rpc".to_string(), outcome);
+    }
+
+    let client = reqwest::Client::builder().timeout(CHECK_TIMEOUT).build()?;
+    let mut endpoints = vec![];
+    if !config.paper_trading {
+        for builder in config.builders.iter().filter(|builder| builder.enabled) {
+            endpoints.push((format!("builder {}", builder.name), builder.url.clone()));
+        }
+        if config.bundle_simulation == BundleSimulationMode::Relay {
+            endpoints.push((
+                "simulation relay".to_string(),
+                config.simulation_relay.clone(),
+            ));
+        }
+        if config.mev_share_url.is_some() {
+            endpoints.push((
+                "mev-share relay".to_string(),
+                config.mev_share_relay.clone(),
+            ));
+        }
+    }
+    if let Some(url) = &config.mev_share_url {
+        endpoints.push(("mev-share stream".to_string(), url.clone()));
+    }
+    for webhook in &config.webhooks {
+        endpoints.push((
+            format!("webhook {}", webhook.url.host_str().unwrap_or_default()),
+            webhook.url.clone(),
+        ));
+    }
+    for (name, url) in endpoints {
+        report(name, check_http(&client, url).await);
+    }
+
+    match failed {
+        0 => {
+            info!("config ok");
+            Ok(())
+        }
+        failed => Err(anyhow!("{} config check(s) failed", failed)),
+    }
+}
+
+async fn with_timeout<T, E>(future: impl Future<Output = Result<T, E>>) -> Result<T>
+where
+    E: Into<anyhow::Error>,
+{
+    tokio::time::timeout(CHECK_TIMEOUT, future)
+        .await
+        .map_err(|_| anyhow!("timed out after {:?}", CHECK_TIMEOUT))?
+        .map_err(Into::into)
+}
+
+/// Node answers with its chain and latest block
+async fn check_node<M: Middleware + 'static>(provider: &M) -> Result<String> {
+    let chain_id = with_timeout(provider.get_chainid())
+        .await
+        .map_err(|e| anyhow!("failed to get chain id: {}", e))?;
+    let block = with_timeout(provider.get_block_number())
+        .await
+        .map_err(|e| anyhow!("failed to get block number: {}", e))?;
+
+    Ok(format!("chain {} at block {}", chain_id, block))
+}
+
+/// Sando contract is deployed, and was deployed at the configured block
+async fn check_sando<M: Middleware + 'static>(provider: &M, config: &Config) -> Result<String> {
+    let code = with_timeout(provider.get_code(config.sando_address, None)).await?;
+    if code.is_empty() {
+        return Err(anyhow!("no code at {:?}", config.sando_address));
+    }
+
+    let block = with_timeout(provider.get_block_number()).await?;
+    if config.sando_inception_block > block {
+        return Err(anyhow!(
+            "inception block {} is after the latest block {}",
+            config.sando_inception_block,
+            block
+        ));
+    }
+
+    Ok(format!(
+        "{:?} ({} bytes of code)",
+        config.sando_address,
+        code.len()
+    ))
+}
+
+/// Endpoint answers http requests, whatever the status (most only accept json-rpc posts)
+async fn check_http(client: &reqwest::Client, url: Url) -> Result<String> {
+    let response = client.get(url).send().await?;
+    Ok(format!("responded with {}", response.status()))
+}
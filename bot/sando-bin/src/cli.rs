@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+use toml::Value;
+
+use crate::{
+    backtest::BacktestArgs, inventory::WithdrawArgs, replay::ReplayArgs, simulate::SimulateArgs,
+};
+
+/// Optimized sandwich bot written using Rust and Huff
+///
+/// Settings are read from the config file, then overridden by env vars (and `.env`), then by the
+/// flags below
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    /// Config file to read (defaults to `SANDO_CONFIG`, or `sando.toml` if it exists)
+    #[arg(short, long, global = true)]
+    pub config: Option<PathBuf>,
+
+    /// Validate the config's keys and addresses, check that every endpoint is reachable, then exit
+    #[arg(long, global = true)]
+    pub check_config: bool,
+
+    /// Overrides `rpc.wss`
+    #[arg(long, global = true)]
+    pub wss_rpc: Option<String>,
+
+    /// Overrides `engine.paper_trading`
+    #[arg(long, global = true)]
+    pub paper_trading: bool,
+
+    /// Overrides `strategy.backrun_only`
+    #[arg(long, global = true)]
+    pub backrun_only: bool,
+
+    /// Overrides `logging.level`
+    #[arg(long, global = true)]
+    pub log_level: Option<String>,
+
+    /// What to do, runs the bot if not set
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the bot
+    Run,
+    /// Show the eth, weth, and dust balances of the sando contract
+    Inventory,
+    /// Sweep weth or eth from the sando contract to the searcher
+    Withdraw(WithdrawArgs),
+    /// Sync every pool from scratch and rebuild the cfmms checkpoint
+    SyncPools,
+    /// Check whether a single tx could be sandwiched
+    Simulate(SimulateArgs),
+    /// Replay historical blocks through the strategy and report what it would have captured
+    Backtest(BacktestArgs),
+    /// Feed historical blocks (or a live run's captured events) through the engine, actions are
+    /// logged instead of being executed
+    Replay(ReplayArgs),
+}
+
+impl Cli {
+    /// Config keys set by the flags, as `(dotted path, value)` pairs
+    pub fn overrides(&self) -> Vec<(&'static str, Value)> {
+        let mut overrides = vec![];
+
+        if let Some(wss_rpc) = &self.wss_rpc {
+            overrides.push(("rpc.wss", Value::String(wss_rpc.clone())));
+        }
+        if self.paper_trading {
+            overrides.push(("engine.paper_trading", Value::Boolean(true)));
+        }
+        if self.backrun_only {
+            overrides.push(("strategy.backrun_only", Value::Boolean(true)));
+        }
+        if let Some(log_level) = &self.log_level {
+            overrides.push(("logging.level", Value::String(log_level.clone())));
+        }
+
+        overrides
+    }
+}
@@ -1,12 +1,211 @@
+use artemis_core::executors::{
+    multi_builder_executor::BuilderConfig,
+    webhook_executor::{WebhookConfig, WebhookFormat},
+};
+use cfmms::dex::{Dex, DexVariant};
 use dotenv::dotenv;
+use log::LevelFilter;
 use reqwest::Url;
-use std::{env, str::FromStr};
+use serde::Deserialize;
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+    time::Duration,
+};
+use toml::{Table, Value};
 
 use anyhow::{anyhow, Result};
 use ethers::{
+    providers::{Http, Provider},
     signers::LocalWallet,
-    types::{Address, U64},
+    types::{Address, H256, U256, U64},
+    utils::parse_ether,
 };
+use strategy::{
+    simulator::{backend::SimulationBackend, rpc_backend::RpcBackend},
+    types::{BribePolicy, RiskLimits, SalmonellaPolicy, StratConfig},
+};
+
+use crate::cli::Cli;
+
+/// Config file read when neither `--config` nor `SANDO_CONFIG` is set (skipped if missing)
+const DEFAULT_CONFIG_PATH: &str = "sando.toml";
+
+/// The only base token the sando contract supports
+pub(crate) const WETH_ADDRESS: &str = "0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2";
+
+/// Builders that bundles are sent to when `relays.builders` is not set, any other builder has to be
+/// configured explicitly
+const DEFAULT_BUILDERS: [(&str, &str); 1] = [("flashbots", "https://relay.flashbots.net")];
+
+/// How an env var's value is turned into the config key it overrides
+#[derive(Debug, Clone, Copy)]
+enum EnvKind {
+    String,
+    Integer,
+    Boolean,
+    Float,
+    /// Comma separated strings
+    List,
+}
+
+/// Env vars that override config keys, as `(env var, dotted path, kind)`
+const ENV_OVERRIDES: &[(&str, &str, EnvKind)] = &[
+    ("WSS_RPC", "rpc.wss", EnvKind::String),
+    ("SIMULATION_RPC", "rpc.simulation", EnvKind::String),
+    ("SEARCHER_PRIVATE_KEY", "keys.searcher", EnvKind::String),
+    ("FLASHBOTS_AUTH_KEY", "keys.flashbots_auth", EnvKind::String),
+    ("SANDWICH_CONTRACT", "sando.address", EnvKind::String),
+    (
+        "SANDWICH_INCEPTION_BLOCK",
+        "sando.inception_block",
+        EnvKind::Integer,
+    ),
+    (
+        "BUNDLE_SIMULATION",
+        "relays.bundle_simulation",
+        EnvKind::String,
+    ),
+    (
+        "BUNDLE_SIMULATION_TOLERANCE_BPS",
+        "relays.bundle_simulation_tolerance_bps",
+        EnvKind::Integer,
+    ),
+    (
+        "SIMULATION_RELAY",
+        "relays.simulation_relay",
+        EnvKind::String,
+    ),
+    ("MEV_SHARE_URL", "relays.mev_share_url", EnvKind::String),
+    ("MEV_SHARE_RELAY", "relays.mev_share_relay", EnvKind::String),
+    ("BASE_TOKENS", "strategy.base_tokens", EnvKind::List),
+    (
+        "DIFFERENTIAL_CHECK",
+        "strategy.differential_check",
+        EnvKind::Boolean,
+    ),
+    ("BACKRUN_ONLY", "strategy.backrun_only", EnvKind::Boolean),
+    (
+        "SALMONELLA_FATAL_OPCODES",
+        "strategy.salmonella.fatal_opcodes",
+        EnvKind::List,
+    ),
+    (
+        "SALMONELLA_WARNING_OPCODES",
+        "strategy.salmonella.warning_opcodes",
+        EnvKind::List,
+    ),
+    (
+        "SALMONELLA_EXEMPT_ADDRESSES",
+        "strategy.salmonella.exempt_addresses",
+        EnvKind::List,
+    ),
+    (
+        "SALMONELLA_EXEMPT_CODEHASHES",
+        "strategy.salmonella.exempt_codehashes",
+        EnvKind::List,
+    ),
+    ("BRIBE_REVENUE_SHARE", "bribe.revenue_share", EnvKind::Float),
+    ("BRIBE_DUST_OVERPAY", "bribe.dust_overpay", EnvKind::String),
+    ("MAX_INPUT", "risk.max_input", EnvKind::String),
+    ("MIN_REVENUE", "risk.min_revenue", EnvKind::String),
+    ("PAPER_TRADING", "engine.paper_trading", EnvKind::Boolean),
+    (
+        "EVENT_CHANNEL_CAPACITY",
+        "engine.event_channel_capacity",
+        EnvKind::Integer,
+    ),
+    (
+        "ACTION_CHANNEL_CAPACITY",
+        "engine.action_channel_capacity",
+        EnvKind::Integer,
+    ),
+    ("BACKPRESSURE", "engine.backpressure", EnvKind::String),
+    ("DATABASE_PATH", "storage.database", EnvKind::String),
+    ("CAPTURE_PATH", "storage.capture", EnvKind::String),
+    ("LOG_LEVEL", "logging.level", EnvKind::String),
+    ("LOG_FILE", "logging.file", EnvKind::String),
+    (
+        "DISCORD_WEBHOOK",
+        "notifications.discord_webhook",
+        EnvKind::String,
+    ),
+    ("WEBHOOK_URL", "notifications.webhook", EnvKind::String),
+    (
+        "NOTIFY_BATCH_INTERVAL_MS",
+        "notifications.batch_interval_ms",
+        EnvKind::Integer,
+    ),
+    (
+        "NOTIFY_MAX_BATCH_SIZE",
+        "notifications.max_batch_size",
+        EnvKind::Integer,
+    ),
+    (
+        "NOTIFY_MIN_INTERVAL_MS",
+        "notifications.min_interval_ms",
+        EnvKind::Integer,
+    ),
+];
+
+/// How bundles are simulated before being sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BundleSimulationMode {
+    /// Bundles are sent without being simulated
+    Disabled,
+    /// Simulate using the relay's `eth_callBundle`
+    Relay,
+    /// Simulate locally using revm on a fork
+    Local,
+}
+
+impl FromStr for BundleSimulationMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" | "disabled" => Ok(Self::Disabled),
+            "relay" => Ok(Self::Relay),
+            "local" => Ok(Self::Local),
+            _ => Err(anyhow!("unknown bundle simulation mode \"{}\"", s)),
+        }
+    }
+}
+
+/// What happens to events when the strategy falls behind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressureMode {
+    /// Skip the oldest events
+    DropOldest,
+    /// Collectors wait for the strategy to catch up
+    Block,
+    /// Never skip new blocks, skip the oldest pending txs
+    PrioritiseBlocks,
+}
+
+impl FromStr for BackpressureMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "drop-oldest" => Ok(Self::DropOldest),
+            "block" => Ok(Self::Block),
+            "prioritise-blocks" => Ok(Self::PrioritiseBlocks),
+            _ => Err(anyhow!("unknown backpressure mode \"{}\"", s)),
+        }
+    }
+}
+
+/// Extra node that pending txs are streamed from
+#[derive(Debug, Clone)]
+pub struct MempoolSourceConfig {
+    pub name: String,
+    pub wss_rpc: Url,
+    /// Subscribe to `newPendingTransactionsWithBody` instead of `newPendingTransactions`
+    pub with_body: bool,
+}
 
 pub struct Config {
     pub searcher_signer: LocalWallet,
@@ -14,38 +213,368 @@ pub struct Config {
     pub sando_address: Address,
     pub bundle_signer: LocalWallet,
     pub wss_rpc: Url,
-    pub discord_webhook: String,
+    /// Webhooks that landed sandwiches, failures, inventory changes, and health alerts are posted to
+    pub webhooks: Vec<WebhookConfig>,
+    pub salmonella_policy: SalmonellaPolicy,
+    pub differential_check: bool,
+    pub bundle_simulation: BundleSimulationMode,
+    pub bundle_simulation_tolerance_bps: u64,
+    /// Relay whose `eth_callBundle` is used when `bundle_simulation` is `Relay`
+    pub simulation_relay: Url,
+    pub builders: Vec<BuilderConfig<LocalWallet>>,
+    pub database_path: Option<PathBuf>,
+    pub paper_trading: bool,
+    pub event_channel_capacity: usize,
+    pub action_channel_capacity: usize,
+    pub backpressure: BackpressureMode,
+    pub mempool_sources: Vec<MempoolSourceConfig>,
+    pub mev_share_url: Option<Url>,
+    pub mev_share_relay: Url,
+    pub backrun_only: bool,
+    pub capture_path: Option<PathBuf>,
+    /// Node that frontrun inputs are simulated on with `eth_callMany` (simulated locally if `None`)
+    pub simulation_rpc: Option<Url>,
+    /// Which dexes' pools are synced and sandwiched (mainnet dexes are used if `None`)
+    pub dexes: Option<Vec<Dex>>,
+    pub bribe_policy: BribePolicy,
+    pub risk_limits: RiskLimits,
+    pub log_level: LevelFilter,
+    /// File that logs are also written to (stdout only if `None`)
+    pub log_file: Option<PathBuf>,
+}
+
+/// Layout of the config file, every key is optional so that it can also come from env vars
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    rpc: RpcSection,
+    keys: KeysSection,
+    sando: SandoSection,
+    relays: RelaysSection,
+    strategy: StrategySection,
+    bribe: BribeSection,
+    risk: RiskSection,
+    engine: EngineSection,
+    storage: StorageSection,
+    logging: LoggingSection,
+    notifications: NotificationsSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RpcSection {
+    wss: Option<String>,
+    simulation: Option<String>,
+    mempool_sources: Vec<MempoolSourceSection>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MempoolSourceSection {
+    name: String,
+    url: String,
+    #[serde(default)]
+    with_body: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct KeysSection {
+    searcher: Option<String>,
+    flashbots_auth: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SandoSection {
+    address: Option<String>,
+    inception_block: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RelaysSection {
+    builders: Option<Vec<BuilderSection>>,
+    bundle_simulation: Option<String>,
+    bundle_simulation_tolerance_bps: Option<u64>,
+    simulation_relay: Option<String>,
+    mev_share_url: Option<String>,
+    mev_share_relay: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct BuilderSection {
+    name: String,
+    url: String,
+    auth_key: Option<String>,
+    timeout_ms: Option<u64>,
+    enabled: Option<bool>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StrategySection {
+    dexes: Option<Vec<DexSection>>,
+    base_tokens: Option<Vec<String>>,
+    differential_check: bool,
+    backrun_only: bool,
+    salmonella: SalmonellaSection,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DexSection {
+    /// `uniswap_v2` or `uniswap_v3`
+    factory: String,
+    variant: String,
+    #[serde(default)]
+    creation_block: u64,
+    /// Swap fee in bips of a bip (defaults to uniswap v2's 300)
+    fee: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SalmonellaSection {
+    fatal_opcodes: Vec<String>,
+    warning_opcodes: Vec<String>,
+    exempt_addresses: Vec<String>,
+    exempt_codehashes: Vec<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct BribeSection {
+    revenue_share: Option<f64>,
+    dust_overpay: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct RiskSection {
+    max_input: Option<String>,
+    min_revenue: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct EngineSection {
+    paper_trading: bool,
+    event_channel_capacity: Option<usize>,
+    action_channel_capacity: Option<usize>,
+    backpressure: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct StorageSection {
+    database: Option<PathBuf>,
+    capture: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LoggingSection {
+    level: Option<String>,
+    file: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct NotificationsSection {
+    discord_webhook: Option<String>,
+    webhook: Option<String>,
+    batch_interval_ms: Option<u64>,
+    max_batch_size: Option<usize>,
+    min_interval_ms: Option<u64>,
 }
 
 impl Config {
-    pub async fn read_from_dotenv() -> Result<Self> {
+    /// Read the config file, then apply the env var (and `.env`) overrides, then `cli`'s flags
+    pub fn load(cli: &Cli) -> Result<Self> {
         dotenv().ok();
 
-        let get_env = |var| {
-            env::var(var).map_err(|_| anyhow!("Required environment variable \"{}\" not set", var))
+        let path = match &cli.config {
+            Some(path) => Some(path.clone()),
+            None => get_env("SANDO_CONFIG").map(PathBuf::from),
         };
+        let table = read_config_file(path.as_deref())?;
 
-        let searcher_signer = get_env("SEARCHER_PRIVATE_KEY")?
-            .parse::<LocalWallet>()
-            .map_err(|_| anyhow!("Failed to parse \"SEARCHER_PRIVATE_KEY\""))?;
+        Self::from_table(table, |var| env::var(var).ok(), cli.overrides())
+    }
+
+    /// Apply the env var overrides (read using `env`), then `overrides`, on top of the config
+    /// file's `table`
+    fn from_table(
+        mut table: Table,
+        env: impl Fn(&str) -> Option<String>,
+        overrides: Vec<(&str, Value)>,
+    ) -> Result<Self> {
+        apply_env_overrides(&mut table, env)?;
+        for (path, value) in overrides {
+            set_key(&mut table, path, value);
+        }
 
-        let sando_inception_block = get_env("SANDWICH_INCEPTION_BLOCK")?
-            .parse::<u64>()
-            .map(U64::from)
-            .map_err(|_| anyhow!("Failed to parse \"SANDWICH_INCEPTION_BLOCK\" into u64"))?;
+        let file: FileConfig = Value::Table(table)
+            .try_into()
+            .map_err(|e| anyhow!("Invalid config: {}", e))?;
 
-        let sando_address = Address::from_str(&get_env("SANDWICH_CONTRACT")?)
-            .map_err(|_| anyhow!("Failed to parse \"SANDWICH_CONTRACT\""))?;
+        Self::resolve(file)
+    }
+
+    /// Validate every key and fill in the defaults
+    fn resolve(file: FileConfig) -> Result<Self> {
+        let searcher_signer = required("keys.searcher", file.keys.searcher)?
+            .parse::<LocalWallet>()
+            .map_err(|_| parse_error("keys.searcher"))?;
 
-        let bundle_signer = get_env("FLASHBOTS_AUTH_KEY")?
+        let bundle_signer = required("keys.flashbots_auth", file.keys.flashbots_auth)?
             .parse::<LocalWallet>()
-            .map_err(|_| anyhow!("Failed to parse \"FLASHBOTS_AUTH_KEY\""))?;
+            .map_err(|_| parse_error("keys.flashbots_auth"))?;
+
+        let sando_address = Address::from_str(&required("sando.address", file.sando.address)?)
+            .map_err(|_| parse_error("sando.address"))?;
+
+        let sando_inception_block = U64::from(required(
+            "sando.inception_block",
+            file.sando.inception_block,
+        )?);
+
+        let wss_rpc = parse_url("rpc.wss", &required("rpc.wss", file.rpc.wss)?)?;
+
+        let simulation_rpc = file
+            .rpc
+            .simulation
+            .map(|url| parse_url("rpc.simulation", &url))
+            .transpose()?;
+
+        let mempool_sources = read_mempool_sources(file.rpc.mempool_sources)?;
+
+        let bundle_simulation = match file.relays.bundle_simulation {
+            Some(mode) => mode.parse()?,
+            None => BundleSimulationMode::Disabled,
+        };
+        let bundle_simulation_tolerance_bps =
+            file.relays.bundle_simulation_tolerance_bps.unwrap_or(100);
+
+        let simulation_relay = parse_url(
+            "relays.simulation_relay",
+            file.relays
+                .simulation_relay
+                .as_deref()
+                .unwrap_or("https://relay.flashbots.net"),
+        )?;
+
+        let builders = read_builders(file.relays.builders, &bundle_signer)?;
+
+        let mev_share_url = file
+            .relays
+            .mev_share_url
+            .map(|url| parse_url("relays.mev_share_url", &url))
+            .transpose()?;
+
+        let mev_share_relay = parse_url(
+            "relays.mev_share_relay",
+            file.relays
+                .mev_share_relay
+                .as_deref()
+                .unwrap_or("https://relay.flashbots.net"),
+        )?;
+
+        let backrun_only = file.strategy.backrun_only;
+        if backrun_only && mev_share_url.is_none() {
+            return Err(anyhow!(
+                "{} requires {} to be set",
+                key_name("strategy.backrun_only"),
+                key_name("relays.mev_share_url")
+            ));
+        }
 
-        let wss_rpc = get_env("WSS_RPC")?
-            .parse()
-            .map_err(|_| anyhow!("Failed to parse \"WSS_RPC\""))?;
+        let dexes = file.strategy.dexes.map(read_dexes).transpose()?;
 
-        let discord_webhook = get_env("DISCORD_WEBHOOK")?;
+        // the sando contract only ever swaps from and back into weth
+        let weth = Address::from_str(WETH_ADDRESS).expect("valid weth address");
+        for token in file.strategy.base_tokens.unwrap_or_default() {
+            let token = Address::from_str(&token).map_err(|_| {
+                anyhow!(
+                    "Failed to parse \"{}\" in {}",
+                    token,
+                    key_name("strategy.base_tokens")
+                )
+            })?;
+            if token != weth {
+                return Err(anyhow!(
+                    "{} only supports weth ({:?}), found {:?}",
+                    key_name("strategy.base_tokens"),
+                    weth,
+                    token
+                ));
+            }
+        }
+
+        let salmonella_policy = read_salmonella_policy(file.strategy.salmonella)?;
+
+        let mut bribe_policy = BribePolicy::default();
+        if let Some(share) = file.bribe.revenue_share {
+            if !(0.0..=1.0).contains(&share) {
+                return Err(anyhow!(
+                    "{} must be between 0 and 1",
+                    key_name("bribe.revenue_share")
+                ));
+            }
+            bribe_policy.revenue_share_ppb = (share * 1e9).round() as u64;
+        }
+        if let Some(dust_overpay) = file.bribe.dust_overpay {
+            bribe_policy.dust_overpay = parse_amount("bribe.dust_overpay", &dust_overpay)?;
+        }
+
+        let risk_limits = RiskLimits {
+            max_input: file
+                .risk
+                .max_input
+                .map(|amount| parse_amount("risk.max_input", &amount))
+                .transpose()?,
+            min_revenue: file
+                .risk
+                .min_revenue
+                .map(|amount| parse_amount("risk.min_revenue", &amount))
+                .transpose()?
+                .unwrap_or_default(),
+        };
+
+        let get_capacity = |path, capacity: Option<usize>| match capacity {
+            Some(0) => Err(anyhow!("{} must be non zero", key_name(path))),
+            Some(capacity) => Ok(capacity),
+            None => Ok(512),
+        };
+        let event_channel_capacity = get_capacity(
+            "engine.event_channel_capacity",
+            file.engine.event_channel_capacity,
+        )?;
+        let action_channel_capacity = get_capacity(
+            "engine.action_channel_capacity",
+            file.engine.action_channel_capacity,
+        )?;
+
+        let backpressure = match file.engine.backpressure {
+            Some(mode) => mode.parse()?,
+            None => BackpressureMode::DropOldest,
+        };
+
+        let webhooks = read_webhooks(file.notifications)?;
+
+        let log_level = match file.logging.level {
+            Some(level) => level
+                .parse::<LevelFilter>()
+                .map_err(|_| parse_error("logging.level"))?,
+            None => LevelFilter::Info,
+        };
+        let log_file = match file.logging.file {
+            Some(path) if path.as_os_str().is_empty() => None,
+            Some(path) => Some(path),
+            None => Some(PathBuf::from("output.log")),
+        };
 
         Ok(Self {
             searcher_signer,
@@ -53,7 +582,531 @@ impl Config {
             sando_address,
             bundle_signer,
             wss_rpc,
-            discord_webhook,
+            webhooks,
+            salmonella_policy,
+            differential_check: file.strategy.differential_check,
+            bundle_simulation,
+            bundle_simulation_tolerance_bps,
+            simulation_relay,
+            builders,
+            database_path: file.storage.database,
+            paper_trading: file.engine.paper_trading,
+            event_channel_capacity,
+            action_channel_capacity,
+            backpressure,
+            mempool_sources,
+            mev_share_url,
+            mev_share_relay,
+            backrun_only,
+            capture_path: file.storage.capture,
+            simulation_rpc,
+            dexes,
+            bribe_policy,
+            risk_limits,
+            log_level,
+            log_file,
         })
     }
+
+    /// Strategy config shared by every command (opportunities aren't recorded)
+    pub fn strat_config(&self) -> Result<StratConfig> {
+        Ok(StratConfig {
+            sando_address: self.sando_address,
+            sando_inception_block: self.sando_inception_block,
+            searcher_signer: self.searcher_signer.clone(),
+            salmonella_policy: self.salmonella_policy.clone(),
+            differential_check: self.differential_check,
+            backrun_only: self.backrun_only,
+            store: None,
+            dexes: self.dexes.clone(),
+            simulation_backend: self.simulation_backend()?,
+            bribe_policy: self.bribe_policy,
+            risk_limits: self.risk_limits,
+            local_state_diffs: false,
+            pool_checkpoints: true,
+        })
+    }
+
+    /// Backend that the strategy searches for optimal inputs with, `None` to simulate locally
+    pub fn simulation_backend(&self) -> Result<Option<Arc<dyn SimulationBackend>>> {
+        match &self.simulation_rpc {
+            Some(url) => {
+                let provider = Provider::<Http>::try_from(url.as_str())
+                    .map_err(|_| parse_error("rpc.simulation"))?;
+                Ok(Some(Arc::new(RpcBackend::new(Arc::new(provider)))))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+/// Read an env var, treating empty values as unset
+fn get_env(var: &str) -> Option<String> {
+    env::var(var).ok().filter(|value| !value.trim().is_empty())
+}
+
+/// Parse the config file at `path`, or the default one if it exists
+fn read_config_file(path: Option<&Path>) -> Result<Table> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG_PATH).exists() => Path::new(DEFAULT_CONFIG_PATH),
+        None => return Ok(Table::new()),
+    };
+
+    let contents = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read config file {:?}: {}", path, e))?;
+
+    toml::from_str(&contents).map_err(|e| anyhow!("Failed to parse config file {:?}: {}", path, e))
+}
+
+/// Overwrite config keys with the env vars that are set (empty values are treated as unset)
+fn apply_env_overrides(table: &mut Table, env: impl Fn(&str) -> Option<String>) -> Result<()> {
+    let get_env = |var: &str| env(var).filter(|value| !value.trim().is_empty());
+
+    for (var, path, kind) in ENV_OVERRIDES {
+        let raw = match get_env(var) {
+            Some(raw) => raw,
+            None => continue,
+        };
+        let raw = raw.trim();
+
+        let value = match kind {
+            EnvKind::String => Value::String(raw.to_string()),
+            EnvKind::Integer => Value::Integer(
+                raw.parse()
+                    .map_err(|_| anyhow!("Failed to parse \"{}\" into an integer", var))?,
+            ),
+            EnvKind::Boolean => Value::Boolean(
+                raw.parse()
+                    .map_err(|_| anyhow!("Failed to parse \"{}\" into bool", var))?,
+            ),
+            EnvKind::Float => Value::Float(
+                raw.parse()
+                    .map_err(|_| anyhow!("Failed to parse \"{}\" into a float", var))?,
+            ),
+            EnvKind::List => Value::Array(
+                split_list(raw)
+                    .map(|item| Value::String(item.to_string()))
+                    .collect(),
+            ),
+        };
+        set_key(table, path, value);
+    }
+
+    // comma separated `name=url` pairs, replacing the file's lists
+    for (var, path, url_key) in [
+        ("BUILDERS", "relays.builders", "url"),
+        ("MEMPOOL_SOURCES", "rpc.mempool_sources", "url"),
+    ] {
+        let raw = match get_env(var) {
+            Some(raw) => raw,
+            None => continue,
+        };
+
+        let entries = split_list(&raw)
+            .map(|entry| {
+                let (name, url) = entry.split_once('=').ok_or(anyhow!(
+                    "Failed to parse \"{}\" in \"{}\"",
+                    entry,
+                    var
+                ))?;
+
+                let mut entry = Table::new();
+                entry.insert("name".to_string(), Value::String(name.trim().to_string()));
+                entry.insert(url_key.to_string(), Value::String(url.trim().to_string()));
+                Ok(Value::Table(entry))
+            })
+            .collect::<Result<_>>()?;
+        set_key(table, path, Value::Array(entries));
+    }
+
+    Ok(())
+}
+
+/// Set the key at the dotted `path`, creating the tables on the way
+fn set_key(table: &mut Table, path: &str, value: Value) {
+    let mut keys: Vec<&str> = path.split('.').collect();
+    let key = keys.pop().expect("non empty path");
+
+    let mut table = table;
+    for parent in keys {
+        let entry = table
+            .entry(parent.to_string())
+            .or_insert_with(|| Value::Table(Table::new()));
+        if !entry.is_table() {
+            *entry = Value::Table(Table::new());
+        }
+        table = entry.as_table_mut().expect("just made a table");
+    }
+
+    table.insert(key.to_string(), value);
+}
+
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+}
+
+/// Name of the key at `path` for errors, along with the env var overriding it
+fn key_name(path: &str) -> String {
+    match ENV_OVERRIDES.iter().find(|(_, key, _)| *key == path) {
+        Some((var, _, _)) => format!("\"{}\" (env \"{}\")", path, var),
+        None => format!("\"{}\"", path),
+    }
+}
+
+fn required<T>(path: &str, value: Option<T>) -> Result<T> {
+    value.ok_or_else(|| anyhow!("Required config key {} not set", key_name(path)))
+}
+
+fn parse_error(path: &str) -> anyhow::Error {
+    anyhow!("Failed to parse {}", key_name(path))
+}
+
+fn parse_url(path: &str, url: &str) -> Result<Url> {
+    url.parse().map_err(|_| parse_error(path))
+}
+
+/// Parse an amount given in ether into wei
+fn parse_amount(path: &str, amount: &str) -> Result<U256> {
+    parse_ether(amount)
+        .map_err(|_| anyhow!("Failed to parse {} into an ether amount", key_name(path)))
+}
+
+/// Build salmonella policy on top of the default one
+fn read_salmonella_policy(section: SalmonellaSection) -> Result<SalmonellaPolicy> {
+    let mut policy = SalmonellaPolicy::default();
+
+    for name in section.fatal_opcodes {
+        policy.add_fatal_opcode(SalmonellaPolicy::parse_opcode(&name)?);
+    }
+
+    for name in section.warning_opcodes {
+        policy.add_warning_opcode(SalmonellaPolicy::parse_opcode(&name)?);
+    }
+
+    for address in section.exempt_addresses {
+        let address = Address::from_str(&address).map_err(|_| {
+            anyhow!(
+                "Failed to parse \"{}\" in {}",
+                address,
+                key_name("strategy.salmonella.exempt_addresses")
+            )
+        })?;
+        policy.exempt_address(address);
+    }
+
+    for codehash in section.exempt_codehashes {
+        let codehash = H256::from_str(&codehash).map_err(|_| {
+            anyhow!(
+                "Failed to parse \"{}\" in {}",
+                codehash,
+                key_name("strategy.salmonella.exempt_codehashes")
+            )
+        })?;
+        policy.exempt_codehash(codehash);
+    }
+
+    Ok(policy)
+}
+
+/// Build the builders (only the flashbots relay if none are configured), each builder's auth key, timeout,
+/// and enabled flag can be overridden using `BUILDER_<NAME>_AUTH_KEY`, `BUILDER_<NAME>_TIMEOUT_MS`,
+/// and `BUILDER_<NAME>_ENABLED`
+fn read_builders(
+    builders: Option<Vec<BuilderSection>>,
+    default_signer: &LocalWallet,
+) -> Result<Vec<BuilderConfig<LocalWallet>>> {
+    let builders = builders.unwrap_or_else(|| {
+        DEFAULT_BUILDERS
+            .iter()
+            .map(|(name, url)| BuilderSection {
+                name: name.to_string(),
+                url: url.to_string(),
+                auth_key: None,
+                timeout_ms: None,
+                enabled: None,
+            })
+            .collect()
+    });
+
+    builders
+        .into_iter()
+        .map(|builder| {
+            let var = |suffix: &str| format!("BUILDER_{}_{}", builder.name.to_uppercase(), suffix);
+
+            let url = builder
+                .url
+                .parse()
+                .map_err(|_| anyhow!("Failed to parse url of builder \"{}\"", builder.name))?;
+
+            let signer = match get_env(&var("AUTH_KEY")).or(builder.auth_key) {
+                Some(key) => key.parse::<LocalWallet>().map_err(|_| {
+                    anyhow!("Failed to parse auth key of builder \"{}\"", builder.name)
+                })?,
+                None => default_signer.clone(),
+            };
+
+            let timeout = match get_env(&var("TIMEOUT_MS")) {
+                Some(timeout) => timeout
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("Failed to parse \"{}\" into u64", var("TIMEOUT_MS")))?,
+                None => builder.timeout_ms.unwrap_or(2000),
+            };
+
+            let enabled = match get_env(&var("ENABLED")) {
+                Some(enabled) => enabled
+                    .parse::<bool>()
+                    .map_err(|_| anyhow!("Failed to parse \"{}\" into bool", var("ENABLED")))?,
+                None => builder.enabled.unwrap_or(true),
+            };
+
+            Ok(BuilderConfig {
+                name: builder.name,
+                url,
+                signer,
+                timeout: Duration::from_millis(timeout),
+                enabled,
+            })
+        })
+        .collect()
+}
+
+/// Build the extra mempool sources, each source's subscription can be overridden using
+/// `MEMPOOL_SOURCE_<NAME>_WITH_BODY`
+fn read_mempool_sources(sources: Vec<MempoolSourceSection>) -> Result<Vec<MempoolSourceConfig>> {
+    sources
+        .into_iter()
+        .map(|source| {
+            let wss_rpc = source.url.parse().map_err(|_| {
+                anyhow!("Failed to parse url of mempool source \"{}\"", source.name)
+            })?;
+
+            let var = format!("MEMPOOL_SOURCE_{}_WITH_BODY", source.name.to_uppercase());
+            let with_body = match get_env(&var) {
+                Some(with_body) => with_body
+                    .parse::<bool>()
+                    .map_err(|_| anyhow!("Failed to parse \"{}\" into bool", var))?,
+                None => source.with_body,
+            };
+
+            Ok(MempoolSourceConfig {
+                name: source.name,
+                wss_rpc,
+                with_body,
+            })
+        })
+        .collect()
+}
+
+/// Build the discord and generic webhooks, both share the batching and rate limiting settings
+fn read_webhooks(section: NotificationsSection) -> Result<Vec<WebhookConfig>> {
+    let urls = [
+        (
+            "notifications.discord_webhook",
+            section.discord_webhook,
+            WebhookFormat::Discord,
+        ),
+        (
+            "notifications.webhook",
+            section.webhook,
+            WebhookFormat::Generic,
+        ),
+    ];
+
+    let mut webhooks = vec![];
+    for (path, url, format) in urls {
+        let url = match url {
+            Some(url) if !url.is_empty() => parse_url(path, &url)?,
+            _ => continue,
+        };
+
+        let mut webhook = WebhookConfig::new(url, format);
+        if let Some(batch_interval_ms) = section.batch_interval_ms {
+            webhook.batch_interval = Duration::from_millis(batch_interval_ms);
+        }
+        match section.max_batch_size {
+            Some(0) => {
+                return Err(anyhow!(
+                    "{} must be non zero",
+                    key_name("notifications.max_batch_size")
+                ))
+            }
+            Some(max_batch_size) => webhook.max_batch_size = max_batch_size,
+            None => {}
+        }
+        if let Some(min_interval_ms) = section.min_interval_ms {
+            webhook.min_interval = Duration::from_millis(min_interval_ms);
+        }
+        webhooks.push(webhook);
+    }
+
+    Ok(webhooks)
+}
+
+/// Build the dexes whose pools are synced
+fn read_dexes(dexes: Vec<DexSection>) -> Result<Vec<Dex>> {
+    dexes
+        .into_iter()
+        .map(|dex| {
+            let factory = Address::from_str(&dex.factory).map_err(|_| {
+                anyhow!(
+                    "Failed to parse factory \"{}\" in \"strategy.dexes\"",
+                    dex.factory
+                )
+            })?;
+
+            let variant = match dex.variant.to_lowercase().as_str() {
+                "uniswap_v2" => DexVariant::UniswapV2,
+                "uniswap_v3" => DexVariant::UniswapV3,
+                _ => {
+                    return Err(anyhow!(
+                        "unknown dex variant \"{}\" in \"strategy.dexes\" (uniswap_v2|uniswap_v3)",
+                        dex.variant
+                    ))
+                }
+            };
+
+            Ok(Dex::new(
+                factory,
+                variant,
+                dex.creation_block,
+                Some(dex.fee.unwrap_or(300)),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"
+        [rpc]
+        wss = "ws://file:8545"
+
+        [keys]
+        searcher = "0xac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80"
+        flashbots_auth = "0x59c6995e998f97a5a0044966f0945389dc9e86dae88c7a8412f4603b6b78690d"
+
+        [sando]
+        address = "0x5FbDB2315678afecb367f032d93F642f64180aa3"
+        inception_block = 1
+    "#;
+
+    fn load(
+        extra: &str,
+        env: &[(&str, &str)],
+        overrides: Vec<(&'static str, Value)>,
+    ) -> Result<Config> {
+        let table: Table = toml::from_str(&format!("{}\n{}", CONFIG, extra)).unwrap();
+        let env = |var: &str| {
+            env.iter()
+                .find(|(name, _)| *name == var)
+                .map(|(_, value)| value.to_string())
+        };
+        Config::from_table(table, env, overrides)
+    }
+
+    fn load_err(extra: &str, env: &[(&str, &str)]) -> String {
+        match load(extra, env, vec![]) {
+            Ok(_) => panic!("config should be rejected"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn env_overrides_file_and_flags_override_env() {
+        let config = load("", &[], vec![]).unwrap();
+        assert_eq!(config.wss_rpc.as_str(), "ws://file:8545/");
+        assert_eq!(config.sando_inception_block, U64::from(1));
+
+        let env = [
+            ("WSS_RPC", "ws://env:8545"),
+            ("SANDWICH_INCEPTION_BLOCK", "5"),
+        ];
+        let config = load("", &env, vec![]).unwrap();
+        assert_eq!(config.wss_rpc.as_str(), "ws://env:8545/");
+        assert_eq!(config.sando_inception_block, U64::from(5));
+
+        let overrides = vec![("rpc.wss", Value::String("ws://cli:8545".to_string()))];
+        let config = load("", &env, overrides).unwrap();
+        assert_eq!(config.wss_rpc.as_str(), "ws://cli:8545/");
+        assert_eq!(config.sando_inception_block, U64::from(5));
+    }
+
+    #[test]
+    fn empty_env_values_are_ignored() {
+        let env = [("WSS_RPC", ""), ("SANDWICH_INCEPTION_BLOCK", "  ")];
+        let config = load("", &env, vec![]).unwrap();
+        assert_eq!(config.wss_rpc.as_str(), "ws://file:8545/");
+        assert_eq!(config.sando_inception_block, U64::from(1));
+
+        let err = load_err("", &[("SANDWICH_INCEPTION_BLOCK", "soon")]);
+        assert!(err.contains("SANDWICH_INCEPTION_BLOCK"), "{}", err);
+    }
+
+    #[test]
+    fn unknown_keys_are_rejected() {
+        let err = load_err("[engine]\npaper_trade = true", &[]);
+        assert!(err.contains("unknown field `paper_trade`"), "{}", err);
+
+        let err = load_err("[strategies]\nbackrun_only = true", &[]);
+        assert!(err.contains("unknown field `strategies`"), "{}", err);
+    }
+
+    #[test]
+    fn invalid_values_name_their_key() {
+        let overrides = vec![("keys.searcher", Value::String("0x1234".to_string()))];
+        let err = load("", &[], overrides).err().unwrap().to_string();
+        assert!(err.contains("\"keys.searcher\""), "{}", err);
+
+        let err = load_err("", &[("SANDWICH_CONTRACT", "0xsando")]);
+        assert!(
+            err.contains("\"sando.address\" (env \"SANDWICH_CONTRACT\")"),
+            "{}",
+            err
+        );
+
+        let err = load_err("[risk]\nmax_input = \"ten\"", &[]);
+        assert!(err.contains("\"risk.max_input\""), "{}", err);
+        assert!(err.contains("ether amount"), "{}", err);
+
+        let config = load("[risk]\nmax_input = \"1.5\"", &[], vec![]).unwrap();
+        assert_eq!(
+            config.risk_limits.max_input,
+            Some(parse_ether("1.5").unwrap())
+        );
+    }
+
+    #[test]
+    fn backrun_only_requires_mev_share_url() {
+        let err = load_err("", &[("BACKRUN_ONLY", "true")]);
+        assert!(err.contains("\"strategy.backrun_only\""), "{}", err);
+        assert!(err.contains("\"relays.mev_share_url\""), "{}", err);
+
+        let env = [
+            ("BACKRUN_ONLY", "true"),
+            ("MEV_SHARE_URL", "https://mev-share.flashbots.net"),
+        ];
+        let config = load("", &env, vec![]).unwrap();
+        assert!(config.backrun_only);
+
+        let overrides = vec![("strategy.backrun_only", Value::Boolean(true))];
+        assert!(load("", &[], overrides).is_err());
+    }
+
+    #[test]
+    fn base_tokens_only_accept_weth() {
+        let err = load_err("", &[("BASE_TOKENS", "0xdead")]);
+        assert!(err.contains("Failed to parse \"0xdead\""), "{}", err);
+
+        let usdc = "0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48";
+        let err = load_err("", &[("BASE_TOKENS", usdc)]);
+        assert!(err.contains("only supports weth"), "{}", err);
+
+        let base_tokens = format!("{}, {}", WETH_ADDRESS.to_lowercase(), WETH_ADDRESS);
+        load("", &[("BASE_TOKENS", &base_tokens)], vec![]).unwrap();
+    }
 }
@@ -3,6 +3,8 @@ use colored::Colorize;
 use fern::colors::{Color, ColoredLevelConfig};
 use indoc::indoc;
 use log::LevelFilter;
+use std::path::Path;
+use tokio::signal::unix::{signal, SignalKind};
 
 pub fn print_banner() {
     let banner = indoc! {
@@ -26,7 +28,8 @@ ______ __   __     _____       ___  ___ _____  _   _  _____  _____  _      _____
     log::info!("{}", format!("{}", banner.green().bold()));
 }
 
-pub fn setup_logger() -> Result<()> {
+/// Log our crates at `level` to stdout, and to `file` if set
+pub fn setup_logger(level: LevelFilter, file: Option<&Path>) -> Result<()> {
     let colors = ColoredLevelConfig {
         trace: Color::Cyan,
         debug: Color::Magenta,
@@ -36,7 +39,7 @@ pub fn setup_logger() -> Result<()> {
         ..ColoredLevelConfig::new()
     };
 
-    fern::Dispatch::new()
+    let mut dispatch = fern::Dispatch::new()
         .format(move |out, message, record| {
             out.finish(format_args!(
                 "{}[{}] {}",
@@ -45,13 +48,27 @@ pub fn setup_logger() -> Result<()> {
                 message
             ))
         })
-        .chain(std::io::stdout())
-        .chain(fern::log_file("output.log")?)
+        .chain(std::io::stdout());
+    if let Some(file) = file {
+        dispatch = dispatch.chain(fern::log_file(file)?);
+    }
+
+    dispatch
         .level(log::LevelFilter::Error)
-        .level_for("rusty_sando", LevelFilter::Info)
-        .level_for("strategy", LevelFilter::Info)
-        .level_for("artemis_core", LevelFilter::Info)
+        .level_for("rusty_sando", level)
+        .level_for("strategy", level)
+        .level_for("artemis_core", level)
         .apply()?;
 
     Ok(())
 }
+
+/// Resolves once the process receives SIGINT or SIGTERM
+pub async fn shutdown_signal() -> Result<()> {
+    let mut sigterm = signal(SignalKind::terminate())?;
+    tokio::select! {
+        res = tokio::signal::ctrl_c() => res?,
+        _ = sigterm.recv() => {}
+    }
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::{str::FromStr, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use clap::Args;
+use ethers::{
+    middleware::SignerMiddleware,
+    providers::{Middleware, Provider, Ws},
+    signers::Signer,
+    types::{Address, U64},
+    utils::{format_ether, parse_ether},
+};
+use log::info;
+use strategy::inventory::{weth_balance, withdraw_tx, SandoInventory, Withdrawal};
+
+use crate::config::{Config, WETH_ADDRESS};
+
+/// Arguments for `withdraw [--token <weth|eth>] [--amount <ether>]`
+#[derive(Debug, Args)]
+pub struct WithdrawArgs {
+    /// `weth` (or its address) or `eth`, the sando contract can't transfer out other tokens
+    #[arg(long, default_value = "weth")]
+    pub token: String,
+    /// How much weth to withdraw in ether (all of it if not set, eth is always withdrawn in full)
+    #[arg(long)]
+    pub amount: Option<String>,
+}
+
+/// Log the sando contract's eth, weth, and dust balances
+pub async fn run_inventory(config: Config) -> Result<()> {
+    let ws = Ws::connect(config.wss_rpc.clone()).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    let inventory =
+        SandoInventory::fetch(provider, config.sando_address, config.sando_inception_block).await?;
+
+    info!("sando contract {:?}", config.sando_address);
+    info!("eth  : {}", format_ether(inventory.eth));
+    info!("weth : {}", format_ether(inventory.weth));
+    info!("dust : {} tokens", inventory.dust.len());
+    for (token, balance) in inventory.dust {
+        info!("  {:?}: {}", token, balance);
+    }
+
+    Ok(())
+}
+
+/// Sweep weth or eth from the sando contract to the searcher, waits for the tx to be mined
+pub async fn run_withdraw(config: Config, args: WithdrawArgs) -> Result<()> {
+    let ws = Ws::connect(config.wss_rpc.clone()).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    let weth = Address::from_str(WETH_ADDRESS).expect("valid weth address");
+    let token = args.token.to_lowercase();
+    let withdrawal = if token == "eth" {
+        if args.amount.is_some() {
+            return Err(anyhow!(
+                "eth is always withdrawn in full, --amount only applies to weth"
+            ));
+        }
+        Withdrawal::Eth
+    } else if token == "weth" || Address::from_str(&token).ok() == Some(weth) {
+        let balance = weth_balance(provider.clone(), config.sando_address).await?;
+        let amount = match &args.amount {
+            Some(amount) => parse_ether(amount)
+                .map_err(|_| anyhow!("Failed to parse --amount \"{}\"", amount))?,
+            None => balance,
+        };
+        if amount.is_zero() || amount > balance {
+            return Err(anyhow!(
+                "can't withdraw {} weth, sando contract holds {}",
+                format_ether(amount),
+                format_ether(balance)
+            ));
+        }
+        Withdrawal::Weth(amount)
+    } else {
+        return Err(anyhow!(
+            "the sando contract can only transfer out weth and eth, not \"{}\"",
+            args.token
+        ));
+    };
+
+    let chain_id = provider.get_chainid().await?.as_u64();
+    let searcher_signer = config.searcher_signer.with_chain_id(chain_id);
+    let searcher = searcher_signer.address();
+    let client = SignerMiddleware::new(provider.clone(), searcher_signer);
+
+    let tx = withdraw_tx(config.sando_address, searcher, withdrawal, chain_id)?;
+    let searcher_weth = weth_balance(provider.clone(), searcher).await?;
+    let pending_tx = client.send_transaction(tx, None).await?;
+    info!("sent withdrawal {:?}", pending_tx.tx_hash());
+
+    let receipt = pending_tx
+        .await?
+        .ok_or(anyhow!("withdrawal was dropped from the mempool"))?;
+    if receipt.status != Some(U64::one()) {
+        return Err(anyhow!(
+            "withdrawal {:?} reverted",
+            receipt.transaction_hash
+        ));
+    }
+
+    // the contract doesn't revert when it's handed a malformed amount, it transfers it as is
+    if let Withdrawal::Weth(amount) = withdrawal {
+        let received = weth_balance(provider, searcher)
+            .await?
+            .saturating_sub(searcher_weth);
+        if received != amount {
+            return Err(anyhow!(
+                "withdrawal {:?} transferred {} weth instead of {}",
+                receipt.transaction_hash,
+                format_ether(received),
+                format_ether(amount)
+            ));
+        }
+    }
+
+    info!(
+        "withdrew {:?} to {:?} in block {:?}",
+        withdrawal,
+        searcher,
+        receipt.block_number.unwrap_or_default()
+    );
+
+    Ok(())
+}
@@ -1,2 +1,9 @@
+pub mod backtest;
+pub mod check;
+pub mod cli;
 pub mod config;
 pub mod initialization;
+pub mod inventory;
+pub mod pools;
+pub mod replay;
+pub mod simulate;
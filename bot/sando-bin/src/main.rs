@@ -1,71 +1,401 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
 use artemis_core::{
-    collectors::{block_collector::BlockCollector, mempool_collector::MempoolCollector},
-    engine::Engine,
-    executors::flashbots_executor::FlashbotsExecutor,
-    types::{CollectorMap, ExecutorMap},
+    collectors::{
+        block_collector::{BlockCollector, NewBlock},
+        broadcast_collector::BroadcastCollector,
+        mev_share_collector::{MevShareCollector, MevShareEvent},
+        multi_mempool_collector::{
+            MempoolEvent, MempoolSource, MultiMempoolCollector, PendingTxSubscription,
+        },
+        reconnecting_collector::{ReconnectingCollector, StreamEvent},
+        recording_collector::RecordingCollector,
+    },
+    engine::{BackpressurePolicy, Engine},
+    executors::{
+        bundle_simulator::RelayBundleSimulator, mev_share_executor::MevShareExecutor,
+        multi_builder_executor::MultiBuilderExecutor, paper_trading_executor::PaperTradingExecutor,
+        recorder_executor::RecorderExecutor, webhook_executor::WebhookExecutor,
+    },
+    types::{Collector, CollectorMap, ExecutorMap},
+    utilities::capture::CaptureWriter,
 };
+use clap::Parser;
 use ethers::providers::{Provider, Ws};
 use log::info;
-use reqwest::Url;
 use rusty_sando::{
-    config::Config,
-    initialization::{print_banner, setup_logger},
+    backtest::run_backtest,
+    check::check_config,
+    cli::{Cli, Command},
+    config::{BackpressureMode, BundleSimulationMode, Config},
+    initialization::{print_banner, setup_logger, shutdown_signal},
+    inventory::{run_inventory, run_withdraw},
+    pools::run_sync_pools,
+    replay::run_replay,
+    simulate::run_simulate,
 };
 use strategy::{
     bot::SandoBot,
-    types::{Action, Event, StratConfig},
+    simulator::bundle_simulator::LocalBundleSimulator,
+    storage::{bundle_hash, sqlite::SqliteStore, writer::StoreWriter, OpportunityStore},
+    types::{Action, Event, StratConfig, TxSource},
 };
+use tokio::sync::broadcast;
+
+/// How long the engine gets to flush before the process exits anyway
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
 
 #[tokio::main]
 async fn main() -> Result<()> {
     // Setup
-    setup_logger()?;
+    let cli = Cli::parse();
+    let config = Config::load(&cli)?;
+    setup_logger(config.log_level, config.log_file.as_deref())?;
     print_banner();
-    let config = Config::read_from_dotenv().await?;
+
+    if cli.check_config {
+        return check_config(&config).await;
+    }
+
+    // Operational tasks, or replaying historical blocks instead of running live
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => {}
+        Command::Inventory => return run_inventory(config).await,
+        Command::Withdraw(args) => return run_withdraw(config, args).await,
+        Command::SyncPools => return run_sync_pools(config).await,
+        Command::Simulate(args) => return run_simulate(config, args).await,
+        Command::Backtest(args) => return run_backtest(config, args).await,
+        Command::Replay(args) => return run_replay(config, args).await,
+    }
+
+    // Setup strategy config (before config's fields are moved out)
+    let strat_config = config.strat_config()?;
 
     // Setup ethers provider
-    let ws = Ws::connect(config.wss_rpc).await?;
+    let ws = Ws::connect_with_reconnects(config.wss_rpc, usize::MAX).await?;
     let provider = Arc::new(Provider::new(ws));
 
     // Setup signers
     let flashbots_signer = config.bundle_signer;
-    let searcher_signer = config.searcher_signer;
 
     // Create engine
-    let mut engine: Engine<Event, Action> = Engine::default();
+    let event_policy: BackpressurePolicy<Event> = match config.backpressure {
+        BackpressureMode::DropOldest => BackpressurePolicy::DropOldest,
+        BackpressureMode::Block => BackpressurePolicy::Block,
+        BackpressureMode::PrioritiseBlocks => BackpressurePolicy::Prioritise(|event| {
+            matches!(event, Event::NewBlock(_) | Event::BlockGap(_))
+        }),
+    };
+    let mut engine: Engine<Event, Action> = Engine::default()
+        .with_event_channel_capacity(config.event_channel_capacity)
+        .with_action_channel_capacity(config.action_channel_capacity)
+        .with_event_backpressure(event_policy);
+    let engine_metrics = engine.metrics();
+
+    // Record every event and action when capturing, so that incidents can be replayed
+    let capture = match &config.capture_path {
+        Some(path) => Some(CaptureWriter::open(path)?),
+        None => None,
+    };
+    let record = |collector: Box<dyn Collector<Event>>| -> Box<dyn Collector<Event>> {
+        match &capture {
+            Some(writer) => Box::new(RecordingCollector::new(collector, writer.clone())),
+            None => collector,
+        }
+    };
+    if let Some(writer) = &capture {
+        engine.add_executor(Box::new(RecorderExecutor::new(writer.clone())));
+    }
 
-    // Setup block collector
+    // Setup block collector, resubscribing if the subscription drops
     let block_collector = Box::new(BlockCollector::new(provider.clone()));
-    let block_collector = CollectorMap::new(block_collector, Event::NewBlock);
-    engine.add_collector(Box::new(block_collector));
+    let block_collector = Box::new(ReconnectingCollector::new(block_collector));
+    let block_collector =
+        CollectorMap::new(
+            block_collector,
+            |event: StreamEvent<NewBlock>| match event {
+                StreamEvent::Event(block) => Event::NewBlock(block),
+                StreamEvent::Gap(gap) => Event::BlockGap(gap),
+            },
+        );
+    engine.add_collector(record(Box::new(block_collector)));
 
-    // Setup mempool collector
-    let mempool_collector = Box::new(MempoolCollector::new(provider.clone()));
-    let mempool_collector = CollectorMap::new(mempool_collector, Event::NewTransaction);
-    engine.add_collector(Box::new(mempool_collector));
+    // Setup mempool collector merging our node's pending txs with any extra sources, each source is
+    // resubscribed on its own if its subscription drops
+    let mut mempool_sources = vec![MempoolSource {
+        name: "primary".to_string(),
+        provider: provider.clone(),
+        subscription: PendingTxSubscription::WithBody,
+    }];
+    for source in config.mempool_sources {
+        let ws = Ws::connect_with_reconnects(source.wss_rpc, usize::MAX).await?;
+        mempool_sources.push(MempoolSource {
+            name: source.name,
+            provider: Arc::new(Provider::new(ws)),
+            subscription: match source.with_body {
+                true => PendingTxSubscription::WithBody,
+                false => PendingTxSubscription::Hashes,
+            },
+        });
+    }
+    let mempool_collector = MultiMempoolCollector::new(mempool_sources);
+    let first_seen_counts = mempool_collector.first_seen_counts();
+    let mempool_collector = CollectorMap::new(
+        Box::new(mempool_collector),
+        |event: MempoolEvent| match event {
+            MempoolEvent::Transaction(seen) => Event::TaggedTransaction(
+                seen.tx,
+                TxSource {
+                    name: seen.source,
+                    first_seen: seen.first_seen,
+                },
+            ),
+            MempoolEvent::SourceGap { source, gap } => Event::MempoolSourceGap(source, gap),
+        },
+    );
+    if !config.backrun_only {
+        engine.add_collector(record(Box::new(mempool_collector)));
+    }
+
+    // Setup MEV-Share collector streaming hints about txs sent to the matchmaker
+    if let Some(mev_share_url) = config.mev_share_url.clone() {
+        let mev_share_collector = Box::new(MevShareCollector::new(mev_share_url));
+        let mev_share_collector = Box::new(ReconnectingCollector::new(mev_share_collector));
+        let mev_share_collector = CollectorMap::new(
+            mev_share_collector,
+            |event: StreamEvent<MevShareEvent>| match event {
+                StreamEvent::Event(event) => Event::MevShareEvent(event),
+                StreamEvent::Gap(gap) => Event::MevShareGap(gap),
+            },
+        );
+        engine.add_collector(record(Box::new(mev_share_collector)));
+    }
+
+    // Setup opportunity database
+    let store: Option<Arc<dyn OpportunityStore>> = match &config.database_path {
+        Some(path) => Some(Arc::new(SqliteStore::open(path)?)),
+        None => None,
+    };
 
     // Setup strategy
     let configs = StratConfig {
-        sando_address: config.sando_address,
-        sando_inception_block: config.sando_inception_block,
-        searcher_signer,
+        store: store.clone(),
+        ..strat_config
     };
     let strategy = SandoBot::new(provider.clone(), configs);
     engine.add_strategy(Box::new(strategy));
 
-    // Setup flashbots executor
-    let executor = Box::new(FlashbotsExecutor::new(
-        provider.clone(),
-        flashbots_signer,
-        Url::parse("https://relay.flashbots.net")?,
-    ));
-    let executor = ExecutorMap::new(executor, |action| match action {
-        Action::SubmitToFlashbots(bundle) => Some(bundle),
+    if config.paper_trading {
+        // Setup executor that simulates bundles against the real chain instead of sending them
+        let executor = PaperTradingExecutor::new(
+            provider.clone(),
+            Box::new(LocalBundleSimulator::new(
+                provider.clone(),
+                config.sando_address,
+            )),
+        );
+        let paper_stats = executor.stats();
+        let cancel_executor = ExecutorMap::new(Box::new(executor.clone()), |action| match action {
+            Action::CancelBundle(uuid) => Some(uuid),
+            _ => None,
+        });
+        let backrun_executor =
+            ExecutorMap::new(Box::new(executor.clone()), |action| match action {
+                Action::SubmitToMevShare(bundle) => Some(bundle),
+                _ => None,
+            });
+        let executor = ExecutorMap::new(Box::new(executor), |action| match action {
+            Action::SubmitToFlashbots(bundle) => Some(bundle),
+            _ => None,
+        });
+        engine.add_executor(Box::new(executor));
+        engine.add_executor(Box::new(cancel_executor));
+        engine.add_executor(Box::new(backrun_executor));
+
+        // Periodically log the hypothetical pnl
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(600));
+            loop {
+                interval.tick().await;
+                let stats = paper_stats.lock().unwrap().clone();
+                info!(
+                    "paper trading: simulated {} (failed {}, replaced {}, unmatched {}), profit {}, coinbase payments {}",
+                    stats.simulated,
+                    stats.failed,
+                    stats.replaced,
+                    stats.unmatched,
+                    stats.profit,
+                    stats.coinbase_payment
+                );
+            }
+        });
+    } else {
+        // Report what happened to each bundle back to the strategy, so that bundles which never
+        // reached a builder don't hold back later ones
+        let (reports, _) = broadcast::channel(256);
+        let report_collector = Box::new(BroadcastCollector::new(reports.clone()));
+        let report_collector = CollectorMap::new(report_collector, Event::SubmissionReport);
+        engine.add_collector(record(Box::new(report_collector)));
+
+        // Setup executor that sends bundles to all configured builders
+        let executor = MultiBuilderExecutor::new(provider.clone(), config.builders.clone())
+            .with_reports(reports);
+        let tolerance_bps = config.bundle_simulation_tolerance_bps;
+        let executor = match config.bundle_simulation {
+            BundleSimulationMode::Disabled => executor,
+            BundleSimulationMode::Relay => executor.with_simulator(
+                Box::new(RelayBundleSimulator::new(
+                    provider.clone(),
+                    flashbots_signer.clone(),
+                    config.simulation_relay.clone(),
+                )),
+                tolerance_bps,
+            ),
+            BundleSimulationMode::Local => executor.with_simulator(
+                Box::new(LocalBundleSimulator::new(
+                    provider.clone(),
+                    config.sando_address,
+                )),
+                tolerance_bps,
+            ),
+        };
+        let executor = match store {
+            Some(store) => {
+                // record submissions without blocking the executor on disk I/O
+                let store = StoreWriter::spawn(store);
+                executor.with_submission_hook(Box::new(move |submission, builder, outcome| {
+                    let hash = bundle_hash(&submission.request.transaction_hashes());
+                    if let Err(e) =
+                        store.record_submission(hash, builder, &format!("{:?}", outcome))
+                    {
+                        log::error!("Failed to record submission: {}", e);
+                    }
+                }))
+            }
+            None => executor,
+        };
+        let builder_stats = executor.stats();
+        let executor = ExecutorMap::new(Box::new(executor), |action| match action {
+            Action::SubmitToFlashbots(bundle) => Some(bundle),
+            _ => None,
+        });
+        engine.add_executor(Box::new(executor));
+
+        // Setup executor that cancels stale bundles on the same builders
+        let cancel_executor = MultiBuilderExecutor::new(provider.clone(), config.builders);
+        let cancel_executor = ExecutorMap::new(Box::new(cancel_executor), |action| match action {
+            Action::CancelBundle(uuid) => Some(uuid),
+            _ => None,
+        });
+        engine.add_executor(Box::new(cancel_executor));
+
+        // Setup executor that sends backruns of MEV-Share hints to the matchmaker
+        if config.mev_share_url.is_some() {
+            let mev_share_executor = MevShareExecutor::new(
+                provider.clone(),
+                flashbots_signer,
+                config.mev_share_relay.clone(),
+            );
+            let mev_share_executor =
+                ExecutorMap::new(Box::new(mev_share_executor), |action| match action {
+                    Action::SubmitToMevShare(bundle) => Some(bundle),
+                    _ => None,
+                });
+            engine.add_executor(Box::new(mev_share_executor));
+        }
+
+        // Periodically log how each builder is handling our bundles
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(600));
+            loop {
+                interval.tick().await;
+                for (name, stats) in builder_stats.lock().unwrap().iter() {
+                    info!(
+                        "{}: accepted {}/{} (timed out {}), avg latency {:?}",
+                        name,
+                        stats.accepted,
+                        stats.submitted,
+                        stats.timed_out,
+                        stats.average_latency()
+                    );
+                }
+            }
+        });
+    }
+
+    // Setup executors that post notifications to the configured webhooks
+    let mut webhook_stats = vec![];
+    for webhook in config.webhooks {
+        let url = webhook.url.clone();
+        let executor = WebhookExecutor::new(webhook);
+        webhook_stats.push((url, executor.stats()));
+        let executor = ExecutorMap::new(Box::new(executor), |action| match action {
+            Action::Notify(notification) => Some(notification),
+            _ => None,
+        });
+        engine.add_executor(Box::new(executor));
+    }
+
+    // Periodically log how the webhooks are keeping up
+    if !webhook_stats.is_empty() {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(600));
+            loop {
+                interval.tick().await;
+                for (url, stats) in &webhook_stats {
+                    let stats = stats.lock().unwrap().clone();
+                    info!(
+                        "webhook {}: sent {} notifications in {} posts (rate limited {}), dropped {}, failed {}",
+                        url.host_str().unwrap_or_default(),
+                        stats.sent,
+                        stats.posts,
+                        stats.rate_limited,
+                        stats.dropped,
+                        stats.failed
+                    );
+                }
+            }
+        });
+    }
+
+    // Periodically log events and actions lost to lagging
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(600));
+        loop {
+            interval.tick().await;
+            for stats in engine_metrics.strategies() {
+                info!(
+                    "strategy: received {} events, lagged {}, blocked collectors {} times",
+                    stats.received, stats.lagged, stats.blocked
+                );
+            }
+            for stats in engine_metrics.executors() {
+                info!(
+                    "executor: received {} actions, lagged {}",
+                    stats.received, stats.lagged
+                );
+            }
+            for (source, count) in first_seen_counts.lock().unwrap().iter() {
+                info!("mempool source {} saw {} txs first", source, count);
+            }
+        }
+    });
+
+    // Shut down gracefully on SIGINT/SIGTERM, giving executors time to send what is left
+    let shutdown = engine.shutdown_token();
+    tokio::spawn(async move {
+        if let Err(e) = shutdown_signal().await {
+            log::error!("Failed to listen for shutdown signals: {}", e);
+            return;
+        }
+        info!("Shutting down...");
+        shutdown.cancel();
+
+        tokio::time::sleep(SHUTDOWN_TIMEOUT).await;
+        log::error!("Shutdown timed out after {:?}", SHUTDOWN_TIMEOUT);
+        std::process::exit(1);
     });
-    engine.add_executor(Box::new(executor));
 
     // Start engine
     if let Ok(mut set) = engine.run().await {
@@ -0,0 +1,20 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use ethers::providers::{Provider, Ws};
+use log::info;
+use strategy::bot::SandoBot;
+
+use crate::config::Config;
+
+/// Sync every pool from scratch, rebuilding the checkpoint that later runs start from
+pub async fn run_sync_pools(config: Config) -> Result<()> {
+    let ws = Ws::connect(config.wss_rpc.clone()).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    let mut bot = SandoBot::new(provider, config.strat_config()?);
+    let synced = bot.resync_pools().await?;
+    info!("synced {} pools", synced);
+
+    Ok(())
+}
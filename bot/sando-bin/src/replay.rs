@@ -0,0 +1,196 @@
+use std::{path::PathBuf, sync::Arc};
+
+use anyhow::{anyhow, Result};
+use artemis_core::{
+    collectors::{
+        capture_collector::CaptureCollector,
+        replay_collector::{
+            FileBlockSource, ReplayBlock, ReplayCollector, ReplaySpeed, RpcBlockSource,
+        },
+    },
+    engine::{BackpressurePolicy, Engine},
+    types::{Collector, CollectorMap, Events, Executor},
+};
+use async_trait::async_trait;
+use clap::Args;
+use ethers::{
+    providers::{Provider, Ws},
+    types::U64,
+};
+use log::info;
+use strategy::{
+    bot::SandoBot,
+    types::{Action, Event, StratConfig},
+};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::Config;
+
+/// Arguments for `replay (<from_block> <to_block> [--record <path>] | --file <path> |
+/// --capture <path>) [--speed <factor>]`
+#[derive(Debug, Args)]
+pub struct ReplayArgs {
+    /// First and last block to fetch from the node
+    #[arg(
+        num_args = 2,
+        value_names = ["FROM_BLOCK", "TO_BLOCK"],
+        required_unless_present_any = ["file", "capture"],
+        conflicts_with_all = ["file", "capture"]
+    )]
+    pub blocks: Vec<u64>,
+    /// Replay blocks recorded with `--record`
+    #[arg(long, conflicts_with = "capture")]
+    pub file: Option<PathBuf>,
+    /// Replay events recorded by a live run with `storage.capture` set
+    #[arg(long)]
+    pub capture: Option<PathBuf>,
+    /// Write the fetched blocks to this file before replaying them
+    #[arg(long, requires = "blocks")]
+    pub record: Option<PathBuf>,
+    /// Replay at this many times real time (as fast as possible if not set)
+    #[arg(long)]
+    pub speed: Option<f64>,
+}
+
+/// Where replayed events come from
+#[derive(Debug)]
+pub enum ReplaySource {
+    Rpc {
+        from_block: u64,
+        to_block: u64,
+    },
+    /// Blocks recorded with `--record`
+    File(PathBuf),
+    /// Events recorded by a live run with `storage.capture` set
+    Capture(PathBuf),
+}
+
+impl ReplayArgs {
+    pub fn source(&self) -> Result<ReplaySource> {
+        match (self.blocks.as_slice(), &self.file, &self.capture) {
+            ([from_block, to_block], None, None) if from_block <= to_block => {
+                Ok(ReplaySource::Rpc {
+                    from_block: *from_block,
+                    to_block: *to_block,
+                })
+            }
+            ([_, _], None, None) => Err(anyhow!("from_block must not be after to_block")),
+            ([], Some(file), None) => Ok(ReplaySource::File(file.clone())),
+            ([], None, Some(capture)) => Ok(ReplaySource::Capture(capture.clone())),
+            _ => Err(anyhow!(
+                "replay either a block range, a --file, or a --capture"
+            )),
+        }
+    }
+
+    pub fn speed(&self) -> ReplaySpeed {
+        match self.speed {
+            Some(factor) => ReplaySpeed::Scaled(factor),
+            None => ReplaySpeed::Instant,
+        }
+    }
+}
+
+/// Feed historical blocks and their txs (or a live run's captured events) through the same engine
+/// wiring as a live run, actions are logged instead of being executed
+pub async fn run_replay(config: Config, args: ReplayArgs) -> Result<()> {
+    let ws = Ws::connect(config.wss_rpc.clone()).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    // replayed events can't be missed, wait for the strategy instead of dropping them
+    let mut engine: Engine<Event, Action> =
+        Engine::default().with_event_backpressure(BackpressurePolicy::Block);
+
+    let speed = args.speed();
+    let blocks: Box<dyn Collector<ReplayBlock>> = match args.source()? {
+        ReplaySource::Rpc {
+            from_block,
+            to_block,
+        } => {
+            let source =
+                RpcBlockSource::new(provider.clone(), U64::from(from_block), U64::from(to_block));
+            match args.record {
+                Some(path) => {
+                    let recorded = source.record(&path).await?;
+                    info!("recorded {} blocks to {}", recorded, path.display());
+                    Box::new(FileBlockSource::new(path))
+                }
+                None => Box::new(source),
+            }
+        }
+        ReplaySource::File(path) => Box::new(FileBlockSource::new(path)),
+        ReplaySource::Capture(path) => {
+            let collector = CaptureCollector::<Event>::new(path).with_speed(speed);
+            let finished = collector.finished();
+            engine.add_collector(Box::new(collector));
+            return run_replay_engine(engine, provider, config, finished).await;
+        }
+    };
+
+    let collector = ReplayCollector::new(blocks).with_speed(speed);
+    let finished = collector.finished();
+    let collector = CollectorMap::new(Box::new(collector), |event: Events| match event {
+        Events::NewBlock(block) => Event::NewBlock(block),
+        Events::Transaction(tx) => Event::NewTransaction(tx),
+    });
+    engine.add_collector(Box::new(collector));
+
+    run_replay_engine(engine, provider, config, finished).await
+}
+
+/// Add the strategy and run the engine until `finished` is cancelled and every replayed event has
+/// been processed
+async fn run_replay_engine(
+    mut engine: Engine<Event, Action>,
+    provider: Arc<Provider<Ws>>,
+    config: Config,
+    finished: CancellationToken,
+) -> Result<()> {
+    let configs = StratConfig {
+        backrun_only: false,
+        ..config.strat_config()?
+    };
+    engine.add_strategy(Box::new(SandoBot::new(provider, configs)));
+    engine.add_executor(Box::new(ReplayExecutor));
+
+    let shutdown = engine.shutdown_token();
+    tokio::spawn(async move {
+        finished.cancelled().await;
+        info!("replayed every event, shutting down...");
+        shutdown.cancel();
+    });
+
+    let mut set = engine
+        .run()
+        .await
+        .map_err(|e| anyhow!("Failed to start engine: {}", e))?;
+    while set.join_next().await.is_some() {}
+
+    Ok(())
+}
+
+/// Logs the actions the strategy took during a replay
+struct ReplayExecutor;
+
+#[async_trait]
+impl Executor<Action> for ReplayExecutor {
+    async fn execute(&self, action: Action) -> Result<()> {
+        match action {
+            Action::SubmitToFlashbots(bundle) => info!(
+                "replay: bundle of {} txs targeting block {:?}",
+                bundle.request.transactions().len(),
+                bundle.request.block()
+            ),
+            Action::CancelBundle(uuid) => info!("replay: cancelled bundle {}", uuid),
+            Action::SubmitToMevShare(bundle) => info!(
+                "replay: backrun of {:?} targeting block {}",
+                bundle.victim_hash, bundle.block
+            ),
+            Action::Notify(notification) => info!(
+                "replay: notified {:?} {}: {}",
+                notification.level, notification.title, notification.message
+            ),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use artemis_core::types::Strategy;
+use clap::Args;
+use ethers::{
+    providers::{Provider, Ws},
+    types::H256,
+    utils::format_ether,
+};
+use log::info;
+use strategy::{bot::SandoBot, types::StratConfig};
+
+use crate::config::Config;
+
+/// Arguments for `simulate --tx <hash>`
+#[derive(Debug, Args)]
+pub struct SimulateArgs {
+    /// Tx to evaluate, a mined tx is evaluated at the block before its own
+    #[arg(long)]
+    pub tx: H256,
+}
+
+/// Check whether a single tx could be sandwiched on each pool it touches
+pub async fn run_simulate(config: Config, args: SimulateArgs) -> Result<()> {
+    let ws = Ws::connect(config.wss_rpc.clone()).await?;
+    let provider = Arc::new(Provider::new(ws));
+
+    let configs = StratConfig {
+        backrun_only: false,
+        ..config.strat_config()?
+    };
+    let mut bot = SandoBot::new(provider, configs);
+    bot.sync_state().await?;
+
+    let results = bot.simulate_tx(args.tx).await?;
+    if results.is_empty() {
+        info!("{:?} doesn't touch any sandwichable pool", args.tx);
+    }
+
+    for result in results {
+        match (result.optimal_input, result.revenue) {
+            (Some(optimal_input), Some(revenue)) => info!(
+                "pool {:?}: sandwichable in block {}, optimal input {} weth, revenue {} weth, gas used {}/{}",
+                result.pool,
+                result.block,
+                format_ether(optimal_input),
+                format_ether(revenue),
+                result.frontrun_gas_used.unwrap_or_default(),
+                result.backrun_gas_used.unwrap_or_default()
+            ),
+            _ => info!(
+                "pool {:?}: not sandwichable, {}",
+                result.pool,
+                result.error.unwrap_or_default()
+            ),
+        }
+    }
+
+    Ok(())
+}
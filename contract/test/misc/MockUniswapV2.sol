@@ -0,0 +1,252 @@
+// SPDX-License-Identifier: MIT
+pragma solidity ^0.8.15;
+
+/// @title MockWETH
+/// @notice Minimal WETH9 used by the bot's anvil harness
+/// @dev Storage layout matches WETH9 so that `balanceOf` lives at slot 3 (bot reads WETH state diffs from that slot)
+contract MockWETH {
+    string public name = "Wrapped Ether";
+    string public symbol = "WETH";
+    uint8 public decimals = 18;
+    mapping(address => uint256) public balanceOf;
+    mapping(address => mapping(address => uint256)) public allowance;
+
+    event Transfer(address indexed from, address indexed to, uint256 value);
+    event Approval(address indexed owner, address indexed spender, uint256 value);
+
+    receive() external payable {
+        deposit();
+    }
+
+    function deposit() public payable {
+        balanceOf[msg.sender] += msg.value;
+        emit Transfer(address(0), msg.sender, msg.value);
+    }
+
+    function withdraw(uint256 amount) external {
+        balanceOf[msg.sender] -= amount;
+        emit Transfer(msg.sender, address(0), amount);
+        payable(msg.sender).transfer(amount);
+    }
+
+    function totalSupply() external view returns (uint256) {
+        return address(this).balance;
+    }
+
+    function approve(address spender, uint256 amount) external returns (bool) {
+        allowance[msg.sender][spender] = amount;
+        emit Approval(msg.sender, spender, amount);
+        return true;
+    }
+
+    function transfer(address to, uint256 amount) external returns (bool) {
+        return transferFrom(msg.sender, to, amount);
+    }
+
+    function transferFrom(address from, address to, uint256 amount) public returns (bool) {
+        if (from != msg.sender && allowance[from][msg.sender] != type(uint256).max) {
+            allowance[from][msg.sender] -= amount;
+        }
+        balanceOf[from] -= amount;
+        balanceOf[to] += amount;
+        emit Transfer(from, to, amount);
+        return true;
+    }
+}
+
+/// @title MockERC20
+/// @notice Plain ERC20 that mints its whole supply to the deployer
+contract MockERC20 {
+    string public name;
+    string public symbol;
+    uint8 public constant decimals = 18;
+    uint256 public totalSupply;
+    mapping(address => uint256) public balanceOf;
+    mapping(address => mapping(address => uint256)) public allowance;
+
+    event Transfer(address indexed from, address indexed to, uint256 value);
+    event Approval(address indexed owner, address indexed spender, uint256 value);
+
+    constructor(string memory _name, string memory _symbol, uint256 supply) {
+        name = _name;
+        symbol = _symbol;
+        totalSupply = supply;
+        balanceOf[msg.sender] = supply;
+        emit Transfer(address(0), msg.sender, supply);
+    }
+
+    function approve(address spender, uint256 amount) external returns (bool) {
+        allowance[msg.sender][spender] = amount;
+        emit Approval(msg.sender, spender, amount);
+        return true;
+    }
+
+    function transfer(address to, uint256 amount) external returns (bool) {
+        return transferFrom(msg.sender, to, amount);
+    }
+
+    function transferFrom(address from, address to, uint256 amount) public virtual returns (bool) {
+        if (from != msg.sender && allowance[from][msg.sender] != type(uint256).max) {
+            allowance[from][msg.sender] -= amount;
+        }
+        balanceOf[from] -= amount;
+        balanceOf[to] += amount;
+        emit Transfer(from, to, amount);
+        return true;
+    }
+}
+
+/// @title MockTimeBombERC20
+/// @notice MockERC20 that burns half of every transfer once `block.timestamp` passes `taxAfter`
+/// @dev Etched over a deployed MockERC20 so it shares its storage layout, `taxAfter` lives in slot 5
+contract MockTimeBombERC20 is MockERC20 {
+    uint256 public taxAfter;
+
+    constructor(uint256 _taxAfter) MockERC20("Time Bomb", "TB", 0) {
+        taxAfter = _taxAfter;
+    }
+
+    function transferFrom(address from, address to, uint256 amount) public override returns (bool) {
+        if (from != msg.sender && allowance[from][msg.sender] != type(uint256).max) {
+            allowance[from][msg.sender] -= amount;
+        }
+        uint256 received = block.timestamp > taxAfter ? amount / 2 : amount;
+        balanceOf[from] -= amount;
+        balanceOf[to] += received;
+        totalSupply -= amount - received;
+        emit Transfer(from, to, received);
+        return true;
+    }
+}
+
+interface IMockERC20 {
+    function balanceOf(address owner) external view returns (uint256);
+    function transfer(address to, uint256 amount) external returns (bool);
+    function transferFrom(address from, address to, uint256 amount) external returns (bool);
+}
+
+/// @title MockUniswapV2Pair
+/// @notice UniswapV2 pair without lp tokens, liquidity is added by transferring tokens in and calling `sync`
+/// @dev Swaps follow UniswapV2's 0.3% fee constant product check so that the bot's amount out maths holds
+contract MockUniswapV2Pair {
+    address public immutable factory;
+    address public token0;
+    address public token1;
+
+    uint112 private reserve0;
+    uint112 private reserve1;
+    uint32 private blockTimestampLast;
+
+    event Swap(
+        address indexed sender,
+        uint256 amount0In,
+        uint256 amount1In,
+        uint256 amount0Out,
+        uint256 amount1Out,
+        address indexed to
+    );
+    event Sync(uint112 reserve0, uint112 reserve1);
+
+    constructor() {
+        factory = msg.sender;
+    }
+
+    function initialize(address _token0, address _token1) external {
+        require(msg.sender == factory, "MockUniswapV2: FORBIDDEN");
+        token0 = _token0;
+        token1 = _token1;
+    }
+
+    function getReserves() public view returns (uint112 _reserve0, uint112 _reserve1, uint32 _blockTimestampLast) {
+        _reserve0 = reserve0;
+        _reserve1 = reserve1;
+        _blockTimestampLast = blockTimestampLast;
+    }
+
+    function swap(uint256 amount0Out, uint256 amount1Out, address to, bytes calldata) external {
+        require(amount0Out > 0 || amount1Out > 0, "MockUniswapV2: INSUFFICIENT_OUTPUT_AMOUNT");
+        (uint112 _reserve0, uint112 _reserve1,) = getReserves();
+        require(amount0Out < _reserve0 && amount1Out < _reserve1, "MockUniswapV2: INSUFFICIENT_LIQUIDITY");
+
+        if (amount0Out > 0) IMockERC20(token0).transfer(to, amount0Out);
+        if (amount1Out > 0) IMockERC20(token1).transfer(to, amount1Out);
+
+        uint256 balance0 = IMockERC20(token0).balanceOf(address(this));
+        uint256 balance1 = IMockERC20(token1).balanceOf(address(this));
+
+        uint256 amount0In = balance0 > _reserve0 - amount0Out ? balance0 - (_reserve0 - amount0Out) : 0;
+        uint256 amount1In = balance1 > _reserve1 - amount1Out ? balance1 - (_reserve1 - amount1Out) : 0;
+        require(amount0In > 0 || amount1In > 0, "MockUniswapV2: INSUFFICIENT_INPUT_AMOUNT");
+
+        uint256 balance0Adjusted = balance0 * 1000 - amount0In * 3;
+        uint256 balance1Adjusted = balance1 * 1000 - amount1In * 3;
+        require(
+            balance0Adjusted * balance1Adjusted >= uint256(_reserve0) * uint256(_reserve1) * 1000 ** 2,
+            "MockUniswapV2: K"
+        );
+
+        _update(balance0, balance1);
+        emit Swap(msg.sender, amount0In, amount1In, amount0Out, amount1Out, to);
+    }
+
+    function sync() external {
+        _update(IMockERC20(token0).balanceOf(address(this)), IMockERC20(token1).balanceOf(address(this)));
+    }
+
+    function _update(uint256 balance0, uint256 balance1) private {
+        require(balance0 <= type(uint112).max && balance1 <= type(uint112).max, "MockUniswapV2: OVERFLOW");
+        reserve0 = uint112(balance0);
+        reserve1 = uint112(balance1);
+        blockTimestampLast = uint32(block.timestamp);
+        emit Sync(reserve0, reserve1);
+    }
+}
+
+/// @title MockUniswapV2Factory
+/// @notice Exposes the same pair discovery interface as UniswapV2's factory (`allPairs`, `PairCreated`)
+contract MockUniswapV2Factory {
+    mapping(address => mapping(address => address)) public getPair;
+    address[] public allPairs;
+
+    event PairCreated(address indexed token0, address indexed token1, address pair, uint256);
+
+    function allPairsLength() external view returns (uint256) {
+        return allPairs.length;
+    }
+
+    function createPair(address tokenA, address tokenB) external returns (address pair) {
+        require(tokenA != tokenB, "MockUniswapV2: IDENTICAL_ADDRESSES");
+        (address token0, address token1) = tokenA < tokenB ? (tokenA, tokenB) : (tokenB, tokenA);
+        require(getPair[token0][token1] == address(0), "MockUniswapV2: PAIR_EXISTS");
+
+        pair = address(new MockUniswapV2Pair());
+        MockUniswapV2Pair(pair).initialize(token0, token1);
+
+        getPair[token0][token1] = pair;
+        getPair[token1][token0] = pair;
+        allPairs.push(pair);
+        emit PairCreated(token0, token1, pair, allPairs.length);
+    }
+}
+
+/// @title MockUniswapV2Router
+/// @notice Single hop router, gives the harness's victim a one tx swap that can be sandwiched
+contract MockUniswapV2Router {
+    function swapExactTokensForTokens(address pair, address tokenIn, uint256 amountIn, uint256 amountOutMin, address to)
+        external
+        returns (uint256 amountOut)
+    {
+        MockUniswapV2Pair _pair = MockUniswapV2Pair(pair);
+        (uint112 reserve0, uint112 reserve1,) = _pair.getReserves();
+        bool zeroForOne = tokenIn == _pair.token0();
+        (uint256 reserveIn, uint256 reserveOut) = zeroForOne ? (reserve0, reserve1) : (reserve1, reserve0);
+
+        uint256 amountInWithFee = amountIn * 997;
+        amountOut = (amountInWithFee * reserveOut) / (reserveIn * 1000 + amountInWithFee);
+        require(amountOut >= amountOutMin, "MockUniswapV2Router: INSUFFICIENT_OUTPUT_AMOUNT");
+
+        IMockERC20(tokenIn).transferFrom(msg.sender, pair, amountIn);
+        (uint256 amount0Out, uint256 amount1Out) = zeroForOne ? (uint256(0), amountOut) : (amountOut, uint256(0));
+        _pair.swap(amount0Out, amount1Out, to, new bytes(0));
+    }
+}